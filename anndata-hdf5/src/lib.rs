@@ -1,13 +1,14 @@
 use anndata::{
     backend::*,
-    data::{DynArray, DynCowArray, DynScalar, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
+    data::{f16, DynArray, DynCowArray, DynScalar, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
 };
 
 use anyhow::{bail, Ok, Result};
 use hdf5::{
     dataset::Dataset,
+    filters::{Blosc, Filter, ScaleOffset},
     types::IntSize::*,
-    types::{FloatSize, TypeDescriptor, VarLenUnicode},
+    types::{FixedAscii, FixedUnicode, FloatSize, TypeDescriptor, VarLenUnicode},
     File, Group, H5Type, Location, Selection,
 };
 use ndarray::{Array, ArrayD, ArrayView, CowArray, Dimension, IxDyn, SliceInfo, SliceInfoElem};
@@ -117,6 +118,7 @@ fn new_dataset<T: BackendData>(
         ScalarType::I16 => group.new_dataset::<i16>(),
         ScalarType::I32 => group.new_dataset::<i32>(),
         ScalarType::I64 => group.new_dataset::<i64>(),
+        ScalarType::F16 => group.new_dataset::<f16>(),
         ScalarType::F32 => group.new_dataset::<f32>(),
         ScalarType::F64 => group.new_dataset::<f64>(),
         ScalarType::Bool => group.new_dataset::<bool>(),
@@ -130,11 +132,19 @@ fn new_dataset<T: BackendData>(
                 ScalarType::String => builder.deflate(3),
                 _ => builder.blosc_zstd(lvl, hdf5::filters::BloscShuffle::Byte),
             }
+            Compression::Lzf => builder.lzf(),
         }
     } else {
         builder
     };
 
+    builder = match (config.quantize, dtype) {
+        (Some(Quantize::ScaleOffset { digits }), ScalarType::F32 | ScalarType::F64) => {
+            builder.scale_offset(hdf5::filters::ScaleOffset::FloatDScale(digits))
+        }
+        _ => builder,
+    };
+
     builder = if let Some(s) = config.block_size {
         if s.as_ref().iter().all(|&x| x > 0) {
             builder.chunk(s.as_ref())
@@ -162,6 +172,13 @@ fn exists(group: &Group, name: &str) -> Result<bool> {
     Ok(group.link_exists(name))
 }
 
+fn rename(group: &Group, name: &str, new_name: &str) -> Result<()> {
+    if group.link_exists(new_name) {
+        bail!("cannot rename '{}': '{}' already exists", name, new_name);
+    }
+    Ok(group.relink(name, new_name)?)
+}
+
 fn create_scalar_data<D: BackendData>(group: &Group, name: &str, data: &D) -> Result<H5Dataset> {
     match data.into_dyn() {
         DynScalar::U8(x) => {
@@ -204,6 +221,11 @@ fn create_scalar_data<D: BackendData>(group: &Group, name: &str, data: &D) -> Re
             dataset.write_scalar(&x)?;
             Ok(dataset)
         }
+        DynScalar::F16(x) => {
+            let dataset = group.new_dataset::<f16>().create(name)?;
+            dataset.write_scalar(&x)?;
+            Ok(dataset)
+        }
         DynScalar::F32(x) => {
             let dataset = group.new_dataset::<f32>().create(name)?;
             dataset.write_scalar(&x)?;
@@ -239,24 +261,53 @@ impl DatasetOp<H5> for H5Dataset {
             TypeDescriptor::Integer(U2) => ScalarType::I16,
             TypeDescriptor::Integer(U4) => ScalarType::I32,
             TypeDescriptor::Integer(U8) => ScalarType::I64,
+            TypeDescriptor::Float(FloatSize::U2) => ScalarType::F16,
             TypeDescriptor::Float(FloatSize::U4) => ScalarType::F32,
             TypeDescriptor::Float(FloatSize::U8) => ScalarType::F64,
             TypeDescriptor::Boolean => ScalarType::Bool,
             TypeDescriptor::VarLenAscii => ScalarType::String,
             TypeDescriptor::VarLenUnicode => ScalarType::String,
+            TypeDescriptor::FixedAscii(_) => ScalarType::String,
+            TypeDescriptor::FixedUnicode(_) => ScalarType::String,
             ty => bail!("Unsupported type: {:?}", ty),
         };
         Ok(ty)
     }
 
-    fn shape(&self) -> Shape {
-        hdf5::Container::shape(self).into()
+    fn shape(&self) -> Result<Shape> {
+        Ok(hdf5::Container::shape(self).into())
     }
 
     fn reshape(&mut self, shape: &Shape) -> Result<()> {
         Ok(Dataset::resize(self, shape.as_ref())?)
     }
 
+    /// Datasets written by other tools often have fixed dimensions, which
+    /// cannot be changed once the dataset is created.
+    fn is_resizable(&self) -> Result<bool> {
+        Ok(Dataset::space(self)?.is_resizable())
+    }
+
+    fn write_config(&self) -> Result<WriteConfig> {
+        let mut config = WriteConfig {
+            compression: None,
+            block_size: Dataset::chunk(self).map(Into::into),
+            quantize: None,
+        };
+        for filter in Dataset::filters(self) {
+            match filter {
+                Filter::Deflate(lvl) => config.compression = Some(Compression::Gzip(lvl)),
+                Filter::Blosc(Blosc::ZStd, lvl, _) => config.compression = Some(Compression::Zst(lvl)),
+                Filter::LZF => config.compression = Some(Compression::Lzf),
+                Filter::ScaleOffset(ScaleOffset::FloatDScale(digits)) => {
+                    config.quantize = Some(Quantize::ScaleOffset { digits })
+                }
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
     fn read_scalar<T: BackendData>(&self) -> Result<T> {
         let val = match T::DTYPE {
             ScalarType::Bool => self.deref().read_scalar::<bool>()?.into_dyn(),
@@ -268,6 +319,7 @@ impl DatasetOp<H5> for H5Dataset {
             ScalarType::I16 => self.deref().read_scalar::<i16>()?.into_dyn(),
             ScalarType::I32 => self.deref().read_scalar::<i32>()?.into_dyn(),
             ScalarType::I64 => self.deref().read_scalar::<i64>()?.into_dyn(),
+            ScalarType::F16 => self.deref().read_scalar::<f16>()?.into_dyn(),
             ScalarType::F32 => self.deref().read_scalar::<f32>()?.into_dyn(),
             ScalarType::F64 => self.deref().read_scalar::<f64>()?.into_dyn(),
             ScalarType::String => {
@@ -322,6 +374,27 @@ impl DatasetOp<H5> for H5Dataset {
             .unwrap()
         }
 
+        /// Fixed-length strings, e.g. those written by h5py for `S` arrays, are
+        /// read through a fixed-length type large enough to hold them.
+        fn read_fixed_str<D: Dimension>(
+            dataset: &H5Dataset,
+            descriptor: &TypeDescriptor,
+        ) -> Result<Array<String, D>> {
+            macro_rules! read {
+                ($ty:ident, $n:literal) => {
+                    dataset.deref().read::<$ty<$n>, D>()?.map(|s| s.as_str().to_string())
+                };
+            }
+            let arr = match *descriptor {
+                TypeDescriptor::FixedAscii(n) if n <= 64 => read!(FixedAscii, 64),
+                TypeDescriptor::FixedAscii(n) if n <= 1024 => read!(FixedAscii, 1024),
+                TypeDescriptor::FixedUnicode(n) if n <= 64 => read!(FixedUnicode, 64),
+                TypeDescriptor::FixedUnicode(n) if n <= 1024 => read!(FixedUnicode, 1024),
+                ref ty => bail!("Unsupported type: {:?}", ty),
+            };
+            Ok(arr)
+        }
+
         fn read_arr<T, S, D>(dataset: &H5Dataset, selection: &[S]) -> Result<Array<T, D>>
         where
             T: H5Type + BackendData,
@@ -333,7 +406,7 @@ impl DatasetOp<H5> for H5Dataset {
                 let arr = dataset.deref().read::<T, D>()?;
                 Ok(select(&arr, selection))
             } else {
-                let (select, shape) = into_selection(selection, dataset.shape());
+                let (select, shape) = into_selection(selection, dataset.shape()?)?;
                 if matches!(select, Selection::Points(_)) {
                     let slice_1d = hdf5::Container::read_slice_1d::<T, _>(dataset, select)?;
                     Ok(slice_1d
@@ -354,18 +427,23 @@ impl DatasetOp<H5> for H5Dataset {
             ScalarType::U16 => read_arr::<u16, _, D>(self, selection)?.into(),
             ScalarType::U32 => read_arr::<u32, _, D>(self, selection)?.into(),
             ScalarType::U64 => read_arr::<u64, _, D>(self, selection)?.into(),
+            ScalarType::F16 => read_arr::<f16, _, D>(self, selection)?.into(),
             ScalarType::F32 => read_arr::<f32, _, D>(self, selection)?.into(),
             ScalarType::F64 => read_arr::<f64, _, D>(self, selection)?.into(),
             ScalarType::Bool => read_arr::<bool, _, D>(self, selection)?.into(),
             ScalarType::String => {
-                if selection.as_ref().iter().any(|x| x.as_ref().is_index()) {
+                let descriptor = hdf5::Container::dtype(self)?.to_descriptor()?;
+                if matches!(descriptor, TypeDescriptor::FixedAscii(_) | TypeDescriptor::FixedUnicode(_)) {
+                    let arr = read_fixed_str::<D>(self, &descriptor)?;
+                    Ok(select(&arr, selection))
+                } else if selection.as_ref().iter().any(|x| x.as_ref().is_index()) {
                     // fancy indexing is too slow, just read all
                     let arr = self.deref().read::<VarLenUnicode, D>()?;
                     let arr_ = arr.map(|s| s.to_string());
                     let r: Result<_> = Ok(select(&arr_, selection));
                     r
                 } else {
-                    let (select, shape) = into_selection(selection, self.shape());
+                    let (select, shape) = into_selection(selection, self.shape()?)?;
                     let arr: Result<_> = if matches!(select, Selection::Points(_)) {
                         let slice_1d = self.deref().read_slice_1d::<VarLenUnicode, _>(select)?;
                         Ok(slice_1d
@@ -402,7 +480,7 @@ impl DatasetOp<H5> for H5Dataset {
             T: H5Type + Clone,
             S: AsRef<SelectInfoElem>,
         {
-            let (select, _) = into_selection(selection, container.shape());
+            let (select, _) = into_selection(selection, container.shape()?)?;
             container
                 .deref()
                 .write_slice(&arr.as_standard_layout(), select)?;
@@ -418,6 +496,7 @@ impl DatasetOp<H5> for H5Dataset {
             DynCowArray::I16(x) => write_array_impl(self, x, selection),
             DynCowArray::I32(x) => write_array_impl(self, x, selection),
             DynCowArray::I64(x) => write_array_impl(self, x, selection),
+            DynCowArray::F16(x) => write_array_impl(self, x, selection),
             DynCowArray::F32(x) => write_array_impl(self, x, selection),
             DynCowArray::F64(x) => write_array_impl(self, x, selection),
             DynCowArray::Bool(x) => write_array_impl(self, x, selection),
@@ -477,6 +556,10 @@ impl GroupOp<H5> for H5File {
         exists(self, name)
     }
 
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        rename(self, name, new_name)
+    }
+
     fn new_scalar_dataset<D: BackendData>(
         &self,
         name: &str,
@@ -520,6 +603,10 @@ impl GroupOp<H5> for H5Group {
         exists(self, name)
     }
 
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        rename(self, name, new_name)
+    }
+
     fn new_scalar_dataset<D: BackendData>(
         &self,
         name: &str,
@@ -650,6 +737,7 @@ where
         DynCowArray::I16(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::I32(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::I64(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
+        DynCowArray::F16(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::F32(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::F64(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
         DynCowArray::Bool(x) => loc.new_attr_builder().with_data(x.view()).create(name)?,
@@ -672,6 +760,7 @@ fn write_scalar_attr<D: BackendData>(loc: &Location, name: &str, value: D) -> Re
         DynScalar::I16(x) => loc.new_attr::<i16>().create(name)?.write_scalar(&x)?,
         DynScalar::I32(x) => loc.new_attr::<i32>().create(name)?.write_scalar(&x)?,
         DynScalar::I64(x) => loc.new_attr::<i64>().create(name)?.write_scalar(&x)?,
+        DynScalar::F16(x) => loc.new_attr::<f16>().create(name)?.write_scalar(&x)?,
         DynScalar::F32(x) => loc.new_attr::<f32>().create(name)?.write_scalar(&x)?,
         DynScalar::F64(x) => loc.new_attr::<f64>().create(name)?.write_scalar(&x)?,
         DynScalar::Bool(x) => loc.new_attr::<bool>().create(name)?.write_scalar(&x)?,
@@ -685,15 +774,15 @@ fn write_scalar_attr<D: BackendData>(loc: &Location, name: &str, value: D) -> Re
     Ok(())
 }
 
-fn into_selection<S, E>(selection: S, shape: Shape) -> (Selection, Shape)
+fn into_selection<S, E>(selection: S, shape: Shape) -> Result<(Selection, Shape)>
 where
     S: AsRef<[E]>,
     E: AsRef<SelectInfoElem>,
 {
-    if selection.as_ref().into_iter().all(|x| x.as_ref().is_full()) {
+    let selection = if selection.as_ref().into_iter().all(|x| x.as_ref().is_full()) {
         (Selection::All, shape)
     } else {
-        let bounded_selection = SelectInfoBounds::new(&selection, &shape);
+        let bounded_selection = SelectInfoBounds::try_new(&selection, &shape)?;
         let out_shape = bounded_selection.out_shape();
         if let Some(idx) = bounded_selection.try_into_indices() {
             (Selection::from(idx), out_shape)
@@ -701,7 +790,8 @@ where
             let slice: SliceInfo<_, _, _> = bounded_selection.try_into().unwrap();
             (Selection::try_from(slice).unwrap(), out_shape)
         }
-    }
+    };
+    Ok(selection)
 }
 
 fn del_attr(loc: &Location, name: &str) {
@@ -861,4 +951,24 @@ mod tests {
             Ok(())
         })
     }
+
+    #[test]
+    fn test_write_quantized() -> Result<()> {
+        with_tmp_path(|path| -> Result<()> {
+            let file = H5::new(&path)?;
+            let config = WriteConfig {
+                quantize: Some(Quantize::ScaleOffset { digits: 2 }),
+                ..Default::default()
+            };
+
+            let arr = Array::random((100, 20), Uniform::new(-10.0, 10.0));
+            let dataset = file.new_array_dataset("test", arr.view().into(), config)?;
+            let read = dataset.read_array::<f64, ndarray::Ix2>()?;
+            assert!(arr
+                .iter()
+                .zip(read.iter())
+                .all(|(a, b)| (a - b).abs() <= 0.005 + 1e-9));
+            Ok(())
+        })
+    }
 }
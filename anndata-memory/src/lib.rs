@@ -0,0 +1,769 @@
+use anndata::{
+    backend::*,
+    data::{DynArray, DynCowArray, SelectInfoElem, SelectInfoElemBounds, Shape},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use ndarray::{Array, ArrayD, ArrayView, CowArray, Dimension, IxDyn, SliceInfoElem};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, Weak},
+};
+
+/// The in-memory backend.
+///
+/// Stores live in a process-wide registry keyed by their path, so a store created
+/// with `Memory::new` can be reopened with `Memory::open` while a handle to it is
+/// alive. Closing a store keeps it in the registry until it is removed with
+/// [`Memory::remove`]; a store dropped without being closed is released with its
+/// last handle. Nothing is ever written to disk.
+pub struct Memory;
+
+type Attributes = HashMap<String, Value>;
+
+enum Node {
+    Group(Attributes),
+    Dataset(DynArray, Attributes),
+}
+
+impl Node {
+    fn attrs(&self) -> &Attributes {
+        match self {
+            Node::Group(attrs) => attrs,
+            Node::Dataset(_, attrs) => attrs,
+        }
+    }
+
+    fn attrs_mut(&mut self) -> &mut Attributes {
+        match self {
+            Node::Group(attrs) => attrs,
+            Node::Dataset(_, attrs) => attrs,
+        }
+    }
+}
+
+/// Nodes are stored in a flat map keyed by their absolute path, e.g. "/obsm/X_pca".
+#[derive(Clone)]
+pub struct MemoryStore {
+    nodes: Arc<Nodes>,
+    path: PathBuf,
+}
+
+pub struct MemoryGroup {
+    store: MemoryStore,
+    path: String,
+}
+
+pub struct MemoryDataset {
+    store: MemoryStore,
+    path: String,
+}
+
+type Nodes = RwLock<BTreeMap<String, Node>>;
+
+/// Open stores are only referenced weakly by the registry, so that they are
+/// released together with their last handle. Closed stores are kept alive.
+enum Entry {
+    Open(Weak<Nodes>),
+    Closed(MemoryStore),
+}
+
+impl Entry {
+    fn get(&self, path: &Path) -> Option<MemoryStore> {
+        match self {
+            Entry::Open(nodes) => nodes.upgrade().map(|nodes| MemoryStore {
+                nodes,
+                path: path.to_path_buf(),
+            }),
+            Entry::Closed(store) => Some(store.clone()),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            Entry::Open(nodes) => nodes.strong_count() > 0,
+            Entry::Closed(_) => true,
+        }
+    }
+
+    fn refers_to(&self, store: &MemoryStore) -> bool {
+        match self {
+            Entry::Open(nodes) => std::ptr::eq(nodes.as_ptr(), Arc::as_ptr(&store.nodes)),
+            Entry::Closed(x) => Arc::ptr_eq(&x.nodes, &store.nodes),
+        }
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<PathBuf, Entry>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<PathBuf, Entry>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+impl Memory {
+    /// Remove the store registered at the given path. The memory is released once
+    /// all remaining handles to the store are dropped. Returns `false` if no store
+    /// was registered at this path.
+    pub fn remove<P: AsRef<Path>>(path: P) -> bool {
+        let entry = registry().lock().remove(path.as_ref());
+        entry.is_some_and(|x| x.is_alive())
+    }
+}
+
+impl Backend for Memory {
+    const NAME: &'static str = "memory";
+
+    type Store = MemoryStore;
+
+    type Group = MemoryGroup;
+
+    type Dataset = MemoryDataset;
+
+    /// Create a new store, replacing any store registered at the same path.
+    fn new<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        let mut nodes = BTreeMap::new();
+        nodes.insert("/".to_string(), Node::Group(Attributes::new()));
+        let store = MemoryStore {
+            nodes: Arc::new(RwLock::new(nodes)),
+            path: path.as_ref().to_path_buf(),
+        };
+        let mut registry = registry().lock();
+        registry.retain(|_, entry| entry.is_alive());
+        registry.insert(store.path.clone(), Entry::Open(Arc::downgrade(&store.nodes)));
+        Ok(store)
+    }
+
+    /// Opens a store created by `Memory::new`.
+    fn open<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        registry()
+            .lock()
+            .get(path.as_ref())
+            .and_then(|entry| entry.get(path.as_ref()))
+            .with_context(|| format!("no in-memory store at {}", path.as_ref().display()))
+    }
+
+    /// Opens a store created by `Memory::new`.
+    fn open_rw<P: AsRef<Path>>(path: P) -> Result<Self::Store> {
+        Self::open(path)
+    }
+}
+
+impl MemoryStore {
+    fn root(&self) -> MemoryGroup {
+        MemoryGroup {
+            store: self.clone(),
+            path: "/".to_string(),
+        }
+    }
+
+    fn list_children(&self, path: &str) -> Vec<String> {
+        let prefix = if path == "/" { path.to_string() } else { format!("{}/", path) };
+        self.nodes
+            .read()
+            .range(prefix.clone()..)
+            .take_while(|(k, _)| k.starts_with(&prefix))
+            .filter_map(|(k, _)| {
+                let name = &k[prefix.len()..];
+                if name.is_empty() || name.contains('/') {
+                    None
+                } else {
+                    Some(name.to_string())
+                }
+            })
+            .collect()
+    }
+
+    /// Insert a new node, creating missing parent groups along the way.
+    fn insert(&self, path: &str, node: Node) -> Result<()> {
+        let mut nodes = self.nodes.write();
+        ensure!(!nodes.contains_key(path), "'{}' already exists", path);
+        let mut parent = parent_path(path);
+        while !nodes.contains_key(parent) {
+            nodes.insert(parent.to_string(), Node::Group(Attributes::new()));
+            parent = parent_path(parent);
+        }
+        if let Some(Node::Dataset(..)) = nodes.get(parent) {
+            bail!("cannot create '{}': '{}' is a dataset", path, parent);
+        }
+        nodes.insert(path.to_string(), node);
+        Ok(())
+    }
+
+    fn delete_node(&self, path: &str) -> Result<()> {
+        ensure!(path != "/", "cannot delete the root group");
+        let mut nodes = self.nodes.write();
+        ensure!(nodes.remove(path).is_some(), "'{}' does not exist", path);
+        let prefix = format!("{}/", path);
+        nodes.retain(|k, _| !k.starts_with(&prefix));
+        Ok(())
+    }
+
+    /// Move a node and all nodes below it to `new_path`.
+    fn rename_node(&self, path: &str, new_path: &str) -> Result<()> {
+        ensure!(path != "/", "cannot rename the root group");
+        let mut nodes = self.nodes.write();
+        ensure!(nodes.contains_key(path), "'{}' does not exist", path);
+        ensure!(!nodes.contains_key(new_path), "'{}' already exists", new_path);
+        let prefix = format!("{}/", path);
+        let keys: Vec<String> = nodes
+            .keys()
+            .filter(|k| k.as_str() == path || k.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for key in keys {
+            let node = nodes.remove(&key).unwrap();
+            nodes.insert(format!("{}{}", new_path, &key[path.len()..]), node);
+        }
+        Ok(())
+    }
+
+    fn with_node<R>(&self, path: &str, f: impl FnOnce(&Node) -> R) -> Result<R> {
+        let nodes = self.nodes.read();
+        let node = nodes.get(path).with_context(|| format!("'{}' does not exist", path))?;
+        Ok(f(node))
+    }
+
+    fn with_node_mut<R>(&self, path: &str, f: impl FnOnce(&mut Node) -> R) -> Result<R> {
+        let mut nodes = self.nodes.write();
+        let node = nodes.get_mut(path).with_context(|| format!("'{}' does not exist", path))?;
+        Ok(f(node))
+    }
+
+    fn with_data<R>(&self, path: &str, f: impl FnOnce(&DynArray) -> R) -> Result<R> {
+        self.with_node(path, |node| match node {
+            Node::Dataset(data, _) => Ok(f(data)),
+            Node::Group(_) => bail!("'{}' is not a dataset", path),
+        })?
+    }
+
+    fn with_data_mut<R>(&self, path: &str, f: impl FnOnce(&mut DynArray) -> R) -> Result<R> {
+        self.with_node_mut(path, |node| match node {
+            Node::Dataset(data, _) => Ok(f(data)),
+            Node::Group(_) => bail!("'{}' is not a dataset", path),
+        })?
+    }
+}
+
+impl StoreOp<Memory> for MemoryStore {
+    /// Returns the path under which the store is registered.
+    fn filename(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Close the store. The store is kept in the registry, so that it can be
+    /// reopened until it is removed with [`Memory::remove`].
+    fn close(self) -> Result<()> {
+        let mut registry = registry().lock();
+        if let Some(entry) = registry.get_mut(&self.path) {
+            if entry.refers_to(&self) {
+                *entry = Entry::Closed(self);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GroupOp<Memory> for MemoryStore {
+    fn list(&self) -> Result<Vec<String>> {
+        self.root().list()
+    }
+
+    fn new_group(&self, name: &str) -> Result<MemoryGroup> {
+        self.root().new_group(name)
+    }
+
+    fn open_group(&self, name: &str) -> Result<MemoryGroup> {
+        self.root().open_group(name)
+    }
+
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        config: WriteConfig,
+    ) -> Result<MemoryDataset> {
+        self.root().new_empty_dataset::<T>(name, shape, config)
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<MemoryDataset> {
+        self.root().open_dataset(name)
+    }
+
+    fn delete(&self, name: &str) -> Result<()> {
+        self.root().delete(name)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        self.root().exists(name)
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        self.root().rename(name, new_name)
+    }
+}
+
+impl GroupOp<Memory> for MemoryGroup {
+    /// List all groups and datasets in this group.
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.store.list_children(&self.path))
+    }
+
+    /// Create a new group.
+    fn new_group(&self, name: &str) -> Result<MemoryGroup> {
+        let path = join_path(&self.path, name);
+        self.store.insert(&path, Node::Group(Attributes::new()))?;
+        Ok(MemoryGroup {
+            store: self.store.clone(),
+            path,
+        })
+    }
+
+    /// Open an existing group.
+    fn open_group(&self, name: &str) -> Result<MemoryGroup> {
+        let path = join_path(&self.path, name);
+        self.store.with_node(&path, |node| match node {
+            Node::Group(_) => Ok(()),
+            Node::Dataset(..) => bail!("'{}' is not a group", path),
+        })??;
+        Ok(MemoryGroup {
+            store: self.store.clone(),
+            path,
+        })
+    }
+
+    /// Create an empty dataset holding an array value. Block sizes and compression
+    /// settings are ignored.
+    fn new_empty_dataset<T: BackendData>(
+        &self,
+        name: &str,
+        shape: &Shape,
+        config: WriteConfig,
+    ) -> Result<MemoryDataset> {
+        if config.quantize.is_some() {
+            bail!("quantization is not supported by the memory backend");
+        }
+        let shape = IxDyn(shape.as_ref());
+        let data = match T::DTYPE {
+            ScalarType::U8 => DynArray::U8(ArrayD::default(shape)),
+            ScalarType::U16 => DynArray::U16(ArrayD::default(shape)),
+            ScalarType::U32 => DynArray::U32(ArrayD::default(shape)),
+            ScalarType::U64 => DynArray::U64(ArrayD::default(shape)),
+            ScalarType::I8 => DynArray::I8(ArrayD::default(shape)),
+            ScalarType::I16 => DynArray::I16(ArrayD::default(shape)),
+            ScalarType::I32 => DynArray::I32(ArrayD::default(shape)),
+            ScalarType::I64 => DynArray::I64(ArrayD::default(shape)),
+            ScalarType::F16 => DynArray::F16(ArrayD::default(shape)),
+            ScalarType::F32 => DynArray::F32(ArrayD::default(shape)),
+            ScalarType::F64 => DynArray::F64(ArrayD::default(shape)),
+            ScalarType::Bool => DynArray::Bool(ArrayD::default(shape)),
+            ScalarType::String => DynArray::String(ArrayD::default(shape)),
+        };
+        let path = join_path(&self.path, name);
+        self.store.insert(&path, Node::Dataset(data, Attributes::new()))?;
+        Ok(MemoryDataset {
+            store: self.store.clone(),
+            path,
+        })
+    }
+
+    fn open_dataset(&self, name: &str) -> Result<MemoryDataset> {
+        let path = join_path(&self.path, name);
+        self.store.with_data(&path, |_| ())?;
+        Ok(MemoryDataset {
+            store: self.store.clone(),
+            path,
+        })
+    }
+
+    /// Delete a group or dataset.
+    fn delete(&self, name: &str) -> Result<()> {
+        self.store.delete_node(&join_path(&self.path, name))
+    }
+
+    /// Check if a group or dataset exists.
+    fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.store.nodes.read().contains_key(&join_path(&self.path, name)))
+    }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        self.store
+            .rename_node(&join_path(&self.path, name), &join_path(&self.path, new_name))
+    }
+}
+
+impl AttributeOp<Memory> for MemoryGroup {
+    /// Returns the Root.
+    fn store(&self) -> Result<MemoryStore> {
+        Ok(self.store.clone())
+    }
+
+    /// Returns the path of the location relative to the file root.
+    fn path(&self) -> PathBuf {
+        PathBuf::from(&self.path)
+    }
+
+    /// Write an attribute at a given location.
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        self.store.with_node_mut(&self.path, |node| {
+            node.attrs_mut().insert(name.to_string(), value.clone());
+        })
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        self.store
+            .with_node(&self.path, |node| node.attrs().get(name).cloned())?
+            .with_context(|| format!("Attribute {} not found", name))
+    }
+}
+
+impl AttributeOp<Memory> for MemoryDataset {
+    /// Returns the Root.
+    fn store(&self) -> Result<MemoryStore> {
+        Ok(self.store.clone())
+    }
+
+    /// Returns the path of the location relative to the file root.
+    fn path(&self) -> PathBuf {
+        PathBuf::from(&self.path)
+    }
+
+    /// Write an attribute at a given location.
+    fn new_json_attr(&mut self, name: &str, value: &Value) -> Result<()> {
+        self.store.with_node_mut(&self.path, |node| {
+            node.attrs_mut().insert(name.to_string(), value.clone());
+        })
+    }
+
+    fn get_json_attr(&self, name: &str) -> Result<Value> {
+        self.store
+            .with_node(&self.path, |node| node.attrs().get(name).cloned())?
+            .with_context(|| format!("Attribute {} not found", name))
+    }
+}
+
+/// Apply an expression to the array inside any `DynArray` variant.
+macro_rules! map_dyn_array {
+    ($data:expr, $x:ident => $body:expr) => {
+        match $data {
+            DynArray::U8($x) => $body,
+            DynArray::U16($x) => $body,
+            DynArray::U32($x) => $body,
+            DynArray::U64($x) => $body,
+            DynArray::I8($x) => $body,
+            DynArray::I16($x) => $body,
+            DynArray::I32($x) => $body,
+            DynArray::I64($x) => $body,
+            DynArray::F16($x) => $body,
+            DynArray::F32($x) => $body,
+            DynArray::F64($x) => $body,
+            DynArray::Bool($x) => $body,
+            DynArray::String($x) => $body,
+        }
+    };
+}
+
+impl DatasetOp<Memory> for MemoryDataset {
+    fn dtype(&self) -> Result<ScalarType> {
+        self.store.with_data(&self.path, |data| match data {
+            DynArray::U8(_) => ScalarType::U8,
+            DynArray::U16(_) => ScalarType::U16,
+            DynArray::U32(_) => ScalarType::U32,
+            DynArray::U64(_) => ScalarType::U64,
+            DynArray::I8(_) => ScalarType::I8,
+            DynArray::I16(_) => ScalarType::I16,
+            DynArray::I32(_) => ScalarType::I32,
+            DynArray::I64(_) => ScalarType::I64,
+            DynArray::F16(_) => ScalarType::F16,
+            DynArray::F32(_) => ScalarType::F32,
+            DynArray::F64(_) => ScalarType::F64,
+            DynArray::Bool(_) => ScalarType::Bool,
+            DynArray::String(_) => ScalarType::String,
+        })
+    }
+
+    fn shape(&self) -> Result<Shape> {
+        self.store.with_data(&self.path, |data| data.shape())
+    }
+
+    /// Resize the dataset. Existing values within the new bounds are kept and
+    /// new elements are filled with the default value of the data type.
+    fn reshape(&mut self, shape: &Shape) -> Result<()> {
+        self.store.with_data_mut(&self.path, |data| {
+            ensure!(
+                data.ndim() == shape.as_ref().len(),
+                "cannot reshape a {}-dimensional dataset to {} dimensions",
+                data.ndim(),
+                shape.as_ref().len(),
+            );
+            *data = map_dyn_array!(data, x => resize(x, shape.as_ref()).into());
+            Ok(())
+        })?
+    }
+
+    fn read_array_slice<T: BackendData, S, D>(&self, selection: &[S]) -> Result<Array<T, D>>
+    where
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        let array = self.store.with_data(&self.path, |data| -> Result<DynArray> {
+            check_selection(selection, data.shape().as_ref())?;
+            Ok(map_dyn_array!(data, x => select(x, selection).into()))
+        })??;
+        Ok(BackendData::from_dyn_arr(array)?.into_dimensionality::<D>()?)
+    }
+
+    fn write_array_slice<S, T, D>(&self, arr: CowArray<'_, T, D>, selection: &[S]) -> Result<()>
+    where
+        T: BackendData,
+        S: AsRef<SelectInfoElem>,
+        D: Dimension,
+    {
+        let dtype = self.dtype()?;
+        ensure!(
+            dtype == T::DTYPE,
+            "cannot write {:?} values into a dataset of type {:?}",
+            T::DTYPE,
+            dtype,
+        );
+        let arr = BackendData::into_dyn_arr(arr.into_dyn());
+        self.store.with_data_mut(&self.path, |data| match (data, arr) {
+            (DynArray::U8(x), DynCowArray::U8(y)) => assign(x, y.view(), selection),
+            (DynArray::U16(x), DynCowArray::U16(y)) => assign(x, y.view(), selection),
+            (DynArray::U32(x), DynCowArray::U32(y)) => assign(x, y.view(), selection),
+            (DynArray::U64(x), DynCowArray::U64(y)) => assign(x, y.view(), selection),
+            (DynArray::I8(x), DynCowArray::I8(y)) => assign(x, y.view(), selection),
+            (DynArray::I16(x), DynCowArray::I16(y)) => assign(x, y.view(), selection),
+            (DynArray::I32(x), DynCowArray::I32(y)) => assign(x, y.view(), selection),
+            (DynArray::I64(x), DynCowArray::I64(y)) => assign(x, y.view(), selection),
+            (DynArray::F16(x), DynCowArray::F16(y)) => assign(x, y.view(), selection),
+            (DynArray::F32(x), DynCowArray::F32(y)) => assign(x, y.view(), selection),
+            (DynArray::F64(x), DynCowArray::F64(y)) => assign(x, y.view(), selection),
+            (DynArray::Bool(x), DynCowArray::Bool(y)) => assign(x, y.view(), selection),
+            (DynArray::String(x), DynCowArray::String(y)) => assign(x, y.view(), selection),
+            _ => unreachable!(),
+        })?
+    }
+}
+
+fn join_path(parent: &str, name: &str) -> String {
+    let path = if name.starts_with('/') {
+        name.to_string()
+    } else if parent == "/" {
+        format!("/{}", name)
+    } else {
+        format!("{}/{}", parent, name)
+    };
+    if path.len() > 1 {
+        path.trim_end_matches('/').to_string()
+    } else {
+        path
+    }
+}
+
+fn parent_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn check_selection<S: AsRef<SelectInfoElem>>(selection: &[S], shape: &[usize]) -> Result<()> {
+    ensure!(
+        selection.len() == shape.len(),
+        "selection has {} dimensions but the dataset has {}",
+        selection.len(),
+        shape.len(),
+    );
+    selection
+        .iter()
+        .zip(shape)
+        .try_for_each(|(sel, n)| sel.as_ref().bound_check(*n))
+}
+
+fn resize<T: Clone + Default>(arr: &ArrayD<T>, shape: &[usize]) -> ArrayD<T> {
+    let mut result = ArrayD::default(IxDyn(shape));
+    let overlap: Vec<SliceInfoElem> = arr
+        .shape()
+        .iter()
+        .zip(shape)
+        .map(|(a, b)| (0..(*a).min(*b)).into())
+        .collect();
+    result
+        .slice_mut(overlap.as_slice())
+        .assign(&arr.slice(overlap.as_slice()));
+    result
+}
+
+fn select<T: Clone, S: AsRef<SelectInfoElem>>(arr: &ArrayD<T>, info: &[S]) -> ArrayD<T> {
+    let slices = info
+        .iter()
+        .map(|x| match x.as_ref() {
+            SelectInfoElem::Slice(slice) => Some(SliceInfoElem::from(slice.clone())),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    if let Some(slices) = slices {
+        arr.slice(slices.as_slice()).into_owned()
+    } else {
+        let select: Vec<_> = info
+            .iter()
+            .zip(arr.shape())
+            .map(|(x, n)| SelectInfoElemBounds::new(x.as_ref(), *n))
+            .collect();
+        let new_shape = select.iter().map(|x| x.len()).collect::<Vec<_>>();
+        ArrayD::from_shape_fn(new_shape, |idx| {
+            let new_idx: Vec<_> = (0..idx.ndim()).map(|i| select[i].index(idx[i])).collect();
+            arr[new_idx.as_slice()].clone()
+        })
+    }
+}
+
+fn assign<T: Clone, S: AsRef<SelectInfoElem>>(
+    dst: &mut ArrayD<T>,
+    src: ArrayView<'_, T, IxDyn>,
+    info: &[S],
+) -> Result<()> {
+    check_selection(info, dst.shape())?;
+    let select: Vec<_> = info
+        .iter()
+        .zip(dst.shape())
+        .map(|(x, n)| SelectInfoElemBounds::new(x.as_ref(), *n))
+        .collect();
+    let out_shape: Vec<_> = select.iter().map(|x| x.len()).collect();
+    ensure!(
+        out_shape.as_slice() == src.shape(),
+        "selection has shape {:?} but the data has shape {:?}",
+        out_shape,
+        src.shape(),
+    );
+
+    let slices = select
+        .iter()
+        .map(|x| match x {
+            SelectInfoElemBounds::Slice(slice) if slice.step == 1 => Some((*slice).into()),
+            _ => None,
+        })
+        .collect::<Option<Vec<SliceInfoElem>>>();
+    if let Some(slices) = slices {
+        dst.slice_mut(slices.as_slice()).assign(&src);
+    } else {
+        for (idx, value) in src.indexed_iter() {
+            let new_idx: Vec<_> = (0..idx.ndim()).map(|i| select[i].index(idx[i])).collect();
+            dst[new_idx.as_slice()] = value.clone();
+        }
+    }
+    Ok(())
+}
+
+/// test module
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anndata::s;
+    use ndarray::{concatenate, Array2, Axis, Ix1, Ix2};
+    use ndarray_rand::rand_distr::Uniform;
+    use ndarray_rand::RandomExt;
+
+    #[test]
+    fn test_basic() -> Result<()> {
+        let store = Memory::new("test_memory_basic")?;
+        store.open_group("/")?;
+
+        store.new_scalar_dataset("data", &4)?;
+        store.open_dataset("data")?;
+
+        let mut group = store.new_group("group")?;
+        group.new_attr("key", "value")?;
+        assert_eq!("value", group.get_attr::<String>("key")?);
+
+        let subgroup = group.new_group("group")?;
+        let data = subgroup.new_scalar_dataset("data", &4)?;
+        assert_eq!(PathBuf::from("/group/group/data"), data.path());
+
+        {
+            let store = Memory::open("test_memory_basic")?;
+            assert_eq!(4, store.open_group("group/group")?.open_dataset("data")?.read_scalar::<i32>()?);
+            DataContainer::open(&store, "group")?;
+        }
+
+        let mut names = store.list()?;
+        names.sort();
+        assert_eq!(vec!["data", "group"], names);
+        assert_eq!(vec!["group"], group.list()?);
+
+        store.delete("group")?;
+        assert!(!store.exists("group")?);
+        assert!(!subgroup.exists("data")?);
+        assert!(Memory::remove("test_memory_basic"));
+        assert!(Memory::open("test_memory_basic").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_slice() -> Result<()> {
+        let store = Memory::new("test_memory_slice")?;
+        let group = store.new_group("group")?;
+        let mut dataset = group.new_empty_dataset::<i32>("test", &[20, 50].as_slice().into(), WriteConfig::default())?;
+
+        let arr = Array::random((10, 10), Uniform::new(0, 100));
+        dataset.write_array_slice(arr.view().into(), s![5..15, 10..20].as_ref())?;
+        assert_eq!(arr, dataset.read_array_slice::<i32, _, Ix2>(s![5..15, 10..20].as_ref())?);
+
+        // Out-of-bounds writes should fail
+        assert!(dataset.write_array_slice(arr.view().into(), s![15..25, ..10].as_ref()).is_err());
+
+        let arr = Array::random((20, 50), Uniform::new(0, 100));
+        dataset.write_array_slice(arr.view().into(), s![.., ..].as_ref())?;
+
+        // Reshape and write
+        dataset.reshape(&[40, 50].as_slice().into())?;
+        dataset.write_array_slice(arr.view().into(), s![20..40, ..].as_ref())?;
+        let merged = concatenate(Axis(0), &[arr.view(), arr.view()])?;
+        assert_eq!(merged, dataset.read_array::<i32, Ix2>()?);
+
+        // Shrinking is OK
+        dataset.reshape(&[20, 50].as_slice().into())?;
+        assert_eq!(arr, dataset.read_array::<i32, Ix2>()?);
+
+        // Index selections
+        let indices = vec![3, 1, 4];
+        let expected = Array2::from_shape_fn((3, 50), |(i, j)| arr[[indices[i], j]]);
+        let selection = [SelectInfoElem::from(indices.clone()), SelectInfoElem::full()];
+        assert_eq!(expected, dataset.read_array_slice::<i32, _, Ix2>(selection.as_slice())?);
+
+        let vec = store.new_empty_dataset::<f64>("vec", &[4].as_slice().into(), WriteConfig::default())?;
+        let values = ndarray::array![1.0, 2.0];
+        vec.write_array_slice(values.view().into(), [SelectInfoElem::from(vec![3, 0])].as_slice())?;
+        assert_eq!(ndarray::array![2.0, 0.0, 0.0, 1.0], vec.read_array::<f64, Ix1>()?);
+
+        Memory::remove("test_memory_slice");
+        Ok(())
+    }
+
+    #[test]
+    fn test_release() -> Result<()> {
+        let store = Memory::new("test_memory_release")?;
+        {
+            let dataset = store.new_scalar_dataset("data", &4)?;
+            store.delete("data")?;
+            assert!(dataset.shape().is_err());
+        }
+
+        // Stores dropped without being closed are released.
+        drop(store);
+        assert!(Memory::open("test_memory_release").is_err());
+        assert!(!Memory::remove("test_memory_release"));
+
+        // Closed stores can be reopened until they are removed.
+        let store = Memory::new("test_memory_release")?;
+        store.new_scalar_dataset("data", &4)?;
+        store.close()?;
+        let store = Memory::open("test_memory_release")?;
+        assert_eq!(4, store.open_dataset("data")?.read_scalar::<i32>()?);
+        drop(store);
+        assert!(Memory::remove("test_memory_release"));
+        assert!(Memory::open("test_memory_release").is_err());
+        Ok(())
+    }
+}
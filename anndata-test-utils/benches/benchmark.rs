@@ -3,7 +3,9 @@ use anndata_zarr::Zarr;
 use criterion::{criterion_group, criterion_main, Criterion};
 use anndata_test_utils::with_tmp_dir;
 use anndata::*;
+use anndata::backend::DataContainer;
 use ndarray::{Array, Array2};
+use polars::prelude::{DataFrame, IntoColumn, NamedFrom, Series};
 use ndarray_rand::rand_distr::Uniform;
 use ndarray_rand::RandomExt;
 
@@ -16,7 +18,7 @@ fn bench_array_io(c: &mut Criterion) {
             let output = dir.join("test");
             let arr = Array::random([n, n], Uniform::new(-128i64, 127i64));
 
-            let adata = AnnData::<H5>::open(H5::new(&output).unwrap()).unwrap();
+            let adata = AnnData::<H5>::new(&output).unwrap();
             group.bench_with_input(format!("Write H5 ({} x {})", n, n), &arr, |b, arr|
                 b.iter(|| adata.set_x(arr.clone()).unwrap())
             );
@@ -24,7 +26,7 @@ fn bench_array_io(c: &mut Criterion) {
                 b.iter(|| adata.x().get::<Array2<i64>>().unwrap())
             );
 
-            let adata = AnnData::<Zarr>::open(Zarr::new(&output).unwrap()).unwrap();
+            let adata = AnnData::<Zarr>::new(&output).unwrap();
             group.bench_with_input(format!("Write Zarr ({} x {})", n, n), &arr, |b, arr|
                 b.iter(|| adata.set_x(arr.clone()).unwrap())
             );
@@ -44,13 +46,13 @@ fn bench_array_slice(c: &mut Criterion) {
             let output = dir.join("test");
             let arr = Array::random([n, n], Uniform::new(-128i64, 127i64));
 
-            let adata = AnnData::<H5>::open(H5::new(&output).unwrap()).unwrap();
+            let adata = AnnData::<H5>::new(&output).unwrap();
             adata.set_x(arr.clone()).unwrap();
             group.bench_function(&format!("H5 {} x {}", n, n), |b| b.iter(||
                 adata.x().slice::<Array2<i64>, _>(s![5..591, 5..591]).unwrap()
             ));
 
-            let adata = AnnData::<Zarr>::open(Zarr::new(&output).unwrap()).unwrap();
+            let adata = AnnData::<Zarr>::new(&output).unwrap();
             adata.set_x(arr).unwrap();
             group.bench_function(&format!("Zarr {} x {}", n, n), |b| b.iter(||
                 adata.x().slice::<Array2<i64>, _>(s![5..591, 5..591]).unwrap()
@@ -71,7 +73,7 @@ fn bench_par_read(c: &mut Criterion) {
             
             let adatas = (0..10).map(|i| {
                 let output = dir.join(format!("test_{}", i));
-                let adata = AnnData::<H5>::open(H5::new(&output).unwrap()).unwrap();
+                let adata = AnnData::<H5>::new(&output).unwrap();
                 adata.set_x(arr.clone()).unwrap();
                 (i.to_string(), adata)
             }).collect::<Vec<_>>();
@@ -86,7 +88,7 @@ fn bench_par_read(c: &mut Criterion) {
 
             let adatas = (0..10).map(|i| {
                 let output = dir.join(format!("test_{}", i));
-                let adata = AnnData::<Zarr>::open(Zarr::new(&output).unwrap()).unwrap();
+                let adata = AnnData::<Zarr>::new(&output).unwrap();
                 adata.set_x(arr.clone()).unwrap();
                 (i.to_string(), adata)
             }).collect::<Vec<_>>();
@@ -103,5 +105,97 @@ fn bench_par_read(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_array_io, bench_array_slice, bench_par_read);
+fn bench_dataframe_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("DataFrame read");
+    group.sample_size(10);
+    // Reading with a single thread gives the sequential baseline.
+    let single_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+
+    with_tmp_dir(|dir| {
+        let n = 100000;
+        let columns = (0..100).map(|i| {
+            let values = Array::random(n, Uniform::new(0.0, 1.0)).to_vec();
+            Series::new(format!("column_{}", i).into(), values).into_column()
+        }).collect();
+        let obs = DataFrame::new(columns).unwrap();
+
+        let output = dir.join("test_h5");
+        let adata = AnnData::<H5>::new(&output).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.close().unwrap();
+        let file = H5::open(&output).unwrap();
+        let container = DataContainer::<H5>::open(&file, "obs").unwrap();
+        group.bench_function("H5 series 100 columns", |b| b.iter(||
+            single_thread.install(|| DataFrame::read(&container).unwrap())
+        ));
+        group.bench_function("H5 parallel 100 columns", |b| b.iter(||
+            DataFrame::read(&container).unwrap()
+        ));
+
+        let output = dir.join("test_zarr");
+        let adata = AnnData::<Zarr>::new(&output).unwrap();
+        adata.set_obs(obs).unwrap();
+        adata.close().unwrap();
+        let file = Zarr::open(&output).unwrap();
+        let container = DataContainer::<Zarr>::open(&file, "obs").unwrap();
+        group.bench_function("Zarr series 100 columns", |b| b.iter(||
+            single_thread.install(|| DataFrame::read(&container).unwrap())
+        ));
+        group.bench_function("Zarr parallel 100 columns", |b| b.iter(||
+            DataFrame::read(&container).unwrap()
+        ));
+    });
+    group.finish();
+}
+
+fn bench_dataframe_select(c: &mut Criterion) {
+    let mut group = c.benchmark_group("DataFrame select");
+    group.sample_size(10);
+
+    with_tmp_dir(|dir| {
+        let n = 1000000;
+        let columns = (0..10).map(|i| {
+            let values = Array::random(n, Uniform::new(0.0, 1.0)).to_vec();
+            Series::new(format!("column_{}", i).into(), values).into_column()
+        }).collect();
+        let obs = DataFrame::new(columns).unwrap();
+        let select = [data::SelectInfoElem::from(5000..6000), data::SelectInfoElem::full()];
+
+        let output = dir.join("test_h5");
+        let adata = AnnData::<H5>::new(&output).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.close().unwrap();
+        let file = H5::open(&output).unwrap();
+        let container = DataContainer::<H5>::open(&file, "obs").unwrap();
+        group.bench_function("H5 read then select 1000 rows", |b| b.iter(||
+            Selectable::select(&DataFrame::read(&container).unwrap(), &select)
+        ));
+        group.bench_function("H5 read_select 1000 rows", |b| b.iter(||
+            DataFrame::read_select(&container, &select).unwrap()
+        ));
+
+        let output = dir.join("test_zarr");
+        let adata = AnnData::<Zarr>::new(&output).unwrap();
+        adata.set_obs(obs).unwrap();
+        adata.close().unwrap();
+        let file = Zarr::open(&output).unwrap();
+        let container = DataContainer::<Zarr>::open(&file, "obs").unwrap();
+        group.bench_function("Zarr read then select 1000 rows", |b| b.iter(||
+            Selectable::select(&DataFrame::read(&container).unwrap(), &select)
+        ));
+        group.bench_function("Zarr read_select 1000 rows", |b| b.iter(||
+            DataFrame::read_select(&container, &select).unwrap()
+        ));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_array_io,
+    bench_array_slice,
+    bench_par_read,
+    bench_dataframe_read,
+    bench_dataframe_select
+);
 criterion_main!(benches);
\ No newline at end of file
@@ -372,6 +372,7 @@ pub fn array_select(arr: &ArrayData, select: &[SelectInfoElem]) -> ArrayData {
         ArrayData::Array(data::DynArray::I16(arr)) => dense_array_select(arr, select).into(),
         ArrayData::Array(data::DynArray::I32(arr)) => dense_array_select(arr, select).into(),
         ArrayData::Array(data::DynArray::I64(arr)) => dense_array_select(arr, select).into(),
+        ArrayData::Array(data::DynArray::F16(arr)) => dense_array_select(arr, select).into(),
         ArrayData::Array(data::DynArray::F32(arr)) => dense_array_select(arr, select).into(),
         ArrayData::Array(data::DynArray::F64(arr)) => dense_array_select(arr, select).into(),
         ArrayData::Array(data::DynArray::String(arr)) => dense_array_select(arr, select).into(),
@@ -431,6 +432,9 @@ pub fn array_chunks(
         ArrayData::Array(data::DynArray::I64(arr)) => {
             Box::new(dense_array_chunks(arr, chunk_size).map(|x| x.into()))
         }
+        ArrayData::Array(data::DynArray::F16(arr)) => {
+            Box::new(dense_array_chunks(arr, chunk_size).map(|x| x.into()))
+        }
         ArrayData::Array(data::DynArray::F32(arr)) => {
             Box::new(dense_array_chunks(arr, chunk_size).map(|x| x.into()))
         }
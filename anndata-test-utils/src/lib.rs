@@ -2,11 +2,12 @@ mod common;
 pub use common::*;
 
 use anndata::concat::{concat, JoinType};
-use anndata::{data::CsrNonCanonical, *};
+use anndata::{data::{CsrNonCanonical, DataFrameIndex, SelectInfoElem}, *};
 use data::ArrayConvert;
 use nalgebra_sparse::{CooMatrix, CsrMatrix};
 use ndarray::Array2;
 use proptest::prelude::*;
+use std::collections::HashMap;
 
 pub fn test_basic<B: Backend>() {
     with_tmp_dir(|dir| {
@@ -25,6 +26,2861 @@ pub fn test_basic<B: Backend>() {
     })
 }
 
+pub fn test_stack_outer_join<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        let x1 = Array2::from_shape_fn((3, 3), |(i, j)| (i * 3 + j + 1) as i32);
+        ann1.set_x(&x1).unwrap();
+        ann1.set_var_names(names(&["a", "b", "c"])).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        let x2 = Array2::from_shape_fn((2, 2), |(i, j)| -((i * 2 + j + 1) as i32));
+        ann2.set_x(&x2).unwrap();
+        ann2.set_var_names(names(&["d", "b"])).unwrap();
+
+        let dataset = AnnDataSet::<B>::new_with_join(
+            [("ann1", ann1), ("ann2", ann2)],
+            dir.join("dataset"),
+            "sample",
+            JoinType::Outer,
+            None,
+        )
+        .unwrap();
+        assert_eq!(dataset.var_names(), names(&["a", "b", "c", "d"]));
+        let expected = ndarray::arr2(&[
+            [1, 2, 3, 0],
+            [4, 5, 6, 0],
+            [7, 8, 9, 0],
+            [0, -2, 0, -1],
+            [0, -4, 0, -3],
+        ]);
+        assert_eq!(expected, dataset.x().get::<Array2<i32>>().unwrap().unwrap());
+        for (chunk, start, end) in dataset.x().iter::<Array2<i32>>(2) {
+            assert_eq!(expected.slice(ndarray::s![start..end, ..]), chunk);
+        }
+        let select = [SelectInfoElem::from(vec![4, 0]), SelectInfoElem::from(vec![3, 1])];
+        assert_eq!(
+            ndarray::arr2(&[[-3, -4], [0, 2]]),
+            dataset.x().slice::<Array2<i32>, _>(select).unwrap().unwrap(),
+        );
+        dataset.close().unwrap();
+
+        // The outer join is recovered when reopening the dataset.
+        let dataset = AnnDataSet::<B>::open_with_mode(
+            dir.join("dataset"),
+            OpenMode::ReadOnly,
+            None::<Result<HashMap<String, String>, String>>,
+        )
+        .unwrap();
+        assert_eq!(expected, dataset.x().get::<Array2<i32>>().unwrap().unwrap());
+        dataset.close().unwrap();
+    })
+}
+
+pub fn test_view<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((10, 5), |(i, j)| (i * 5 + j) as i32);
+        adata.set_x(&x).unwrap();
+        adata
+            .set_obs_names((0..10).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+
+        let view = adata
+            .view([SelectInfoElem::from(2..8), SelectInfoElem::full()])
+            .unwrap();
+        let view = view
+            .view([SelectInfoElem::from(vec![0, 3, 5]), SelectInfoElem::from(vec![4, 1])])
+            .unwrap();
+        assert_eq!((view.n_obs(), view.n_vars()), (3, 2));
+        assert_eq!(
+            ndarray::arr2(&[[14, 11], [29, 26], [39, 36]]),
+            view.read::<Array2<i32>>().unwrap().unwrap(),
+        );
+        assert_eq!(
+            view.obs_names().into_vec(),
+            vec!["cell2".to_string(), "cell5".to_string(), "cell7".to_string()],
+        );
+        assert!(view.view([SelectInfoElem::from(vec![3]), SelectInfoElem::full()]).is_err());
+    })
+}
+
+pub fn test_fetch_column<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let obs = polars::df!(
+            "cell_type" => ["a", "b", "a"],
+            "n_genes" => [10u32, 20, 30],
+        )
+        .unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let column = adata.fetch_obs_column("n_genes").unwrap().unwrap();
+        assert_eq!(&column, obs.column("n_genes").unwrap().as_materialized_series());
+        assert!(adata.fetch_obs_column("missing").unwrap().is_none());
+        assert!(adata.fetch_var_column("n_genes").unwrap().is_none());
+    })
+}
+
+pub fn test_write_csv<B: Backend>() {
+    use polars::prelude::{CategoricalOrdering, DataType};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let mut obs = polars::df!(
+            "cell_type" => ["a", "b", "a"],
+            "n_genes" => [10u32, 20, 30],
+        )
+        .unwrap();
+        obs.apply("cell_type", |x| {
+            x.cast(&DataType::Categorical(None, CategoricalOrdering::Physical))
+                .unwrap()
+        })
+        .unwrap();
+        adata.set_obs(obs).unwrap();
+        adata
+            .set_obs_names((0..3).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+
+        adata.write_obs_csv(dir.join("obs.csv")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("obs.csv")).unwrap(),
+            "index,cell_type,n_genes\ncell0,a,10\ncell1,b,20\ncell2,a,30\n",
+        );
+
+        // Large dataframes are written in several batches with a single header.
+        let adata = AnnData::<B>::new(dir.join("large")).unwrap();
+        let obs = polars::df!("n_genes" => (0..25000).collect::<Vec<i32>>()).unwrap();
+        adata.set_obs(obs).unwrap();
+        adata.write_obs_csv(dir.join("large.csv")).unwrap();
+        let csv = std::fs::read_to_string(dir.join("large.csv")).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 25001);
+        assert_eq!(lines[0], "index,n_genes");
+        assert_eq!(lines[10001], "10000,10000");
+        assert_eq!(lines[25000], "24999,24999");
+    })
+}
+
+pub fn test_write_mtx<B: Backend>() {
+    use anndata::reader::MMReader;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i64>(1200, 30, 5000, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata
+            .set_obs_names((0..1200).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+        adata
+            .set_var_names((0..30).map(|i| format!("gene{}", i)).collect())
+            .unwrap();
+        adata.write_mtx(dir.join("matrix.mtx")).unwrap();
+
+        let output = AnnData::<B>::new(dir.join("output")).unwrap();
+        MMReader::from_path(dir.join("matrix.mtx"))
+            .unwrap()
+            .obs_names(dir.join("barcodes.tsv"))
+            .unwrap()
+            .var_names(dir.join("features.tsv"))
+            .unwrap()
+            .finish(&output)
+            .unwrap();
+        assert_eq!(x, output.x().get::<CsrMatrix<i64>>().unwrap().unwrap());
+        assert_eq!(adata.obs_names(), output.obs_names());
+        assert_eq!(adata.var_names(), output.var_names());
+
+        // Dense matrices are converted to coordinates.
+        let dense = Array2::from_shape_fn((4, 3), |(i, j)| if i == j { 1.5 } else { 0.0 });
+        let adata = AnnData::<B>::new(dir.join("dense")).unwrap();
+        adata.set_x(&dense).unwrap();
+        adata.write_mtx(dir.join("matrix.mtx")).unwrap();
+        let output = AnnData::<B>::new(dir.join("dense_output")).unwrap();
+        MMReader::from_path(dir.join("matrix.mtx"))
+            .unwrap()
+            .finish(&output)
+            .unwrap();
+        let x: CsrMatrix<f64> = output.x().get().unwrap().unwrap();
+        assert_eq!(
+            x.triplet_iter().map(|(i, j, v)| (i, j, *v)).collect::<Vec<_>>(),
+            vec![(0, 0, 1.5), (1, 1, 1.5), (2, 2, 1.5)],
+        );
+    })
+}
+
+pub fn test_read_10x_h5<B: Backend>() {
+    use anndata::backend::{GroupOp, StoreOp};
+    use ndarray::Array1;
+
+    with_tmp_dir(|dir| {
+        let x = rand_csr::<i32>(20, 4, 30, 1, 100);
+        let to_i64 = |x: &[usize]| x.iter().map(|&i| i as i64).collect::<Vec<_>>();
+        let to_string = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let ids: Vec<String> = (0..4).map(|i| format!("ENSG{}", i)).collect();
+        let barcodes: Vec<String> = (0..20).map(|i| format!("AAAC{}-1", i)).collect();
+        let feature_types = to_string(&[
+            "Gene Expression",
+            "Gene Expression",
+            "Gene Expression",
+            "Antibody Capture",
+        ]);
+
+        // CellRanger stores the transposed matrix in CSC format.
+        let file = B::new(dir.join("matrix.h5")).unwrap();
+        let matrix = file.new_group("matrix").unwrap();
+        Array1::from(x.values().to_vec()).write(&matrix, "data").unwrap();
+        Array1::from(to_i64(x.col_indices())).write(&matrix, "indices").unwrap();
+        Array1::from(to_i64(x.row_offsets())).write(&matrix, "indptr").unwrap();
+        Array1::from(vec![4i32, 20]).write(&matrix, "shape").unwrap();
+        Array1::from(barcodes.clone()).write(&matrix, "barcodes").unwrap();
+        let features = matrix.new_group("features").unwrap();
+        Array1::from(ids.clone()).write(&features, "id").unwrap();
+        Array1::from(to_string(&["A", "B", "C", "D"])).write(&features, "name").unwrap();
+        Array1::from(feature_types).write(&features, "feature_type").unwrap();
+        file.close().unwrap();
+
+        let adata =
+            AnnData::<B>::from_10x_h5::<B, _, _>(dir.join("matrix.h5"), dir.join("out")).unwrap();
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        assert_eq!(adata.obs_names().into_vec(), barcodes);
+        assert_eq!(adata.var_names().into_vec(), ids);
+        let var = adata.read_var().unwrap();
+        assert_eq!(
+            var.column("feature_type").unwrap().str().unwrap().get(3),
+            Some("Antibody Capture"),
+        );
+    })
+}
+
+pub fn test_loom<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(1100, 20, 2000, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata.layers().add("counts", &x).unwrap();
+        adata
+            .set_obs(polars::df!("n_genes" => (0..1100u32).collect::<Vec<_>>()).unwrap())
+            .unwrap();
+        adata
+            .set_obs_names((0..1100).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+        adata
+            .set_var_names((0..20).map(|i| format!("gene{}", i)).collect())
+            .unwrap();
+        adata.write_loom::<B, _>(dir.join("test.loom")).unwrap();
+
+        let loom =
+            AnnData::<B>::from_loom::<B, _, _>(dir.join("test.loom"), dir.join("out")).unwrap();
+        let mut expected = Array2::<i32>::zeros((1100, 20));
+        x.triplet_iter().for_each(|(i, j, v)| expected[[i, j]] = *v);
+        assert_eq!(expected, loom.x().get::<Array2<i32>>().unwrap().unwrap());
+        assert_eq!(
+            expected,
+            loom.layers().get_item::<Array2<i32>>("counts").unwrap().unwrap()
+        );
+        assert_eq!(adata.obs_names(), loom.obs_names());
+        assert_eq!(adata.var_names(), loom.var_names());
+        assert_eq!(adata.read_obs().unwrap(), loom.read_obs().unwrap());
+    })
+}
+
+pub fn test_layers<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata.add_layer("counts", &x).unwrap();
+        assert!(adata.add_layer("bad", rand_csr::<i32>(10, 4, 20, 1, 100)).is_err());
+        assert_eq!(adata.layers_keys(), vec!["counts".to_string()]);
+        assert_eq!(x, adata.fetch_layer::<CsrMatrix<i32>>("counts").unwrap().unwrap());
+
+        adata.del_layer("counts").unwrap();
+        assert!(adata.layers_keys().is_empty());
+        assert!(adata.fetch_layer::<CsrMatrix<i32>>("counts").unwrap().is_none());
+    })
+}
+
+pub fn test_raw<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata
+            .set_var(polars::df!("highly_variable" => [true, false, true, false, false]).unwrap())
+            .unwrap();
+        adata
+            .set_var_names((0..5).map(|i| format!("gene{}", i)).collect())
+            .unwrap();
+        adata.freeze_raw().unwrap();
+
+        adata
+            .subset([SelectInfoElem::full(), SelectInfoElem::from(vec![0, 2])])
+            .unwrap();
+        assert_eq!(adata.n_vars(), 2);
+        let raw: CsrMatrix<i32> = adata
+            .read_raw_x_slice([SelectInfoElem::full(), SelectInfoElem::full()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw, x);
+
+        // Subsetting observations keeps raw aligned.
+        let obs = SelectInfoElem::from(vec![7, 1, 3]);
+        adata.subset([obs.clone(), SelectInfoElem::full()]).unwrap();
+        let expected = x.select(&[obs, SelectInfoElem::full()]);
+        let raw: CsrMatrix<i32> = adata
+            .read_raw_x_slice([SelectInfoElem::full(), SelectInfoElem::full()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw, expected);
+
+        adata.write::<B, _>(dir.join("copy")).unwrap();
+        let copy = AnnData::<B>::open_with_mode(dir.join("copy"), OpenMode::ReadWrite).unwrap();
+        let raw: CsrMatrix<i32> = copy
+            .read_raw_x_slice([SelectInfoElem::full(), SelectInfoElem::full()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw, expected);
+        assert_eq!(copy.read_raw_var().unwrap().unwrap().height(), 5);
+
+        copy.del_raw().unwrap();
+        assert!(copy.raw().is_none());
+    })
+}
+
+pub fn test_set_x_parallel<B: Backend>() {
+    use rayon::iter::IntoParallelIterator;
+    with_tmp_dir(|dir| {
+        let chunks: Vec<_> = (0..8)
+            .map(|i| rand_csr::<i32>(10 + i, 5, 20, 1, 100))
+            .collect();
+        let adata = AnnData::<B>::new(dir.join("sequential")).unwrap();
+        adata.set_x_from_iter(chunks.clone().into_iter()).unwrap();
+        let expected: CsrMatrix<i32> = adata.x().get().unwrap().unwrap();
+
+        let adata = AnnData::<B>::new(dir.join("parallel")).unwrap();
+        adata.set_x_from_iter_parallel(chunks.into_par_iter()).unwrap();
+        assert_eq!(adata.n_obs(), expected.nrows());
+        assert_eq!(expected, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+    })
+}
+
+pub fn test_cache<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata.get_x().enable_cache();
+        assert!(!adata.get_x().is_cached());
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        assert!(adata.get_x().is_cached());
+
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        adata.set_x(&x).unwrap();
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+
+        let select = [SelectInfoElem::from(vec![1, 3, 5]), SelectInfoElem::full()];
+        let expected: CsrMatrix<i32> = adata.x().slice(&select).unwrap().unwrap();
+        adata.subset(&select).unwrap();
+        assert!(adata.get_x().is_cached());
+        assert_eq!(expected, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+
+        adata.get_x().disable_cache();
+        assert!(!adata.get_x().is_cached());
+        assert_eq!(expected, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        assert!(!adata.get_x().is_cached());
+    })
+}
+
+pub fn test_chunked_cols<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        let x1 = Array2::from_shape_fn((3, 3), |(i, j)| (i * 3 + j + 1) as i32);
+        ann1.set_x(&x1).unwrap();
+        ann1.set_var_names(names(&["a", "b", "c"])).unwrap();
+        let ranges: Vec<_> = ann1
+            .x()
+            .chunked_cols::<Array2<i32>>(2)
+            .map(|x| (x.1, x.2))
+            .collect();
+        assert_eq!(ranges, vec![(0, 2), (2, 3)]);
+        for (chunk, start, end) in ann1.x().chunked_cols::<Array2<i32>>(2) {
+            assert_eq!(x1.slice(ndarray::s![.., start..end]), chunk);
+        }
+
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        let x2 = Array2::from_shape_fn((2, 2), |(i, j)| -((i * 2 + j + 1) as i32));
+        ann2.set_x(&x2).unwrap();
+        ann2.set_var_names(names(&["d", "b"])).unwrap();
+        let dataset = AnnDataSet::<B>::new_with_join(
+            [("ann1", ann1), ("ann2", ann2)],
+            dir.join("dataset"),
+            "sample",
+            JoinType::Outer,
+            None,
+        )
+        .unwrap();
+        let expected: Array2<i32> = dataset.x().get().unwrap().unwrap();
+        let chunked = dataset.x().chunked_cols::<Array2<i32>>(3);
+        assert_eq!(chunked.len(), 2);
+        for (chunk, start, end) in chunked {
+            assert_eq!(expected.slice(ndarray::s![.., start..end]), chunk);
+        }
+        dataset.close().unwrap();
+
+        let adata = AnnData::<B>::new(dir.join("csc")).unwrap();
+        let csc = rand_csc::<i32>(10, 5, 20, 1, 100);
+        adata.set_x(&csc).unwrap();
+        let expected = ArrayData::from(csc);
+        for (chunk, start, end) in adata.x().chunked_cols::<ArrayData>(2) {
+            assert_eq!(expected.select_axis(1, SelectInfoElem::from(start..end)), chunk);
+        }
+    })
+}
+
+pub fn test_chunked_with_obsm<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(rand_csr::<i32>(3, 4, 6, 1, 100)).unwrap();
+        let pca1 = Array2::from_shape_fn((3, 2), |(i, j)| (i * 2 + j) as f64);
+        ann1.obsm().add("X_pca", pca1).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(rand_csr::<i32>(2, 4, 4, 1, 100)).unwrap();
+        let pca2 = Array2::from_shape_fn((2, 2), |(i, j)| -((i * 2 + j) as f64));
+        ann2.obsm().add("X_pca", pca2).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+
+        let x: CsrMatrix<i32> = dataset.x().get().unwrap().unwrap();
+        let pca: Array2<f64> = dataset.obsm().get_item("X_pca").unwrap().unwrap();
+        let chunks = dataset.chunked_with_obsm::<ArrayData>(2, &["X_pca"]).unwrap();
+        assert_eq!(chunks.len(), 3);
+        for (chunk, obsm, start, end) in chunks {
+            let select = SelectInfoElem::from(start..end);
+            assert_eq!(ArrayData::from(x.clone()).select_axis(0, &select), chunk);
+            assert_eq!(obsm.len(), 1);
+            assert_eq!(ArrayData::from(pca.clone()).select_axis(0, &select), obsm[0]);
+        }
+        assert!(dataset.chunked_with_obsm::<ArrayData>(2, &["missing"]).is_err());
+        dataset.close().unwrap();
+    })
+}
+
+pub fn test_transposed_view<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(5, 4, 10, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata.set_obs(polars::df!("n_genes" => [1, 2, 3, 4, 5]).unwrap()).unwrap();
+        adata.obsm().add("pca", Array2::<f64>::zeros((5, 2))).unwrap();
+
+        let view = adata.transposed_view();
+        assert_eq!((view.n_obs(), view.n_vars()), (4, 5));
+        let expected = nalgebra_sparse::CscMatrix::from(&x.transpose());
+        assert_eq!(expected, view.read_x().unwrap().unwrap());
+
+        let select = [SelectInfoElem::from(vec![3, 1]), SelectInfoElem::from(1..4)];
+        let sub: CsrMatrix<i32> = adata
+            .x()
+            .slice([SelectInfoElem::from(1..4), SelectInfoElem::from(vec![3, 1])])
+            .unwrap()
+            .unwrap();
+        let expected = nalgebra_sparse::CscMatrix::from(&sub.transpose());
+        assert_eq!(expected, view.read_x_slice(&select).unwrap().unwrap());
+
+        assert_eq!(view.read_var().unwrap(), adata.read_obs().unwrap());
+        assert_eq!(view.varm().keys(), vec!["pca".to_string()]);
+        assert!(view.obsm().keys().is_empty());
+    })
+}
+
+pub fn test_rename_columns<B: Backend>() {
+    use anndata::backend::GroupOp;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let obs = polars::df!(
+            "celltype" => ["a", "b", "a"],
+            "x" => [1, 2, 3],
+            "y" => [4.0, 5.0, 6.0],
+        )
+        .unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.set_obs_column_attr("celltype", "description", "cell type").unwrap();
+
+        let mapping = |x: &[(&str, &str)]| {
+            x.iter()
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+                .collect::<HashMap<_, _>>()
+        };
+        assert!(adata.rename_obs(&mapping(&[("celltype", "x")])).is_err());
+        assert!(adata.rename_obs(&mapping(&[("missing", "z")])).is_err());
+        adata
+            .rename_obs(&mapping(&[("celltype", "cell_type"), ("x", "y"), ("y", "x")]))
+            .unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let expected = polars::df!(
+            "cell_type" => ["a", "b", "a"],
+            "y" => [1, 2, 3],
+            "x" => [4.0, 5.0, 6.0],
+        )
+        .unwrap();
+        assert_eq!(expected, adata.read_obs().unwrap());
+        assert_eq!(
+            adata.obs_column_attrs("cell_type").unwrap()["description"],
+            "cell type"
+        );
+        adata.close().unwrap();
+
+        // The columns are moved, so no temporary entries are left in the group.
+        let mut names = B::open(dir.join("test")).unwrap().open_group("obs").unwrap().list().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["cell_type", "index", "x", "y"]);
+    })
+}
+
+pub fn test_duplicated_names<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        assert!(adata.set_obs_names_checked(names(&["a", "b", "a"])).is_err());
+        assert_eq!(adata.n_obs(), 0);
+        adata.set_obs_names(names(&["a", "b", "a"])).unwrap();
+        assert_eq!(adata.obs_ix(["a", "b"]).unwrap(), vec![0, 2, 1]);
+        assert!(adata.obs_ix(["c"]).is_err());
+        adata.set_var_names_checked(names(&["x", "y"])).unwrap();
+        assert_eq!(adata.var_ix(["y"]).unwrap(), vec![1]);
+    })
+}
+
+pub fn test_read_x_by_names<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((3, 4), |(i, j)| (i * 4 + j) as i32);
+        adata.set_x(&x).unwrap();
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        adata.set_obs_names(names(&["c1", "c2", "c3"])).unwrap();
+        adata.set_var_names(names(&["g1", "g2", "g3", "g4"])).unwrap();
+
+        let sub: Array2<i32> = adata
+            .read_x_slice_by_names(Some(&["c3", "c1"]), Some(&["g4", "g2"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(sub, ndarray::arr2(&[[11, 9], [3, 1]]));
+        let sub: Array2<i32> = adata
+            .read_x_slice_by_names(None, Some(&["g3"]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(sub, x.slice(ndarray::s![.., 2..3]));
+
+        let err = adata
+            .read_x_slice_by_names::<Array2<i32>>(None, Some(&["g1", "foo", "bar"]))
+            .unwrap_err();
+        assert!(err.to_string().contains("foo, bar"));
+    })
+}
+
+pub fn test_obsm_shape_check<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(Array2::<f64>::zeros((5, 3))).unwrap();
+        adata.obsm().add("pca", Array2::<f64>::zeros((5, 2))).unwrap();
+
+        let err = adata.obsm().add("bad", Array2::<f64>::zeros((4, 2))).unwrap_err();
+        assert!(err.to_string().contains('5'));
+        assert!(adata
+            .set_obsm([
+                ("a".to_string(), Array2::<f64>::zeros((5, 2))),
+                ("b".to_string(), Array2::<f64>::zeros((4, 2))),
+            ])
+            .is_err());
+        assert_eq!(adata.obsm().keys(), vec!["pca".to_string()]);
+        assert!(adata
+            .set_layers([("counts".to_string(), Array2::<f64>::zeros((5, 4)))])
+            .is_err());
+        assert!(adata.varm().add("bad", Array2::<f64>::zeros((5, 2))).is_err());
+        assert!(adata.obsp().add("bad", Array2::<f64>::zeros((5, 4))).is_err());
+        assert_eq!((adata.n_obs(), adata.n_vars()), (5, 3));
+    })
+}
+
+pub fn test_set_obs_unsupported<B: Backend>() {
+    use polars::prelude::{DataType, IntoColumn, NamedFrom, Series};
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let obs = polars::df!("a" => [1, 2, 3], "b" => ["x", "y", "z"]).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+
+        let mut bad = polars::df!("a" => [4, 5, 6]).unwrap();
+        let date = Series::new("d".into(), &[1i32, 2, 3]).cast(&DataType::Date).unwrap();
+        bad.with_column(date.into_column()).unwrap();
+        let err = adata.set_obs(bad).unwrap_err();
+        assert!(format!("{:#}", err).contains("'d'"));
+        assert_eq!(obs, adata.read_obs().unwrap());
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(obs, adata.read_obs().unwrap());
+    })
+}
+
+pub fn test_dataframe_missing_column<B: Backend>() {
+    use anndata::backend::{DataContainer, GroupOp, StoreOp};
+    use polars::prelude::DataFrame;
+
+    with_tmp_dir(|dir| {
+        let file = B::new(dir.join("test")).unwrap();
+        let df = polars::df!("a" => [1, 2, 3], "b" => ["x", "y", "z"]).unwrap();
+        df.write(&file, "df").unwrap();
+        let container = DataContainer::open(&file, "df").unwrap();
+        assert_eq!(DataFrame::read(&container).unwrap(), df);
+
+        let group = file.open_group("df").unwrap();
+        group.delete("b").unwrap();
+        ndarray::Array1::from(vec![1.0, 2.0, 3.0]).write(&group, "c").unwrap();
+        let err = format!("{:#}", DataFrame::read(&container).unwrap_err());
+        assert!(err.contains("not stored: b"));
+        assert!(err.contains("not listed: c"));
+    })
+}
+
+pub fn test_obs_schema<B: Backend>() {
+    use polars::prelude::{DataType, IntoColumn, NamedFrom, Series, TimeUnit};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let mut obs = polars::df!(
+            "count" => [1i32, 2, 3],
+            "score" => [0.5f64, 1.5, 2.5],
+            "label" => ["a", "b", "a"],
+            "flag" => [Some(true), None, Some(false)],
+        )
+        .unwrap();
+        let cluster = Series::new("cluster".into(), &["x", "y", "x"])
+            .cast(&DataType::Categorical(None, Default::default()))
+            .unwrap();
+        let time = Series::new("time".into(), &[0i64, 1, 2])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        obs.with_column(cluster.into_column()).unwrap();
+        obs.with_column(time.into_column()).unwrap();
+        adata.set_obs(obs).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let schema = adata.obs_schema().unwrap();
+        let names: Vec<_> = schema.iter().map(|x| x.0.as_str()).collect();
+        assert_eq!(names, ["count", "score", "label", "flag", "cluster", "time"]);
+        assert_eq!(schema[0].1, DataType::Int32);
+        assert_eq!(schema[1].1, DataType::Float64);
+        assert_eq!(schema[2].1, DataType::String);
+        assert_eq!(schema[3].1, DataType::Boolean);
+        assert!(matches!(schema[4].1, DataType::Categorical(_, _)));
+        assert_eq!(schema[5].1, DataType::Datetime(TimeUnit::Milliseconds, None));
+
+        // The schema agrees with the dtypes of the data.
+        let obs = adata.read_obs().unwrap();
+        for (column, (name, dtype)) in obs.get_columns().iter().zip(&schema) {
+            assert_eq!(column.name().as_str(), name);
+            if dtype.is_categorical() {
+                assert!(column.dtype().is_categorical());
+            } else {
+                assert_eq!(column.dtype(), dtype);
+            }
+        }
+        assert!(adata.var_schema().unwrap().is_empty());
+    })
+}
+
+pub fn test_f16<B: Backend>() {
+    use anndata::data::f16;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((120, 7), |(i, j)| f16::from_f32((i * 7 + j) as f32 / 8.0));
+        adata.set_x(&x).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(x, adata.x().get::<Array2<f16>>().unwrap().unwrap());
+        let widened = x.mapv(|v| v.to_f32());
+        assert_eq!(widened, adata.x().get::<Array2<f32>>().unwrap().unwrap());
+        let arr: ArrayData = adata.x().get().unwrap().unwrap();
+        let arr: Array2<f64> = arr.try_convert().unwrap();
+        assert_eq!(x.mapv(|v| v.to_f64()), arr);
+
+        let slice: Array2<f16> = adata
+            .x()
+            .slice([SelectInfoElem::from(10..20), SelectInfoElem::from(vec![0, 3, 6])])
+            .unwrap()
+            .unwrap();
+        let expected = x.slice(ndarray::s![10..20, ..]).select(ndarray::Axis(1), &[0, 3, 6]);
+        assert_eq!(expected, slice);
+
+        let chunks: Vec<Array2<f16>> = adata.x().chunked(50).map(|(x, _, _)| x).collect();
+        assert_eq!(chunks.len(), 3);
+        let views: Vec<_> = chunks.iter().map(|x| x.view()).collect();
+        assert_eq!(x, ndarray::concatenate(ndarray::Axis(0), &views).unwrap());
+    })
+}
+
+pub fn test_astype<B: Backend>() {
+    use anndata::backend::{Compression, DataType, GroupOp, ScalarType};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((300, 5), |(i, j)| (i * 5 + j) as i32);
+        adata.set_x(&x).unwrap();
+        adata.x().astype(ScalarType::F32, false).unwrap();
+        assert_eq!(adata.x().inner().dtype(), DataType::Array(ScalarType::F32));
+
+        let csr = rand_csr::<i32>(300, 5, 500, 1, 100);
+        adata.layers().add("counts", &csr).unwrap();
+        let counts = adata.layers().get("counts").unwrap();
+        counts.astype(ScalarType::F64, false).unwrap();
+        assert_eq!(counts.inner().dtype(), DataType::CsrMatrix(ScalarType::F64));
+        assert!(counts.astype(ScalarType::F16, false).is_err());
+
+        let wide = Array2::from_shape_fn((300, 2), |(i, j)| {
+            if i == 150 { 1000 } else { (i + j) as i64 % 100 }
+        });
+        adata.obsm().add("wide", &wide).unwrap();
+        let elem = adata.obsm().get("wide").unwrap();
+        assert!(elem.astype(ScalarType::I8, false).is_err());
+        assert_eq!(elem.inner().dtype(), DataType::Array(ScalarType::I64));
+        elem.astype(ScalarType::I8, true).unwrap();
+
+        // The converted array keeps its chunk shape and compression.
+        let options = WriteOptions {
+            block_size: Some(vec![100, 2].as_slice().into()),
+            compression: Some(Compression::Gzip(6)),
+            ..Default::default()
+        };
+        adata.add_obsm_with_opts("chunked", &wide, &options).unwrap();
+        let elem = adata.obsm().get("chunked").unwrap();
+        elem.astype(ScalarType::F32, false).unwrap();
+        let config = elem.inner().write_config().unwrap();
+        assert_eq!(config.block_size, Some(vec![100, 2].into()));
+        if B::NAME == "hdf5" {
+            assert!(matches!(config.compression, Some(Compression::Gzip(6))));
+        }
+        adata.close().unwrap();
+
+        // No temporary datasets are left behind.
+        let file = B::open(dir.join("test")).unwrap();
+        assert!(!file.exists("X.astype").unwrap());
+        assert!(!file.exists("X.astype_old").unwrap());
+        file.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(x.mapv(|v| v as f32), adata.x().get::<Array2<f32>>().unwrap().unwrap());
+        let counts: CsrMatrix<f64> = adata.layers().get_item("counts").unwrap().unwrap();
+        assert_eq!(counts.pattern(), csr.pattern());
+        let values: Vec<f64> = csr.values().iter().map(|v| *v as f64).collect();
+        assert_eq!(counts.values(), values.as_slice());
+        let wide_i8: Array2<i8> = adata.obsm().get_item("wide").unwrap().unwrap();
+        assert_eq!(wide.mapv(|v| v.min(i8::MAX as i64) as i8), wide_i8);
+    })
+}
+
+pub fn test_split_by_obs<B: Backend>() {
+    use polars::prelude::{CategoricalOrdering, DataType};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((5, 3), |(i, j)| (i * 3 + j) as i32);
+        adata.set_x(&x).unwrap();
+        let mut obs = polars::df!("batch" => ["b", "a", "b", "c", "a"]).unwrap();
+        obs.apply("batch", |x| {
+            x.cast(&DataType::Categorical(None, CategoricalOrdering::Physical))
+                .unwrap()
+        })
+        .unwrap();
+        adata.set_obs(obs).unwrap();
+        adata
+            .set_obs_names((0..5).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+        adata.obsm().add("pca", &x.mapv(|v| v as f64)).unwrap();
+
+        let files = adata.split_by_obs::<B, _>("batch", dir.join("split")).unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["b", "a", "c"]);
+        assert_eq!(files["a"], dir.join("split").join("a.h5ad"));
+        for (batch, rows) in [("b", vec![0, 2]), ("a", vec![1, 4]), ("c", vec![3])] {
+            let part = AnnData::<B>::open_with_mode(&files[batch], OpenMode::ReadOnly).unwrap();
+            let expected = x.select(ndarray::Axis(0), &rows);
+            assert_eq!(expected, part.x().get::<Array2<i32>>().unwrap().unwrap());
+            assert_eq!(
+                expected.mapv(|v| v as f64),
+                part.obsm().get_item::<Array2<f64>>("pca").unwrap().unwrap()
+            );
+            let names: Vec<_> = rows.iter().map(|i| format!("cell{}", i)).collect();
+            assert_eq!(part.obs_names().into_vec(), names);
+            assert_eq!(part.read_obs().unwrap().height(), rows.len());
+        }
+
+        assert!(adata.split_by_obs::<B, _>("missing", dir.join("split")).is_err());
+    })
+}
+
+pub fn test_from_dir<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = Array2::from_shape_fn((5, 3), |(i, j)| (i * 3 + j) as i32);
+        adata.set_x(&x).unwrap();
+        adata
+            .set_obs(polars::df!("batch" => ["b", "a", "b", "c", "a"]).unwrap())
+            .unwrap();
+        adata
+            .set_var_names((0..3).map(|i| format!("gene{}", i)).collect())
+            .unwrap();
+        let split = dir.join("split");
+        adata.split_by_obs::<B, _>("batch", &split).unwrap();
+
+        let expected = x.select(ndarray::Axis(0), &[1, 4, 0, 2, 3]);
+        let dataset = AnnDataSet::<B>::from_dir(&split).unwrap();
+        assert_eq!(expected, dataset.x().get::<Array2<i32>>().unwrap().unwrap());
+        dataset.close().unwrap();
+
+        let dataset = AnnDataSet::<B>::open_with_mode(
+            split.join("_dataset.h5ads"),
+            OpenMode::ReadOnly,
+            None::<Result<HashMap<String, String>, String>>,
+        )
+        .unwrap();
+        assert_eq!(expected, dataset.x().get::<Array2<i32>>().unwrap().unwrap());
+        let obs = dataset.read_obs().unwrap();
+        let samples: Vec<_> = obs
+            .column("sample")
+            .unwrap()
+            .as_materialized_series()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(samples, ["a", "a", "b", "b", "c"]);
+        dataset.close().unwrap();
+
+        // The file whose var names differ is named in the error.
+        let other = AnnData::<B>::new(split.join("d.h5ad")).unwrap();
+        other.set_x(&Array2::<i32>::zeros((2, 3))).unwrap();
+        other
+            .set_var_names((0..3).map(|i| format!("other{}", i)).collect())
+            .unwrap();
+        other.close().unwrap();
+        let err = AnnDataSet::<B>::from_dir(&split).err().unwrap();
+        assert!(err.to_string().contains("'d'"));
+    })
+}
+
+pub fn test_progress<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = rand_csr::<i32>(1200, 10, 3000, 1, 100);
+        let chunks: Vec<CsrMatrix<i32>> = (0..3)
+            .map(|i| {
+                let rows = SelectInfoElem::from(i * 400..(i + 1) * 400);
+                x.select(&[rows, SelectInfoElem::full()])
+            })
+            .collect();
+        let mut calls = Vec::new();
+        adata
+            .set_x_from_iter_with_progress(chunks.into_iter(), |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+        assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+
+        let mut calls = Vec::new();
+        adata
+            .write_with_progress::<B, _, _>(dir.join("copy"), |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+        assert_eq!(calls, [(1, 3), (2, 3), (3, 3)]);
+        let copy = AnnData::<B>::open_with_mode(dir.join("copy"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(x, copy.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+
+        let other = AnnData::<B>::new(dir.join("other")).unwrap();
+        other.set_x(&x).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("a", adata), ("b", other)], dir.join("dataset"), "sample")
+                .unwrap();
+        let mut calls = Vec::new();
+        dataset
+            .write_select_with_progress::<B, _, _, _>(
+                [SelectInfoElem::from(1000..1500), SelectInfoElem::full()],
+                dir.join("subset"),
+                |done, total| calls.push((done, total)),
+            )
+            .unwrap();
+        assert_eq!(calls, [(1, 2), (2, 2)]);
+    })
+}
+
+pub fn test_fetch_obsm_slice<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let umap = Array2::from_shape_fn((20, 2), |(i, j)| (i * 2 + j) as f64);
+        let loadings = Array2::from_shape_fn((8, 3), |(i, j)| (i * 3 + j) as f32);
+        let distances = rand_csr::<f64>(20, 20, 60, 0.0, 1.0);
+        let corr = Array2::from_shape_fn((8, 8), |(i, j)| (i * 8 + j) as i32);
+        adata.obsm().add("X_umap", &umap).unwrap();
+        adata.varm().add("PCs", &loadings).unwrap();
+        adata.obsp().add("distances", &distances).unwrap();
+        adata.varp().add("corr", &corr).unwrap();
+
+        let rows = SelectInfoElem::from(vec![3, 0, 17]);
+        let slice: Array2<f64> = adata.fetch_obsm_slice("X_umap", &rows).unwrap().unwrap();
+        assert_eq!(umap.select(ndarray::Axis(0), &[3, 0, 17]), slice);
+        let slice: Array2<f32> = adata
+            .fetch_varm_slice("PCs", SelectInfoElem::from(2..5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(loadings.slice(ndarray::s![2..5, ..]), slice);
+
+        let slice: CsrMatrix<f64> = adata.fetch_obsp_slice("distances", &rows).unwrap().unwrap();
+        assert_eq!(distances.select(&[rows.clone(), rows.clone()]), slice);
+        let slice: Array2<i32> = adata
+            .fetch_varp_slice("corr", SelectInfoElem::from(1..3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ndarray::arr2(&[[9, 10], [17, 18]]), slice);
+
+        assert!(adata.fetch_obsm_slice::<Array2<f64>, _>("missing", &rows).unwrap().is_none());
+        assert!(adata.fetch_obsp_slice::<Array2<f64>, _>("missing", &rows).unwrap().is_none());
+    })
+}
+
+pub fn test_column_stats<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let csr1 = rand_csr::<i32>(700, 20, 2000, 1, 100);
+        let csr2 = rand_csr::<i32>(300, 20, 1000, 1, 100);
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&csr1).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&csr2).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+
+        let mut x = Array2::<f64>::zeros((1000, 20));
+        csr1.triplet_iter().for_each(|(i, j, v)| x[[i, j]] = *v as f64);
+        csr2.triplet_iter().for_each(|(i, j, v)| x[[700 + i, j]] = *v as f64);
+        let sum = x.sum_axis(ndarray::Axis(0));
+        assert_eq!(sum, dataset.x().column_sum().unwrap());
+        let mean = dataset.x().column_mean().unwrap();
+        assert!(mean.iter().zip(sum.iter()).all(|(m, s)| (m - s / 1000.0).abs() < 1e-9));
+
+        // Dense components give the same result.
+        let ann3 = AnnData::<B>::new(dir.join("test3")).unwrap();
+        ann3.set_x(&x).unwrap();
+        let dense =
+            AnnDataSet::<B>::new([("ann3", ann3)], dir.join("dense"), "sample").unwrap();
+        assert_eq!(sum, dense.x().column_sum().unwrap());
+    })
+}
+
+pub fn test_row_stats<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let csr1 = rand_csr::<u32>(700, 20, 2000, 1, 100);
+        let csr2 = rand_csr::<u32>(300, 20, 1000, 1, 100);
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&csr1).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&csr2).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+
+        let mut x = Array2::<f64>::zeros((1000, 20));
+        csr1.triplet_iter().for_each(|(i, j, v)| x[[i, j]] = *v as f64);
+        csr2.triplet_iter().for_each(|(i, j, v)| x[[700 + i, j]] = *v as f64);
+        let nnz = x.map_axis(ndarray::Axis(1), |row| row.iter().filter(|v| **v != 0.0).count());
+        assert_eq!(x.sum_axis(ndarray::Axis(1)), dataset.x().row_sum().unwrap());
+        assert_eq!(nnz, dataset.x().row_nnz().unwrap());
+
+        // The results can be stored in obs directly.
+        let total_counts = dataset.x().row_sum().unwrap().to_vec();
+        let obs = polars::df!("total_counts" => total_counts).unwrap();
+        dataset.set_obs(obs).unwrap();
+        assert_eq!(dataset.read_obs().unwrap().height(), 1000);
+    })
+}
+
+pub fn test_append_obs<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let file = dir.join("dense");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        let x1 = Array2::from_shape_fn((3, 2), |(i, j)| (i * 2 + j) as i32);
+        adata.set_x(&x1).unwrap();
+        adata
+            .set_obs(polars::df!("batch" => ["a", "b", "a"]).unwrap())
+            .unwrap();
+        adata
+            .set_obs_names((0..3).map(|i| format!("cell{}", i)).collect())
+            .unwrap();
+
+        let x2 = Array2::from_shape_fn((2, 2), |(i, j)| -((i * 2 + j) as i32));
+        let rows = polars::df!("index" => ["cell3", "cell4"], "batch" => ["c", "a"]).unwrap();
+        adata.append_obs((&x2).into(), rows).unwrap();
+        assert_eq!(adata.n_obs(), 5);
+
+        // Mismatched var dimension, dtype or columns.
+        let rows = polars::df!("batch" => ["d"]).unwrap();
+        let x = Array2::<i32>::zeros((1, 3));
+        assert!(adata.append_obs((&x).into(), rows.clone()).is_err());
+        let x = Array2::<f64>::zeros((1, 2));
+        assert!(adata.append_obs((&x).into(), rows).is_err());
+        let rows = polars::df!("group" => ["d"]).unwrap();
+        let x = Array2::<i32>::zeros((1, 2));
+        assert!(adata.append_obs((&x).into(), rows).is_err());
+        let rows = polars::df!("batch" => [1i64]).unwrap();
+        assert!(adata.append_obs((&x).into(), rows).is_err());
+        let rows = polars::df!("index" => ["cell5", "cell6"], "batch" => ["d", "d"]).unwrap();
+        assert!(adata.append_obs((&x).into(), rows).is_err());
+        // Rejected rows leave X untouched.
+        assert_eq!(adata.n_obs(), 5);
+        assert_eq!(adata.x().shape().unwrap()[0], 5);
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(&file, OpenMode::ReadOnly).unwrap();
+        let expected = ndarray::concatenate(ndarray::Axis(0), &[x1.view(), x2.view()]).unwrap();
+        assert_eq!(expected, adata.x().get::<Array2<i32>>().unwrap().unwrap());
+        let names: Vec<_> = (0..5).map(|i| format!("cell{}", i)).collect();
+        assert_eq!(adata.obs_names().into_vec(), names);
+        assert_eq!(
+            adata.read_obs().unwrap(),
+            polars::df!("batch" => ["a", "b", "a", "c", "a"]).unwrap()
+        );
+
+        let adata = AnnData::<B>::new(dir.join("csr")).unwrap();
+        let csr1 = rand_csr::<i32>(4, 3, 5, 1, 10);
+        let csr2 = rand_csr::<i32>(2, 3, 4, 1, 10);
+        adata.set_x(&csr1).unwrap();
+        adata
+            .append_obs((&csr2).into(), polars::prelude::DataFrame::empty())
+            .unwrap();
+        let mut coo = CooMatrix::new(6, 3);
+        csr1.triplet_iter().for_each(|(i, j, v)| coo.push(i, j, *v));
+        csr2.triplet_iter().for_each(|(i, j, v)| coo.push(i + 4, j, *v));
+        assert_eq!(
+            CsrMatrix::from(&coo),
+            adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap()
+        );
+        let names: Vec<_> = (0..6).map(|i| i.to_string()).collect();
+        assert_eq!(adata.obs_names().into_vec(), names);
+    })
+}
+
+pub fn test_concurrent_reads<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let arrays: Vec<_> = (0..4)
+            .map(|k| Array2::from_shape_fn((50, 10), |(i, j)| (k * 1000 + i * 10 + j) as f64))
+            .collect();
+        for (k, arr) in arrays.iter().enumerate() {
+            adata.obsm().add(&format!("key{}", k), arr).unwrap();
+        }
+
+        std::thread::scope(|s| {
+            for (k, arr) in arrays.iter().enumerate() {
+                let adata = &adata;
+                s.spawn(move || {
+                    for _ in 0..10 {
+                        let key = format!("key{}", k);
+                        let x = adata.obsm().get_item::<Array2<f64>>(&key).unwrap().unwrap();
+                        assert_eq!(&x, arr);
+                    }
+                });
+            }
+        });
+
+        // A reader holding the lock does not block other readers.
+        let elem = adata.obsm().get("key0").unwrap();
+        let _guard = elem.read();
+        let x = std::thread::scope(|s| {
+            s.spawn(|| elem.get::<Array2<f64>>().unwrap().unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(x, arrays[0]);
+    })
+}
+
+pub fn test_remove_and_rename_arrays<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let file = dir.join("test");
+        let adata = AnnData::<B>::new(&file).unwrap();
+        let x = Array2::from_shape_fn((4, 3), |(i, j)| (i * 3 + j) as f64);
+        let g = Array2::from_shape_fn((4, 4), |(i, j)| (i * 4 + j) as f64);
+        let p = Array2::from_shape_fn((3, 3), |(i, j)| (i * 3 + j) as f64);
+        adata.obsm().add("a", &x).unwrap();
+        adata.obsm().add("b", &x).unwrap();
+        adata.obsp().add("g", &g).unwrap();
+        adata.obsp().add("h", &g).unwrap();
+        adata.varm().add("v", &x.t().to_owned()).unwrap();
+        adata.varp().add("p", &p).unwrap();
+
+        adata.remove_obsm("a").unwrap();
+        adata.remove_obsp("g").unwrap();
+        adata.remove_varm("v").unwrap();
+        adata.remove_varp("p").unwrap();
+        assert_eq!(adata.obsm().keys(), ["b"]);
+        assert_eq!(adata.obsp().keys(), ["h"]);
+        assert!(adata.varm().keys().is_empty());
+        assert!(adata.varp().keys().is_empty());
+
+        adata.obsm().rename("b", "c").unwrap();
+        adata.obsp().rename("h", "g").unwrap();
+        assert!(adata.obsm().rename("missing", "d").is_err());
+        adata.obsm().add("d", &x).unwrap();
+        assert!(adata.obsm().rename("c", "d").is_err());
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(&file, OpenMode::ReadOnly).unwrap();
+        let mut keys = adata.obsm().keys();
+        keys.sort();
+        assert_eq!(keys, ["c", "d"]);
+        assert_eq!(x, adata.obsm().get_item::<Array2<f64>>("c").unwrap().unwrap());
+        assert_eq!(adata.obsp().keys(), ["g"]);
+        assert_eq!(g, adata.obsp().get_item::<Array2<f64>>("g").unwrap().unwrap());
+        assert!(adata.varm().keys().is_empty());
+    })
+}
+
+pub fn test_pairwise_subset<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let dense = Array2::from_shape_fn((10, 10), |(i, j)| (i * 10 + j) as f64);
+        let csr = rand_csr::<f64>(10, 10, 30, 0.0, 1.0);
+        let csc = rand_csc::<f64>(10, 10, 30, 0.0, 1.0);
+        adata.obsp().add("dense", &dense).unwrap();
+        adata.obsp().add("csr", &csr).unwrap();
+        adata.obsp().add("csc", &csc).unwrap();
+
+        let idx = [7, 2, 2, 9];
+        let rows = SelectInfoElem::from(idx.to_vec());
+        adata.subset([rows.clone(), SelectInfoElem::full()]).unwrap();
+        assert_eq!(adata.n_obs(), 4);
+        for key in ["dense", "csr", "csc"] {
+            let shape = adata.obsp().get(key).unwrap().shape().unwrap();
+            assert_eq!(shape.as_ref(), [4, 4]);
+        }
+        assert_eq!(
+            dense.select(ndarray::Axis(0), &idx).select(ndarray::Axis(1), &idx),
+            adata.obsp().get_item::<Array2<f64>>("dense").unwrap().unwrap()
+        );
+        assert_eq!(
+            csr.select(&[rows.clone(), rows.clone()]),
+            adata.obsp().get_item::<CsrMatrix<f64>>("csr").unwrap().unwrap()
+        );
+        assert_eq!(
+            Selectable::select(&csc, &[rows.clone(), rows.clone()]),
+            adata
+                .obsp()
+                .get_item::<nalgebra_sparse::CscMatrix<f64>>("csc")
+                .unwrap()
+                .unwrap()
+        );
+    })
+}
+
+pub fn test_nullable_columns<B: Backend>() {
+    use anndata::backend::{GroupOp, StoreOp};
+    use polars::prelude::DataFrame;
+
+    with_tmp_dir(|dir| {
+        let obs = polars::df!(
+            "count" => [Some(1i64), None, Some(3), None],
+            "flag" => [None, Some(true), Some(false), Some(true)],
+        )
+        .unwrap();
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.obsm().add("annot", obs.clone()).unwrap();
+
+        // Partial reads keep the missing values.
+        let select = [SelectInfoElem::from(vec![3, 1, 0]), SelectInfoElem::full()];
+        let annot: DataFrame = adata.obsm().get_item_slice("annot", select).unwrap().unwrap();
+        let expected = polars::df!(
+            "count" => [None, None, Some(1i64)],
+            "flag" => [Some(true), Some(true), None],
+        )
+        .unwrap();
+        assert!(annot.equals_missing(&expected));
+        adata.close().unwrap();
+
+        // Masks stored as integers are accepted.
+        let file = B::open_rw(dir.join("test")).unwrap();
+        let group = file.open_group("obs").unwrap().open_group("count").unwrap();
+        group.delete("mask").unwrap();
+        ndarray::Array1::from(vec![0u8, 1, 0, 1]).write(&group, "mask").unwrap();
+        let adata = AnnData::<B>::open_store(file, OpenMode::ReadWrite).unwrap();
+        assert!(adata.read_obs().unwrap().equals_missing(&obs));
+    })
+}
+
+pub fn test_uns_scalars<B: Backend>() {
+    use anndata::backend::{GroupOp, StoreOp};
+    use anndata::data::{f16, DynScalar};
+
+    with_tmp_dir(|dir| {
+        let scalars = vec![
+            DynScalar::I8(-8),
+            DynScalar::I16(-16),
+            DynScalar::I32(-32),
+            DynScalar::I64(-64),
+            DynScalar::U8(8),
+            DynScalar::U16(16),
+            DynScalar::U32(32),
+            DynScalar::U64(64),
+            DynScalar::F16(f16::from_f32(0.5)),
+            DynScalar::F32(0.25),
+            DynScalar::F64(0.125),
+            DynScalar::Bool(true),
+            DynScalar::String("threshold".to_string()),
+        ];
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        for (i, x) in scalars.iter().enumerate() {
+            adata.uns().add(&format!("s{}", i), x.clone()).unwrap();
+        }
+        adata.uns().add("min_genes", 0.5f64).unwrap();
+        adata.close().unwrap();
+
+        // Scalars written without encoding attributes are read as well.
+        let file = B::open_rw(dir.join("test")).unwrap();
+        file.open_group("uns").unwrap().new_scalar_dataset("raw", &7u32).unwrap();
+        let adata = AnnData::<B>::open_store(file, OpenMode::ReadWrite).unwrap();
+        for (i, x) in scalars.into_iter().enumerate() {
+            let key = format!("s{}", i);
+            assert_eq!(adata.uns().get_item::<DynScalar>(&key).unwrap(), Some(x));
+        }
+        assert_eq!(adata.uns().get_item::<f64>("min_genes").unwrap(), Some(0.5));
+        assert_eq!(adata.uns().get_item::<String>("s12").unwrap().unwrap(), "threshold");
+        assert_eq!(adata.uns().get_item::<u32>("raw").unwrap(), Some(7));
+        assert!(adata.uns().get_item::<f64>("s0").is_err());
+    })
+}
+
+pub fn test_uns_nested<B: Backend>() {
+    use anndata::data::Mapping;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let params: Mapping = [
+            ("n_neighbors", Data::from(15i64)),
+            ("method", Data::from("umap".to_string())),
+        ]
+        .into_iter()
+        .collect();
+        let neighbors: Mapping = [
+            ("params", Data::from(params.clone())),
+            ("connectivities_key", Data::from("connectivities".to_string())),
+        ]
+        .into_iter()
+        .collect();
+        adata.uns().add("neighbors", neighbors).unwrap();
+        adata.uns().add("neighbors/params/metric", "euclidean".to_string()).unwrap();
+        adata.uns().add("pca/params/zero_center", true).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadWrite).unwrap();
+        let mut expected = params;
+        expected.insert_path("metric", "euclidean".to_string().into()).unwrap();
+        assert_eq!(adata.uns().get_item::<Mapping>("neighbors/params").unwrap(), Some(expected));
+        assert_eq!(adata.uns().get_item::<i64>("neighbors/params/n_neighbors").unwrap(), Some(15));
+        assert_eq!(adata.uns().get_item::<bool>("pca/params/zero_center").unwrap(), Some(true));
+        assert!(adata.uns().get_item::<i64>("neighbors/missing").unwrap().is_none());
+        assert!(adata.uns().add("neighbors/params/n_neighbors/x", 1i64).is_err());
+
+        adata.uns().remove("neighbors/params").unwrap();
+        let neighbors: Mapping = adata.uns().get_item("neighbors").unwrap().unwrap();
+        assert_eq!(neighbors.keys().collect::<Vec<_>>(), vec!["connectivities_key"]);
+
+        let copied = adata.copy::<B, _>(dir.join("copy")).unwrap();
+        assert_eq!(copied.uns().get_item::<bool>("pca/params/zero_center").unwrap(), Some(true));
+    })
+}
+
+pub fn test_write_with_manifest<B: Backend>() {
+    use polars::prelude::DataFrame;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("input")).unwrap();
+        adata.set_x(&rand_csr::<i32>(20, 8, 50, 1, 100)).unwrap();
+        adata.obsm().add("X_pca", Array2::<f64>::zeros((20, 3))).unwrap();
+        adata.varp().add("corr", Array2::<f32>::zeros((8, 8))).unwrap();
+        adata.write_with_manifest::<B, _>(dir.join("output")).unwrap();
+
+        let output = AnnData::<B>::open_with_mode(dir.join("output"), OpenMode::ReadOnly).unwrap();
+        let manifest: DataFrame = output.uns().get_item("_schema").unwrap().unwrap();
+        let column = |name: &str| -> Vec<String> {
+            let column = manifest.column(name).unwrap().as_materialized_series();
+            column.str().unwrap().into_no_null_iter().map(|x| x.to_string()).collect()
+        };
+        assert_eq!(column("element"), vec!["X", "obsm/X_pca", "varp/corr"]);
+        assert_eq!(column("shape"), vec!["20 x 8", "20 x 3", "8 x 8"]);
+        let pca = output.obsm().get("X_pca").unwrap();
+        assert_eq!(column("dtype")[1], pca.dtype().unwrap().to_string());
+    })
+}
+
+pub fn test_builder<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let x = rand_csr::<i32>(10, 4, 20, 1, 100);
+        let obs = polars::df!("n_genes" => vec![1i64; 10]).unwrap();
+        let builder = AnnDataBuilder::new()
+            .x(&x)
+            .obs(obs.clone())
+            .var_names(["a", "b", "c", "d"].into_iter().map(|x| x.to_string()).collect())
+            .obsm("X_pca", Array2::<f64>::zeros((10, 2)))
+            .obsp("distances", rand_csr::<f32>(10, 10, 15, 0.0, 1.0))
+            .layer("counts", &x)
+            .uns("min_genes", 3i64);
+
+        // Mismatched dimensions are reported before anything is written.
+        let err = builder
+            .clone()
+            .varm("loadings", Array2::<f64>::zeros((5, 2)))
+            .build::<B, _>(dir.join("bad"))
+            .unwrap_err();
+        assert!(err.to_string().contains("varm 'loadings' has 5 variables"), "{}", err);
+        assert!(!dir.join("bad").exists());
+        let err = builder
+            .clone()
+            .obsp("graph", Array2::<f64>::zeros((10, 9)))
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("must be square"));
+        let err = builder
+            .clone()
+            .obsm("scalar", ndarray::arr0(1.0f64).into_dyn())
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("at least one dimension"), "{}", err);
+        let err = builder
+            .clone()
+            .layer("flat", ndarray::Array1::<i32>::zeros(10).into_dyn())
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("must be two-dimensional"), "{}", err);
+
+        let adata = builder.build::<B, _>(dir.join("test")).unwrap();
+        adata.close().unwrap();
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(adata.n_obs(), 10);
+        assert_eq!(adata.n_vars(), 4);
+        assert_eq!(adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap(), x);
+        assert_eq!(adata.read_obs().unwrap(), obs);
+        assert_eq!(adata.var_names().into_vec(), vec!["a", "b", "c", "d"]);
+        assert_eq!(adata.obsm().keys(), vec!["X_pca".to_string()]);
+        assert_eq!(adata.obsp().keys(), vec!["distances".to_string()]);
+        assert_eq!(adata.layers().keys(), vec!["counts".to_string()]);
+        assert_eq!(adata.uns().get_item::<i64>("min_genes").unwrap(), Some(3));
+    })
+}
+
+pub fn test_structured_errors<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(Array2::<f64>::zeros((5, 3))).unwrap();
+
+        let err = adata.obsm().add("bad", Array2::<f64>::zeros((4, 2))).unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::DimMismatch { expected: 5, found: 4 }),
+        );
+        let err = adata.obs_ix(["cell"]).unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::key_not_found("cell", "obs_names")),
+        );
+        let err = adata.obsm().rename("pca", "X_pca").unwrap_err();
+        assert!(matches!(AnnDataError::find(&err), Some(AnnDataError::KeyNotFound { .. })));
+        adata.set_x(rand_csr::<f64>(5, 3, 4, 0.0, 1.0)).unwrap();
+        let err = adata.x().astype(anndata::backend::ScalarType::F16, false).unwrap_err();
+        assert!(matches!(AnnDataError::find(&err), Some(AnnDataError::UnsupportedDtype(_))));
+        adata.close().unwrap();
+
+        // A plain AnnData file is not an AnnDataSet.
+        let err = AnnDataSet::<B>::open_with_mode(
+            dir.join("test"),
+            OpenMode::ReadOnly,
+            None::<Result<HashMap<String, String>, String>>,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::MissingUns("AnnDataSet".to_string())),
+        );
+    })
+}
+
+pub fn test_open_mode<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let x = rand_csr::<i32>(10, 5, 20, 1, 100);
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        assert_eq!(adata.mode(), OpenMode::ReadWrite);
+        adata.set_x(&x).unwrap();
+        adata.set_obs(polars::df!("n_genes" => (0..10u32).collect::<Vec<_>>()).unwrap()).unwrap();
+        adata.obsm().add("pca", Array2::<f64>::zeros((10, 2))).unwrap();
+        adata.uns().add("version", 1i64).unwrap();
+        adata.close().unwrap();
+
+        let is_read_only = |err: anyhow::Error| {
+            matches!(AnnDataError::find(&err), Some(AnnDataError::ReadOnly(_)))
+        };
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(adata.mode(), OpenMode::ReadOnly);
+        assert!(is_read_only(adata.set_x(&x).unwrap_err()));
+        assert!(is_read_only(adata.del_x().unwrap_err()));
+        let names: DataFrameIndex = (0..10).map(|i| i.to_string()).collect();
+        assert!(is_read_only(adata.set_obs_names(names).unwrap_err()));
+        assert!(is_read_only(adata.obsm().add("umap", Array2::<f64>::zeros((10, 2))).unwrap_err()));
+        assert!(is_read_only(adata.obsm().remove("pca").unwrap_err()));
+        assert!(is_read_only(adata.uns().add("version", 2i64).unwrap_err()));
+        assert!(is_read_only(adata.del_obsm().unwrap_err()));
+        assert!(is_read_only(
+            adata.x().astype(anndata::backend::ScalarType::F64, false).unwrap_err()
+        ));
+        let pca = adata.obsm().get("pca").unwrap();
+        assert!(is_read_only(
+            pca.astype(anndata::backend::ScalarType::F32, false).unwrap_err()
+        ));
+        assert!(is_read_only(
+            adata.get_obs().inner().set_column_attr("n_genes", "unit", "count").unwrap_err()
+        ));
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        assert_eq!(adata.obsm().keys(), vec!["pca"]);
+        assert_eq!(adata.uns().get_item::<i64>("version").unwrap(), Some(1));
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open(B::open_rw(dir.join("test")).unwrap()).unwrap();
+        assert_eq!(adata.mode(), OpenMode::ReadWrite);
+        adata.obsm().add("umap", Array2::<f64>::zeros((10, 2))).unwrap();
+        adata.uns().add("version", 2i64).unwrap();
+        assert_eq!(adata.uns().get_item::<i64>("version").unwrap(), Some(2));
+    })
+}
+
+pub fn test_open_partial<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&Array2::from_elem((3, 2), 1)).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&Array2::from_elem((2, 2), 2)).unwrap();
+        AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+            .unwrap()
+            .close()
+            .unwrap();
+        std::fs::rename(dir.join("test2"), dir.join("moved")).unwrap();
+
+        let file = dir.join("dataset");
+        let no_update = None::<Result<HashMap<String, String>, String>>;
+        assert!(
+            AnnDataSet::<B>::open_with_mode(&file, OpenMode::ReadWrite, no_update.clone()).is_err()
+        );
+        // A file that exists but cannot be opened is an error, not a missing child.
+        std::fs::write(dir.join("test2"), b"not an AnnData file").unwrap();
+        assert!(
+            AnnDataSet::<B>::open_partial(&file, OpenMode::ReadWrite, no_update.clone()).is_err()
+        );
+        std::fs::remove_file(dir.join("test2")).unwrap();
+        let mut dataset =
+            AnnDataSet::<B>::open_partial(&file, OpenMode::ReadWrite, no_update).unwrap();
+        assert_eq!(dataset.missing_children().len(), 1);
+        assert_eq!(dataset.missing_children()[0].0, "ann2");
+        assert_eq!(dataset.n_obs(), 5);
+        assert!(dataset.x().shape().is_none());
+        let missing = |err: anyhow::Error| {
+            assert_eq!(
+                AnnDataError::find(&err),
+                Some(&AnnDataError::MissingChildren(vec!["ann2".to_string()])),
+            );
+        };
+        missing(dataset.write_mtx(dir.join("x.mtx")).unwrap_err());
+        // Reading X fails instead of returning nothing.
+        missing(dataset.read_x_dyn().unwrap_err());
+        let rows = SelectInfoElem::from(0..2);
+        missing(dataset.x().slice_axis::<ArrayData, _>(0, rows).unwrap_err());
+        missing(dataset.x().try_chunked::<ArrayData>(2).err().unwrap());
+        missing(dataset.x().row_sum().unwrap_err());
+        assert!(!dataset.validate().unwrap().is_valid());
+        // Lazy obs columns cannot be stacked without all the objects.
+        dataset.set_lazy_obs(true);
+        assert!(dataset.fetch_obs_column("sample").unwrap().is_some());
+        let err = dataset.fetch_obs_column("n_genes").unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::MissingChildren(vec!["ann2".to_string()])),
+        );
+
+        let moved = HashMap::from([("ann2".to_string(), dir.join("moved"))]);
+        dataset.update_anndata_locations(moved).unwrap();
+        assert!(dataset.missing_children().is_empty());
+        assert_eq!(dataset.x().shape().as_ref().unwrap().as_ref(), &[5, 2]);
+        dataset.close().unwrap();
+
+        // The repaired locations are stored in the file.
+        let dataset = AnnDataSet::<B>::open_with_mode(
+            dir.join("dataset"),
+            OpenMode::ReadOnly,
+            None::<Result<HashMap<String, String>, String>>,
+        )
+        .unwrap();
+        assert_eq!(dataset.x().shape().as_ref().unwrap().as_ref(), &[5, 2]);
+        dataset.close().unwrap();
+    })
+}
+
+pub fn test_relocate_dataset<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let data = dir.join("a").join("data");
+        std::fs::create_dir_all(&data).unwrap();
+        let ann1 = AnnData::<B>::new(data.join("test1")).unwrap();
+        ann1.set_x(&Array2::from_elem((3, 2), 1)).unwrap();
+        let ann2 = AnnData::<B>::new(data.join("test2")).unwrap();
+        ann2.set_x(&Array2::from_elem((2, 2), 2)).unwrap();
+        let dataset = AnnDataSet::<B>::new(
+            [("ann1", ann1), ("ann2", ann2)],
+            dir.join("a").join("dataset"),
+            "sample",
+        )
+        .unwrap();
+        dataset.make_paths_relative().unwrap();
+        let locations: polars::prelude::DataFrame =
+            dataset.get_anno().uns().get_item("AnnDataSet").unwrap().unwrap();
+        let paths: Vec<_> = locations
+            .column("file_path")
+            .unwrap()
+            .as_materialized_series()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(std::path::PathBuf::from)
+            .collect();
+        let expected: Vec<_> = ["test1", "test2"]
+            .iter()
+            .map(|x| std::path::Path::new("data").join(x))
+            .collect();
+        assert_eq!(paths, expected);
+        dataset.close().unwrap();
+
+        // Relative locations survive moving the whole directory.
+        std::fs::rename(dir.join("a"), dir.join("b")).unwrap();
+        let no_update = None::<Result<HashMap<String, String>, String>>;
+        let file = dir.join("b").join("dataset");
+        let dataset = AnnDataSet::<B>::open_with_mode(&file, OpenMode::ReadOnly, no_update.clone()).unwrap();
+        assert_eq!(dataset.x().shape().as_ref().unwrap().as_ref(), &[5, 2]);
+        dataset.close().unwrap();
+
+        // Moving only the AnnData files is repaired by rebasing.
+        std::fs::rename(dir.join("b").join("data"), dir.join("moved")).unwrap();
+        let mut dataset =
+            AnnDataSet::<B>::open_partial(&file, OpenMode::ReadWrite, no_update).unwrap();
+        assert_eq!(dataset.missing_children().len(), 2);
+        dataset.rebase(dir.join("moved")).unwrap();
+        assert!(dataset.missing_children().is_empty());
+        assert_eq!(dataset.x().shape().as_ref().unwrap().as_ref(), &[5, 2]);
+        dataset.close().unwrap();
+    })
+}
+
+pub fn test_index_unique<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&Array2::<i32>::zeros((2, 3))).unwrap();
+        ann1.set_obs_names(names(&["AAAC-1", "AAAG-1"])).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&Array2::<i32>::zeros((1, 3))).unwrap();
+        ann2.set_obs_names(names(&["AAAC-1"])).unwrap();
+
+        let dataset = AnnDataSet::<B>::new_with_join(
+            [("a", ann1), ("b", ann2)],
+            dir.join("dataset"),
+            "sample",
+            JoinType::Inner,
+            Some("_"),
+        )
+        .unwrap();
+        assert_eq!(dataset.obs_names(), names(&["AAAC-1_a", "AAAG-1_a", "AAAC-1_b"]));
+        dataset.close().unwrap();
+    })
+}
+
+pub fn test_range_index<B: Backend>() {
+    use anndata::backend::{AttributeOp, DatasetOp, GroupOp};
+    use ndarray::{Array1, Ix1};
+
+    // The index type and the names stored in the index dataset.
+    let read_index = |path: std::path::PathBuf, name: &str| -> (String, Array1<String>) {
+        let file = B::open(path).unwrap();
+        let dataset = file.open_group("obs").unwrap().open_dataset(name).unwrap();
+        (dataset.get_attr("index_type").unwrap(), dataset.read_array::<String, Ix1>().unwrap())
+    };
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((4, 2))).unwrap();
+        let names = DataFrameIndex::from(0..4);
+        adata.set_obs_names(names.clone()).unwrap();
+        adata.close().unwrap();
+        // The names are still written as strings for Python anndata.
+        let (index_type, stored) = read_index(dir.join("test"), "index");
+        assert_eq!(index_type, "range");
+        assert_eq!(stored.to_vec(), vec!["0", "1", "2", "3"]);
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadWrite).unwrap();
+        assert_eq!(adata.obs_names(), names);
+        assert_eq!(adata.obs_ix(["3"]).unwrap(), vec![3]);
+        // Lists are not checked for range-like names.
+        let list: DataFrameIndex = (0..4).map(|i| i.to_string()).collect();
+        adata.set_obs_names(list).unwrap();
+        adata.close().unwrap();
+        assert_eq!(read_index(dir.join("test"), "index").0, "list");
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadWrite).unwrap();
+        let names: DataFrameIndex = (1..5).map(|i| i.to_string()).collect();
+        adata.set_obs_names(names.clone()).unwrap();
+        adata.close().unwrap();
+        assert_eq!(read_index(dir.join("test"), "index").0, "list");
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(adata.obs_names(), names);
+    })
+}
+
+pub fn test_verify<B: Backend>() {
+    use anndata::backend::{AttributeOp, GroupOp, StoreOp};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((3, 2))).unwrap();
+        adata.set_obs(polars::df!("a" => [1, 2, 3]).unwrap()).unwrap();
+        adata.obsm().add("pca", &Array2::<f64>::zeros((3, 4))).unwrap();
+        adata.obsp().add("graph", &Array2::<f64>::zeros((3, 3))).unwrap();
+        assert!(adata.verify().unwrap().is_valid());
+        adata.close().unwrap();
+
+        let file = B::open_rw(dir.join("test")).unwrap();
+        let obs = file.open_group("obs").unwrap();
+        ndarray::Array1::from(vec![1, 2, 3]).write(&obs, "b").unwrap();
+        let nested = file.open_group("uns").unwrap().new_group("nested").unwrap();
+        let mut data = nested.new_scalar_dataset("value", &1u32).unwrap();
+        data.new_attr("encoding-type", "unknown").unwrap();
+        file.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let report = adata.verify().unwrap();
+        assert_eq!(report.problems.len(), 2, "{}", report);
+        assert!(report.problems.iter().any(|x| x.contains("/obs") && x.contains(": b")));
+        assert!(report.problems.iter().any(|x| x.contains("/uns/nested/value")));
+    })
+}
+
+pub fn test_iter_sparse_rows<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let csr = |n: usize, offsets: Vec<usize>, indices: Vec<usize>, values: Vec<f32>| {
+            CsrMatrix::try_from_csr_data(n, 4, offsets, indices, values).unwrap()
+        };
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&csr(3, vec![0, 2, 2, 3], vec![0, 3, 2], vec![1.0, 2.0, 3.0])).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&csr(2, vec![0, 1, 3], vec![1, 0, 1], vec![4.0, 5.0, 6.0])).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+
+        // Rows are numbered across the stacked elements.
+        let rows: Vec<(usize, Vec<usize>, Vec<f64>)> = dataset
+            .x()
+            .iter_sparse_rows(2)
+            .unwrap()
+            .map(|x| x.map(|(i, row)| (i, row.indices().to_vec(), row.values().to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = vec![
+            (0, vec![0, 3], vec![1.0, 2.0]),
+            (1, vec![], vec![]),
+            (2, vec![2], vec![3.0]),
+            (3, vec![1], vec![4.0]),
+            (4, vec![0, 1], vec![5.0, 6.0]),
+        ];
+        assert_eq!(rows, expected);
+
+        // Rows of dense arrays hold every column.
+        let adata = AnnData::<B>::new(dir.join("dense")).unwrap();
+        adata.set_x(&ndarray::arr2(&[[1, 0, 2], [0, 0, 3]])).unwrap();
+        let rows: Vec<(usize, Vec<usize>, Vec<f64>)> = adata
+            .x()
+            .iter_sparse_rows(1)
+            .unwrap()
+            .map(|x| x.map(|(i, row)| (i, row.indices().to_vec(), row.values().to_vec())))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected = vec![
+            (0, vec![0, 1, 2], vec![1.0, 0.0, 2.0]),
+            (1, vec![0, 1, 2], vec![0.0, 0.0, 3.0]),
+        ];
+        assert_eq!(rows, expected);
+
+        let adata = AnnData::<B>::new(dir.join("str")).unwrap();
+        adata.set_x(&Array2::from_elem((2, 2), "a".to_string())).unwrap();
+        assert!(adata.x().iter_sparse_rows(1).is_err());
+    })
+}
+
+pub fn test_rechunk_x<B: Backend>() {
+    use anndata::backend::{Compression, DataType, DatasetOp, GroupOp, ScalarType};
+    use nalgebra_sparse::CscMatrix;
+
+    with_tmp_dir(|dir| {
+        // More rows than are read at a time, so X is copied in several chunks.
+        let dense = Array2::from_shape_fn((2500, 3), |(i, j)| (i * 3 + j) as i32);
+        let adata = AnnData::<B>::new(dir.join("dense")).unwrap();
+        let options = WriteOptions {
+            block_size: Some(vec![10, 3].as_slice().into()),
+            compression: Some(Compression::Gzip(6)),
+            ..Default::default()
+        };
+        adata.set_x_with_opts(&dense, &options).unwrap();
+        assert!(adata.rechunk_x(vec![0usize, 3]).is_err());
+        adata.rechunk_x(vec![500usize, 3]).unwrap();
+        assert_eq!(adata.x().inner().dtype(), DataType::Array(ScalarType::I32));
+        assert_eq!(adata.x().get::<Array2<i32>>().unwrap().unwrap(), dense);
+        adata.close().unwrap();
+
+        // The compression is kept and no temporary datasets are left behind.
+        let file = B::open(dir.join("dense")).unwrap();
+        let config = file.open_dataset("X").unwrap().write_config().unwrap();
+        assert_eq!(config.block_size, Some(vec![500, 3].into()));
+        if B::NAME == "hdf5" {
+            assert!(matches!(config.compression, Some(Compression::Gzip(6))));
+        }
+        assert!(!file.exists("__X_rechunked").unwrap());
+        assert!(!file.exists("__X_old").unwrap());
+        file.close().unwrap();
+
+        let csr = CsrMatrix::try_from_csr_data(
+            3, 4, vec![0, 2, 2, 3], vec![0, 3, 2], vec![1u16, 2, 3],
+        ).unwrap();
+        let adata = AnnData::<B>::new(dir.join("csr")).unwrap();
+        adata.set_x(&csr).unwrap();
+        adata.rechunk_x(2usize).unwrap();
+        assert_eq!(adata.x().inner().dtype(), DataType::CsrMatrix(ScalarType::U16));
+        assert_eq!(adata.x().get::<CsrMatrix<u16>>().unwrap().unwrap(), csr);
+        adata.close().unwrap();
+
+        // CSC matrices are copied by columns and stay in the CSC format.
+        let csc = CscMatrix::from(&csr);
+        let adata = AnnData::<B>::new(dir.join("csc")).unwrap();
+        adata.set_x(&csc).unwrap();
+        adata.rechunk_x(2usize).unwrap();
+        assert_eq!(adata.x().inner().dtype(), DataType::CscMatrix(ScalarType::U16));
+        assert_eq!(adata.x().get::<CscMatrix<u16>>().unwrap().unwrap(), csc);
+        adata.close().unwrap();
+
+        // Rechunking needs a writable file.
+        let adata = AnnData::<B>::open_with_mode(dir.join("csr"), OpenMode::ReadOnly).unwrap();
+        assert!(adata.rechunk_x(2usize).is_err());
+    })
+}
+
+pub fn test_string_columns<B: Backend>() {
+    use anndata::backend::{DataContainer, EncodingType, GroupOp, StoreOp};
+    use polars::prelude::{DataType, IntoColumn, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        // A high-cardinality text column, where every value is distinct.
+        let n = 2000;
+        let descriptions: Vec<String> =
+            (0..n).map(|i| format!("sample {} from donor {}", i, i % 7)).collect();
+        let description = Series::new("description".into(), descriptions.clone());
+        let groups: Vec<String> = (0..n).map(|i| format!("g{}", i % 3)).collect();
+        let group = Series::new("group".into(), groups)
+            .cast(&DataType::Categorical(None, Default::default()))
+            .unwrap();
+        let columns = vec![description.into_column(), group.into_column()];
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_obs(polars::frame::DataFrame::new(columns).unwrap()).unwrap();
+        adata.close().unwrap();
+
+        let file = B::open(dir.join("test")).unwrap();
+        let obs = file.open_group("obs").unwrap();
+        let encoding = |name: &str| {
+            DataContainer::<B>::open(&obs, name).unwrap().encoding().unwrap()
+        };
+        assert_eq!(encoding("description"), EncodingType::StringArray);
+        assert_eq!(encoding("group"), EncodingType::Categorical);
+        file.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let obs = adata.read_obs().unwrap();
+        let description = obs.column("description").unwrap();
+        assert_eq!(description.dtype(), &DataType::String);
+        let values: Vec<String> = description
+            .as_materialized_series()
+            .str()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.unwrap().to_string())
+            .collect();
+        assert_eq!(values, descriptions);
+        assert!(matches!(obs.column("group").unwrap().dtype(), DataType::Categorical(..)));
+    })
+}
+
+pub fn test_read_obs_columns<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let obs = polars::df!(
+            "a" => [1, 2, 3],
+            "b" => ["x", "y", "z"],
+            "c" => [0.5, 1.5, 2.5],
+        )
+        .unwrap();
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.close().unwrap();
+
+        // Columns are returned in the requested order.
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let df = adata.read_obs_columns(&["c", "a"]).unwrap();
+        assert_eq!(df, obs.select(["c", "a"]).unwrap());
+        assert_eq!(adata.read_var_columns(&[]).unwrap().width(), 0);
+
+        let err = adata.read_obs_columns(&["a", "x", "y"]).unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::KeyNotFound {
+                keys: vec!["x".to_string(), "y".to_string()],
+                collection: "obs".to_string(),
+            })
+        );
+    })
+}
+
+pub fn test_uns_entries<B: Backend>() {
+    use anndata::backend::EncodingType;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.uns().add("title", "pbmc".to_string()).unwrap();
+        adata.uns().add("counts", Array2::<i32>::zeros((2, 3))).unwrap();
+        adata.uns().add("groups", polars::df!("a" => [1, 2, 3], "b" => ["x", "y", "z"]).unwrap())
+            .unwrap();
+        adata.uns().add("neighbors/params/k", 15i64).unwrap();
+        adata.uns().add("neighbors/metric", "cosine".to_string()).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let entries = adata.uns_entries().unwrap();
+        let expected: Vec<(String, EncodingType, data::Shape)> = vec![
+            ("counts".into(), EncodingType::Array, vec![2, 3].into()),
+            ("groups".into(), EncodingType::DataFrame, vec![3, 2].into()),
+            ("neighbors".into(), EncodingType::Dict, 2.into()),
+            ("neighbors/metric".into(), EncodingType::String, Vec::new().into()),
+            ("neighbors/params".into(), EncodingType::Dict, 1.into()),
+            ("neighbors/params/k".into(), EncodingType::NumericScalar, Vec::new().into()),
+            ("title".into(), EncodingType::String, Vec::new().into()),
+        ];
+        assert_eq!(entries, expected);
+    })
+}
+
+pub fn test_read_x_dyn<B: Backend>() {
+    use anndata::backend::ScalarType;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        assert!(adata.read_x_dyn().unwrap().is_none());
+        assert!(adata.x_dtype().is_err());
+
+        let dense = Array2::from_shape_fn((3, 2), |(i, j)| (i + j) as f32);
+        adata.set_x(&dense).unwrap();
+        assert_eq!(adata.x_dtype().unwrap(), ScalarType::F32);
+        assert_eq!(adata.read_x_dyn().unwrap().unwrap(), ArrayData::from(dense));
+
+        let csr = CsrMatrix::try_from_csr_data(
+            2, 3, vec![0, 1, 2], vec![2, 0], vec![7u16, 8],
+        ).unwrap();
+        adata.set_x(&csr).unwrap();
+        assert_eq!(adata.x_dtype().unwrap(), ScalarType::U16);
+        assert_eq!(adata.read_x_dyn().unwrap().unwrap(), ArrayData::from(csr));
+    })
+}
+
+pub fn test_display<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((3, 2))).unwrap();
+        adata.set_obs(polars::df!("cell_type" => ["a", "b", "a"]).unwrap()).unwrap();
+        adata.set_var(polars::df!("gene_id" => ["g1", "g2"]).unwrap()).unwrap();
+        adata.uns().add("version", 1i64).unwrap();
+        adata.uns().add("title", "pbmc".to_string()).unwrap();
+        adata.obsm().add("X_pca", &Array2::<f64>::zeros((3, 2))).unwrap();
+        adata.varp().add("corr", &Array2::<f64>::zeros((2, 2))).unwrap();
+        adata.layers().add("counts", &Array2::<i32>::zeros((3, 2))).unwrap();
+
+        let expected = format!(
+            "AnnData object with n_obs x n_vars = 3 x 2 backed at '{}'\n    \
+            obs: 'cell_type'\n    var: 'gene_id'\n    uns: 'title', 'version'\n    \
+            obsm: 'X_pca'\n    varp: 'corr'\n    layers: 'counts'",
+            adata.filename().display(),
+        );
+        assert_eq!(adata.to_string(), expected);
+        assert_eq!(format!("{:?}", adata), expected);
+    })
+}
+
+pub fn test_obsm_shape<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.obsm().add("X_pca", &Array2::<f64>::zeros((4, 30))).unwrap();
+        adata.varm().add("PCs", &Array2::<f64>::zeros((5, 30))).unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        assert_eq!(adata.obsm_shape("X_pca"), Some(vec![4, 30].into()));
+        assert_eq!(adata.varm_shape("PCs"), Some(vec![5, 30].into()));
+        assert_eq!(adata.obsm_shape("X_umap"), None);
+        assert_eq!(adata.varm_shape("X_pca"), None);
+    })
+}
+
+pub fn test_add_obs_column<B: Backend>() {
+    use polars::prelude::{NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.add_obs_column("n_genes", Series::new("".into(), &[5i32, 7, 9])).unwrap();
+        adata.add_obs_column("sample", Series::new("".into(), &["a", "b", "a"])).unwrap();
+        adata.set_obs_column_attr("n_genes", "unit", "genes").unwrap();
+
+        // Replacing a column keeps its position and attributes.
+        adata.add_obs_column("n_genes", Series::new("".into(), &[1.5f64, 2.5, 3.5])).unwrap();
+        let err = adata.add_obs_column("bad", Series::new("".into(), &[1i32])).unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::DimMismatch { expected: 3, found: 1 })
+        );
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(dir.join("test"), OpenMode::ReadOnly).unwrap();
+        let expected = polars::df!(
+            "n_genes" => [1.5f64, 2.5, 3.5],
+            "sample" => ["a", "b", "a"],
+        )
+        .unwrap();
+        assert_eq!(adata.read_obs().unwrap(), expected);
+        assert_eq!(adata.obs_column_attrs("n_genes").unwrap()["unit"], "genes");
+    })
+}
+
+pub fn test_legacy_sparse<B: Backend>() {
+    use anndata::backend::{
+        AttributeOp, DataContainer, DataType, EncodingType, GroupOp, ScalarType,
+    };
+    use nalgebra_sparse::CscMatrix;
+
+    with_tmp_dir(|dir| {
+        let csr = CsrMatrix::try_from_csr_data(
+            3,
+            4,
+            vec![0, 2, 2, 3],
+            vec![0, 3, 2],
+            vec![1.0f32, 2.0, 3.0],
+        )
+        .unwrap();
+        let csc = CscMatrix::from(&csr);
+        let to_i32 = |x: &[usize]| x.iter().map(|&i| i as i32).collect::<Vec<_>>();
+
+        // Layout written by anndata < 0.7: no `encoding-type`, and the format and
+        // shape in the `h5sparse_format` and `h5sparse_shape` attributes.
+        let file = B::new(dir.join("test")).unwrap();
+        for (name, offsets, indices, values) in [
+            ("csr", csr.row_offsets(), csr.col_indices(), csr.values()),
+            ("csc", csc.col_offsets(), csc.row_indices(), csc.values()),
+        ] {
+            let mut group = file.new_group(name).unwrap();
+            group.new_attr("h5sparse_format", name).unwrap();
+            group.new_attr("h5sparse_shape", vec![3i64, 4]).unwrap();
+            ndarray::Array1::from(values.to_vec()).write(&group, "data").unwrap();
+            ndarray::Array1::from(to_i32(indices)).write(&group, "indices").unwrap();
+            ndarray::Array1::from(to_i32(offsets)).write(&group, "indptr").unwrap();
+        }
+        let mut group = file.new_group("coo").unwrap();
+        group.new_attr("h5sparse_format", "coo").unwrap();
+
+        let container = DataContainer::<B>::open(&file, "csr").unwrap();
+        assert_eq!(container.encoding().unwrap(), EncodingType::CsrMatrix);
+        assert_eq!(container.encoding_type().unwrap(), DataType::CsrMatrix(ScalarType::F32));
+        assert_eq!(CsrMatrix::<f32>::get_shape(&container).unwrap(), (3usize, 4usize).into());
+        assert_eq!(ArrayData::read(&container).unwrap(), ArrayData::from(csr.clone()));
+        let rows = [SelectInfoElem::from(1..3), SelectInfoElem::full()];
+        assert_eq!(
+            CsrMatrix::<f32>::read_select(&container, &rows).unwrap(),
+            CsrMatrix::try_from_csr_data(2, 4, vec![0, 0, 1], vec![2], vec![3.0f32]).unwrap(),
+        );
+
+        let container = DataContainer::<B>::open(&file, "csc").unwrap();
+        assert_eq!(container.encoding().unwrap(), EncodingType::CscMatrix);
+        assert_eq!(CscMatrix::<f32>::read(&container).unwrap(), csc);
+
+        let container = DataContainer::<B>::open(&file, "coo").unwrap();
+        assert!(container.encoding().is_err());
+    })
+}
+
+pub fn test_join_obs<B: Backend>() {
+    use polars::prelude::{DataFrame, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((4, 2))).unwrap();
+        adata.set_obs_names((0..4).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata.add_obs_column("sample", Series::new("".into(), &["a", "b", "a", "c"])).unwrap();
+
+        let meta = polars::df!(
+            "barcode" => ["cell2", "cell0", "cell9"],
+            "score" => [0.5, 1.5, 2.5],
+            "batch" => [2i32, 1, 3],
+        )
+        .unwrap();
+        adata.join_obs(meta.clone(), "barcode").unwrap();
+        let obs = adata.read_obs().unwrap();
+        let batch: Vec<_> = obs.column("batch").unwrap().i32().unwrap().into_iter().collect();
+        assert_eq!(batch, [Some(1), None, Some(2), None]);
+        // Missing floats are stored as NaN.
+        let score = obs.column("score").unwrap().f64().unwrap();
+        assert_eq!(score.get(0), Some(1.5));
+        assert!(score.get(1).unwrap().is_nan());
+
+        // Existing columns are not overwritten.
+        let err = adata.join_obs(meta, "barcode").unwrap_err();
+        assert!(format!("{:#}", err).contains("columns already exist in obs: batch, score"));
+
+        // Nothing is written if a column cannot be stored.
+        let mut bad = polars::df!("barcode" => ["cell1", "cell3"], "tissue" => ["x", "y"]).unwrap();
+        bad.with_column(Series::new("raw".into(), &[b"x".as_ref(), b"y".as_ref()])).unwrap();
+        assert!(adata.join_obs(bad, "barcode").is_err());
+        assert!(adata.fetch_obs_column("tissue").unwrap().is_none());
+
+        // Duplicated keys, matched against an obs column.
+        let samples = polars::df!("id" => ["a", "b", "a"], "donor" => ["x", "y", "z"]).unwrap();
+        assert!(adata.join_obs(samples.clone(), "id").is_err());
+        let options = JoinOptions {
+            left_on: Some("sample".into()),
+            ..Default::default()
+        };
+        let err = adata.join_obs_with(samples.clone(), "id", &options).unwrap_err();
+        assert!(format!("{:#}", err).contains("duplicated keys in column 'id': a"));
+        let options = JoinOptions { allow_duplicates: true, ..options };
+        adata.join_obs_with(samples, "id", &options).unwrap();
+        let donor: DataFrame = adata.read_obs_columns(&["donor"]).unwrap();
+        assert_eq!(
+            donor,
+            polars::df!("donor" => [Some("x"), Some("y"), Some("x"), None]).unwrap()
+        );
+    })
+}
+
+pub fn test_bool_sparse<B: Backend>() {
+    use anndata::backend::{DataType, ScalarType};
+    use nalgebra_sparse::CscMatrix;
+
+    with_tmp_dir(|dir| {
+        // A symmetric adjacency matrix of 4 observations.
+        let adj = CsrMatrix::try_from_csr_data(
+            4,
+            4,
+            vec![0, 2, 4, 5, 6],
+            vec![1, 2, 0, 3, 0, 1],
+            vec![true; 6],
+        )
+        .unwrap();
+        let mask = CscMatrix::from(&adj);
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<f32>::zeros((4, 4))).unwrap();
+        adata.obsp().add("connectivities", &adj).unwrap();
+        adata.layers().add("mask", &mask).unwrap();
+
+        let elem = adata.obsp().get("connectivities").unwrap();
+        assert_eq!(elem.dtype(), Some(DataType::CsrMatrix(ScalarType::Bool)));
+        assert_eq!(
+            adata.obsp().get_item::<CsrMatrix<bool>>("connectivities").unwrap(),
+            Some(adj.clone())
+        );
+        let rows = [SelectInfoElem::from(vec![3, 0]), SelectInfoElem::from(1..4)];
+        let expected =
+            CsrMatrix::try_from_csr_data(2, 3, vec![0, 1, 3], vec![0, 0, 1], vec![true; 3])
+                .unwrap();
+        let sliced: ArrayData =
+            adata.obsp().get_item_slice("connectivities", &rows).unwrap().unwrap();
+        assert_eq!(sliced, ArrayData::from(expected.clone()));
+        assert_eq!(ArrayData::from(adj.clone()).select(&rows), sliced);
+        let sliced: CscMatrix<bool> =
+            adata.layers().get_item_slice("mask", &rows).unwrap().unwrap();
+        assert_eq!(sliced, CscMatrix::from(&expected));
+
+        adata.subset([SelectInfoElem::from(vec![0, 1, 3]), SelectInfoElem::full()]).unwrap();
+        let elem = adata.obsp().get("connectivities").unwrap();
+        assert_eq!(elem.dtype(), Some(DataType::CsrMatrix(ScalarType::Bool)));
+        assert_eq!(elem.shape(), Some((3usize, 3usize).into()));
+
+        // Weighted graphs are converted to masks by thresholding.
+        let weights = CsrMatrix::try_from_csr_data(
+            2,
+            2,
+            vec![0, 2, 3],
+            vec![0, 1, 1],
+            vec![0.2f64, 0.8, 0.6],
+        )
+        .unwrap();
+        let expected =
+            CsrMatrix::try_from_csr_data(2, 2, vec![0, 1, 2], vec![1, 1], vec![true; 2]).unwrap();
+        assert_eq!(ArrayData::from(weights).to_mask(0.5).unwrap(), ArrayData::from(expected));
+    })
+}
+
+pub fn test_negative_slice<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let arr = Array2::from_shape_fn((6, 3), |(i, j)| (i * 3 + j) as i32);
+        let csr = rand_csr::<i32>(6, 3, 10, 1, 100);
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&arr).unwrap();
+        adata.layers().add("counts", &csr).unwrap();
+
+        let last = [SelectInfoElem::slice_from_end(2), SelectInfoElem::full()];
+        let expected = [SelectInfoElem::from(4..6), SelectInfoElem::full()];
+        let x: Array2<i32> = adata.x().slice(&last).unwrap().unwrap();
+        assert_eq!(x, arr.select(&expected));
+        let counts: CsrMatrix<i32> =
+            adata.layers().get_item_slice("counts", &last).unwrap().unwrap();
+        assert_eq!(counts, csr.select(&expected));
+
+        // Bounds beyond the axis are clamped.
+        let all = [SelectInfoElem::slice_from_end(100), SelectInfoElem::from(-2isize..)];
+        let x: Array2<i32> = adata.x().slice(&all).unwrap().unwrap();
+        assert_eq!(x, arr.select(&[SelectInfoElem::full(), SelectInfoElem::from(1..3)]));
+
+        adata.subset([SelectInfoElem::from(-3isize..-1), SelectInfoElem::full()]).unwrap();
+        assert_eq!(adata.n_obs(), 2);
+        let x: Array2<i32> = adata.x().get().unwrap().unwrap();
+        assert_eq!(x, arr.select(&[SelectInfoElem::from(3..5), SelectInfoElem::full()]));
+    })
+}
+
+pub fn test_fetch_graph<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        // 0 -> 1 (1.0), 0 -> 2 (4.0), 1 -> 0 (3.0), 2 -> 1 (2.0)
+        let graph = CsrMatrix::try_from_csr_data(
+            3,
+            3,
+            vec![0, 2, 3, 4],
+            vec![1, 2, 0, 1],
+            vec![1.0f32, 4.0, 3.0, 2.0],
+        )
+        .unwrap();
+        adata.obsp().add("distances", &graph).unwrap();
+        let dense = |csr: CsrMatrix<f64>| {
+            let mut arr = Array2::<f64>::zeros((csr.nrows(), csr.ncols()));
+            csr.triplet_iter().for_each(|(i, j, v)| arr[[i, j]] = *v);
+            arr
+        };
+
+        let raw = adata.fetch_graph("distances", None).unwrap();
+        assert_eq!(raw.nnz(), 4);
+        assert_eq!(raw.values(), &[1.0, 4.0, 3.0, 2.0]);
+
+        let max = adata.fetch_graph("distances", Some(SymMode::Max)).unwrap();
+        assert_eq!(
+            dense(max),
+            ndarray::array![[0.0, 3.0, 4.0], [3.0, 0.0, 2.0], [4.0, 2.0, 0.0]],
+        );
+        let min = adata.fetch_graph("distances", Some(SymMode::Min)).unwrap();
+        assert_eq!(min.nnz(), 2);
+        assert_eq!(
+            dense(min),
+            ndarray::array![[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+        );
+        let mean = adata.fetch_graph("distances", Some(SymMode::Mean)).unwrap();
+        assert_eq!(
+            dense(mean),
+            ndarray::array![[0.0, 2.0, 2.0], [2.0, 0.0, 1.0], [2.0, 1.0, 0.0]],
+        );
+
+        // Dense graphs keep their nonzero entries.
+        let arr = ndarray::array![[0i32, 5, 0], [0, 0, 0], [0, 0, 0]];
+        adata.obsp().add("connectivities", &arr).unwrap();
+        let graph = adata.fetch_graph("connectivities", None).unwrap();
+        assert_eq!((graph.nnz(), graph.values()), (1, &[5.0][..]));
+        let graph = adata.fetch_graph("connectivities", Some(SymMode::Max)).unwrap();
+        assert_eq!(
+            dense(graph),
+            ndarray::array![[0.0, 5.0, 0.0], [5.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+        );
+
+        let err = adata.fetch_graph("missing", None).unwrap_err();
+        assert_eq!(
+            AnnDataError::find(&err),
+            Some(&AnnDataError::key_not_found("missing", "obsp"))
+        );
+    })
+}
+
+pub fn test_index_name<B: Backend>() {
+    use polars::prelude::{DataFrame, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let path = dir.join("test");
+        let adata = AnnData::<B>::new(&path).unwrap();
+        let names: DataFrameIndex = (0..3).map(|i| format!("cell{}", i)).collect();
+        assert_eq!(names.index_name, "index");
+        adata.set_obs_names(names.with_name("cell_id")).unwrap();
+        // Replacing the columns keeps the index and its name.
+        let obs = DataFrame::new(vec![Series::new("n".into(), &[1, 2, 3]).into()]).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.set_obs(obs).unwrap();
+        adata.set_var_names((0..2).map(|i| format!("gene{}", i)).collect()).unwrap();
+        adata.set_var_index_name("gene_ids").unwrap();
+        adata.close().unwrap();
+
+        let adata = AnnData::<B>::open_with_mode(&path, OpenMode::ReadWrite).unwrap();
+        assert_eq!(adata.obs_names().index_name, "cell_id");
+        assert_eq!(adata.obs_names().into_vec(), ["cell0", "cell1", "cell2"]);
+        assert_eq!(adata.var_names().index_name, "gene_ids");
+        adata.add_obs_column("m", Series::new("".into(), &[0.5, 1.5, 2.5])).unwrap();
+
+        let copy = dir.join("copy");
+        adata.write::<B, _>(&copy).unwrap();
+        let subset = dir.join("subset");
+        let select = [SelectInfoElem::from(vec![0, 2]), SelectInfoElem::full()];
+        adata.write_select::<B, _, _>(select, &subset).unwrap();
+        for path in [copy, subset] {
+            let adata = AnnData::<B>::open_with_mode(&path, OpenMode::ReadOnly).unwrap();
+            assert_eq!(adata.obs_names().index_name, "cell_id");
+            assert_eq!(adata.var_names().index_name, "gene_ids");
+        }
+
+        // Renaming the index keeps the names.
+        adata.set_obs_index_name("barcode").unwrap();
+        assert_eq!(adata.obs_names().index_name, "barcode");
+        assert_eq!(adata.obs_names().into_vec(), ["cell0", "cell1", "cell2"]);
+        assert_eq!(adata.read_obs().unwrap().get_column_names(), ["n", "m"]);
+    })
+}
+
+pub fn test_to_dense_sparse<B: Backend>() {
+    use nalgebra_sparse::CscMatrix;
+
+    with_tmp_dir(|dir| {
+        let csr = rand_csr::<i32>(10, 5, 20, 1, 100);
+        let dense = ArrayData::from(csr.clone()).to_dense().unwrap();
+        let arr: Array2<i32> = dense.clone().try_into().unwrap();
+        assert_eq!(arr.iter().filter(|x| **x != 0).count(), csr.nnz());
+        csr.triplet_iter().for_each(|(i, j, v)| assert_eq!(arr[[i, j]], *v));
+        assert_eq!(dense.clone().to_dense().unwrap(), dense);
+        assert_eq!(dense.to_sparse(0.0).unwrap(), ArrayData::from(csr.clone()));
+
+        // Small values are dropped whatever their sign, and sparse matrices
+        // keep their format.
+        let values = ndarray::array![[0.0, 0.05, -2.0], [-0.01, 1.0, 0.0]];
+        let expected =
+            CsrMatrix::try_from_csr_data(2, 3, vec![0, 1, 2], vec![2, 1], vec![-2.0, 1.0])
+                .unwrap();
+        let sparse = ArrayData::from(values.clone()).to_sparse(0.1).unwrap();
+        assert_eq!(sparse, ArrayData::from(expected.clone()));
+        let all: CsrMatrix<f64> =
+            ArrayData::from(values).to_sparse(0.0).unwrap().try_into().unwrap();
+        assert_eq!(all.nnz(), 4);
+        let pruned = ArrayData::from(CscMatrix::from(&all)).to_sparse(0.1).unwrap();
+        assert_eq!(pruned, ArrayData::from(CscMatrix::from(&expected)));
+
+        let mask = ndarray::array![[true, false], [false, false]];
+        let mask: CsrMatrix<bool> =
+            ArrayData::from(mask).to_sparse(0.0).unwrap().try_into().unwrap();
+        assert_eq!(mask.triplet_iter().collect::<Vec<_>>(), [(0, 0, &true)]);
+
+        assert!(ArrayData::from(ndarray::array![1.0, 2.0]).to_sparse(0.0).is_err());
+        assert!(ArrayData::from(ndarray::array![[1.0]]).to_sparse(-1.0).is_err());
+
+        // Normalize the representation of each chunk.
+        let adata = AnnData::<B>::new(dir.join("sparse")).unwrap();
+        adata.set_x(&csr).unwrap();
+        let copy = AnnData::<B>::new(dir.join("dense")).unwrap();
+        let chunks = adata.x().iter::<ArrayData>(3).map(|(x, _, _)| x.to_dense().unwrap());
+        copy.set_x_from_iter(chunks).unwrap();
+        assert_eq!(copy.x().get::<Array2<i32>>().unwrap().unwrap(), arr);
+        let chunks = copy.x().iter::<ArrayData>(4).map(|(x, _, _)| x.to_sparse(0.0).unwrap());
+        adata.set_x_from_iter(chunks).unwrap();
+        assert_eq!(adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap(), csr);
+    })
+}
+
+pub fn test_set_obsm_batch<B: Backend>() {
+    use anndata::backend::Compression;
+    use polars::prelude::{DataFrame, DataType, IntoColumn, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let umap = Array2::<f64>::zeros((3, 2));
+        let options = WriteOptions {
+            block_size: Some(vec![2, 2].as_slice().into()),
+            compression: Some(Compression::Gzip(6)),
+            ..Default::default()
+        };
+        adata.add_obsm_with_opts("X_umap", &umap, &options).unwrap();
+
+        let batch = |pca: Array2<f64>| {
+            HashMap::from([
+                ("X_umap".to_string(), ArrayData::from(Array2::<f64>::ones((3, 2)))),
+                ("X_pca".to_string(), ArrayData::from(pca)),
+            ])
+        };
+        // Shapes are checked before anything is written.
+        let err = adata.set_obsm_batch(batch(Array2::zeros((4, 5)))).unwrap_err();
+        assert!(format!("{:#}", err).contains("obsm 'X_pca'"));
+        assert_eq!(adata.obsm().keys(), ["X_umap"]);
+
+        // A failed write rolls back the arrays written before it.
+        let dates = Series::new("date".into(), &[1i32, 2, 3]).cast(&DataType::Date).unwrap();
+        let mut data = batch(Array2::zeros((3, 5)));
+        data.insert("meta".into(), DataFrame::new(vec![dates.into_column()]).unwrap().into());
+        let failed = data.clone();
+        assert!(adata.set_obsm_batch(data).is_err());
+        assert_eq!(adata.obsm().keys(), ["X_umap"]);
+        assert_eq!(adata.obsm().get_item::<Array2<f64>>("X_umap").unwrap().unwrap(), umap);
+        // The restored array keeps its chunk shape and compression.
+        let config = adata.obsm().get("X_umap").unwrap().inner().write_config().unwrap();
+        assert_eq!(config.block_size, Some(vec![2, 2].into()));
+        if B::NAME == "hdf5" {
+            assert!(matches!(config.compression, Some(Compression::Gzip(6))));
+        }
+
+        // The number of observations set by a failed batch is unset again.
+        let empty = AnnData::<B>::new(dir.join("empty")).unwrap();
+        assert!(empty.set_obsm_batch(failed).is_err());
+        assert!(empty.obsm().keys().is_empty());
+        empty.obsm().add("X_pca", Array2::<f64>::zeros((5, 2))).unwrap();
+        assert_eq!(empty.n_obs(), 5);
+
+        adata.set_obsm_batch(batch(Array2::zeros((3, 5)))).unwrap();
+        let mut keys = adata.obsm().keys();
+        keys.sort();
+        assert_eq!(keys, ["X_pca", "X_umap"]);
+        assert_eq!(
+            adata.obsm().get_item::<Array2<f64>>("X_umap").unwrap().unwrap(),
+            Array2::<f64>::ones((3, 2))
+        );
+    })
+}
+
+pub fn test_filter_expr<B: Backend>() {
+    use polars::prelude::{col, lit, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((4, 3))).unwrap();
+        adata.set_obs_names((0..4).map(|i| format!("cell{}", i)).collect()).unwrap();
+        adata.set_obs(polars::df!(
+            "n_genes" => [100, 300, 250, 50],
+            "sample" => ["a", "b", "c", "b"],
+        ).unwrap()).unwrap();
+        adata.set_var(polars::df!("highly_variable" => [true, false, true]).unwrap()).unwrap();
+
+        let cells = adata.filter_obs_expr(col("n_genes").gt(lit(200))).unwrap();
+        assert_eq!(cells, [1, 2]);
+        let samples = lit(Series::new("".into(), &["b", "c"]));
+        let cells = adata.filter_obs_expr(col("sample").is_in(samples)).unwrap();
+        assert_eq!(cells, [1, 2, 3]);
+        let expr = col("sample").eq(lit("b")).and(col("n_genes").lt(lit(100)));
+        assert_eq!(adata.filter_obs_expr(expr).unwrap(), [3]);
+        let genes = adata.filter_var_expr(col("highly_variable")).unwrap();
+        assert_eq!(genes, [0, 2]);
+
+        assert!(adata.filter_obs_expr(col("n_genes")).is_err());
+        assert!(adata.filter_obs_expr(col("missing").gt(lit(0))).is_err());
+
+        let cells = adata.filter_obs_expr(col("sample").neq(lit("a"))).unwrap();
+        adata.subset([SelectInfoElem::from(cells), SelectInfoElem::from(genes)]).unwrap();
+        assert_eq!(adata.obs_names().into_vec(), ["cell1", "cell2", "cell3"]);
+        assert_eq!(adata.n_vars(), 2);
+    })
+}
+
+pub fn test_key_order<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let path = dir.join("test");
+        let adata = AnnData::<B>::new(&path).unwrap();
+        for key in ["X_umap", "b", "X_pca", "a"] {
+            adata.obsm().add(key, &Array2::<f64>::zeros((3, 2))).unwrap();
+            adata.uns().add(key, 1i32).unwrap();
+        }
+        assert_eq!(adata.obsm().keys(), ["X_umap", "b", "X_pca", "a"]);
+        assert_eq!(adata.uns().keys(), ["X_umap", "b", "X_pca", "a"]);
+
+        // Replacing an array keeps its position, a renamed array takes the
+        // position of the old key.
+        adata.obsm().add("b", &Array2::<f64>::ones((3, 2))).unwrap();
+        adata.obsm().remove("X_umap").unwrap();
+        adata.obsm().rename("b", "c").unwrap();
+        adata.uns().remove("b").unwrap();
+        assert_eq!(adata.obsm().keys(), ["c", "X_pca", "a"]);
+        assert_eq!(adata.uns().keys(), ["X_umap", "X_pca", "a"]);
+
+        // Arrays read from a file are ordered by their keys.
+        adata.write::<B, _>(dir.join("copy")).unwrap();
+        adata.close().unwrap();
+        for path in [path, dir.join("copy")] {
+            let adata = AnnData::<B>::open_with_mode(&path, OpenMode::ReadOnly).unwrap();
+            assert_eq!(adata.obsm().keys(), ["X_pca", "a", "c"]);
+            assert_eq!(adata.uns().keys(), ["X_pca", "X_umap", "a"]);
+        }
+    })
+}
+
+pub fn test_dataframe_read_select<B: Backend>() {
+    use anndata::backend::{AttributeOp, DataContainer, GroupOp};
+    use polars::prelude::{col, DataType, IntoColumn, IntoLazy, NamedFrom, Series, TimeUnit};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let time = Series::new("time".into(), &[0i64, 1, 2, 3, 4])
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+            .unwrap();
+        let group = Series::new("group".into(), &["a", "b", "a", "c", "b"])
+            .cast(&DataType::Categorical(None, Default::default()))
+            .unwrap();
+        let mut obs = polars::df!(
+            "value" => [0.5f64, 1.5, 2.5, 3.5, 4.5],
+            "count" => [Some(1i32), None, Some(3), None, Some(5)],
+            "label" => ["v", "w", "x", "y", "z"],
+        )
+        .unwrap();
+        obs.with_column(time.into_column()).unwrap();
+        obs.with_column(group.into_column()).unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        adata.close().unwrap();
+        // Categories are compared by their values.
+        let uncategorize = |df: polars::frame::DataFrame| {
+            df.lazy()
+                .with_column(col("group").cast(DataType::String))
+                .collect()
+                .unwrap()
+        };
+
+        let file = B::open(dir.join("test")).unwrap();
+        let container = DataContainer::<B>::open(&file, "obs").unwrap();
+        for rows in [
+            SelectInfoElem::from(1..4),
+            SelectInfoElem::from(vec![4, 0, 4]),
+            SelectInfoElem::from(Vec::<usize>::new()),
+        ] {
+            let select = [rows, SelectInfoElem::full()];
+            let df = polars::frame::DataFrame::read_select(&container, &select).unwrap();
+            let expected = Selectable::select(&obs, &select);
+            assert!(uncategorize(df).equals_missing(&uncategorize(expected)));
+        }
+
+        // Columns can be selected as well.
+        let select = [SelectInfoElem::from(vec![3, 1]), SelectInfoElem::from(vec![4, 1])];
+        let df = polars::frame::DataFrame::read_select(&container, &select).unwrap();
+        assert_eq!(df.get_column_names(), ["group", "count"]);
+        let expected = Selectable::select(&obs, &select);
+        assert!(uncategorize(df).equals_missing(&uncategorize(expected)));
+
+        // Only the selected codes are read, but all categories are kept.
+        let select = [SelectInfoElem::from(vec![0, 2]), SelectInfoElem::from(vec![4])];
+        let df = polars::frame::DataFrame::read_select(&container, &select).unwrap();
+        let group = df.column("group").unwrap().as_materialized_series();
+        let group = group.categorical().unwrap();
+        assert_eq!(group.iter_str().flatten().collect::<Vec<_>>(), ["a", "a"]);
+        let categories = group.get_rev_map().get_categories();
+        assert_eq!(categories.values_iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+
+        // Codes written by pandas are usually int8.
+        let file = B::new(dir.join("int8")).unwrap();
+        let mut cat = file.new_group("cat").unwrap();
+        cat.new_attr("encoding-type", "categorical").unwrap();
+        cat.new_attr("encoding-version", "0.2.0").unwrap();
+        cat.new_attr("ordered", false).unwrap();
+        ndarray::arr1(&[0i8, 2, -1, 1]).write(&cat, "codes").unwrap();
+        let categories = ndarray::Array1::from(vec!["a".to_string(), "b".into(), "c".into()]);
+        categories.write(&cat, "categories").unwrap();
+        let container = DataContainer::<B>::open(&file, "cat").unwrap();
+        let select = [SelectInfoElem::from(vec![1, 2])];
+        let cat: Series =
+            anndata::data::CategoricalArray::read_select(&container, &select).unwrap().into();
+        let values: Vec<_> = cat.categorical().unwrap().iter_str().collect();
+        assert_eq!(values, [Some("c"), None]);
+    })
+}
+
+pub fn test_stacked_obs_column<B: Backend>() {
+    use polars::prelude::{DataType, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let cell_type = |x: &[&str]| {
+            Series::new("cell_type".into(), x)
+                .cast(&DataType::Categorical(None, Default::default()))
+                .unwrap()
+        };
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&Array2::<i32>::zeros((2, 3))).unwrap();
+        let mut obs = polars::df!("n_genes" => [10, 20], "batch" => ["x", "x"]).unwrap();
+        obs.with_column(cell_type(&["B", "T"])).unwrap();
+        ann1.set_obs(obs).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&Array2::<i32>::zeros((3, 3))).unwrap();
+        let mut obs = polars::df!("n_genes" => [30, 40, 50]).unwrap();
+        obs.with_column(cell_type(&["NK", "T", "NK"])).unwrap();
+        ann2.set_obs(obs).unwrap();
+        // An object without obs contributes null rows.
+        let ann3 = AnnData::<B>::new(dir.join("test3")).unwrap();
+        ann3.set_x(&Array2::<i32>::zeros((2, 3))).unwrap();
+        let mut dataset = AnnDataSet::<B>::new(
+            [("ann1", ann1), ("ann2", ann2), ("ann3", ann3)],
+            dir.join("dataset"),
+            "sample",
+        )
+        .unwrap();
+
+        let stacked = dataset.adatas().inner().get_obs().clone();
+        assert_eq!(stacked.height(), 7);
+        let n_genes = stacked.read_column("n_genes").unwrap().unwrap();
+        assert_eq!(n_genes.name().as_str(), "n_genes");
+        assert_eq!(n_genes.null_count(), 2);
+        let n_genes: Vec<_> = n_genes.i32().unwrap().into_iter().flatten().collect();
+        assert_eq!(n_genes, [10, 20, 30, 40, 50]);
+        let types = stacked.read_column("cell_type").unwrap().unwrap();
+        let types = types.cast(&DataType::String).unwrap();
+        assert_eq!(types.null_count(), 2);
+        let types: Vec<_> = types.str().unwrap().into_iter().flatten().collect();
+        assert_eq!(types, ["B", "T", "NK", "T", "NK"]);
+        // Columns must be present in all components that have obs.
+        assert!(stacked.read_column("batch").unwrap().is_none());
+        let df = stacked.data().unwrap();
+        assert_eq!(df.get_column_names(), ["n_genes", "cell_type"]);
+        assert_eq!(df.height(), 7);
+        let df = stacked
+            .select(&[SelectInfoElem::from(vec![6, 0]), SelectInfoElem::full()])
+            .unwrap();
+        let n_genes = df.column("n_genes").unwrap().i32().unwrap();
+        assert_eq!(n_genes.into_iter().collect::<Vec<_>>(), [None, Some(10)]);
+
+        // Columns missing from the obs of the AnnDataSet are only read from
+        // the components in lazy mode.
+        assert!(dataset.fetch_obs_column("n_genes").unwrap().is_none());
+        dataset.set_lazy_obs(true);
+        let n_genes = dataset.fetch_obs_column("n_genes").unwrap().unwrap();
+        assert_eq!(n_genes.len(), 7);
+        assert!(dataset.fetch_obs_column("sample").unwrap().is_some());
+        assert!(dataset.fetch_obs_column("batch").unwrap().is_none());
+    })
+}
+
+
+pub fn test_names_from_series<B: Backend>() {
+    use polars::prelude::{DataType, NamedFrom, Series};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        adata.set_x(&Array2::<i32>::zeros((3, 2))).unwrap();
+        let names = Series::new("barcode".into(), &["AAC", "GTA", "TTC"]);
+        adata.set_obs_names_from_series(&names).unwrap();
+        assert_eq!(adata.obs_names().into_vec(), ["AAC", "GTA", "TTC"]);
+
+        let genes = Series::new("gene".into(), &["CD3E", "MS4A1"])
+            .cast(&DataType::Categorical(None, Default::default()))
+            .unwrap();
+        adata.set_var_names_from_series(&genes).unwrap();
+        assert_eq!(adata.var_names().into_vec(), ["CD3E", "MS4A1"]);
+
+        // Nulls, duplicates and non-string series are rejected.
+        let names = Series::new("".into(), &[Some("a"), None, Some("c")]);
+        assert!(adata.set_obs_names_from_series(&names).is_err());
+        let names = Series::new("".into(), &["a", "b", "a"]);
+        assert!(adata.set_obs_names_from_series(&names).is_err());
+        let names = Series::new("".into(), &[1i32, 2, 3]);
+        assert!(adata.set_obs_names_from_series(&names).is_err());
+        assert_eq!(adata.obs_names().into_vec(), ["AAC", "GTA", "TTC"]);
+    })
+}
+
+
+pub fn test_array_inventory<B: Backend>() {
+    use anndata::backend::{DataType, EncodingType, ScalarType};
+
+    with_tmp_dir(|dir| {
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&Array2::<i32>::zeros((4, 3))).unwrap();
+        ann1.obsm().add("pca", &Array2::<f32>::zeros((4, 2))).unwrap();
+        ann1.obsp().add("graph", &rand_csr::<f64>(4, 4, 5, 0.0, 1.0)).unwrap();
+        ann1.varm().add("loadings", &rand_csc::<f32>(3, 2, 3, 0.0, 1.0)).unwrap();
+        ann1.layers().add("counts", &rand_csr::<u32>(4, 3, 6, 1, 10)).unwrap();
+
+        let info = |key: &str, shape: (usize, usize), encoding, dtype| ArrayInfo {
+            key: key.to_string(),
+            shape: shape.into(),
+            encoding,
+            dtype,
+        };
+        let inventory = ann1.array_inventory().unwrap();
+        let pca = info("pca", (4, 2), EncodingType::Array, DataType::Array(ScalarType::F32));
+        assert_eq!(inventory.obsm, [pca]);
+        let graph = DataType::CsrMatrix(ScalarType::F64);
+        assert_eq!(inventory.obsp, [info("graph", (4, 4), EncodingType::CsrMatrix, graph)]);
+        let loadings = DataType::CscMatrix(ScalarType::F32);
+        assert_eq!(inventory.varm, [info("loadings", (3, 2), EncodingType::CscMatrix, loadings)]);
+        assert_eq!(inventory.layers[0].encoding, EncodingType::CsrMatrix);
+        assert!(inventory.varp.is_empty());
+        assert!(inventory.stacked_obsm.is_empty());
+
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&Array2::<i32>::zeros((2, 3))).unwrap();
+        ann2.obsm().add("pca", &Array2::<f32>::zeros((2, 2))).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+        let inventory = dataset.array_inventory().unwrap();
+        assert!(inventory.obsm.is_empty());
+        let pca = info("pca", (6, 2), EncodingType::Array, DataType::Array(ScalarType::F32));
+        assert_eq!(inventory.stacked_obsm, [pca]);
+    })
+}
+
+
+pub fn test_column_attrs<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let path = dir.join("test");
+        let adata = AnnData::<B>::new(&path).unwrap();
+        adata.set_obs(polars::df!("n_genes" => [5i32, 7], "sample" => ["a", "b"]).unwrap()).unwrap();
+        adata.set_var(polars::df!("gene_type" => ["coding", "lncRNA"]).unwrap()).unwrap();
+        assert!(adata.obs_column_attrs("n_genes").unwrap().is_empty());
+
+        adata.set_obs_column_attr("n_genes", "unit", "genes").unwrap();
+        adata.set_obs_column_attr("n_genes", "source", "cellranger").unwrap();
+        adata.set_obs_column_attr("n_genes", "unit", "count").unwrap();
+        adata.set_var_column_attr("gene_type", "source", "gencode").unwrap();
+        let attrs = adata.obs_column_attrs("n_genes").unwrap();
+        assert_eq!(attrs.len(), 2);
+        assert_eq!(attrs["unit"], "count");
+        assert_eq!(attrs["source"], "cellranger");
+        assert!(adata.obs_column_attrs("sample").unwrap().is_empty());
+
+        // Missing columns and reserved attribute names are rejected.
+        assert!(adata.set_obs_column_attr("missing", "unit", "genes").is_err());
+        assert!(adata.obs_column_attrs("missing").is_err());
+        assert!(adata.set_obs_column_attr("sample", "encoding-type", "x").is_err());
+        assert!(adata.set_obs_column_attr("sample", "column-attrs", "x").is_err());
+
+        // Attributes survive overwriting the dataframe, exporting and reopening.
+        adata.set_obs(polars::df!("n_genes" => [1i32, 2], "sample" => ["c", "d"]).unwrap()).unwrap();
+        assert_eq!(adata.obs_column_attrs("n_genes").unwrap()["unit"], "count");
+        adata.write::<B, _>(dir.join("copy")).unwrap();
+        adata.close().unwrap();
+        for path in [path, dir.join("copy")] {
+            let adata = AnnData::<B>::open_with_mode(&path, OpenMode::ReadOnly).unwrap();
+            assert_eq!(adata.obs_column_attrs("n_genes").unwrap(), attrs);
+            assert_eq!(adata.var_column_attrs("gene_type").unwrap()["source"], "gencode");
+        }
+    })
+}
+
+
+pub fn test_validate_dataset<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        ann1.set_x(&Array2::from_elem((3, 2), 1)).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(&Array2::from_elem((2, 2), 2)).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+        let report = dataset.validate().unwrap();
+        assert!(report.is_valid(), "{}", report);
+        assert_eq!(report.to_string(), "no problems found");
+        dataset.close().unwrap();
+
+        // Add observations to a component behind the back of the dataset.
+        let ann2 = AnnData::<B>::open_with_mode(dir.join("test2"), OpenMode::ReadWrite).unwrap();
+        let rows = polars::frame::DataFrame::empty();
+        ann2.append_obs(Array2::from_elem((1, 2), 3).into(), rows).unwrap();
+        ann2.close().unwrap();
+
+        let no_update = None::<Result<HashMap<String, String>, String>>;
+        let dataset =
+            AnnDataSet::<B>::open_with_mode(dir.join("dataset"), OpenMode::ReadWrite, no_update).unwrap();
+        // The location table lists a component that is not loaded.
+        let table = polars::df!("keys" => ["ann1", "ann3"], "file_path" => ["test1", "test3"]);
+        dataset.uns().add("AnnDataSet", table.unwrap()).unwrap();
+
+        // All problems are reported.
+        let report = dataset.validate().unwrap();
+        assert_eq!(report.problems.len(), 2, "{}", report);
+        assert!(report.problems[0].contains("location table"), "{}", report);
+        assert!(report.problems[1].contains("components sum to 6"), "{}", report);
+    })
+}
+
+pub fn test_binarize_x<B: Backend>() {
+    use anndata::backend::GroupOp;
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let x = ndarray::array![[0.0, 1.5, -2.0], [0.5, 3.0, 0.2]];
+        adata.set_x(x.clone()).unwrap();
+        adata.binarize_x(0.5).unwrap();
+        let expected = ndarray::array![[0.0f32, 1.0, 0.0], [0.0, 1.0, 0.0]];
+        assert_eq!(adata.x().get::<Array2<f32>>().unwrap(), Some(expected));
+        assert_eq!(adata.uns().get_item::<f64>("binarize/threshold").unwrap(), Some(0.5));
+
+        // Sparse matrices drop the entries that become zero.
+        let mut coo = CooMatrix::new(2, 3);
+        [(0, 1, 1.5), (0, 2, 0.1), (1, 0, 0.5), (1, 1, 3.0)]
+            .into_iter()
+            .for_each(|(i, j, v)| coo.push(i, j, v));
+        adata.set_x(CsrMatrix::from(&coo)).unwrap();
+        adata.binarize_x(0.2).unwrap();
+        let csr: CsrMatrix<f32> = adata.x().get().unwrap().unwrap();
+        assert_eq!(csr.nnz(), 3);
+        assert_eq!(
+            csr.triplet_iter().map(|(i, j, v)| (i, j, *v)).collect::<Vec<_>>(),
+            vec![(0, 1, 1.0), (1, 0, 1.0), (1, 1, 1.0)],
+        );
+
+        // A failed binarization leaves X untouched.
+        assert!(adata.binarize_x(-1.0).is_err());
+        assert_eq!(adata.x().get::<CsrMatrix<f32>>().unwrap(), Some(csr));
+        adata.close().unwrap();
+        let names = B::open(dir.join("test")).unwrap().list().unwrap();
+        assert!(names.iter().all(|x| !x.starts_with("__X")), "{:?}", names);
+    })
+}
+
+pub fn test_stack_outer_join_noncanonical<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let names = |x: &[&str]| x.iter().map(|s| s.to_string()).collect::<DataFrameIndex>();
+        // The columns of the first row are not sorted.
+        let ann1 = AnnData::<B>::new(dir.join("test1")).unwrap();
+        let x1 = CsrNonCanonical::from_csr_data(2, 2, vec![0, 2, 3], vec![1, 0, 1], vec![1, 2, 3]);
+        ann1.set_x(x1).unwrap();
+        ann1.set_var_names(names(&["a", "b"])).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test2")).unwrap();
+        ann2.set_x(CsrNonCanonical::from_csr_data(1, 1, vec![0, 1], vec![0], vec![4])).unwrap();
+        ann2.set_var_names(names(&["c"])).unwrap();
+
+        let dataset = AnnDataSet::<B>::new_with_join(
+            [("ann1", ann1), ("ann2", ann2)],
+            dir.join("dataset"),
+            "sample",
+            JoinType::Outer,
+            None,
+        )
+        .unwrap();
+        let x: ArrayData = dataset.x().get().unwrap().unwrap();
+        let x: Array2<i32> = x.to_dense().unwrap().try_into().unwrap();
+        assert_eq!(x, ndarray::arr2(&[[2, 1, 0], [0, 3, 0], [0, 0, 4]]));
+        dataset.close().unwrap();
+
+        // Duplicated entries cannot be reindexed.
+        let ann1 = AnnData::<B>::new(dir.join("test3")).unwrap();
+        ann1.set_x(CsrNonCanonical::from_csr_data(1, 2, vec![0, 2], vec![0, 0], vec![1, 1]))
+            .unwrap();
+        ann1.set_var_names(names(&["a", "b"])).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("test4")).unwrap();
+        ann2.set_x(CsrNonCanonical::from_csr_data(1, 1, vec![0, 1], vec![0], vec![4])).unwrap();
+        ann2.set_var_names(names(&["c"])).unwrap();
+        let dataset = AnnDataSet::<B>::new_with_join(
+            [("ann1", ann1), ("ann2", ann2)],
+            dir.join("dataset2"),
+            "sample",
+            JoinType::Outer,
+            None,
+        )
+        .unwrap();
+        assert!(dataset.x().get::<ArrayData>().is_err());
+    })
+}
+
+pub fn test_copy_write_config<B: Backend>() {
+    use anndata::backend::{Compression, DatasetOp, GroupOp};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("input")).unwrap();
+        let options = WriteOptions {
+            block_size: Some(vec![10, 5].as_slice().into()),
+            compression: Some(Compression::Gzip(6)),
+            ..Default::default()
+        };
+        let x = Array2::from_shape_fn((50, 20), |(i, j)| (i * j) as i32);
+        adata.set_x_with_opts(&x, &options).unwrap();
+        let pca = Array2::from_shape_fn((50, 8), |(i, j)| (i + j) as f64);
+        adata.add_obsm_with_opts("pca", &pca, &options).unwrap();
+        adata.copy::<B, _>(dir.join("output")).unwrap().close().unwrap();
+
+        let file = B::open(dir.join("output")).unwrap();
+        let obsm = file.open_group("obsm").unwrap();
+        for dataset in [file.open_dataset("X").unwrap(), obsm.open_dataset("pca").unwrap()] {
+            let config = dataset.write_config().unwrap();
+            assert_eq!(config.block_size, Some(vec![10, 5].into()));
+            if B::NAME == "hdf5" {
+                assert!(matches!(config.compression, Some(Compression::Gzip(6))));
+            }
+        }
+    })
+}
+
+pub fn test_dataframe_dtypes<B: Backend>() {
+    use polars::prelude::{DataType, IntoColumn, NamedFrom, Series, TimeUnit};
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("test")).unwrap();
+        let time = Series::new("time".into(), &[0i64, 1_000_000, 2_000_000])
+            .cast(&DataType::Datetime(TimeUnit::Microseconds, Some("UTC".into())))
+            .unwrap();
+        let elapsed = Series::new("elapsed".into(), &[5i64, 10, 15])
+            .cast(&DataType::Duration(TimeUnit::Milliseconds))
+            .unwrap();
+        let obs = polars::frame::DataFrame::new(vec![time.into_column(), elapsed.into_column()])
+            .unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        assert_eq!(adata.read_obs().unwrap(), obs);
+
+        // Missing values are preserved.
+        let var = polars::df!(
+            "count" => [Some(1i32), None, Some(3)],
+            "label" => [Some("a"), None, Some("c")],
+            "flag" => [None, Some(true), Some(false)],
+        )
+        .unwrap();
+        adata.set_var(var.clone()).unwrap();
+        assert!(adata.read_var().unwrap().equals_missing(&var));
+
+        // Ordered categoricals keep their category order.
+        let stages = data::CategoricalArray {
+            codes: ndarray::arr1(&[Some(2), Some(0), None]).into_dyn(),
+            categories: ndarray::arr1(&["mild".to_string(), "moderate".into(), "severe".into()]),
+            ordered: true,
+        };
+        let stages: Series = stages.into();
+        assert!(matches!(stages.dtype(), DataType::Enum(_, _)));
+        let obs = polars::frame::DataFrame::new(vec![stages.with_name("stage".into()).into_column()])
+            .unwrap();
+        adata.set_obs(obs.clone()).unwrap();
+        let obs_in = adata.read_obs().unwrap();
+        assert!(obs_in.equals_missing(&obs));
+        assert_eq!(obs_in.column("stage").unwrap().dtype(), obs.column("stage").unwrap().dtype());
+    })
+}
+
 pub fn test_save<B: Backend>() {
     with_tmp_dir(|dir| {
         let input = dir.join("input");
@@ -38,7 +2894,7 @@ pub fn test_save<B: Backend>() {
         });
         proptest!(ProptestConfig::with_cases(100), |((adata, slice_obs, slice_var) in anndatas)| {
             adata.write::<B, _>(&output).unwrap();
-            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            let adata_in = AnnData::<B>::open_with_mode(&output, OpenMode::ReadOnly).unwrap();
             prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
             adata_in.close().unwrap();
 
@@ -47,14 +2903,142 @@ pub fn test_save<B: Backend>() {
 
             let select = [slice_obs, slice_var];
             adata.write_select::<B, _, _>(&select, &output).unwrap();
-            adata.subset(&select).unwrap();
-            let adata_in = AnnData::<B>::open(B::open(&output).unwrap()).unwrap();
+            let obs_names = adata.obs_names().into_vec();
+            let obs_indices = adata.subset(&select).unwrap();
+            prop_assert_eq!(obs_indices.len(), adata.n_obs());
+            if !obs_names.is_empty() {
+                let expected: Vec<_> = obs_indices.iter().map(|i| obs_names[*i].clone()).collect();
+                prop_assert_eq!(adata.obs_names().into_vec(), expected);
+            }
+            let adata_in = AnnData::<B>::open_with_mode(&output, OpenMode::ReadOnly).unwrap();
             prop_assert!(anndata_eq(&adata, &adata_in).unwrap());
             adata_in.close().unwrap();
         });
     });
 }
 
+pub fn test_repack<B: Backend>() {
+    use anndata::backend::GroupOp;
+
+    fn disk_usage(path: &std::path::Path) -> u64 {
+        if path.is_dir() {
+            std::fs::read_dir(path)
+                .unwrap()
+                .map(|entry| disk_usage(&entry.unwrap().path()))
+                .sum()
+        } else {
+            std::fs::metadata(path).unwrap().len()
+        }
+    }
+
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("input")).unwrap();
+        for _ in 0..3 {
+            adata.set_x(&rand_csr::<i32>(50, 20, 100, 1, 100)).unwrap();
+            adata.obsm().add("tmp", &rand_csr::<f32>(50, 10, 100, 0.0, 1.0)).unwrap();
+            adata.obsm().remove("tmp").unwrap();
+        }
+        adata.obsm().add("pca", &rand_csr::<f32>(50, 10, 100, 0.0, 1.0)).unwrap();
+
+        let repacked = adata.repack::<B, _>(dir.join("output")).unwrap();
+        assert!(anndata_eq(&adata, &repacked).unwrap());
+        assert_eq!(repacked.obsm().keys(), vec!["pca".to_string()]);
+        adata.close().unwrap();
+        repacked.close().unwrap();
+
+        // The removed arrays are gone from the new file.
+        let file = B::open(dir.join("output")).unwrap();
+        assert!(!file.open_group("obsm").unwrap().exists("tmp").unwrap());
+        // HDF5 does not release the space of deleted or overwritten datasets,
+        // so the repacked file is smaller.
+        if B::NAME == "hdf5" {
+            let input = disk_usage(&dir.join("input"));
+            let output = disk_usage(&dir.join("output"));
+            assert!(output < input, "{} >= {}", output, input);
+        }
+    })
+}
+
+pub fn test_copy<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("input")).unwrap();
+        let x = rand_csr::<i32>(1200, 20, 3000, 1, 100);
+        adata.set_x(&x).unwrap();
+        adata.set_obs(polars::df!("a" => vec![1i64; 1200]).unwrap()).unwrap();
+        adata.obsm().add("pca", &Array2::from_shape_fn((1200, 5), |(i, j)| (i + j) as f32)).unwrap();
+        adata.obsp().add("graph", &rand_csr::<f64>(1200, 1200, 1000, 0.0, 1.0)).unwrap();
+        adata.layers().add("counts", &x).unwrap();
+        adata.uns().add("version", 3i64).unwrap();
+
+        let copied = adata.copy::<B, _>(dir.join("output")).unwrap();
+        assert!(anndata_eq(&adata, &copied).unwrap());
+        assert_eq!(x, copied.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        assert!(adata.copy::<B, _>(dir.join("input")).is_err());
+    })
+}
+
+pub fn test_copy_sparse_x<B: Backend>() {
+    use anndata::backend::{AttributeOp, GroupOp};
+    use nalgebra_sparse::CscMatrix;
+
+    let encoding = |path: std::path::PathBuf| -> String {
+        let file = B::open(path).unwrap();
+        file.open_group("X").unwrap().get_attr("encoding-type").unwrap()
+    };
+    with_tmp_dir(|dir| {
+        let ann1 = AnnData::<B>::new(dir.join("ann1")).unwrap();
+        ann1.set_x(&rand_csr::<i32>(1500, 100, 5000, 1, 100)).unwrap();
+        let ann2 = AnnData::<B>::new(dir.join("ann2")).unwrap();
+        ann2.set_x(&rand_csr::<i32>(1200, 100, 4000, 1, 100)).unwrap();
+        let dataset =
+            AnnDataSet::<B>::new([("ann1", ann1), ("ann2", ann2)], dir.join("dataset"), "sample")
+                .unwrap();
+        let x: CsrMatrix<i32> = dataset.x().get().unwrap().unwrap();
+        let adata = dataset.to_adata::<B, _>(dir.join("stacked"), true).unwrap();
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+        adata.close().unwrap();
+        assert_eq!(encoding(dir.join("stacked")), "csr_matrix");
+
+        // CSC chunks are streamed as CSR.
+        let csc = rand_csc::<f32>(1500, 100, 5000, 0.0, 1.0);
+        let input = AnnData::<B>::new(dir.join("csc")).unwrap();
+        input.set_x(&csc).unwrap();
+        let copied = input.copy::<B, _>(dir.join("csc_copy")).unwrap();
+        let x: CsrMatrix<f32> = copied.x().get().unwrap().unwrap();
+        assert_eq!(CscMatrix::from(&x), csc);
+        copied.close().unwrap();
+        assert_eq!(encoding(dir.join("csc_copy")), "csr_matrix");
+    })
+}
+
+pub fn test_write_options<B: Backend>() {
+    with_tmp_dir(|dir| {
+        let adata = AnnData::<B>::new(dir.join("input")).unwrap();
+        let options = WriteOptions {
+            block_size: Some(vec![10, 5].as_slice().into()),
+            compression: Some(backend::Compression::Gzip(6)),
+            ..Default::default()
+        };
+
+        let x = rand_csr::<i32>(50, 20, 100, 1, 100);
+        adata.set_x_with_opts(&x, &options).unwrap();
+        adata.set_x_with_opts(&x, &options).unwrap();
+        assert_eq!(x, adata.x().get::<CsrMatrix<i32>>().unwrap().unwrap());
+
+        let pca = Array2::from_shape_fn((50, 8), |(i, j)| (i * j) as f64);
+        adata.add_obsm_with_opts("pca", &pca, &options).unwrap();
+        assert_eq!(pca, adata.obsm().get_item::<Array2<f64>>("pca").unwrap().unwrap());
+
+        // Shape checks still apply
+        let wrong = Array2::<f64>::zeros((10, 2));
+        assert!(adata.add_obsm_with_opts("wrong", &wrong, &options).is_err());
+
+        adata.write_with_options::<B, _>(dir.join("output"), options).unwrap();
+        let output = AnnData::<B>::open_with_mode(dir.join("output"), OpenMode::ReadOnly).unwrap();
+        assert!(anndata_eq(&adata, &output).unwrap());
+    })
+}
+
 pub fn test_speacial_cases<F, T>(adata_gen: F)
 where
     F: Fn() -> T,
@@ -177,8 +3161,16 @@ pub fn test_concat<B: Backend>() {
         proptest!(ProptestConfig::with_cases(100), |((adata1, adata2) in anndatas)| {
             let adatas = [adata1, adata2];
 
+            // An inner join without common var names is an error.
             let out = AnnData::<B>::new(&output).unwrap();
-            concat::<_, _, String>(&adatas, JoinType::Inner, None, None, &out).unwrap();
+            let result = concat::<_, _, String>(&adatas, JoinType::Inner, None, None, &out);
+            let var_names = adatas[1].var_names();
+            let has_common = adatas[0].var_names().into_iter().any(|x| var_names.get_index(&x).is_some());
+            if has_common || adatas.iter().all(|x| x.n_vars() == 0) {
+                result.unwrap();
+            } else {
+                assert!(result.is_err());
+            }
 
             let out = AnnData::<B>::new(&output).unwrap();
             concat::<_, _, String>(&adatas, JoinType::Outer, None, None, &out).unwrap();
@@ -2,12 +2,14 @@ use anndata_test_utils as utils;
 use anndata_test_utils::with_tmp_dir;
 use anndata_hdf5::H5;
 use anndata_zarr::Zarr;
-use anndata::{AnnData, Backend};
+use anndata_memory::Memory;
+use anndata::{AnnData, AnnDataOp, Backend, OpenMode};
 
 #[test]
 fn test_basic() {
     utils::test_basic::<H5>();
     utils::test_basic::<Zarr>();
+    utils::test_basic::<Memory>();
 }
 
 #[test]
@@ -15,18 +17,498 @@ fn test_complex_dataframe() {
     let input = "tests/data/sample.h5ad";
     with_tmp_dir(|dir| {
         let file = dir.join("test.h5");
-        let adata = AnnData::<H5>::open(H5::open(&input).unwrap()).unwrap();
+        let adata = AnnData::<H5>::open_with_mode(&input, OpenMode::ReadOnly).unwrap();
         adata.write::<H5, _>(file).unwrap();
 
         let file = dir.join("test.zarr");
-        adata.write::<Zarr, _>(file).unwrap();
+        adata.write::<Zarr, _>(&file).unwrap();
+
+        // Files written by the zarr backend must be readable with the same encodings.
+        let adata_zarr = AnnData::<Zarr>::open_with_mode(&file, OpenMode::ReadOnly).unwrap();
+        assert_eq!(adata.n_obs(), adata_zarr.n_obs());
+        assert_eq!(adata.n_vars(), adata_zarr.n_vars());
+        assert_eq!(adata.obs_names(), adata_zarr.obs_names());
+        assert_eq!(adata.var_names(), adata_zarr.var_names());
+        assert_eq!(
+            adata.read_obs().unwrap().get_column_names(),
+            adata_zarr.read_obs().unwrap().get_column_names(),
+        );
     })
 }
 
+#[test]
+fn test_stack_outer_join() {
+    utils::test_stack_outer_join::<H5>();
+    utils::test_stack_outer_join::<Zarr>();
+}
+
+#[test]
+fn test_view() {
+    utils::test_view::<H5>();
+    utils::test_view::<Zarr>();
+}
+
+#[test]
+fn test_fetch_column() {
+    utils::test_fetch_column::<H5>();
+    utils::test_fetch_column::<Zarr>();
+}
+
+#[test]
+fn test_dataframe_dtypes() {
+    utils::test_dataframe_dtypes::<H5>();
+    utils::test_dataframe_dtypes::<Zarr>();
+}
+
+#[test]
+fn test_write_csv() {
+    utils::test_write_csv::<H5>();
+    utils::test_write_csv::<Zarr>();
+}
+
+#[test]
+fn test_write_mtx() {
+    utils::test_write_mtx::<H5>();
+    utils::test_write_mtx::<Zarr>();
+}
+
+#[test]
+fn test_read_10x_h5() {
+    utils::test_read_10x_h5::<H5>();
+    utils::test_read_10x_h5::<Zarr>();
+}
+
+#[test]
+fn test_loom() {
+    utils::test_loom::<H5>();
+    utils::test_loom::<Zarr>();
+}
+
+#[test]
+fn test_layers() {
+    utils::test_layers::<H5>();
+    utils::test_layers::<Zarr>();
+}
+
+#[test]
+fn test_raw() {
+    utils::test_raw::<H5>();
+    utils::test_raw::<Zarr>();
+}
+
+#[test]
+fn test_set_x_parallel() {
+    utils::test_set_x_parallel::<H5>();
+    utils::test_set_x_parallel::<Zarr>();
+}
+
+#[test]
+fn test_cache() {
+    utils::test_cache::<H5>();
+    utils::test_cache::<Zarr>();
+}
+
+#[test]
+fn test_chunked_cols() {
+    utils::test_chunked_cols::<H5>();
+    utils::test_chunked_cols::<Zarr>();
+}
+
+#[test]
+fn test_chunked_with_obsm() {
+    utils::test_chunked_with_obsm::<H5>();
+    utils::test_chunked_with_obsm::<Zarr>();
+}
+
+#[test]
+fn test_transposed_view() {
+    utils::test_transposed_view::<H5>();
+    utils::test_transposed_view::<Zarr>();
+}
+
+#[test]
+fn test_rename_columns() {
+    utils::test_rename_columns::<H5>();
+    utils::test_rename_columns::<Zarr>();
+}
+
+#[test]
+fn test_duplicated_names() {
+    utils::test_duplicated_names::<H5>();
+    utils::test_duplicated_names::<Zarr>();
+}
+
+#[test]
+fn test_read_x_by_names() {
+    utils::test_read_x_by_names::<H5>();
+    utils::test_read_x_by_names::<Zarr>();
+}
+
+#[test]
+fn test_obsm_shape_check() {
+    utils::test_obsm_shape_check::<H5>();
+    utils::test_obsm_shape_check::<Zarr>();
+}
+
+#[test]
+fn test_set_obs_unsupported() {
+    utils::test_set_obs_unsupported::<H5>();
+    utils::test_set_obs_unsupported::<Zarr>();
+}
+
+#[test]
+fn test_dataframe_missing_column() {
+    utils::test_dataframe_missing_column::<H5>();
+    utils::test_dataframe_missing_column::<Zarr>();
+}
+
+#[test]
+fn test_copy() {
+    utils::test_copy::<H5>();
+    utils::test_copy::<Zarr>();
+}
+
+#[test]
+fn test_copy_sparse_x() {
+    utils::test_copy_sparse_x::<H5>();
+    utils::test_copy_sparse_x::<Zarr>();
+}
+
+#[test]
+fn test_obs_schema() {
+    utils::test_obs_schema::<H5>();
+    utils::test_obs_schema::<Zarr>();
+}
+
+#[test]
+fn test_f16() {
+    utils::test_f16::<H5>();
+    utils::test_f16::<Zarr>();
+}
+
+#[test]
+fn test_astype() {
+    utils::test_astype::<H5>();
+    utils::test_astype::<Zarr>();
+}
+
+#[test]
+fn test_split_by_obs() {
+    utils::test_split_by_obs::<H5>();
+    utils::test_split_by_obs::<Zarr>();
+}
+
+#[test]
+fn test_from_dir() {
+    utils::test_from_dir::<H5>();
+    utils::test_from_dir::<Zarr>();
+}
+
+#[test]
+fn test_progress() {
+    utils::test_progress::<H5>();
+    utils::test_progress::<Zarr>();
+}
+
+#[test]
+fn test_fetch_obsm_slice() {
+    utils::test_fetch_obsm_slice::<H5>();
+    utils::test_fetch_obsm_slice::<Zarr>();
+}
+
+#[test]
+fn test_column_stats() {
+    utils::test_column_stats::<H5>();
+    utils::test_column_stats::<Zarr>();
+}
+
+#[test]
+fn test_row_stats() {
+    utils::test_row_stats::<H5>();
+    utils::test_row_stats::<Zarr>();
+}
+
+#[test]
+fn test_append_obs() {
+    utils::test_append_obs::<H5>();
+    utils::test_append_obs::<Zarr>();
+}
+
+#[test]
+fn test_concurrent_reads() {
+    utils::test_concurrent_reads::<H5>();
+    utils::test_concurrent_reads::<Zarr>();
+}
+
+#[test]
+fn test_remove_and_rename_arrays() {
+    utils::test_remove_and_rename_arrays::<H5>();
+    utils::test_remove_and_rename_arrays::<Zarr>();
+}
+
+#[test]
+fn test_pairwise_subset() {
+    utils::test_pairwise_subset::<H5>();
+    utils::test_pairwise_subset::<Zarr>();
+}
+
+#[test]
+fn test_nullable_columns() {
+    utils::test_nullable_columns::<H5>();
+    utils::test_nullable_columns::<Zarr>();
+}
+
+#[test]
+fn test_uns_scalars() {
+    utils::test_uns_scalars::<H5>();
+    utils::test_uns_scalars::<Zarr>();
+}
+
+#[test]
+fn test_uns_nested() {
+    utils::test_uns_nested::<H5>();
+    utils::test_uns_nested::<Zarr>();
+}
+
+#[test]
+fn test_write_with_manifest() {
+    utils::test_write_with_manifest::<H5>();
+    utils::test_write_with_manifest::<Zarr>();
+}
+
+#[test]
+fn test_builder() {
+    utils::test_builder::<H5>();
+    utils::test_builder::<Zarr>();
+}
+
+#[test]
+fn test_structured_errors() {
+    utils::test_structured_errors::<H5>();
+    utils::test_structured_errors::<Zarr>();
+}
+
+#[test]
+fn test_open_mode() {
+    utils::test_open_mode::<H5>();
+    utils::test_open_mode::<Zarr>();
+}
+
+#[test]
+fn test_open_partial() {
+    utils::test_open_partial::<H5>();
+    utils::test_open_partial::<Zarr>();
+}
+
+#[test]
+fn test_relocate_dataset() {
+    utils::test_relocate_dataset::<H5>();
+    utils::test_relocate_dataset::<Zarr>();
+}
+
+#[test]
+fn test_index_unique() {
+    utils::test_index_unique::<H5>();
+    utils::test_index_unique::<Zarr>();
+}
+
+#[test]
+fn test_range_index() {
+    utils::test_range_index::<H5>();
+    utils::test_range_index::<Zarr>();
+}
+
+#[test]
+fn test_verify() {
+    utils::test_verify::<H5>();
+    utils::test_verify::<Zarr>();
+}
+
+#[test]
+fn test_iter_sparse_rows() {
+    utils::test_iter_sparse_rows::<H5>();
+    utils::test_iter_sparse_rows::<Zarr>();
+}
+
+#[test]
+fn test_rechunk_x() {
+    utils::test_rechunk_x::<H5>();
+    utils::test_rechunk_x::<Zarr>();
+}
+
+#[test]
+fn test_string_columns() {
+    utils::test_string_columns::<H5>();
+    utils::test_string_columns::<Zarr>();
+}
+
+#[test]
+fn test_read_obs_columns() {
+    utils::test_read_obs_columns::<H5>();
+    utils::test_read_obs_columns::<Zarr>();
+}
+
+#[test]
+fn test_uns_entries() {
+    utils::test_uns_entries::<H5>();
+    utils::test_uns_entries::<Zarr>();
+}
+
+#[test]
+fn test_read_x_dyn() {
+    utils::test_read_x_dyn::<H5>();
+    utils::test_read_x_dyn::<Zarr>();
+}
+
+#[test]
+fn test_display() {
+    utils::test_display::<H5>();
+    utils::test_display::<Zarr>();
+}
+
+#[test]
+fn test_obsm_shape() {
+    utils::test_obsm_shape::<H5>();
+    utils::test_obsm_shape::<Zarr>();
+}
+
+#[test]
+fn test_add_obs_column() {
+    utils::test_add_obs_column::<H5>();
+    utils::test_add_obs_column::<Zarr>();
+}
+
+#[test]
+fn test_legacy_sparse() {
+    utils::test_legacy_sparse::<H5>();
+    utils::test_legacy_sparse::<Zarr>();
+}
+
+#[test]
+fn test_join_obs() {
+    utils::test_join_obs::<H5>();
+    utils::test_join_obs::<Zarr>();
+}
+
+#[test]
+fn test_bool_sparse() {
+    utils::test_bool_sparse::<H5>();
+    utils::test_bool_sparse::<Zarr>();
+}
+
+#[test]
+fn test_negative_slice() {
+    utils::test_negative_slice::<H5>();
+    utils::test_negative_slice::<Zarr>();
+}
+
+#[test]
+fn test_fetch_graph() {
+    utils::test_fetch_graph::<H5>();
+    utils::test_fetch_graph::<Zarr>();
+}
+
+#[test]
+fn test_index_name() {
+    utils::test_index_name::<H5>();
+    utils::test_index_name::<Zarr>();
+}
+
+#[test]
+fn test_to_dense_sparse() {
+    utils::test_to_dense_sparse::<H5>();
+    utils::test_to_dense_sparse::<Zarr>();
+}
+
+#[test]
+fn test_set_obsm_batch() {
+    utils::test_set_obsm_batch::<H5>();
+    utils::test_set_obsm_batch::<Zarr>();
+}
+
+#[test]
+fn test_filter_expr() {
+    utils::test_filter_expr::<H5>();
+    utils::test_filter_expr::<Zarr>();
+}
+
+#[test]
+fn test_key_order() {
+    utils::test_key_order::<H5>();
+    utils::test_key_order::<Zarr>();
+}
+
+#[test]
+fn test_dataframe_read_select() {
+    utils::test_dataframe_read_select::<H5>();
+    utils::test_dataframe_read_select::<Zarr>();
+}
+
+#[test]
+fn test_stacked_obs_column() {
+    utils::test_stacked_obs_column::<H5>();
+    utils::test_stacked_obs_column::<Zarr>();
+}
+
+#[test]
+fn test_names_from_series() {
+    utils::test_names_from_series::<H5>();
+    utils::test_names_from_series::<Zarr>();
+}
+
+#[test]
+fn test_array_inventory() {
+    utils::test_array_inventory::<H5>();
+    utils::test_array_inventory::<Zarr>();
+}
+
+#[test]
+fn test_column_attrs() {
+    utils::test_column_attrs::<H5>();
+    utils::test_column_attrs::<Zarr>();
+}
+
+#[test]
+fn test_validate_dataset() {
+    utils::test_validate_dataset::<H5>();
+    utils::test_validate_dataset::<Zarr>();
+}
+
+#[test]
+fn test_binarize_x() {
+    utils::test_binarize_x::<H5>();
+    utils::test_binarize_x::<Zarr>();
+}
+
+#[test]
+fn test_stack_outer_join_noncanonical() {
+    utils::test_stack_outer_join_noncanonical::<H5>();
+    utils::test_stack_outer_join_noncanonical::<Zarr>();
+}
+
+#[test]
+fn test_copy_write_config() {
+    utils::test_copy_write_config::<H5>();
+    utils::test_copy_write_config::<Zarr>();
+}
+
 #[test]
 fn test_save() {
     utils::test_save::<H5>();
     utils::test_save::<Zarr>();
+    utils::test_save::<Memory>();
+}
+
+#[test]
+fn test_repack() {
+    utils::test_repack::<H5>();
+    utils::test_repack::<Zarr>();
+}
+
+#[test]
+fn test_write_options() {
+    utils::test_write_options::<H5>();
+    utils::test_write_options::<Zarr>();
 }
 
 #[test]
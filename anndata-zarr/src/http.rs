@@ -0,0 +1,240 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use zarrs::storage::{
+    byte_range::ByteRange, Bytes, ListableStorageTraits, ReadableStorageTraits, StorageError,
+    StoreKey, StoreKeyOffsetValue, StoreKeys, StoreKeysPrefixes, StorePrefix,
+    WritableStorageTraits,
+};
+
+/// Name of the metadata document of a Zarr V3 group or array.
+const METADATA_KEY: &str = "zarr.json";
+
+/// A read-only Zarr store served over HTTP.
+///
+/// HTTP servers cannot list keys, so the groups and arrays of the store are
+/// taken from the consolidated metadata in the root `zarr.json`, see
+/// [`crate::ZarrStore::consolidate_metadata`]. Metadata is served from memory,
+/// and chunks are fetched on demand, with range requests for partial reads.
+/// Writes fail with [`StorageError::ReadOnly`].
+pub struct HttpStore {
+    url: String,
+    agent: ureq::Agent,
+    /// Metadata documents of all groups and arrays, keyed by their store key.
+    metadata: HashMap<String, Bytes>,
+}
+
+impl HttpStore {
+    /// Open the store at `url`, which must have consolidated metadata.
+    pub fn new(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let agent = ureq::Agent::new();
+        let root_url = format!("{}/{}", url, METADATA_KEY);
+        let mut root: Value = agent
+            .get(&root_url)
+            .call()
+            .with_context(|| format!("cannot read '{}'", root_url))?
+            .into_json()?;
+        let nodes = match root
+            .get_mut("consolidated_metadata")
+            .and_then(|x| x.get_mut("metadata"))
+            .map(Value::take)
+        {
+            Some(Value::Object(nodes)) => nodes,
+            _ => bail!("'{}' has no consolidated metadata", url),
+        };
+        let mut metadata = HashMap::new();
+        for (path, node) in nodes {
+            let key = format!("{}/{}", path.trim_matches('/'), METADATA_KEY);
+            metadata.insert(key, serde_json::to_vec(&node)?.into());
+        }
+        metadata.insert(METADATA_KEY.to_string(), serde_json::to_vec(&root)?.into());
+        Ok(Self {
+            url,
+            agent,
+            metadata,
+        })
+    }
+
+    fn key_url(&self, key: &StoreKey) -> String {
+        format!("{}/{}", self.url, key.as_str())
+    }
+
+    /// Fetch `key`, or a part of it given by an HTTP `Range` header. Returns
+    /// `None` if the key does not exist.
+    fn fetch(
+        &self,
+        key: &StoreKey,
+        range: Option<String>,
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        let mut request = self.agent.get(&self.key_url(key));
+        if let Some(range) = range.as_ref() {
+            request = request.set("Range", range);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(None),
+            Err(e) => return Err(StorageError::Other(e.to_string())),
+        };
+        // Servers that ignore the range send the whole value.
+        let partial = response.status() == 206;
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| StorageError::Other(e.to_string()))?;
+        if range.is_some() && !partial {
+            return Err(StorageError::Other(format!(
+                "'{}' does not support range requests",
+                self.url
+            )));
+        }
+        Ok(Some(data))
+    }
+
+    /// Paths of the groups and arrays, e.g. `obsm/X_pca/`, including the root
+    /// as an empty prefix.
+    fn nodes(&self) -> impl Iterator<Item = &str> {
+        self.metadata
+            .keys()
+            .map(|x| x.strip_suffix(METADATA_KEY).unwrap())
+    }
+}
+
+impl ReadableStorageTraits for HttpStore {
+    fn get_partial_values_key(
+        &self,
+        key: &StoreKey,
+        byte_ranges: &[ByteRange],
+    ) -> Result<Option<Vec<Bytes>>, StorageError> {
+        if let Some(value) = self.metadata.get(key.as_str()) {
+            return byte_ranges
+                .iter()
+                .map(|range| {
+                    let range = range.to_range_usize(value.len() as u64);
+                    Ok(value.slice(range))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Some);
+        }
+        if key.as_str().ends_with(METADATA_KEY) {
+            return Ok(None);
+        }
+        let mut size = None;
+        let mut values = Vec::with_capacity(byte_ranges.len());
+        for range in byte_ranges {
+            let header = match range {
+                ByteRange::FromStart(0, None) => None,
+                ByteRange::FromStart(start, None) => Some(format!("bytes={}-", start)),
+                ByteRange::FromStart(start, Some(len)) => {
+                    Some(format!("bytes={}-{}", start, start + len - 1))
+                }
+                ByteRange::FromEnd(0, Some(len)) => Some(format!("bytes=-{}", len)),
+                _ => {
+                    if size.is_none() {
+                        size = self.size_key(key)?;
+                    }
+                    match size {
+                        Some(size) => {
+                            let range = range.to_range(size);
+                            Some(format!("bytes={}-{}", range.start, range.end - 1))
+                        }
+                        None => return Ok(None),
+                    }
+                }
+            };
+            match self.fetch(key, header)? {
+                Some(value) => values.push(value.into()),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(values))
+    }
+
+    fn size_key(&self, key: &StoreKey) -> Result<Option<u64>, StorageError> {
+        if let Some(value) = self.metadata.get(key.as_str()) {
+            return Ok(Some(value.len() as u64));
+        }
+        match self.agent.head(&self.key_url(key)).call() {
+            Ok(response) => Ok(response
+                .header("Content-Length")
+                .and_then(|x| x.parse().ok())),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(e) => Err(StorageError::Other(e.to_string())),
+        }
+    }
+}
+
+/// Only the metadata keys are listed, as chunks cannot be enumerated.
+impl ListableStorageTraits for HttpStore {
+    fn list(&self) -> Result<StoreKeys, StorageError> {
+        self.list_prefix(&StorePrefix::root())
+    }
+
+    fn list_prefix(&self, prefix: &StorePrefix) -> Result<StoreKeys, StorageError> {
+        let mut keys = self
+            .metadata
+            .keys()
+            .filter(|x| x.starts_with(prefix.as_str()))
+            .map(|x| StoreKey::new(x.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn list_dir(&self, prefix: &StorePrefix) -> Result<StoreKeysPrefixes, StorageError> {
+        let key = format!("{}{}", prefix.as_str(), METADATA_KEY);
+        let keys = if self.metadata.contains_key(&key) {
+            vec![StoreKey::new(key)?]
+        } else {
+            Vec::new()
+        };
+        let children: BTreeSet<_> = self
+            .nodes()
+            .filter_map(|x| x.strip_prefix(prefix.as_str()))
+            .filter_map(|x| x.split_once('/').map(|(child, _)| child))
+            .filter(|x| !x.is_empty())
+            .collect();
+        let prefixes = children
+            .into_iter()
+            .map(|x| StorePrefix::new(format!("{}{}/", prefix.as_str(), x)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StoreKeysPrefixes::new(keys, prefixes))
+    }
+
+    fn size_prefix(&self, prefix: &StorePrefix) -> Result<u64, StorageError> {
+        Ok(self
+            .metadata
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix.as_str()))
+            .map(|(_, v)| v.len() as u64)
+            .sum())
+    }
+
+    fn size(&self) -> Result<u64, StorageError> {
+        self.size_prefix(&StorePrefix::root())
+    }
+}
+
+impl WritableStorageTraits for HttpStore {
+    fn set(&self, _key: &StoreKey, _value: Bytes) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn set_partial_values(
+        &self,
+        _key_start_values: &[StoreKeyOffsetValue],
+    ) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn erase(&self, _key: &StoreKey) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+
+    fn erase_prefix(&self, _prefix: &StorePrefix) -> Result<(), StorageError> {
+        Err(StorageError::ReadOnly)
+    }
+}
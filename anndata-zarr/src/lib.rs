@@ -1,6 +1,6 @@
 use anndata::{
     backend::*,
-    data::{DynArray, DynCowArray, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
+    data::{f16, DynArray, DynCowArray, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape},
 };
 
 use anyhow::{bail, Context, Result};
@@ -9,12 +9,15 @@ use std::{
     borrow::Cow, ops::{Deref, Index}, path::{Path, PathBuf}
 };
 use std::{sync::Arc, vec};
-use zarrs::{array::{data_type::DataType, Element}, array_subset::ArraySubset, storage::StorePrefix};
+use zarrs::{array::{data_type::DataType, Element}, array_subset::ArraySubset, storage::{StoreKey, StorePrefix}};
 use zarrs::filesystem::FilesystemStore;
 use zarrs::group::Group;
 use zarrs::{array::ElementOwned, storage::ReadableWritableListableStorageTraits};
 use zarrs::array::codec::bytes_to_bytes::zstd::ZstdCodec;
 
+mod http;
+pub use http::HttpStore;
+
 /// The Zarr backend.
 pub struct Zarr;
 
@@ -87,6 +90,46 @@ impl Backend for Zarr {
     }
 }
 
+impl ZarrStore {
+    /// Open a read-only store served over HTTP, which must have consolidated
+    /// metadata, see [`HttpStore`]. Pass it to `AnnData::open_store` with
+    /// `OpenMode::ReadOnly`. Only the chunks that intersect a selection are
+    /// downloaded.
+    pub fn open_http(url: &str) -> Result<Self> {
+        Ok(ZarrStore {
+            path: PathBuf::from(url),
+            inner: Arc::new(HttpStore::new(url)?),
+        })
+    }
+
+    /// Record the metadata of all groups and arrays in the root `zarr.json`,
+    /// so that the store can be read with [`ZarrStore::open_http`]. Groups and
+    /// arrays created afterwards are only visible after consolidating again.
+    pub fn consolidate_metadata(&self) -> Result<()> {
+        let mut nodes = serde_json::Map::new();
+        for key in self.inner.list()? {
+            if let Some(path) = key.as_str().strip_suffix("/zarr.json") {
+                let value = self.inner.get(&key)?.context("metadata disappeared")?;
+                nodes.insert(path.to_string(), serde_json::from_slice(&value)?);
+            }
+        }
+        let root_key = StoreKey::new("zarr.json")?;
+        let root = self
+            .inner
+            .get(&root_key)?
+            .context("the store has no root group")?;
+        let mut root: Value = serde_json::from_slice(&root)?;
+        root["consolidated_metadata"] = serde_json::json!({
+            "kind": "inline",
+            "must_understand": false,
+            "metadata": nodes,
+        });
+        let root = serde_json::to_vec(&root)?;
+        self.inner.set(&root_key, root.into())?;
+        Ok(())
+    }
+}
+
 impl StoreOp<Zarr> for ZarrStore {
     /// Returns the file path.
     fn filename(&self) -> PathBuf {
@@ -134,6 +177,9 @@ impl GroupOp<Zarr> for ZarrStore {
         shape: &Shape,
         config: WriteConfig,
     ) -> Result<<Zarr as Backend>::Dataset> {
+        if config.quantize.is_some() {
+            bail!("quantization is not supported by the zarr backend");
+        }
         let path = canoincalize_path(name);
         let shape = shape.as_ref();
         let sizes: Vec<u64> = match config.block_size {
@@ -159,6 +205,7 @@ impl GroupOp<Zarr> for ZarrStore {
             ScalarType::I16 => (DataType::Int16, 0i16.into()),
             ScalarType::I32 => (DataType::Int32, 0i32.into()),
             ScalarType::I64 => (DataType::Int64, 0i64.into()),
+            ScalarType::F16 => (DataType::Float16, zarrs::array::ZARR_NAN_F16.into()),
             ScalarType::F32 => (DataType::Float32, zarrs::array::ZARR_NAN_F32.into()),
             ScalarType::F64 => (DataType::Float64, zarrs::array::ZARR_NAN_F64.into()),
             ScalarType::Bool => (DataType::Bool, false.into()),
@@ -204,6 +251,10 @@ impl GroupOp<Zarr> for ZarrStore {
             &path.as_str().try_into()?,
         )?)
     }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        rename_prefix(self, name, new_name)
+    }
 }
 
 impl GroupOp<Zarr> for ZarrGroup {
@@ -247,6 +298,9 @@ impl GroupOp<Zarr> for ZarrGroup {
         shape: &Shape,
         config: WriteConfig,
     ) -> Result<<Zarr as Backend>::Dataset> {
+        if config.quantize.is_some() {
+            bail!("quantization is not supported by the zarr backend");
+        }
         let shape = shape.as_ref();
         let sizes: Vec<u64> = match config.block_size {
             Some(s) => s.as_ref().into_iter().map(|x| (*x).max(1) as u64).collect(),
@@ -271,6 +325,7 @@ impl GroupOp<Zarr> for ZarrGroup {
             ScalarType::I16 => (DataType::Int16, 0i16.into()),
             ScalarType::I32 => (DataType::Int32, 0i32.into()),
             ScalarType::I64 => (DataType::Int64, 0i64.into()),
+            ScalarType::F16 => (DataType::Float16, zarrs::array::ZARR_NAN_F16.into()),
             ScalarType::F32 => (DataType::Float32, zarrs::array::ZARR_NAN_F32.into()),
             ScalarType::F64 => (DataType::Float64, zarrs::array::ZARR_NAN_F64.into()),
             ScalarType::Bool => (DataType::Bool, false.into()),
@@ -324,6 +379,15 @@ impl GroupOp<Zarr> for ZarrGroup {
             .try_into()?;
         Ok(zarrs::node::node_exists(&self.store.inner, &path)?)
     }
+
+    fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        let path = self.group.path().as_str();
+        rename_prefix(
+            &self.store,
+            &format!("{}/{}", path, name),
+            &format!("{}/{}", path, new_name),
+        )
+    }
 }
 
 impl AttributeOp<Zarr> for ZarrGroup {
@@ -393,6 +457,7 @@ impl DatasetOp<Zarr> for ZarrDataset {
             DataType::Int16 => Ok(ScalarType::I16),
             DataType::Int32 => Ok(ScalarType::I32),
             DataType::Int64 => Ok(ScalarType::I64),
+            DataType::Float16 => Ok(ScalarType::F16),
             DataType::Float32 => Ok(ScalarType::F32),
             DataType::Float64 => Ok(ScalarType::F64),
             DataType::Bool => Ok(ScalarType::Bool),
@@ -401,12 +466,13 @@ impl DatasetOp<Zarr> for ZarrDataset {
         }
     }
 
-    fn shape(&self) -> Shape {
-        self.dataset
+    fn shape(&self) -> Result<Shape> {
+        Ok(self
+            .dataset
             .shape()
             .into_iter()
             .map(|x| *x as usize)
-            .collect()
+            .collect())
     }
 
     fn reshape(&mut self, shape: &Shape) -> Result<()> {
@@ -416,6 +482,21 @@ impl DatasetOp<Zarr> for ZarrDataset {
         Ok(())
     }
 
+    /// Only the chunk shape is recorded, as datasets are always compressed
+    /// with zstd regardless of the requested compression.
+    fn write_config(&self) -> Result<WriteConfig> {
+        let origin = vec![0; self.dataset.dimensionality()];
+        let block_size = self
+            .dataset
+            .chunk_shape(&origin)
+            .ok()
+            .map(|s| s.iter().map(|x| x.get() as usize).collect());
+        Ok(WriteConfig {
+            block_size,
+            ..WriteConfig::default()
+        })
+    }
+
     /// TODO: current implementation reads the entire array and then selects the slice.
     fn read_array_slice<T: BackendData, S, D>(&self, selection: &[S]) -> Result<Array<T, D>>
     where
@@ -428,7 +509,7 @@ impl DatasetOp<Zarr> for ZarrDataset {
             S: AsRef<SelectInfoElem>,
             D: Dimension,
         {
-            let sel = SelectInfoBounds::new(&selection, &dataset.shape());
+            let sel = SelectInfoBounds::try_new(&selection, &dataset.shape()?)?;
             if let Some(subset) = to_array_subset(sel) {
                 let arr = dataset
                     .dataset
@@ -454,6 +535,7 @@ impl DatasetOp<Zarr> for ZarrDataset {
             ScalarType::I16 => read_arr::<i16, _, D>(self, selection)?.into(),
             ScalarType::I32 => read_arr::<i32, _, D>(self, selection)?.into(),
             ScalarType::I64 => read_arr::<i64, _, D>(self, selection)?.into(),
+            ScalarType::F16 => read_arr::<f16, _, D>(self, selection)?.into(),
             ScalarType::F32 => read_arr::<f32, _, D>(self, selection)?.into(),
             ScalarType::F64 => read_arr::<f64, _, D>(self, selection)?.into(),
             ScalarType::Bool => read_arr::<bool, _, D>(self, selection)?.into(),
@@ -477,7 +559,7 @@ impl DatasetOp<Zarr> for ZarrDataset {
             T: Element + 'static,
             S: AsRef<SelectInfoElem>,
         {
-            let selection = SelectInfoBounds::new(&selection, &container.shape());
+            let selection = SelectInfoBounds::try_new(&selection, &container.shape()?)?;
             let starts: Vec<_> = selection
                 .iter()
                 .flat_map(|x| {
@@ -511,6 +593,7 @@ impl DatasetOp<Zarr> for ZarrDataset {
             DynCowArray::I16(x) => write_array_impl(self, x, selection),
             DynCowArray::I32(x) => write_array_impl(self, x, selection),
             DynCowArray::I64(x) => write_array_impl(self, x, selection),
+            DynCowArray::F16(x) => write_array_impl(self, x, selection),
             DynCowArray::F32(x) => write_array_impl(self, x, selection),
             DynCowArray::F64(x) => write_array_impl(self, x, selection),
             DynCowArray::Bool(x) => write_array_impl(self, x, selection),
@@ -566,6 +649,29 @@ fn str_to_prefix(s: &str) -> StorePrefix {
     }
 }
 
+/// Move all keys below `from` to `to`. The keys are copied as they are, so
+/// the chunks are not decoded, and the old keys are erased only after all of
+/// them were copied.
+fn rename_prefix(store: &ZarrStore, from: &str, to: &str) -> Result<()> {
+    let (from, to) = (str_to_prefix(from), str_to_prefix(to));
+    if !store.list_prefix(&to)?.is_empty() {
+        bail!("cannot rename '{}': '{}' already exists", from.as_str(), to.as_str());
+    }
+    let keys = store.list_prefix(&from)?;
+    if keys.is_empty() {
+        bail!("'{}' does not exist", from.as_str());
+    }
+    for key in keys {
+        let value = store
+            .get(&key)?
+            .with_context(|| format!("'{}' disappeared while renaming", key.as_str()))?;
+        let new_key = format!("{}{}", to.as_str(), &key.as_str()[from.as_str().len()..]);
+        store.set(&StoreKey::new(new_key)?, value)?;
+    }
+    store.erase_prefix(&from)?;
+    Ok(())
+}
+
 fn canoincalize_path<'a>(path: &'a str) -> Cow<'a, str> {
     if path.starts_with("/") {
         path.into()
@@ -600,7 +706,10 @@ mod tests {
     use ndarray::{array, concatenate, Array2, Axis, Ix2};
     use ndarray_rand::rand_distr::Uniform;
     use ndarray_rand::RandomExt;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tempfile::tempdir;
 
     pub fn with_tmp_dir<T, F: FnMut(PathBuf) -> T>(mut func: F) -> T {
@@ -724,4 +833,140 @@ mod tests {
 
         Ok(())
     }
+
+    /// Serve the files below `dir` over HTTP, answering range requests.
+    /// Returns the URL and the number of GET requests received.
+    fn serve(dir: PathBuf) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let gets = Arc::new(AtomicUsize::new(0));
+        let counter = gets.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(x) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        range = Some(x.trim().to_string());
+                    }
+                }
+                let mut parts = request.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = dir.join(parts.next().unwrap().trim_start_matches('/'));
+                if method == "GET" {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+                let (status, body) = match std::fs::read(&path) {
+                    Err(_) => ("404 Not Found", Vec::new()),
+                    Ok(data) => match range {
+                        None => ("200 OK", data),
+                        Some(range) => {
+                            let (start, end) = range.split_once('-').unwrap();
+                            let (start, end) = match (start.parse(), end.parse::<usize>()) {
+                                (Ok(start), Ok(end)) => (start, end + 1),
+                                (Ok(start), Err(_)) => (start, data.len()),
+                                (Err(_), Ok(len)) => (data.len() - len, data.len()),
+                                _ => panic!("invalid range: {}", range),
+                            };
+                            ("206 Partial Content", data[start..end].to_vec())
+                        }
+                    },
+                };
+                let header = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                stream.write_all(header.as_bytes()).unwrap();
+                if method == "GET" {
+                    stream.write_all(&body).unwrap();
+                }
+            }
+        });
+        (url, gets)
+    }
+
+    #[test]
+    fn test_http() -> Result<()> {
+        with_tmp_path(|path| {
+            let store = Zarr::new(&path)?;
+            let config = WriteConfig {
+                block_size: Some(vec![2, 2].as_slice().into()),
+                ..Default::default()
+            };
+            let arr = Array::random((10, 10), Uniform::new(0, 100));
+            let mut group = store.new_group("group")?;
+            group.new_json_attr("encoding-type", &"dict".into())?;
+            group.new_array_dataset("test", arr.view().into(), config)?;
+            store.consolidate_metadata()?;
+
+            let (url, gets) = serve(path.clone());
+            let remote = ZarrStore::open_http(&url)?;
+            assert_eq!(remote.list()?, vec!["group"]);
+            assert!(remote.exists("group")?);
+            assert!(!remote.exists("missing")?);
+            let group = remote.open_group("group")?;
+            assert_eq!(group.list()?, vec!["test"]);
+            assert_eq!(group.get_json_attr("encoding-type")?, "dict");
+
+            // Only the chunk that holds the selection is downloaded.
+            let dataset = group.open_dataset("test")?;
+            assert_eq!(dataset.shape()?.as_ref(), &[10, 10]);
+            let before = gets.load(Ordering::SeqCst);
+            assert_eq!(
+                arr.slice(ndarray::s![4..6, 2..4]),
+                dataset.read_array_slice::<i32, _, Ix2>(s![4..6, 2..4].as_ref())?
+            );
+            assert_eq!(gets.load(Ordering::SeqCst) - before, 1);
+            assert_eq!(arr, dataset.read_array::<i32, Ix2>()?);
+
+            // The store is read-only.
+            assert!(remote.new_group("new").is_err());
+            assert!(remote.delete("group").is_err());
+
+            // Stores without consolidated metadata cannot be listed.
+            let plain = Zarr::new(path.with_file_name("plain"))?;
+            plain.new_group("group")?;
+            let (url, _) = serve(path.with_file_name("plain"));
+            assert!(ZarrStore::open_http(&url).is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_http_anndata() -> Result<()> {
+        use anndata::{AnnData, AnnDataOp, ArrayElemOp, OpenMode};
+
+        with_tmp_path(|path| {
+            let x = Array::random((100, 10), Uniform::new(0, 100));
+            let adata = AnnData::<Zarr>::new(&path)?;
+            adata.set_x(&x)?;
+            adata.close()?;
+            Zarr::open_rw(&path)?.consolidate_metadata()?;
+
+            let (url, _) = serve(path.clone());
+            let adata =
+                AnnData::<Zarr>::open_store(ZarrStore::open_http(&url)?, OpenMode::ReadOnly)?;
+            assert_eq!(adata.n_obs(), 100);
+            let rows: Array2<i32> = adata
+                .x()
+                .slice_axis(0, SelectInfoElem::from(10..20))?
+                .unwrap();
+            assert_eq!(rows, x.slice(ndarray::s![10..20, ..]));
+            let chunks: Vec<Array2<i32>> = adata.x().chunked(25).map(|(x, _, _)| x).collect();
+            assert_eq!(chunks.len(), 4);
+            let views: Vec<_> = chunks.iter().map(|x| x.view()).collect();
+            assert_eq!(concatenate(Axis(0), &views)?, x);
+            assert!(adata.set_x(&x).is_err());
+            Ok(())
+        })
+    }
 }
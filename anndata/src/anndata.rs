@@ -1,23 +1,164 @@
+mod builder;
 mod dataset;
+mod loom;
+mod raw;
+mod transposed;
+mod view;
 
-pub use dataset::{AnnDataSet, StackedAnnData};
+pub use builder::AnnDataBuilder;
+pub use dataset::{AnnDataSet, StackedAnnData, ValidationReport};
+pub use raw::Raw;
+pub use transposed::TransposedAnnData;
+pub use view::AnnDataView;
 use smallvec::SmallVec;
 
 use crate::{
-    backend::{Backend, DataContainer, GroupOp, StoreOp},
-    container::{ArrayElem, Axis, AxisArrays, DataFrameElem, Dim, ElemCollection, Slot},
-    data::*,
-    traits::AnnDataOp,
+    backend::{
+        Backend, Compression, DataContainer, DataType, EncodingType, GroupOp, Quantize, StoreOp,
+        WriteConfig,
+    },
+    container::{
+        base::append_to_dataframe, ArrayElem, Axis, AxisArrays, DataFrameElem, Dim,
+        ElemCollection, InnerDataFrameElem, Slot,
+    },
+    data::{array::dataframe::column_order_mismatch, *},
+    error::AnnDataError,
+    reader::MMWriter,
+    traits::{AnnDataOp, AxisArraysOp},
 };
 
-use anyhow::{anyhow, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use indexmap::IndexMap;
+use polars::prelude::{CsvWriter, DataFrame, SerWriter, Series};
 use itertools::Itertools;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
+/// Number of rows, or columns of a CSC matrix, of X read at a time by
+/// [`AnnData::rechunk_x`].
+const RECHUNK_CHUNK_SIZE: usize = 1000;
+
+/// Number of rows of obs or var read and written at a time by
+/// [`AnnData::write_obs_csv`].
+const CSV_CHUNK_SIZE: usize = 10000;
+
+/// Options controlling how array datasets are created, used by
+/// [`AnnData::write_with_options`] and the `*_with_opts` methods of [`AnnDataOp`].
+/// The default values match the settings used by plain writes.
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Chunk shape of dense arrays. Sparse matrices use the first entry as the
+    /// chunk length of their `data`, `indices` and `indptr` arrays. Chosen
+    /// automatically when `None`.
+    pub block_size: Option<Shape>,
+    /// Compression codec and level. Default: `Some(Compression::Zst(3))`.
+    /// The zarr backend always compresses with zstd and ignores this setting.
+    pub compression: Option<Compression>,
+    /// Lossy quantization applied to dense floating point arrays in `X`, `obsm`,
+    /// `varm` and `layers`. Disabled by default; see [`Quantize`] for the error
+    /// introduced by each scheme.
+    pub quantize: Option<Quantize>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        let config = WriteConfig::default();
+        Self {
+            block_size: config.block_size,
+            compression: config.compression,
+            quantize: config.quantize,
+        }
+    }
+}
+
+impl From<&WriteOptions> for WriteConfig {
+    fn from(options: &WriteOptions) -> Self {
+        WriteConfig {
+            compression: options.compression,
+            block_size: options.block_size.clone(),
+            quantize: options.quantize,
+        }
+    }
+}
+
+/// Options of [`AnnDataOp::join_obs_with`] and [`AnnDataOp::join_var_with`].
+#[derive(Debug, Clone, Default)]
+pub struct JoinOptions {
+    /// Annotation column whose values are matched against the key column of
+    /// the joined dataframe. The observation or variable names are used when
+    /// `None`.
+    pub left_on: Option<String>,
+    /// Use the first row of a key that occurs several times in the joined
+    /// dataframe, instead of failing.
+    pub allow_duplicates: bool,
+}
+
+/// How [`AnnDataOp::fetch_graph`] combines the entries `(i, j)` and `(j, i)`
+/// of a graph to make it symmetric.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SymMode {
+    /// The larger of the two entries. An edge stored in one direction only is
+    /// kept with its value, so the result is the union of both directions.
+    Max,
+    /// The smaller of the two entries. Edges stored in one direction only are
+    /// dropped, e.g. to keep the mutual neighbors of a kNN graph.
+    Min,
+    /// The mean of the two entries, where a missing entry counts as zero,
+    /// i.e. `(A + A^T) / 2`.
+    Mean,
+}
+
+/// Key, shape, encoding and data type of an array, obtained without reading
+/// its data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayInfo {
+    pub key: String,
+    pub shape: Shape,
+    /// The `encoding-type` of the stored array.
+    pub encoding: EncodingType,
+    pub dtype: DataType,
+}
+
+/// Structural overview of the array collections of an AnnData object.
+/// See [`AnnDataOp::array_inventory`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Inventory {
+    pub obsm: Vec<ArrayInfo>,
+    pub obsp: Vec<ArrayInfo>,
+    pub varm: Vec<ArrayInfo>,
+    pub varp: Vec<ArrayInfo>,
+    pub layers: Vec<ArrayInfo>,
+    /// The obsm arrays stacked across the children of an `AnnDataSet`.
+    /// Always empty for `AnnData`.
+    pub stacked_obsm: Vec<ArrayInfo>,
+}
+
+/// How an existing file is opened by [`AnnData::open_with_mode`] and
+/// [`AnnDataSet::open_with_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenMode {
+    /// Writes fail with [`AnnDataError::ReadOnly`] before reaching the backend.
+    #[default]
+    ReadOnly,
+    ReadWrite,
+}
+
+impl OpenMode {
+    /// Open the file at `path` with the backend `B` in this mode.
+    pub fn open<B: Backend, P: AsRef<Path>>(self, path: P) -> Result<B::Store> {
+        match self {
+            OpenMode::ReadOnly => B::open(path),
+            OpenMode::ReadWrite => B::open_rw(path),
+        }
+    }
+}
+
 /// Represents an annotated data object backed by a specified backend.
 pub struct AnnData<B: Backend> {
     /// The file storage backend.
     pub(crate) file: B::Store,
+    /// The mode the file was opened in.
+    pub(crate) mode: OpenMode,
     /// Number of observations (rows).
     pub(crate) n_obs: Dim,
     /// Number of variables (columns).
@@ -40,6 +181,8 @@ pub struct AnnData<B: Backend> {
     pub(crate) uns: ElemCollection<B>,
     /// Layers of data.
     pub(crate) layers: AxisArrays<B>,
+    /// Frozen `X` and `var` from before variables were filtered.
+    pub(crate) raw: Slot<Raw<B>>,
 }
 
 impl<B: Backend> std::fmt::Debug for AnnData<B> {
@@ -55,7 +198,7 @@ impl<B: Backend> std::fmt::Display for AnnData<B> {
             "AnnData object with n_obs x n_vars = {} x {} backed at '{}'",
             self.n_obs(),
             self.n_vars(),
-            self.filename().to_str().unwrap().to_string(),
+            self.filename().display(),
         )?;
         if let Some(obs) = self.obs.lock().as_ref().map(|x| x.get_column_names()) {
             if !obs.is_empty() {
@@ -67,7 +210,7 @@ impl<B: Backend> std::fmt::Display for AnnData<B> {
                 write!(f, "\n    var: '{}'", var.into_iter().join("', '"))?;
             }
         }
-        if let Some(keys) = self.uns.lock().as_ref().map(|x| x.keys().join("', '")) {
+        if let Some(keys) = self.uns.lock().as_ref().map(|x| x.keys().sorted().join("', '")) {
             if !keys.is_empty() {
                 write!(f, "\n    uns: '{}'", keys)?;
             }
@@ -97,6 +240,9 @@ impl<B: Backend> std::fmt::Display for AnnData<B> {
                 write!(f, "\n    layers: '{}'", keys)?;
             }
         }
+        if let Some(n_vars) = self.raw.lock().as_ref().map(|x| x.n_vars()) {
+            write!(f, "\n    raw: n_vars = {}", n_vars)?;
+        }
         Ok(())
     }
 }
@@ -148,8 +294,26 @@ impl<B: Backend> AnnData<B> {
         &self.var
     }
 
-    /// Open an existing AnnData store.
+    /// Open an existing AnnData store. Writes are only restricted by how the
+    /// backend opened the store; use [`AnnData::open_with_mode`] to reject
+    /// them before they reach the backend.
     pub fn open(file: B::Store) -> Result<Self> {
+        Self::open_store(file, OpenMode::ReadWrite)
+    }
+
+    /// Open an existing AnnData file in the given mode.
+    pub fn open_with_mode<P: AsRef<Path>>(filename: P, mode: OpenMode) -> Result<Self> {
+        Self::open_store(mode.open::<B, _>(filename)?, mode)
+    }
+
+    /// Open an AnnData object from a store that was opened in `mode`.
+    ///
+    /// In read-only mode, the methods of [`AnnDataOp`], of the collections
+    /// `obsm`, `obsp`, `varm`, `varp`, `layers` and `uns`, and of the elements
+    /// that modify the file fail with [`AnnDataError::ReadOnly`], and missing
+    /// collections are not created.
+    pub fn open_store(file: B::Store, mode: OpenMode) -> Result<Self> {
+        let writable = mode == OpenMode::ReadWrite;
         let n_obs = Dim::empty();
         let n_vars = Dim::empty();
 
@@ -181,38 +345,72 @@ impl<B: Backend> AnnData<B> {
             Slot::none()
         };
 
-        let obsm = match file.open_group("obsm").or(new_mapping(&file, "obsm")) {
+        // Missing collections are created, unless the file is read-only.
+        let open_mapping = |name: &str| {
+            file.open_group(name).or_else(|e| {
+                if writable {
+                    new_mapping(&file, name)
+                } else {
+                    Err(e)
+                }
+            })
+        };
+
+        let obsm = match open_mapping("obsm") {
             Ok(group) => new_obsm(group, &n_obs)?,
             _ => AxisArrays::empty(),
         };
 
-        let obsp = match file.open_group("obsp").or(new_mapping(&file, "obsp")) {
+        let obsp = match open_mapping("obsp") {
             Ok(group) => new_obsp(group, &n_obs)?,
             _ => AxisArrays::empty(),
         };
 
-        let varm = match file.open_group("varm").or(new_mapping(&file, "varm")) {
+        let varm = match open_mapping("varm") {
             Ok(group) => new_varm(group, &n_vars)?,
             _ => AxisArrays::empty(),
         };
 
-        let varp = match file.open_group("varp").or(new_mapping(&file, "varp")) {
+        let varp = match open_mapping("varp") {
             Ok(group) => new_varp(group, &n_vars)?,
             _ => AxisArrays::empty(),
         };
 
-        let uns = match file.open_group("uns").or(new_mapping(&file, "uns")) {
+        let uns = match open_mapping("uns") {
             Ok(group) => ElemCollection::new(group)?,
             _ => ElemCollection::empty(),
         };
 
-        let layers = match file.open_group("layers").or(new_mapping(&file, "layers")) {
+        let layers = match open_mapping("layers") {
             Ok(group) => new_layers(group, &n_obs, &n_vars)?,
             _ => AxisArrays::empty(),
         };
 
+        let raw = if file.exists("raw")? {
+            let raw = Raw::open(&file.open_group("raw")?)?;
+            raw.x
+                .lock()
+                .as_ref()
+                .map(|x| n_obs.try_set(x.shape()[0]))
+                .transpose()?;
+            Slot::new(raw)
+        } else {
+            Slot::none()
+        };
+
+        if !writable {
+            x.set_read_only();
+            obs.set_read_only();
+            var.set_read_only();
+            for arrays in [&obsm, &obsp, &varm, &varp, &layers] {
+                arrays.set_read_only();
+            }
+            uns.set_read_only();
+        }
+
         Ok(Self {
             file,
+            mode,
             n_obs,
             n_vars,
             x,
@@ -224,6 +422,7 @@ impl<B: Backend> AnnData<B> {
             varp,
             uns,
             layers,
+            raw,
         })
     }
 
@@ -242,66 +441,279 @@ impl<B: Backend> AnnData<B> {
             varp: new_varp(new_mapping(&file, "varp")?, &n_vars)?,
             uns: ElemCollection::new(new_mapping(&file, "uns")?)?,
             layers: new_layers(new_mapping(&file, "layers")?, &n_obs, &n_vars)?,
+            raw: Slot::none(),
             file,
+            mode: OpenMode::ReadWrite,
             n_obs,
             n_vars,
         })
     }
 
+    /// Create a new AnnData file at `out` from the `filtered_feature_bc_matrix.h5`
+    /// or `raw_feature_bc_matrix.h5` file produced by CellRanger. The input file
+    /// is read with the backend `I`. See [`crate::reader::read_10x_h5`].
+    pub fn from_10x_h5<I: Backend, P: AsRef<Path>, Q: AsRef<Path>>(input: P, out: Q) -> Result<Self> {
+        let adata = Self::new(out)?;
+        crate::reader::read_10x_h5::<I, _, _>(input, &adata)?;
+        Ok(adata)
+    }
+
     /// Write the AnnData object to a new file.
     pub fn write<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<()> {
+        self.write_with_options::<O, _>(filename, WriteOptions::default())
+    }
+
+    /// Write the AnnData object to a new file with custom [`WriteOptions`].
+    pub fn write_with_options<O: Backend, P: AsRef<Path>>(
+        &self,
+        filename: P,
+        options: WriteOptions,
+    ) -> Result<()> {
         let file = O::new(filename)?;
+        self.export_elems::<O>(&file, Some(&WriteConfig::from(&options)), true)?;
+        file.close()?;
+        Ok(())
+    }
+
+    /// Write the AnnData object like [`AnnData::write`] and store a manifest of
+    /// its arrays in `uns["_schema"]` of the new file, so that tools can list
+    /// the contents without opening every group. The manifest is a DataFrame
+    /// with one row per array in `X`, `layers`, `obsm`, `obsp`, `varm` and
+    /// `varp`, and the columns `element` (e.g. "obsm/X_pca"), `dtype` and `shape`.
+    pub fn write_with_manifest<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<()> {
+        let manifest = self.array_manifest()?;
+        self.write::<O, _>(&filename)?;
+        let adata = AnnData::<O>::open_with_mode(&filename, OpenMode::ReadWrite)?;
+        adata.uns().add("_schema", manifest)?;
+        adata.close()
+    }
+
+    fn array_manifest(&self) -> Result<DataFrame> {
+        let mut element = Vec::new();
+        let mut dtype = Vec::new();
+        let mut shape = Vec::new();
+        if let Some(x) = self.x.read().as_ref() {
+            element.push("X".to_string());
+            dtype.push(x.dtype().to_string());
+            shape.push(x.shape().to_string());
+        }
+        let inventory = self.array_inventory()?;
+        for (name, infos) in [
+            ("layers", inventory.layers),
+            ("obsm", inventory.obsm),
+            ("obsp", inventory.obsp),
+            ("varm", inventory.varm),
+            ("varp", inventory.varp),
+        ] {
+            for info in infos {
+                element.push(format!("{}/{}", name, info.key));
+                dtype.push(info.dtype.to_string());
+                shape.push(info.shape.to_string());
+            }
+        }
+        Ok(polars::df!("element" => element, "dtype" => dtype, "shape" => shape)?)
+    }
+
+    /// Write the AnnData object to a new file like [`AnnData::write`], but stream
+    /// `X` in chunks of rows and call `progress` with the number of chunks
+    /// written so far and the total number of chunks after each chunk. Datasets
+    /// are created with the default [`WriteOptions`].
+    pub fn write_with_progress<O, P, F>(&self, filename: P, progress: F) -> Result<()>
+    where
+        O: Backend,
+        P: AsRef<Path>,
+        F: FnMut(usize, usize),
+    {
+        let file = O::new(&filename)?;
+        self.export_elems::<O>(&file, Some(&WriteConfig::default()), false)?;
+        file.close()?;
+        if !self.x.is_none() {
+            let adata = AnnData::<O>::open_with_mode(&filename, OpenMode::ReadWrite)?;
+            let chunks = self.x.chunked::<ArrayData>(500).map(|x| x.0);
+            adata.set_x_from_iter_with_progress(chunks, progress)?;
+            adata.close()?;
+        }
+        Ok(())
+    }
+
+    /// Deep-copy the AnnData object, including `obsm`, `obsp`, `varm`, `varp`,
+    /// `uns`, `layers` and `raw`, to a new file at `out` and open the copy with
+    /// the backend `O`. Unlike [`AnnData::write`], `X` is streamed in chunks of
+    /// rows and never fully loaded into memory. Datasets are created with the
+    /// chunk shape and compression of their sources, where the backend of the
+    /// source records them.
+    pub fn copy<O: Backend, P: AsRef<Path>>(&self, out: P) -> Result<AnnData<O>> {
+        ensure!(
+            self.filename() != out.as_ref(),
+            "cannot copy an AnnData object into its own file"
+        );
+        let file = O::new(&out)?;
+        self.export_elems::<O>(&file, None, false)?;
+        if !self.x.is_none() {
+            // As in `export_with_source_config`, quantization is not carried over.
+            let config = WriteConfig {
+                quantize: None,
+                ..self.x.inner().write_config()?
+            };
+            ArrayData::write_by_chunk_with_config(
+                self.x.chunked::<ArrayData>(500).map(|x| x.0),
+                &file,
+                "X",
+                &config,
+            )?;
+        }
+        file.close()?;
+        AnnData::open_with_mode(&out, OpenMode::ReadWrite)
+    }
+
+    /// Export all elements to `file`. `X` is skipped unless `with_x` is set.
+    /// Arrays are created with `config`, or with the configuration of their
+    /// sources if `config` is `None`.
+    fn export_elems<O: Backend>(
+        &self,
+        file: &O::Store,
+        config: Option<&WriteConfig>,
+        with_x: bool,
+    ) -> Result<()> {
+        let pairwise_config = config.map(|config| WriteConfig {
+            quantize: None,
+            ..config.clone()
+        });
+        let export_arrays = |arrays: &AxisArrays<B>, name: &str, config: Option<&WriteConfig>| {
+            arrays
+                .lock()
+                .as_mut()
+                .map(|x| match config {
+                    Some(config) => x.export_with_config::<O, _>(file, name, config),
+                    None => x.export_with_source_config::<O, _>(file, name),
+                })
+                .transpose()
+        };
         let _obs_lock = self.n_obs.lock();
         let _vars_lock = self.n_vars.lock();
-        self.get_x()
-            .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "X"))
-            .transpose()?;
+        if with_x {
+            self.get_x()
+                .lock()
+                .as_mut()
+                .map(|x| match config {
+                    Some(config) => x.export_with_config::<O, _>(file, "X", config),
+                    None => x.export_with_source_config::<O, _>(file, "X"),
+                })
+                .transpose()?;
+        }
         self.get_obs()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obs"))
+            .map(|x| x.export::<O, _>(file, "obs"))
             .transpose()?;
         self.get_var()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "var"))
-            .transpose()?;
-        self.obsm()
-            .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obsm"))
-            .transpose()?;
-        self.obsp()
-            .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "obsp"))
-            .transpose()?;
-        self.varm()
-            .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "varm"))
-            .transpose()?;
-        self.varp()
-            .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "varp"))
+            .map(|x| x.export::<O, _>(file, "var"))
             .transpose()?;
+        export_arrays(self.obsm(), "obsm", config)?;
+        export_arrays(self.obsp(), "obsp", pairwise_config.as_ref())?;
+        export_arrays(self.varm(), "varm", config)?;
+        export_arrays(self.varp(), "varp", pairwise_config.as_ref())?;
         self.uns()
             .lock()
             .as_mut()
-            .map(|x| x.export::<O, _>(&file, "uns"))
+            .map(|x| x.export::<O, _>(file, "uns"))
             .transpose()?;
-        self.layers()
+        export_arrays(self.layers(), "layers", config)?;
+        self.raw
             .lock()
-            .as_mut()
-            .map(|x| x.export::<O, _>(&file, "layers"))
+            .as_ref()
+            .map(|x| x.export_select::<O, _>(&SelectInfoElem::full(), file, "raw"))
             .transpose()?;
-        file.close()?;
         Ok(())
     }
 
+    /// Copy all live objects into a fresh file and open it. This reclaims the space
+    /// left behind by deleted or overwritten elements, which backends like HDF5
+    /// never release on their own (similar to `h5repack`).
+    pub fn repack<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<AnnData<O>> {
+        ensure!(
+            self.filename() != filename.as_ref(),
+            "cannot repack an AnnData object into its own file"
+        );
+        self.write::<O, _>(&filename)?;
+        AnnData::open_with_mode(filename, OpenMode::ReadWrite)
+    }
+
+    /// Rewrite X with `chunk_shape` as the chunk shape of its datasets, e.g. to
+    /// speed up row-wise reads of a file that was written with small chunks.
+    /// X is copied chunk by chunk, so the full matrix is never loaded. The data
+    /// type, the format of sparse matrices and the compression are kept, with
+    /// the first entry of `chunk_shape` used as the chunk length of `data` and
+    /// `indices`. CSR matrices are copied by rows and CSC matrices by columns.
+    /// The original X is only deleted once the copy is in place.
+    pub fn rechunk_x<S: Into<Shape>>(&self, chunk_shape: S) -> Result<()> {
+        self.ensure_writable()?;
+        let chunk_shape = chunk_shape.into();
+        ensure!(
+            chunk_shape.ndim() > 0 && chunk_shape.as_ref().iter().all(|x| *x > 0),
+            "invalid chunk shape: {}",
+            chunk_shape,
+        );
+        if self.x.is_none() {
+            return Ok(());
+        }
+        let (dtype, source) = {
+            let x = self.x.inner();
+            (x.dtype(), x.write_config()?)
+        };
+        // Quantization is not carried over, as the values are already rounded.
+        let config = WriteConfig {
+            compression: source.compression,
+            block_size: Some(chunk_shape),
+            quantize: None,
+        };
+
+        // The copy is written next to X, which is still being read.
+        let written = if let DataType::CscMatrix(_) = dtype {
+            DynCscMatrix::write_by_chunk_with_config(
+                self.x.chunked_cols::<DynCscMatrix>(RECHUNK_CHUNK_SIZE).map(|x| x.0),
+                &self.file,
+                "__X_rechunked",
+                &config,
+            )
+        } else {
+            ArrayData::write_by_chunk_with_config(
+                self.x.chunked::<ArrayData>(RECHUNK_CHUNK_SIZE).map(|x| x.0),
+                &self.file,
+                "__X_rechunked",
+                &config,
+            )
+        };
+        if let Err(e) = written {
+            if self.file.exists("__X_rechunked")? {
+                self.file.delete("__X_rechunked")?;
+            }
+            return Err(e);
+        }
+        self.replace_x_with("__X_rechunked")
+    }
+
+    /// Replace X with the array written to `name`, which is deleted if it
+    /// cannot be moved into place. The old X is renamed rather than deleted
+    /// until the new X is in place, so it is kept if renaming fails.
+    pub(crate) fn replace_x_with(&self, name: &str) -> Result<()> {
+        if let Err(e) = self.file.rename("X", "__X_old") {
+            self.file.delete(name)?;
+            return Err(e);
+        }
+        if let Err(e) = self.file.rename(name, "X") {
+            self.file.rename("__X_old", "X")?;
+            self.file.delete(name)?;
+            return Err(e);
+        }
+        let new_elem = ArrayElem::try_from(DataContainer::open(&self.file, "X")?)?;
+        self.x.swap(&new_elem);
+        drop(new_elem);
+        self.file.delete("__X_old")
+    }
+
     /// Write a subset of the AnnData object to a new file.
     pub fn write_select<O, S, P>(&self, selection: S, filename: P) -> Result<()>
     where
@@ -365,15 +777,167 @@ impl<B: Backend> AnnData<B> {
             .as_mut()
             .map(|x| x.export_select(slice.as_slice(), &file, "layers"))
             .transpose()?;
+        self.raw
+            .lock()
+            .as_ref()
+            .map(|x| x.export_select::<O, _>(slice[0], &file, "raw"))
+            .transpose()?;
         file.close()?;
         Ok(())
     }
 
+    /// Partition the observations by the values of the obs column `column` and
+    /// write each group to `<dir>/<value>.h5ad` with the backend `O`, using
+    /// [`AnnData::write_select`]. Non-categorical columns are grouped by their
+    /// string representation. Returns the file written for each value, in order
+    /// of first appearance.
+    pub fn split_by_obs<O, P>(&self, column: &str, dir: P) -> Result<IndexMap<String, PathBuf>>
+    where
+        O: Backend,
+        P: AsRef<Path>,
+    {
+        let series = self
+            .fetch_obs_column(column)?
+            .ok_or_else(|| AnnDataError::key_not_found(column, "obs"))?;
+        ensure!(
+            series.null_count() == 0,
+            "obs column '{}' contains missing values",
+            column
+        );
+        let mut groups: IndexMap<String, Vec<usize>> = IndexMap::new();
+        let values = series.cast(&polars::prelude::DataType::String)?;
+        for (i, value) in values.str()?.into_iter().enumerate() {
+            groups.entry(value.unwrap().to_string()).or_default().push(i);
+        }
+
+        if let Some(value) = groups
+            .keys()
+            .find(|x| matches!(x.as_str(), "" | "." | "..") || x.contains(['/', '\\']))
+        {
+            bail!("cannot use '{}' as a file name", value);
+        }
+
+        std::fs::create_dir_all(dir.as_ref())?;
+        groups
+            .into_iter()
+            .map(|(value, idx)| {
+                let filename = dir.as_ref().join(format!("{}.h5ad", value));
+                self.write_select::<O, _, _>(
+                    [SelectInfoElem::from(idx), SelectInfoElem::full()],
+                    &filename,
+                )?;
+                Ok((value, filename))
+            })
+            .collect()
+    }
+
+    /// Export `obs` to a CSV file. The first column holds the observation names
+    /// and is named after the index of the dataframe. Categorical columns are
+    /// written as their string labels. The rows are read and written in
+    /// batches, and the dataframe is not cached.
+    pub fn write_obs_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_csv(self.get_obs().lock().as_mut(), path)
+    }
+
+    /// Export `var` to a CSV file. See [`AnnData::write_obs_csv`].
+    pub fn write_var_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_csv(self.get_var().lock().as_mut(), path)
+    }
+
+    /// Export `X` to a MatrixMarket coordinate file. The observation and
+    /// variable names are written to `barcodes.tsv` and `features.tsv` in the
+    /// same directory. `X` is streamed in chunks of rows, and dense matrices
+    /// are converted to coordinates on the fly.
+    pub fn write_mtx<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        ensure!(!self.get_x().is_none(), "X is empty");
+        write_mtx(
+            path.as_ref(),
+            self.n_obs(),
+            self.n_vars(),
+            self.get_x().chunked::<ArrayData>(500),
+            self.obs_names(),
+            self.var_names(),
+        )
+    }
+
     /// Get the filename of the AnnData file.
     pub fn filename(&self) -> PathBuf {
         self.file.filename()
     }
 
+    /// The mode the file was opened in. Files created by [`AnnData::new`] are
+    /// always writable.
+    pub fn mode(&self) -> OpenMode {
+        self.mode
+    }
+
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        ensure!(
+            self.mode == OpenMode::ReadWrite,
+            AnnDataError::ReadOnly(self.filename().display().to_string())
+        );
+        Ok(())
+    }
+
+    /// Check the structural consistency of the file, e.g. after writing it and
+    /// before deleting the source. All problems are collected in the returned
+    /// report rather than failing on the first one. The checks are:
+    ///
+    /// - the `encoding-type` attributes of all elements are recognized,
+    /// - the `column-order` of each dataframe lists exactly its stored columns,
+    /// - `X` and `layers` have shape `n_obs x n_vars`,
+    /// - `obsm` and `varm` have `n_obs` and `n_vars` rows,
+    /// - `obsp` and `varp` are square with `n_obs` and `n_vars` rows,
+    /// - the obs and var names, if any, match `n_obs` and `n_vars`.
+    pub fn verify(&self) -> Result<ValidationReport> {
+        let mut problems = Vec::new();
+        verify_group::<B, _>(&self.file, "", &mut problems);
+
+        let n_obs = self.n_obs();
+        let n_vars = self.n_vars();
+        let mut check = |name: &str, shape: Option<Shape>, expected: &[usize]| {
+            if let Some(shape) = shape {
+                let dims = shape.as_ref();
+                let matched = match expected {
+                    [n] => dims.first() == Some(n),
+                    _ => dims == expected,
+                };
+                if !matched {
+                    let expected = match expected {
+                        [n] => format!("{} rows", n),
+                        _ => expected.iter().join(" x "),
+                    };
+                    problems.push(format!(
+                        "{} has shape {} but expecting {}",
+                        name, shape, expected
+                    ));
+                }
+            }
+        };
+        check("X", self.x.lock().as_ref().map(|x| x.shape().clone()), &[n_obs, n_vars]);
+        for (name, arrays, expected) in [
+            ("obsm", self.obsm(), vec![n_obs]),
+            ("varm", self.varm(), vec![n_vars]),
+            ("obsp", self.obsp(), vec![n_obs, n_obs]),
+            ("varp", self.varp(), vec![n_vars, n_vars]),
+            ("layers", self.layers(), vec![n_obs, n_vars]),
+        ] {
+            for key in arrays.keys() {
+                let shape = arrays.get(&key).and_then(|x| x.shape());
+                check(&format!("{} '{}'", name, key), shape, &expected);
+            }
+        }
+        for (name, names, n) in [
+            ("obs", self.obs_names(), n_obs),
+            ("var", self.var_names(), n_vars),
+        ] {
+            if !names.is_empty() && names.len() != n {
+                problems.push(format!("{} has {} names but expecting {}", name, names.len(), n));
+            }
+        }
+        Ok(ValidationReport { problems })
+    }
+
     /// Close the AnnData object and release all resources.
     pub fn close(self) -> Result<()> {
         macro_rules! close {
@@ -387,15 +951,24 @@ impl<B: Backend> AnnData<B> {
         self.x.drop();
         self.obs.drop();
         self.var.drop();
+        self.raw.lock().as_ref().map(|x| x.drop());
+        self.raw.drop();
         close!(obsm, obsp, varm, varp, uns);
         self.file.close()
     }
 
-    /// Subset the AnnData object based on a selection.
-    pub fn subset<S>(&self, selection: S) -> Result<()>
+    /// Subset the AnnData object in place based on a selection. `X`, `obs`,
+    /// `obsm`, `obsp`, `var`, `varm`, `varp` and `layers` are subset consistently,
+    /// with pairwise arrays subset along both axes.
+    ///
+    /// Returns the original obs indices of the retained observations, in
+    /// their new order, so that external per-observation data can be kept
+    /// aligned.
+    pub fn subset<S>(&self, selection: S) -> Result<Vec<usize>>
     where
         S: AsRef<[SelectInfoElem]>,
     {
+        self.ensure_writable()?;
         let mut obs_lock = self.n_obs.lock();
         let mut vars_lock = self.n_vars.lock();
         let slice = selection.as_ref();
@@ -405,6 +978,17 @@ impl<B: Backend> AnnData<B> {
         );
         let obs_ix = &slice[0];
         let var_ix = &slice[1];
+        if !obs_lock.is_empty() {
+            obs_ix
+                .bound_check(obs_lock.get())
+                .map_err(|e| anyhow!("AnnData obs {}", e))?;
+        }
+        if !vars_lock.is_empty() {
+            var_ix
+                .bound_check(vars_lock.get())
+                .map_err(|e| anyhow!("AnnData var {}", e))?;
+        }
+        let obs_indices = SelectInfoElemBounds::new(obs_ix, obs_lock.get()).to_vec();
 
         self.x
             .lock()
@@ -427,6 +1011,11 @@ impl<B: Backend> AnnData<B> {
             .as_mut()
             .map(|obsp| obsp.subset(&[obs_ix]))
             .transpose()?;
+        self.raw
+            .lock()
+            .as_ref()
+            .map(|raw| raw.subset_obs(obs_ix))
+            .transpose()?;
 
         self.var
             .lock()
@@ -457,6 +1046,214 @@ impl<B: Backend> AnnData<B> {
             vars_lock.set(SelectInfoElemBounds::new(var_ix, vars_lock.get()).len());
         }
 
+        Ok(obs_indices)
+    }
+
+    /// Append observations without rewriting the existing data. `x_block` is
+    /// written below the rows of `X`, which must be a dense array or a CSR
+    /// matrix, and `obs_rows` is appended to `obs`. The block must have as many
+    /// columns as there are variables and the same data type as `X`.
+    ///
+    /// The names of the new observations are read from the column of `obs_rows`
+    /// named after the obs index, see [`DataFrameIndex::index_name`]. Without
+    /// such a column, the new observations are named by their positions.
+    /// Appending is not supported when `obsm`, `obsp`, `layers` or `raw` hold
+    /// data, as they would no longer be aligned with the observations.
+    ///
+    /// The inputs are validated before anything is written, and the new rows
+    /// of `X` are removed again if `obs` cannot be written. If the datasets of
+    /// `X` cannot grow in place, e.g. in HDF5 files written by other tools,
+    /// `X` is first copied into resizable datasets.
+    pub fn append_obs(&self, x_block: ArrayData, obs_rows: DataFrame) -> Result<()> {
+        self.ensure_writable()?;
+        let mut obs_lock = self.n_obs.lock();
+        ensure!(!self.x.is_none(), "cannot append observations: X is empty");
+        ensure!(
+            self.obsm().is_empty()
+                && self.obsp().is_empty()
+                && self.layers().is_empty()
+                && self.raw.is_none(),
+            "cannot append observations when obsm, obsp, layers or raw data are present"
+        );
+        let shape = x_block.shape();
+        let n_vars = self.n_vars.get();
+        ensure!(
+            shape.ndim() == 2 && shape[1] == n_vars,
+            "expecting a block with {} columns, found shape {}",
+            n_vars,
+            shape,
+        );
+        let dtype = self.x.inner().dtype();
+        ensure!(
+            x_block.data_type() == dtype,
+            "cannot append {} to X of type {}",
+            x_block.data_type(),
+            dtype,
+        );
+        ensure!(
+            matches!(dtype, DataType::Array(_) | DataType::CsrMatrix(_)),
+            "cannot append observations to X of type {}",
+            dtype,
+        );
+        let n = obs_lock.get();
+        let m = shape[0];
+
+        // The new obs is built before X is modified, so invalid rows are
+        // rejected without writing anything.
+        let (index, df) = match self.obs.lock().as_mut() {
+            Some(obs) => append_to_dataframe(obs.index.clone(), obs.data()?.clone(), obs_rows, m)?,
+            None => append_to_dataframe(n.into(), DataFrame::empty(), obs_rows, m)?,
+        };
+
+        // Datasets written without resizable dimensions, e.g. by other tools,
+        // are copied into resizable ones first.
+        if !self.x.inner().is_resizable()? {
+            let config = WriteConfig {
+                quantize: None,
+                ..self.x.inner().write_config()?
+            };
+            let result = ArrayData::write_by_chunk_with_config(
+                self.x.chunked::<ArrayData>(RECHUNK_CHUNK_SIZE).map(|x| x.0),
+                &self.file,
+                "__X_resizable",
+                &config,
+            );
+            if result.is_err() && self.file.exists("__X_resizable")? {
+                self.file.delete("__X_resizable")?;
+            }
+            drop(result?);
+            self.replace_x_with("__X_resizable")?;
+        }
+
+        // X is appended first, as its rows can be truncated again if obs
+        // cannot be written.
+        self.x.inner().append_rows(x_block)?;
+        let result = if self.obs.is_none() {
+            InnerDataFrameElem::new(&self.file, "obs", Some(index), &df)
+                .map(|obs| drop(self.obs.insert(obs)))
+        } else {
+            self.obs.inner().replace(index, df)
+        };
+        if let Err(e) = result {
+            self.x.inner().truncate_rows(n)?;
+            return Err(e);
+        }
+        obs_lock.set(n + m);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Write a dataframe to a CSV file, with the index as the first column.
+/// The columns of `df` are shared with the cached dataframe, so no copy of the
+/// data is made besides the index.
+fn write_csv<B: Backend, P: AsRef<Path>>(
+    df: Option<&mut InnerDataFrameElem<B>>,
+    path: P,
+) -> Result<()> {
+    let file = std::fs::File::create(path.as_ref())
+        .with_context(|| format!("cannot create file: {}", path.as_ref().display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+    let df = match df {
+        Some(df) => df,
+        None => return Ok(()),
+    };
+    let n = df.height();
+    let mut start = 0;
+    loop {
+        let end = (start + CSV_CHUNK_SIZE).min(n);
+        let rows = SelectInfoElem::from(start..end);
+        let mut chunk = df.select(&[rows.clone(), SelectInfoElem::full()])?;
+        if !df.index.is_empty() {
+            let index = df.index.select(&rows);
+            let name = index.index_name.clone();
+            chunk.insert_column(0, Series::new(name.into(), index.into_vec()))?;
+        }
+        CsvWriter::new(&mut writer)
+            .include_header(start == 0)
+            .finish(&mut chunk)?;
+        start = end;
+        if start >= n {
+            break;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Check that the `encoding-type` attributes of the elements in `group` are
+/// recognized and that dataframes list their stored columns, recursing into
+/// subgroups. Problems are named by the path of the element.
+fn verify_group<B: Backend, G: GroupOp<B>>(group: &G, path: &str, problems: &mut Vec<String>) {
+    let names = match group.list() {
+        Ok(names) => names,
+        Err(e) => return problems.push(format!("'{}': cannot list elements: {}", path, e)),
+    };
+    for name in names {
+        let path = format!("{}/{}", path, name);
+        let container = match DataContainer::<B>::open(group, &name) {
+            Ok(container) => container,
+            Err(e) => {
+                problems.push(format!("'{}': {}", path, e));
+                continue;
+            }
+        };
+        match container.encoding() {
+            Ok(EncodingType::DataFrame) => match column_order_mismatch(&container) {
+                Ok((missing, extra)) => {
+                    if !missing.is_empty() {
+                        problems.push(format!(
+                            "'{}': columns listed in 'column-order' but not stored: {}",
+                            path,
+                            missing.join(", ")
+                        ));
+                    }
+                    if !extra.is_empty() {
+                        problems.push(format!(
+                            "'{}': columns stored but not listed in 'column-order': {}",
+                            path,
+                            extra.join(", ")
+                        ));
+                    }
+                }
+                Err(e) => problems.push(format!("'{}': invalid dataframe: {}", path, e)),
+            },
+            Ok(_) => {}
+            Err(e) => problems.push(format!("'{}': {}", path, e)),
+        }
+        if let DataContainer::Group(group) = &container {
+            verify_group::<B, _>(group, &path, problems);
+        }
+    }
+}
+
+/// Write the chunks of a matrix and its row and column names in the layout
+/// produced by 10x Genomics: `matrix.mtx`, `barcodes.tsv` and `features.tsv`.
+pub(crate) fn write_mtx<I>(
+    path: &Path,
+    nrows: usize,
+    ncols: usize,
+    chunks: I,
+    obs_names: DataFrameIndex,
+    var_names: DataFrameIndex,
+) -> Result<()>
+where
+    I: Iterator<Item = (ArrayData, usize, usize)>,
+{
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("cannot create file: {}", path.display()))?;
+    let mut writer = MMWriter::new(std::io::BufWriter::new(file), nrows, ncols);
+    for (chunk, start, _) in chunks {
+        writer.write_chunk(chunk, start)?;
+    }
+    writer.finish()?;
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for (name, index) in [("barcodes.tsv", obs_names), ("features.tsv", var_names)] {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(dir.join(name))?);
+        for line in index.into_vec() {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
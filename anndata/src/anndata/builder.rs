@@ -0,0 +1,258 @@
+use crate::{
+    anndata::AnnData,
+    backend::Backend,
+    data::*,
+    traits::{AnnDataOp, AxisArraysOp, ElemCollectionOp},
+};
+
+use anyhow::{ensure, Result};
+use indexmap::IndexMap;
+use polars::prelude::DataFrame;
+use std::path::Path;
+
+/// Collects the elements of an AnnData object in memory and writes them in a
+/// single call to [`AnnDataBuilder::build`].
+///
+/// The dimensions of all elements are checked before the file is created, so
+/// a mismatch never leaves a partially written file behind. Adding an array
+/// under an existing key replaces it.
+///
+/// ```ignore
+/// let adata = AnnDataBuilder::new()
+///     .x(counts)
+///     .obs(obs)
+///     .obsm("X_pca", pca)
+///     .build::<H5, _>("out.h5ad")?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AnnDataBuilder {
+    x: Option<ArrayData>,
+    obs: Option<DataFrame>,
+    obs_names: Option<DataFrameIndex>,
+    var: Option<DataFrame>,
+    var_names: Option<DataFrameIndex>,
+    obsm: IndexMap<String, ArrayData>,
+    obsp: IndexMap<String, ArrayData>,
+    varm: IndexMap<String, ArrayData>,
+    varp: IndexMap<String, ArrayData>,
+    layers: IndexMap<String, ArrayData>,
+    uns: IndexMap<String, Data>,
+}
+
+impl AnnDataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn x<D: Into<ArrayData>>(mut self, data: D) -> Self {
+        self.x = Some(data.into());
+        self
+    }
+
+    pub fn obs(mut self, obs: DataFrame) -> Self {
+        self.obs = Some(obs);
+        self
+    }
+
+    pub fn obs_names(mut self, names: DataFrameIndex) -> Self {
+        self.obs_names = Some(names);
+        self
+    }
+
+    pub fn var(mut self, var: DataFrame) -> Self {
+        self.var = Some(var);
+        self
+    }
+
+    pub fn var_names(mut self, names: DataFrameIndex) -> Self {
+        self.var_names = Some(names);
+        self
+    }
+
+    pub fn obsm<D: Into<ArrayData>>(mut self, key: &str, data: D) -> Self {
+        self.obsm.insert(key.to_string(), data.into());
+        self
+    }
+
+    pub fn obsp<D: Into<ArrayData>>(mut self, key: &str, data: D) -> Self {
+        self.obsp.insert(key.to_string(), data.into());
+        self
+    }
+
+    pub fn varm<D: Into<ArrayData>>(mut self, key: &str, data: D) -> Self {
+        self.varm.insert(key.to_string(), data.into());
+        self
+    }
+
+    pub fn varp<D: Into<ArrayData>>(mut self, key: &str, data: D) -> Self {
+        self.varp.insert(key.to_string(), data.into());
+        self
+    }
+
+    pub fn layer<D: Into<ArrayData>>(mut self, key: &str, data: D) -> Self {
+        self.layers.insert(key.to_string(), data.into());
+        self
+    }
+
+    pub fn uns<D: Into<Data>>(mut self, key: &str, data: D) -> Self {
+        self.uns.insert(key.to_string(), data.into());
+        self
+    }
+
+    /// Check that the elements agree on the number of observations and
+    /// variables, which are returned.
+    pub fn validate(&self) -> Result<(Option<usize>, Option<usize>)> {
+        let mut n_obs = AxisSize::new("observations");
+        let mut n_vars = AxisSize::new("variables");
+        if let Some(x) = &self.x {
+            let shape = x.shape();
+            ensure!(shape.ndim() == 2, "X must be two-dimensional, but has shape {}", shape);
+            n_obs.check("X", shape[0])?;
+            n_vars.check("X", shape[1])?;
+        }
+        for (name, df, names, size) in [
+            ("obs", &self.obs, &self.obs_names, &mut n_obs),
+            ("var", &self.var, &self.var_names, &mut n_vars),
+        ] {
+            // A dataframe without columns does not determine the number of rows.
+            if let Some(df) = df.as_ref().filter(|df| df.width() > 0) {
+                size.check(name, df.height())?;
+            }
+            if let Some(names) = names {
+                size.check(&format!("{}_names", name), names.len())?;
+            }
+        }
+        for (name, arrays, size) in [
+            ("obsm", &self.obsm, &mut n_obs),
+            ("varm", &self.varm, &mut n_vars),
+        ] {
+            for (key, data) in arrays {
+                let shape = data.shape();
+                ensure!(
+                    shape.ndim() >= 1,
+                    "{} '{}' must have at least one dimension",
+                    name,
+                    key,
+                );
+                size.check(&format!("{} '{}'", name, key), shape[0])?;
+            }
+        }
+        for (name, arrays, size) in [
+            ("obsp", &self.obsp, &mut n_obs),
+            ("varp", &self.varp, &mut n_vars),
+        ] {
+            for (key, data) in arrays {
+                let shape = data.shape();
+                ensure!(
+                    shape.ndim() == 2 && shape[0] == shape[1],
+                    "{} '{}' must be square, but has shape {}",
+                    name,
+                    key,
+                    shape,
+                );
+                size.check(&format!("{} '{}'", name, key), shape[0])?;
+            }
+        }
+        for (key, data) in &self.layers {
+            let shape = data.shape();
+            ensure!(
+                shape.ndim() == 2,
+                "layer '{}' must be two-dimensional, but has shape {}",
+                key,
+                shape,
+            );
+            n_obs.check(&format!("layer '{}'", key), shape[0])?;
+            n_vars.check(&format!("layer '{}'", key), shape[1])?;
+        }
+        Ok((n_obs.size(), n_vars.size()))
+    }
+
+    /// Validate the elements and write them to a new file at `filename`.
+    /// If writing fails, the file is removed.
+    pub fn build<B: Backend, P: AsRef<Path>>(self, filename: P) -> Result<AnnData<B>> {
+        self.validate()?;
+        let adata = AnnData::<B>::new(&filename)?;
+        match self.write(&adata) {
+            Ok(()) => Ok(adata),
+            Err(e) => {
+                let _ = adata.close();
+                let path = filename.as_ref();
+                let _ = if path.is_dir() {
+                    std::fs::remove_dir_all(path)
+                } else {
+                    std::fs::remove_file(path)
+                };
+                Err(e)
+            }
+        }
+    }
+
+    fn write<B: Backend>(self, adata: &AnnData<B>) -> Result<()> {
+        if let Some(x) = self.x {
+            adata.set_x(x)?;
+        }
+        if let Some(obs) = self.obs {
+            adata.set_obs(obs)?;
+        }
+        if let Some(names) = self.obs_names {
+            adata.set_obs_names(names)?;
+        }
+        if let Some(var) = self.var {
+            adata.set_var(var)?;
+        }
+        if let Some(names) = self.var_names {
+            adata.set_var_names(names)?;
+        }
+        for (key, data) in self.obsm {
+            adata.obsm().add(&key, data)?;
+        }
+        for (key, data) in self.obsp {
+            adata.obsp().add(&key, data)?;
+        }
+        for (key, data) in self.varm {
+            adata.varm().add(&key, data)?;
+        }
+        for (key, data) in self.varp {
+            adata.varp().add(&key, data)?;
+        }
+        for (key, data) in self.layers {
+            adata.layers().add(&key, data)?;
+        }
+        for (key, data) in self.uns {
+            adata.uns().add(&key, data)?;
+        }
+        Ok(())
+    }
+}
+
+/// The size of an axis and the first element that determined it.
+struct AxisSize {
+    unit: &'static str,
+    size: Option<(String, usize)>,
+}
+
+impl AxisSize {
+    fn new(unit: &'static str) -> Self {
+        Self { unit, size: None }
+    }
+
+    fn size(&self) -> Option<usize> {
+        self.size.as_ref().map(|(_, n)| *n)
+    }
+
+    fn check(&mut self, source: &str, n: usize) -> Result<()> {
+        match &self.size {
+            None => self.size = Some((source.to_string(), n)),
+            Some((first, m)) => ensure!(
+                *m == n,
+                "{} has {} {}, but {} has {}",
+                source,
+                n,
+                self.unit,
+                first,
+                m,
+            ),
+        }
+        Ok(())
+    }
+}
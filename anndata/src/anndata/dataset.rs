@@ -1,30 +1,65 @@
 use crate::{
-    anndata::AnnData,
+    anndata::{AnnData, OpenMode},
     backend::Backend,
+    concat::JoinType,
     container::{
-        Axis, AxisArrays, Dim, Slot, StackedArrayElem, StackedAxisArrays, StackedDataFrame,
+        Axis, AxisArrays, Dim, Inner, Slot, StackedArrayElem, StackedAxisArrays, StackedDataFrame,
     },
     data::index::VecVecIndex,
     data::*,
-    traits::{AnnDataOp, ElemCollectionOp},
+    error::AnnDataError,
+    traits::{AnnDataOp, ArrayElemOp, AxisArraysOp, ElemCollectionOp},
 };
 
 use anyhow::{anyhow, bail, ensure, Context, Result};
-use indexmap::map::IndexMap;
+use indexmap::{map::IndexMap, IndexSet};
 use itertools::Itertools;
+use log::warn;
 use polars::{
     df,
     prelude::{Column, DataFrame},
 };
-use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use std::{
     collections::{HashMap, HashSet},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
+/// Problems found by [`AnnDataSet::validate`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub problems: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Returns true if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl std::fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_valid() {
+            write!(f, "no problems found")
+        } else {
+            write!(f, "{} problem(s) found:", self.problems.len())?;
+            self.problems
+                .iter()
+                .try_for_each(|p| write!(f, "\n    - {}", p))
+        }
+    }
+}
+
 pub struct AnnDataSet<B: Backend> {
     pub(crate) annotation: AnnData<B>,
     pub(crate) anndatas: Slot<StackedAnnData<B>>,
+    /// AnnData files that could not be opened by [`AnnDataSet::open_partial`].
+    pub(crate) missing: Vec<(String, PathBuf)>,
+    /// Whether obs columns missing from the annotation are read from the
+    /// AnnData objects, see [`AnnDataSet::set_lazy_obs`].
+    pub(crate) lazy_obs: bool,
 }
 
 impl<B: Backend> std::fmt::Display for AnnDataSet<B> {
@@ -36,8 +71,7 @@ impl<B: Backend> std::fmt::Display for AnnDataSet<B> {
             self.n_vars(),
             self.annotation.filename().display(),
         )?;
-        let adatas = self.anndatas.inner();
-        if adatas.len() > 0 {
+        if let Some(adatas) = self.anndatas.lock().as_ref().filter(|x| x.len() > 0) {
             write!(
                 f,
                 "\ncontains {} AnnData objects with keys: '{}'",
@@ -45,6 +79,13 @@ impl<B: Backend> std::fmt::Display for AnnDataSet<B> {
                 adatas.keys().join("', '")
             )?;
         }
+        if !self.missing.is_empty() {
+            write!(
+                f,
+                "\nmissing AnnData objects with keys: '{}'",
+                self.missing.iter().map(|(k, _)| k).join("', '")
+            )?;
+        }
         if let Some(obs) = self
             .annotation
             .obs
@@ -72,7 +113,7 @@ impl<B: Backend> std::fmt::Display for AnnDataSet<B> {
             .uns
             .lock()
             .as_ref()
-            .map(|x| x.keys().join("', '"))
+            .map(|x| x.keys().sorted().join("', '"))
         {
             if !keys.is_empty() {
                 write!(f, "\n    uns: '{}'", keys)?;
@@ -135,13 +176,63 @@ impl<B: Backend> AnnDataSet<B> {
         &self.annotation
     }
 
+    /// Keys and paths of the AnnData files that could not be opened by
+    /// [`AnnDataSet::open_partial`].
+    pub fn missing_children(&self) -> &[(String, PathBuf)] {
+        &self.missing
+    }
+
+    /// When enabled, obs columns that are not in the annotation are read from
+    /// the AnnData objects on demand, one column at a time, instead of being
+    /// unavailable. Disabled by default.
+    pub fn set_lazy_obs(&mut self, lazy: bool) {
+        self.lazy_obs = lazy;
+    }
+
+    pub fn lazy_obs(&self) -> bool {
+        self.lazy_obs
+    }
+
+    /// The stacked AnnData objects, or [`AnnDataError::MissingChildren`] if
+    /// some of them could not be opened.
+    pub(crate) fn stacked(&self) -> Result<Inner<'_, StackedAnnData<B>>> {
+        ensure!(
+            self.missing.is_empty(),
+            AnnDataError::MissingChildren(self.missing.iter().map(|(k, _)| k.clone()).collect())
+        );
+        Ok(self.anndatas.inner())
+    }
+
     pub fn new<'a, T, S, P>(data: T, filename: P, add_key: &str) -> Result<Self>
     where
         T: IntoIterator<Item = (S, AnnData<B>)>,
         S: ToString,
         P: AsRef<Path>,
     {
-        let anndatas = StackedAnnData::new(data)?;
+        Self::new_with_join(data, filename, add_key, JoinType::Inner, None)
+    }
+
+    /// Create a new AnnDataSet, joining the var names of the components
+    /// according to `join`. See [`StackedAnnData::new_with_join`].
+    ///
+    /// If `index_unique` is given, the key of each component is appended to
+    /// its obs names with `index_unique` as the separator, e.g. `AAAC-1_a`
+    /// for the key `a` and the separator `_`, so that barcodes shared by
+    /// components stay distinguishable. A warning is logged if the obs names
+    /// are still not unique.
+    pub fn new_with_join<'a, T, S, P>(
+        data: T,
+        filename: P,
+        add_key: &str,
+        join: JoinType,
+        index_unique: Option<&str>,
+    ) -> Result<Self>
+    where
+        T: IntoIterator<Item = (S, AnnData<B>)>,
+        S: ToString,
+        P: AsRef<Path>,
+    {
+        let anndatas = StackedAnnData::new_with_join(data, join)?;
         let n_obs = anndatas.n_obs;
         let n_vars = anndatas.n_vars;
 
@@ -185,10 +276,25 @@ impl<B: Backend> AnnDataSet<B> {
         {
             // Set OBS.
             let obs_names: DataFrameIndex = anndatas
-                .values()
-                .flat_map(|x| x.obs_names().into_iter())
+                .iter()
+                .flat_map(|(k, x)| {
+                    x.obs_names().into_iter().map(move |name| match index_unique {
+                        Some(sep) => format!("{}{}{}", name, sep, k),
+                        None => name,
+                    })
+                })
                 .collect();
             if !obs_names.is_empty() && obs_names.len() == n_obs {
+                if obs_names.contain_duplicates() {
+                    warn!(
+                        "obs names of the AnnDataSet are not unique{}",
+                        if index_unique.is_some() {
+                            ""
+                        } else {
+                            ", consider setting `index_unique`"
+                        }
+                    );
+                }
                 annotation.set_obs_names(obs_names)?;
             }
             let keys = anndatas
@@ -200,8 +306,7 @@ impl<B: Backend> AnnDataSet<B> {
         }
         {
             // Set VAR.
-            let adata = anndatas.values().next().unwrap();
-            let var_names = adata.var_names();
+            let var_names = anndatas.var_names.clone();
             if !var_names.is_empty() {
                 annotation.set_var_names(var_names)?;
             }
@@ -209,42 +314,167 @@ impl<B: Backend> AnnDataSet<B> {
         Ok(Self {
             annotation,
             anndatas: Slot::new(anndatas),
+            missing: Vec::new(),
+            lazy_obs: false,
         })
     }
 
+    /// Stack all `.h5ad` files in `dir`, in order of file name, and write the
+    /// annotation to `<dir>/_dataset.h5ads`. Each component is keyed by its
+    /// file stem, which is stored in the `sample` column of `obs`. The var names
+    /// must match as in [`StackedAnnData::new_with_join`] with an inner join,
+    /// and the error names the first file that differs.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut files = std::fs::read_dir(dir)
+            .with_context(|| format!("cannot read directory: {}", dir.display()))?
+            .map(|entry| anyhow::Ok(entry?.path()))
+            .filter(|path| {
+                path.as_ref()
+                    .map_or(true, |x| x.extension().map_or(false, |e| e == "h5ad"))
+            })
+            .collect::<Result<Vec<PathBuf>>>()?;
+        files.sort();
+        ensure!(!files.is_empty(), "no .h5ad files found in {}", dir.display());
+
+        let adatas = files
+            .iter()
+            .map(|path| {
+                let key = path.file_stem().unwrap().to_str().unwrap().to_string();
+                let adata = AnnData::open_with_mode(path, OpenMode::ReadOnly)
+                    .with_context(|| format!("cannot open file: {}", path.display()))?;
+                Ok((key, adata))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let dataset = Self::new(adatas, dir.join("_dataset.h5ads"), "sample")?;
+
+        // Store the locations relative to the annotation file, which lives in
+        // the same directory, so that the directory can be moved.
+        let (keys, filenames): (Vec<_>, Vec<_>) = files
+            .iter()
+            .map(|path| {
+                let key = path.file_stem().unwrap().to_str().unwrap().to_string();
+                (key, path.file_name().unwrap().to_str().unwrap().to_string())
+            })
+            .unzip();
+        let file_loc = df!("keys" => keys, "file_path" => filenames)?;
+        dataset.annotation.uns().add("AnnDataSet", file_loc)?;
+        Ok(dataset)
+    }
+
+    /// Open an existing AnnDataSet store, see [`AnnData::open`].
     pub fn open<P: AsRef<Path>>(
         file: B::Store,
         adata_files_update: Option<Result<HashMap<String, P>, P>>,
     ) -> Result<Self> {
-        let annotation: AnnData<B> = AnnData::open(file)?;
-        let file_path = annotation
-            .filename()
-            .read_link()
-            .unwrap_or(annotation.filename())
-            .to_path_buf();
+        Self::open_with(AnnData::open(file)?, adata_files_update, false)
+    }
+
+    /// Open an existing AnnDataSet file in the given mode. The AnnData files it
+    /// consists of are always opened read-only. Updating their locations with
+    /// `adata_files_update` writes to the file and needs [`OpenMode::ReadWrite`].
+    pub fn open_with_mode<P: AsRef<Path>>(
+        filename: impl AsRef<Path>,
+        mode: OpenMode,
+        adata_files_update: Option<Result<HashMap<String, P>, P>>,
+    ) -> Result<Self> {
+        let annotation = AnnData::open_with_mode(filename, mode)?;
+        Self::open_with(annotation, adata_files_update, false)
+    }
+
+    /// Same as [`AnnDataSet::open_with_mode`], but AnnData files that do not
+    /// exist are recorded in [`AnnDataSet::missing_children`] instead of
+    /// failing.
+    ///
+    /// While files are missing, `X` is empty and operations that read the
+    /// stacked data fail with [`AnnDataError::MissingChildren`]. The annotation
+    /// remains accessible, and the locations can be repaired with
+    /// [`AnnDataSet::update_anndata_locations`].
+    pub fn open_partial<P: AsRef<Path>>(
+        filename: impl AsRef<Path>,
+        mode: OpenMode,
+        adata_files_update: Option<Result<HashMap<String, P>, P>>,
+    ) -> Result<Self> {
+        let annotation = AnnData::open_with_mode(filename, mode)?;
+        Self::open_with(annotation, adata_files_update, true)
+    }
 
+    fn open_with<P: AsRef<Path>>(
+        annotation: AnnData<B>,
+        adata_files_update: Option<Result<HashMap<String, P>, P>>,
+        partial: bool,
+    ) -> Result<Self> {
         let adata_files = match adata_files_update {
             None => update_anndata_locations_by_map(&annotation, HashMap::<String, P>::new())?,
             Some(Ok(adata_files)) => update_anndata_locations_by_map(&annotation, adata_files)?,
             Some(Err(dir)) => update_anndata_location_dir(&annotation, dir)?,
         };
+        let (anndatas, missing) = open_children(&annotation, adata_files, partial)?;
+        Ok(Self {
+            annotation,
+            anndatas: anndatas.map_or(Slot::none(), Slot::new),
+            missing,
+            lazy_obs: false,
+        })
+    }
+
+    /// Update the locations of the AnnData files, given by their keys, and
+    /// reopen the files. Relative paths are resolved against the directory of
+    /// the annotation file. Files that still cannot be opened are listed in
+    /// [`AnnDataSet::missing_children`]. The new locations are written to the
+    /// file, which needs [`OpenMode::ReadWrite`].
+    pub fn update_anndata_locations<P: AsRef<Path>>(
+        &mut self,
+        new_locations: HashMap<String, P>,
+    ) -> Result<()> {
+        self.annotation.ensure_writable()?;
+        let adata_files = update_anndata_locations_by_map(&self.annotation, new_locations)?;
+        if let Some(anndatas) = self.anndatas.extract() {
+            anndatas.elems.into_values().try_for_each(|x| x.close())?;
+        }
+        let (anndatas, missing) = open_children(&self.annotation, adata_files, true)?;
+        self.anndatas = anndatas.map_or(Slot::none(), Slot::new);
+        self.missing = missing;
+        Ok(())
+    }
 
-        let anndatas: Vec<(String, AnnData<B>)> = adata_files
+    /// Move the locations of all AnnData files to `new_dir`, keeping their
+    /// file names, and reopen the files. This repairs the dataset after the
+    /// files were moved together to another directory. A relative `new_dir`
+    /// is resolved against the directory of the annotation file. See
+    /// [`AnnDataSet::update_anndata_locations`].
+    pub fn rebase<P: AsRef<Path>>(&mut self, new_dir: P) -> Result<()> {
+        let new_locations = read_anndata_locations(&self.annotation)?
             .into_iter()
             .map(|(k, path)| {
-                let fl = if path.is_absolute() {
-                    B::open(path)
-                } else {
-                    B::open(file_path.parent().unwrap_or(Path::new("./")).join(path))
-                }?;
-                Ok((k, AnnData::open(fl)?))
+                let name = path
+                    .file_name()
+                    .with_context(|| format!("invalid location of '{}': {}", k, path.display()))?;
+                Ok((k, new_dir.as_ref().join(name)))
             })
-            .collect::<Result<_>>()?;
+            .collect::<Result<HashMap<_, _>>>()?;
+        self.update_anndata_locations(new_locations)
+    }
 
-        Ok(Self {
-            annotation,
-            anndatas: Slot::new(StackedAnnData::new(anndatas.into_iter())?),
-        })
+    /// Store the locations of the AnnData files relative to the directory of
+    /// the annotation file, so that the dataset stays valid when the
+    /// directory tree containing all files is moved or copied to another
+    /// machine. Locations that share no directory with the annotation file,
+    /// e.g. on another drive, stay absolute.
+    pub fn make_paths_relative(&self) -> Result<()> {
+        self.annotation.ensure_writable()?;
+        let base = annotation_dir(&self.annotation);
+        let locations = read_anndata_locations(&self.annotation)?
+            .into_iter()
+            .map(|(k, path)| {
+                if path.is_absolute() {
+                    Ok((k, relative_path(&path, &base)?))
+                } else {
+                    Ok((k, path))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        write_anndata_locations(&self.annotation, &locations)
     }
 
     /// AnnDataSet will not move data across underlying AnnData objects. So the
@@ -257,6 +487,25 @@ impl<B: Backend> AnnDataSet<B> {
         selection: S,
         dir: P,
     ) -> Result<Option<Vec<usize>>> {
+        self.write_select_with_progress::<O, _, _, _>(selection, dir, |_, _| {})
+    }
+
+    /// Same as [`AnnDataSet::write_select`], but calls `progress` with the number
+    /// of AnnData objects written so far and the total number of objects after
+    /// each object. The objects are written one after another on the calling
+    /// thread.
+    pub fn write_select_with_progress<O, S, P, F>(
+        &self,
+        selection: S,
+        dir: P,
+        progress: F,
+    ) -> Result<Option<Vec<usize>>>
+    where
+        O: Backend,
+        S: AsRef<[SelectInfoElem]>,
+        P: AsRef<Path>,
+        F: FnMut(usize, usize),
+    {
         selection.as_ref()[0]
             .bound_check(self.n_obs())
             .map_err(|e| anyhow!("AnnDataSet obs {}", e))?;
@@ -269,9 +518,8 @@ impl<B: Backend> AnnDataSet<B> {
         std::fs::create_dir_all(&anndata_dir)?;
 
         let (files, obs_idx_order) =
-            self.anndatas
-                .inner()
-                .write_select::<O, _, _>(&selection, &anndata_dir, ".h5ad")?;
+            self.stacked()?
+                .write_select_with_progress::<O, _, _, _>(&selection, &anndata_dir, ".h5ad", progress)?;
 
         if let Some(order) = obs_idx_order.as_ref() {
             let idx = SelectInfoElemBounds::new(&selection.as_ref()[0], self.n_obs()).to_vec();
@@ -282,7 +530,7 @@ impl<B: Backend> AnnDataSet<B> {
             self.annotation.write_select::<O, _, _>(selection, &file)?;
         };
 
-        let adata: AnnData<O> = AnnData::open(O::open_rw(&file)?)?;
+        let adata: AnnData<O> = AnnData::open_with_mode(&file, OpenMode::ReadWrite)?;
 
         let parent_dir = if anndata_dir.is_absolute() {
             anndata_dir
@@ -302,26 +550,38 @@ impl<B: Backend> AnnDataSet<B> {
 
     /// Convert AnnDataSet to AnnData object
     pub fn to_adata<O: Backend, P: AsRef<Path>>(&self, out: P, copy_x: bool) -> Result<AnnData<O>> {
+        let stacked = if copy_x { Some(self.stacked()?) } else { None };
         self.annotation.write::<O, _>(&out)?;
-        let adata = AnnData::open(O::open_rw(&out)?)?;
-        if copy_x {
-            adata.set_x_from_iter::<_, ArrayData>(
-                self.anndatas.inner().x.chunked(500).map(|x| x.0),
-            )?;
+        let adata = AnnData::open_with_mode(&out, OpenMode::ReadWrite)?;
+        if let Some(stacked) = stacked {
+            adata.set_x_from_iter::<_, ArrayData>(stacked.x.chunked(500).map(|x| x.0))?;
         }
         Ok(adata)
     }
 
+    /// Export the stacked `X` to a MatrixMarket file. See [`AnnData::write_mtx`].
+    pub fn write_mtx<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        super::write_mtx(
+            path.as_ref(),
+            self.n_obs(),
+            self.n_vars(),
+            self.stacked()?.x.chunked::<ArrayData>(500),
+            self.obs_names(),
+            self.var_names(),
+        )
+    }
+
     pub fn to_adata_select<O, P, S>(&self, select: S, out: P, copy_x: bool) -> Result<AnnData<O>>
     where
         O: Backend,
         P: AsRef<Path>,
         S: AsRef<[SelectInfoElem]>,
     {
+        let stacked = if copy_x { Some(self.stacked()?) } else { None };
         self.annotation.write_select::<O, _, _>(&select, &out)?;
-        let adata = AnnData::open(O::open_rw(&out)?)?;
-        if copy_x {
-            let x: ArrayData = self.anndatas.inner().x.select(select.as_ref())?.unwrap();
+        let adata = AnnData::open_with_mode(&out, OpenMode::ReadWrite)?;
+        if let Some(stacked) = stacked {
+            let x: ArrayData = stacked.x.select(select.as_ref())?.unwrap();
             adata.set_x(x)?;
         }
         Ok(adata)
@@ -331,58 +591,282 @@ impl<B: Backend> AnnDataSet<B> {
     pub fn into_adata(self, copy_x: bool) -> Result<AnnData<B>> {
         if copy_x {
             self.annotation.set_x_from_iter::<_, ArrayData>(
-                self.anndatas.inner().x.chunked(500).map(|x| x.0),
+                self.stacked()?.x.chunked(500).map(|x| x.0),
             )?;
         }
-        for ann in self.anndatas.extract().unwrap().elems.into_values() {
-            ann.close()?;
+        if let Some(anndatas) = self.anndatas.extract() {
+            anndatas.elems.into_values().try_for_each(|x| x.close())?;
         }
         Ok(self.annotation)
     }
 
+    /// Check the structural consistency of the dataset. All problems are
+    /// collected in the returned report rather than failing on the first one.
+    pub fn validate(&self) -> Result<ValidationReport> {
+        let mut problems = Vec::new();
+        if !self.missing.is_empty() {
+            for (key, path) in self.missing.iter() {
+                problems.push(format!("'{}' cannot be opened at {}", key, path.display()));
+            }
+            return Ok(ValidationReport { problems });
+        }
+        let adatas = self.anndatas.inner();
+
+        // Var names must be identical across components, unless they were
+        // stacked with an outer join.
+        let first = adatas.iter().next().filter(|_| !adatas.x.is_reindexed());
+        if let Some((first_key, first)) = first {
+            let reference = first.var_names();
+            let reference_set: HashSet<String> = reference.clone().into_iter().collect();
+            for (key, adata) in adatas.iter().skip(1) {
+                let var_names = adata.var_names();
+                if var_names == reference {
+                    continue;
+                }
+                let names: HashSet<String> = var_names.into_iter().collect();
+                let missing = reference_set.difference(&names).sorted().collect::<Vec<_>>();
+                let extra = names.difference(&reference_set).sorted().collect::<Vec<_>>();
+                if missing.is_empty() && extra.is_empty() {
+                    problems.push(format!(
+                        "var names of '{}' are ordered differently from '{}'",
+                        key, first_key
+                    ));
+                } else {
+                    problems.push(format!(
+                        "var names of '{}' differ from '{}': {} missing (first: {:?}), {} extra (first: {:?})",
+                        key, first_key, missing.len(), missing.first(), extra.len(), extra.first(),
+                    ));
+                }
+            }
+        }
+
+        // Each component must be internally consistent.
+        for (key, adata) in adatas.iter() {
+            if let Some(shape) = adata.x().shape() {
+                if shape[0] != adata.n_obs() {
+                    problems.push(format!(
+                        "'{}': X has {} rows but n_obs is {}",
+                        key, shape[0], adata.n_obs()
+                    ));
+                }
+                if shape[1] != adata.n_vars() {
+                    problems.push(format!(
+                        "'{}': X has {} columns but n_vars is {}",
+                        key, shape[1], adata.n_vars()
+                    ));
+                }
+            }
+            let n_names = adata.obs_names().len();
+            if n_names != 0 && n_names != adata.n_obs() {
+                problems.push(format!(
+                    "'{}': {} obs names for {} observations",
+                    key, n_names, adata.n_obs()
+                ));
+            }
+        }
+
+        // The location table must list exactly the loaded components.
+        match self.annotation.uns().get_item::<DataFrame>("AnnDataSet") {
+            Ok(Some(df)) => match df.column("keys") {
+                Ok(keys) => {
+                    let keys = as_str_vec(keys);
+                    if !keys.iter().eq(adatas.keys()) {
+                        problems.push(format!(
+                            "location table lists '{}' but loaded components are '{}'",
+                            keys.join("', '"),
+                            adatas.keys().join("', '")
+                        ));
+                    }
+                }
+                Err(e) => problems.push(format!("invalid location table: {}", e)),
+            },
+            Ok(None) => problems.push("location table 'AnnDataSet' is missing from uns".into()),
+            Err(e) => problems.push(format!("cannot read location table: {}", e)),
+        }
+
+        // Stacked obsm arrays must agree on all but the first axis.
+        for key in adatas.get_obsm().keys() {
+            let shapes: Vec<_> = adatas
+                .iter()
+                .map(|(k, adata)| (k, adata.obsm().get(&key).and_then(|x| x.shape())))
+                .collect();
+            for (k, shape) in shapes.iter() {
+                if shape.is_none() {
+                    problems.push(format!("obsm '{}' is missing in '{}'", key, k));
+                }
+            }
+            let mut trailing = shapes
+                .iter()
+                .filter_map(|(k, s)| s.as_ref().map(|s| (k, s.as_ref()[1..].to_vec())));
+            if let Some((first_key, first)) = trailing.next() {
+                for (k, s) in trailing {
+                    if s != first {
+                        problems.push(format!(
+                            "obsm '{}' has trailing shape {:?} in '{}' but {:?} in '{}'",
+                            key, s, k, first, first_key
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Annotation arrays must match the stacked dimensions.
+        let n_obs = self.annotation.n_obs();
+        if n_obs != adatas.n_obs() {
+            problems.push(format!(
+                "annotation has {} observations but components sum to {}",
+                n_obs,
+                adatas.n_obs()
+            ));
+        }
+        for key in self.annotation.obsp().keys() {
+            if let Some(shape) = self.annotation.obsp().get(&key).and_then(|x| x.shape()) {
+                if shape[0] != adatas.n_obs() || shape[1] != adatas.n_obs() {
+                    problems.push(format!(
+                        "obsp '{}' has shape {} but expecting {} x {}",
+                        key, shape, adatas.n_obs(), adatas.n_obs()
+                    ));
+                }
+            }
+        }
+        for key in self.annotation.obsm().keys() {
+            if let Some(shape) = self.annotation.obsm().get(&key).and_then(|x| x.shape()) {
+                if shape[0] != adatas.n_obs() {
+                    problems.push(format!(
+                        "obsm '{}' has {} rows but expecting {}",
+                        key, shape[0], adatas.n_obs()
+                    ));
+                }
+            }
+        }
+
+        Ok(ValidationReport { problems })
+    }
+
     pub fn close(self) -> Result<()> {
         self.annotation.close()?;
-        for ann in self.anndatas.extract().unwrap().elems.into_values() {
-            ann.close()?;
+        if let Some(anndatas) = self.anndatas.extract() {
+            anndatas.elems.into_values().try_for_each(|x| x.close())?;
         }
         Ok(())
     }
 }
 
+/// Open the AnnData files of a dataset, given by key and location. If
+/// `partial` is true, files that cannot be opened are returned instead of
+/// failing, and no stacked object is returned if none can be opened.
+fn open_children<B: Backend>(
+    annotation: &AnnData<B>,
+    adata_files: Vec<(String, PathBuf)>,
+    partial: bool,
+) -> Result<(Option<StackedAnnData<B>>, Vec<(String, PathBuf)>)> {
+    let base = annotation_dir(annotation);
+    let mut anndatas = Vec::new();
+    let mut missing = Vec::new();
+    for (k, path) in adata_files {
+        let path = if path.is_absolute() {
+            path
+        } else {
+            base.join(path)
+        };
+        // Only files that do not exist are recorded as missing; other errors,
+        // e.g., a corrupted file, are still reported.
+        if partial && !path.exists() {
+            missing.push((k, path));
+        } else {
+            anndatas.push((k, AnnData::open_with_mode(&path, OpenMode::ReadOnly)?));
+        }
+    }
+    if anndatas.is_empty() && !missing.is_empty() {
+        return Ok((None, missing));
+    }
+
+    // Components whose var names differ from the annotation were stacked
+    // with an outer join.
+    let var_names = annotation.var_names();
+    let join = if anndatas.iter().all(|(_, x)| x.var_names() == var_names) {
+        JoinType::Inner
+    } else {
+        JoinType::Outer
+    };
+    let anndatas = StackedAnnData::new_with_join(anndatas.into_iter(), join)?;
+    // With missing components, an outer join covers only a part of the var names.
+    let partial_outer = !missing.is_empty() && matches!(join, JoinType::Outer);
+    ensure!(
+        partial_outer || anndatas.var_names == var_names,
+        "var names of the AnnData objects do not match the AnnDataSet"
+    );
+    Ok((Some(anndatas), missing))
+}
+
+/// The directory of the annotation file, against which relative locations of
+/// AnnData files are resolved.
+fn annotation_dir<B: Backend>(ann: &AnnData<B>) -> PathBuf {
+    let file_path = ann.filename().read_link().unwrap_or(ann.filename());
+    file_path.parent().unwrap_or(Path::new("./")).to_path_buf()
+}
+
+/// Express the absolute `path` relative to `base`. Both are canonicalized if
+/// they exist, and `path` is returned unchanged if they share no directory.
+fn relative_path(path: &Path, base: &Path) -> Result<PathBuf> {
+    let base = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base
+    };
+    let base = std::fs::canonicalize(base)
+        .with_context(|| format!("cannot resolve directory: {}", base.display()))?;
+    let path = std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common <= 1 {
+        return Ok(path);
+    }
+    let mut relative = PathBuf::new();
+    base.components().skip(common).for_each(|_| relative.push(".."));
+    relative.extend(path.components().skip(common));
+    Ok(relative)
+}
+
+/// Read the locations of AnnData files.
+fn read_anndata_locations<B: Backend>(ann: &AnnData<B>) -> Result<Vec<(String, PathBuf)>> {
+    let df: DataFrame = ann
+        .uns()
+        .get_item("AnnDataSet")?
+        .ok_or_else(|| AnnDataError::MissingUns("AnnDataSet".into()))?;
+    let keys = as_str_vec(df.column("keys")?);
+    let filenames = as_str_vec(df.column("file_path")?);
+    Ok(keys.into_iter().zip(filenames.into_iter().map(PathBuf::from)).collect())
+}
+
+fn write_anndata_locations<B: Backend>(
+    ann: &AnnData<B>,
+    locations: &[(String, PathBuf)],
+) -> Result<()> {
+    let (keys, filenames): (Vec<_>, Vec<_>) = locations
+        .iter()
+        .map(|(k, v)| (k.clone(), v.to_str().unwrap().to_string()))
+        .unzip();
+    ann.uns().add("AnnDataSet", df!("keys" => keys, "file_path" => filenames)?)
+}
+
 /// Update the locations of AnnData files.
 fn update_anndata_locations_by_map<B: Backend, P: AsRef<Path>>(
     ann: &AnnData<B>,
     new_locations: HashMap<String, P>,
 ) -> Result<Vec<(String, PathBuf)>> {
-    let df: DataFrame = ann
-        .uns()
-        .get_item("AnnDataSet")?
-        .context("key 'AnnDataSet' is not present")?;
-    let keys = df.column("keys").unwrap();
-    let filenames = as_str_vec(df.column("file_path")?);
-    let new_files: Vec<_> = as_str_vec(keys)
+    let new_files: Vec<_> = read_anndata_locations(ann)?
         .into_iter()
-        .zip(filenames)
         .map(|(k, v)| {
-            let name = new_locations
-                .get(&k)
-                .map_or(PathBuf::from(v), |x| x.as_ref().to_path_buf());
-            (k.to_string(), name)
+            let name = new_locations.get(&k).map_or(v, |x| x.as_ref().to_path_buf());
+            (k, name)
         })
         .collect();
-    let data = DataFrame::new(vec![
-        keys.clone(),
-        Column::new(
-            "file_path".into(),
-            new_files
-                .iter()
-                .map(|x| x.1.to_str().unwrap().to_string())
-                .collect::<Vec<_>>(),
-        ),
-    ])
-    .unwrap();
     if !new_locations.is_empty() {
-        ann.uns().add("AnnDataSet", data)?;
+        write_anndata_locations(ann, &new_files)?;
     }
     Ok(new_files)
 }
@@ -391,41 +875,20 @@ fn update_anndata_location_dir<B: Backend, P: AsRef<Path>>(
     ann: &AnnData<B>,
     dir: P,
 ) -> Result<Vec<(String, PathBuf)>> {
-    let df: DataFrame = ann
-        .uns()
-        .get_item("AnnDataSet")?
-        .context("key 'AnnDataSet' is not present")?;
-    let keys = df.column("keys").unwrap();
     let file_map: HashMap<String, PathBuf> = std::fs::read_dir(dir)?
         .map(|x| x.map(|entry| (entry.file_name().into_string().unwrap(), entry.path())))
         .collect::<Result<_, std::io::Error>>()?;
-    let filenames = as_str_vec(df.column("file_path")?);
-    let new_files: Vec<_> = as_str_vec(keys)
+    let new_files: Vec<_> = read_anndata_locations(ann)?
         .into_iter()
-        .zip(filenames)
-        .map(|(k, filename)| {
-            let path = PathBuf::from(filename);
+        .map(|(k, path)| {
             let name = path.file_name().unwrap().to_str().unwrap();
-            (
-                k,
-                file_map
-                    .get(name)
-                    .map_or(path, |x| std::fs::canonicalize(x).unwrap()),
-            )
+            let path = file_map
+                .get(name)
+                .map_or(path.clone(), |x| std::fs::canonicalize(x).unwrap());
+            (k, path)
         })
         .collect();
-    let data = DataFrame::new(vec![
-        keys.clone(),
-        Column::new(
-            "file_path".into(),
-            new_files
-                .iter()
-                .map(|x| x.1.to_str().unwrap().to_string())
-                .collect::<Vec<_>>(),
-        ),
-    ])
-    .unwrap();
-    ann.uns().add("AnnDataSet", data)?;
+    write_anndata_locations(ann, &new_files)?;
     Ok(new_files)
 }
 
@@ -434,6 +897,7 @@ pub struct StackedAnnData<B: Backend> {
     elems: IndexMap<String, AnnData<B>>,
     pub(crate) n_obs: usize,
     pub(crate) n_vars: usize,
+    pub(crate) var_names: DataFrameIndex,
     pub(crate) x: StackedArrayElem<B>,
     pub(crate) obs: StackedDataFrame<B>,
     pub(crate) obsm: StackedAxisArrays<B>,
@@ -453,7 +917,13 @@ impl<B: Backend> std::fmt::Display for StackedAnnData<B> {
 }
 
 impl<B: Backend> StackedAnnData<B> {
-    fn new<'a, T, S>(iter: T) -> Result<Self>
+    /// Stack AnnData objects.
+    ///
+    /// With [`JoinType::Inner`], all objects must have identical var names.
+    /// With [`JoinType::Outer`], the stacked var names are the union of the var
+    /// names of all objects, and `X` of each object is reindexed to this order,
+    /// with absent columns read as zeros.
+    pub fn new_with_join<T, S>(iter: T, join: JoinType) -> Result<Self>
     where
         T: IntoIterator<Item = (S, AnnData<B>)>,
         S: ToString,
@@ -462,35 +932,75 @@ impl<B: Backend> StackedAnnData<B> {
             iter.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
         ensure!(!adatas.is_empty(), "no AnnData objects to stack");
 
-        if let Some((_, first)) = adatas.first() {
-            let lock = first.var.lock();
-            let var_names = lock.as_ref().map(|x| &x.index);
-            if !adatas
-                .par_values()
-                .skip(1)
-                .all(|x| x.var.lock().as_ref().map(|x| &x.index).eq(&var_names))
-            {
-                bail!("var names mismatch");
+        let (var_names, columns) = match join {
+            JoinType::Inner => {
+                let (first_key, first) = adatas.first().unwrap();
+                {
+                    let lock = first.var.lock();
+                    let var_names = lock.as_ref().map(|x| &x.index);
+                    if let Some((key, _)) = adatas.par_iter().skip(1).find_first(|(_, x)| {
+                        !x.var.lock().as_ref().map(|x| &x.index).eq(&var_names)
+                    }) {
+                        bail!("var names of '{}' do not match those of '{}'", key, first_key);
+                    }
+                }
+                (first.var_names(), vec![None; adatas.len()])
             }
-        }
+            JoinType::Outer => {
+                ensure!(
+                    adatas
+                        .values()
+                        .all(|x| x.n_vars() == 0 || !x.var_names().is_empty()),
+                    "outer join requires all AnnData objects to have var names"
+                );
+                let names: Vec<Vec<String>> =
+                    adatas.values().map(|x| x.var_names().into_vec()).collect();
+                let union: IndexSet<String> = names.iter().flatten().cloned().collect();
+                let columns = names
+                    .iter()
+                    .map(|names| {
+                        if names.iter().eq(union.iter()) {
+                            None
+                        } else {
+                            let pos: HashMap<&str, usize> = names
+                                .iter()
+                                .enumerate()
+                                .map(|(i, x)| (x.as_str(), i))
+                                .collect();
+                            let cols = union.iter().map(|x| pos.get(x.as_str()).copied());
+                            Some(Arc::new(cols.collect()))
+                        }
+                    })
+                    .collect();
+                (union.into_iter().collect(), columns)
+            }
+        };
 
-        let x = StackedArrayElem::new(adatas.values().map(|x| x.get_x().clone()).collect())?;
+        let x = StackedArrayElem::new_reindexed(
+            adatas.values().map(|x| x.get_x().clone()).collect(),
+            columns,
+        )?;
 
-        let obs = if adatas.values().any(|x| x.obs.is_none()) {
-            StackedDataFrame::new(Vec::new())
-        } else {
-            StackedDataFrame::new(adatas.values().map(|x| x.obs.clone()).collect())
-        }?;
+        // The rows of objects without obs are read as nulls.
+        let obs = StackedDataFrame::with_heights(
+            adatas.values().map(|x| x.obs.clone()).collect(),
+            adatas.values().map(|x| x.n_obs()).collect(),
+        )?;
 
         let obsm = {
             let arrays: Vec<AxisArrays<_>> = adatas.values().map(|x| x.obsm.clone()).collect();
             StackedAxisArrays::new(Axis::Row, arrays)?
         };
 
+        let n_vars = match join {
+            JoinType::Inner => adatas.values().next().unwrap().n_vars(),
+            JoinType::Outer => var_names.len(),
+        };
         Ok(Self {
             index: adatas.values().map(|x| x.n_obs()).collect(),
             n_obs: adatas.values().map(|x| x.n_obs()).sum(),
-            n_vars: adatas.values().next().unwrap().n_vars(),
+            n_vars,
+            var_names,
             elems: adatas,
             x,
             obs,
@@ -506,6 +1016,10 @@ impl<B: Backend> StackedAnnData<B> {
         self.n_vars
     }
 
+    pub fn var_names(&self) -> &DataFrameIndex {
+        &self.var_names
+    }
+
     pub fn get_x(&self) -> &StackedArrayElem<B> {
         &self.x
     }
@@ -546,9 +1060,32 @@ impl<B: Backend> StackedAnnData<B> {
         O: Backend,
         S: AsRef<[SelectInfoElem]>,
         P: AsRef<Path> + std::marker::Sync,
+    {
+        self.write_select_with_progress::<O, _, _, _>(selection, dir, suffix, |_, _| {})
+    }
+
+    /// Same as [`StackedAnnData::write_select`], but calls `progress` with the
+    /// number of AnnData objects written so far and the total number of objects
+    /// after each object.
+    pub fn write_select_with_progress<O, S, P, F>(
+        &self,
+        selection: S,
+        dir: P,
+        suffix: &str,
+        mut progress: F,
+    ) -> Result<(IndexMap<String, String>, Option<Vec<usize>>)>
+    where
+        O: Backend,
+        S: AsRef<[SelectInfoElem]>,
+        P: AsRef<Path> + std::marker::Sync,
+        F: FnMut(usize, usize),
     {
         let slice = selection.as_ref();
         ensure!(slice.len() == 2, "selection must be 2D");
+        ensure!(
+            !self.x.is_reindexed(),
+            "cannot write a selection of AnnData objects stacked with an outer join"
+        );
 
         let (slices, mapping) = self.index.split_select(&slice[0]);
 
@@ -565,6 +1102,7 @@ impl<B: Backend> StackedAnnData<B> {
                     [Vec::new().into(), slice[1].clone()]
                 };
                 adata.write_select::<O, _, _>(select, file)?;
+                progress(i + 1, self.elems.len());
                 Ok((k.clone(), name))
             })
             .collect();
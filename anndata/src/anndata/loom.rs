@@ -0,0 +1,218 @@
+use crate::{
+    anndata::AnnData,
+    backend::{Backend, DatasetOp, GroupOp, ScalarType, StoreOp, WriteConfig},
+    container::ArrayElem,
+    data::{array::dataframe::write_series, *},
+    traits::{AnnDataOp, ArrayElemOp, AxisArraysOp},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use polars::prelude::{DataFrame, DataType, IntoColumn, Series};
+use std::path::Path;
+
+/// Number of cells read or written at a time.
+const CHUNK_SIZE: usize = 500;
+
+impl<B: Backend> AnnData<B> {
+    /// Create a new AnnData file at `out` from a loom file, which is read with
+    /// the backend `I`.
+    ///
+    /// Loom stores matrices as genes x cells, so `/matrix` and `/layers` are
+    /// transposed one chunk of cells at a time. One-dimensional `/col_attrs` and
+    /// `/row_attrs` become columns of `obs` and `var`, and two-dimensional ones
+    /// are stored in `obsm` and `varm`. The `CellID` and `Gene` attributes are
+    /// used as the observation and variable names.
+    pub fn from_loom<I: Backend, P: AsRef<Path>, Q: AsRef<Path>>(input: P, out: Q) -> Result<Self> {
+        let file = I::open(input.as_ref())
+            .with_context(|| format!("cannot open file: {}", input.as_ref().display()))?;
+        let adata = Self::new(out)?;
+
+        let matrix = file.open_dataset("matrix")?;
+        if matrix.shape()?[1] > 0 {
+            itertools::process_results(read_transposed::<I>(&matrix)?, |iter| {
+                adata.set_x_from_iter(iter)
+            })??;
+        }
+        if file.exists("layers")? {
+            let layers = file.open_group("layers")?;
+            for key in layers.list()? {
+                let dataset = layers.open_dataset(&key)?;
+                itertools::process_results(read_transposed::<I>(&dataset)?, |iter| {
+                    adata.layers().add_iter(&key, iter)
+                })??;
+            }
+        }
+
+        if file.exists("col_attrs")? {
+            let col_attrs = file.open_group("col_attrs")?;
+            let (obs, obs_names, obsm) = read_attrs::<I, _>(&col_attrs, "CellID")?;
+            adata.set_obs(obs)?;
+            if let Some(obs_names) = obs_names {
+                adata.set_obs_names(obs_names)?;
+            }
+            for (key, arr) in obsm {
+                adata.obsm().add(&key, arr)?;
+            }
+        }
+        if file.exists("row_attrs")? {
+            let row_attrs = file.open_group("row_attrs")?;
+            let (var, var_names, varm) = read_attrs::<I, _>(&row_attrs, "Gene")?;
+            adata.set_var(var)?;
+            if let Some(var_names) = var_names {
+                adata.set_var_names(var_names)?;
+            }
+            for (key, arr) in varm {
+                adata.varm().add(&key, arr)?;
+            }
+        }
+        Ok(adata)
+    }
+
+    /// Export the AnnData object to a loom file with the backend `O`.
+    /// See [`AnnData::from_loom`] for how the elements are mapped.
+    pub fn write_loom<O: Backend, P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        ensure!(!self.get_x().is_none(), "X is empty");
+        let file = O::new(path)?;
+        file.new_group("attrs")?
+            .new_scalar_dataset("LOOM_SPEC_VERSION", &"3.0.0".to_string())?;
+
+        write_transposed::<B, O, _>(&file, "matrix", self.get_x())?;
+        let layers = file.new_group("layers")?;
+        for key in self.layers().keys() {
+            write_transposed::<B, O, _>(&layers, &key, &self.layers().get(&key).unwrap())?;
+        }
+
+        let col_attrs = file.new_group("col_attrs")?;
+        write_attrs::<O, _>(&col_attrs, self.read_obs()?, self.obs_names(), "CellID")?;
+        for key in self.obsm().keys() {
+            let arr: ArrayData = self.obsm().get(&key).unwrap().get()?.unwrap();
+            DynArray::try_from(arr.to_dense()?)?.write::<O, _>(&col_attrs, &key)?;
+        }
+        let row_attrs = file.new_group("row_attrs")?;
+        write_attrs::<O, _>(&row_attrs, self.read_var()?, self.var_names(), "Gene")?;
+        for key in self.varm().keys() {
+            let arr: ArrayData = self.varm().get(&key).unwrap().get()?.unwrap();
+            DynArray::try_from(arr.to_dense()?)?.write::<O, _>(&row_attrs, &key)?;
+        }
+
+        file.new_group("col_graphs")?;
+        file.new_group("row_graphs")?;
+        file.close()
+    }
+}
+
+/// Read a genes x cells dataset as chunks of cells x genes.
+fn read_transposed<B: Backend>(
+    dataset: &B::Dataset,
+) -> Result<impl Iterator<Item = Result<DynArray>> + '_> {
+    let n = dataset.shape()?[1];
+    Ok((0..n).step_by(CHUNK_SIZE).map(move |start| {
+        let end = (start + CHUNK_SIZE).min(n);
+        let selection = [SelectInfoElem::full(), (start..end).into()];
+        Ok(transpose(dataset.read_dyn_array_slice(&selection)?))
+    }))
+}
+
+/// Write a cells x genes matrix as a genes x cells dataset, one chunk of cells
+/// at a time.
+fn write_transposed<B, O, G>(location: &G, name: &str, elem: &ArrayElem<B>) -> Result<()>
+where
+    B: Backend,
+    O: Backend,
+    G: GroupOp<O>,
+{
+    let (shape, dtype) = {
+        let inner = elem.inner();
+        (inner.shape().clone(), inner.dtype())
+    };
+    let ty = dtype
+        .scalar_type()
+        .with_context(|| format!("cannot write {} to a loom matrix", dtype))?;
+    let config = WriteConfig {
+        block_size: Some(vec![shape[1].clamp(1, 64), shape[0].clamp(1, 64)].into()),
+        ..WriteConfig::default()
+    };
+    let shape: Shape = vec![shape[1], shape[0]].into();
+    macro_rules! create {
+        ($ty:ty) => {
+            location.new_empty_dataset::<$ty>(name, &shape, config)?
+        };
+    }
+    let dataset = crate::macros::dyn_match!(ty, ScalarType, create);
+
+    for (chunk, start, end) in elem.chunked::<ArrayData>(CHUNK_SIZE) {
+        if start == end {
+            continue;
+        }
+        let selection = [SelectInfoElem::full(), (start..end).into()];
+        macro_rules! write {
+            ($variant:ident, $x:expr) => {
+                dataset.write_array_slice($x.view().into(), &selection)?
+            };
+        }
+        let chunk = DynArray::try_from(chunk.to_dense()?)?;
+        crate::macros::dyn_map!(transpose(chunk), DynArray, write);
+    }
+    Ok(())
+}
+
+/// Split loom attributes into the columns of a dataframe, the index, and
+/// two-dimensional arrays.
+fn read_attrs<B: Backend, G: GroupOp<B>>(
+    group: &G,
+    index: &str,
+) -> Result<(DataFrame, Option<DataFrameIndex>, Vec<(String, DynArray)>)> {
+    let mut columns = Vec::new();
+    let mut names = None;
+    let mut arrays = Vec::new();
+    for key in group.list()? {
+        let arr = group.open_dataset(&key)?.read_dyn_array()?;
+        match arr.shape().ndim() {
+            1 if key == index => {
+                let values: ndarray::Array1<String> = arr.try_convert()?;
+                names = Some(values.into_iter().collect());
+            }
+            1 => {
+                let mut series: Series = arr.into();
+                series.rename(key.into());
+                columns.push(series.into_column());
+            }
+            2 => arrays.push((key, arr)),
+            n => bail!("cannot read {}-dimensional loom attribute '{}'", n, key),
+        }
+    }
+    Ok((DataFrame::new(columns)?, names, arrays))
+}
+
+/// Write the names and the columns of a dataframe as loom attributes.
+/// Categorical columns are stored as strings.
+fn write_attrs<B: Backend, G: GroupOp<B>>(
+    group: &G,
+    df: DataFrame,
+    names: DataFrameIndex,
+    index: &str,
+) -> Result<()> {
+    let names = ndarray::Array1::from(names.into_vec()).into_dyn();
+    DynArray::from(names).write::<B, _>(group, index)?;
+    for column in df.get_columns() {
+        if column.name().as_str() == index {
+            continue;
+        }
+        let series = column.as_materialized_series();
+        let series = match series.dtype() {
+            DataType::Categorical(_, _) | DataType::Enum(_, _) => series.cast(&DataType::String)?,
+            _ => series.clone(),
+        };
+        write_series::<B, _>(&series, group, series.name().as_str())?;
+    }
+    Ok(())
+}
+
+pub(super) fn transpose(arr: DynArray) -> DynArray {
+    macro_rules! fun {
+        ($variant:ident, $x:expr) => {
+            DynArray::$variant($x.reversed_axes().as_standard_layout().into_owned())
+        };
+    }
+    crate::macros::dyn_map!(arr, DynArray, fun)
+}
@@ -0,0 +1,144 @@
+use crate::{
+    anndata::{new_mapping, AnnData},
+    backend::{Backend, DataContainer, GroupOp},
+    container::{ArrayElem, DataFrameElem, Slot},
+    data::*,
+    traits::ArrayElemOp,
+};
+
+use anyhow::{ensure, Result};
+use polars::prelude::DataFrame;
+
+/// A frozen copy of `X` and `var`, stored under `/raw` as in Python anndata.
+/// It keeps the full matrix available after variables have been filtered.
+/// The rows of `raw` stay aligned with the observations of the parent object.
+pub struct Raw<B: Backend> {
+    pub(crate) x: ArrayElem<B>,
+    pub(crate) var: DataFrameElem<B>,
+}
+
+impl<B: Backend> Raw<B> {
+    pub(crate) fn open(group: &B::Group) -> Result<Self> {
+        let x = if group.exists("X")? {
+            ArrayElem::try_from(DataContainer::open(group, "X")?)?
+        } else {
+            Slot::none()
+        };
+        let var = if group.exists("var")? {
+            DataFrameElem::try_from(DataContainer::open(group, "var")?)?
+        } else {
+            Slot::none()
+        };
+        Ok(Self { x, var })
+    }
+
+    pub fn get_x(&self) -> &ArrayElem<B> {
+        &self.x
+    }
+
+    pub fn get_var(&self) -> &DataFrameElem<B> {
+        &self.var
+    }
+
+    /// Number of variables in the raw matrix.
+    pub fn n_vars(&self) -> usize {
+        self.x.lock().as_ref().map_or(0, |x| x.shape()[1])
+    }
+
+    pub(crate) fn export_select<O, G>(
+        &self,
+        obs: &SelectInfoElem,
+        location: &G,
+        name: &str,
+    ) -> Result<()>
+    where
+        O: Backend,
+        G: GroupOp<O>,
+    {
+        let group = new_mapping(location, name)?;
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.export_axis::<O, _>(0, obs, &group, "X"))
+            .transpose()?;
+        self.var
+            .lock()
+            .as_ref()
+            .map(|x| x.export::<O, _>(&group, "var"))
+            .transpose()?;
+        Ok(())
+    }
+
+    pub(crate) fn subset_obs(&self, obs: &SelectInfoElem) -> Result<()> {
+        self.x
+            .lock()
+            .as_mut()
+            .map(|x| x.subset_axis(0, obs))
+            .transpose()?;
+        Ok(())
+    }
+
+    pub(crate) fn drop(&self) {
+        self.x.drop();
+        self.var.drop();
+    }
+}
+
+impl<B: Backend> AnnData<B> {
+    /// Get the raw data, if any.
+    pub fn raw(&self) -> &Slot<Raw<B>> {
+        &self.raw
+    }
+
+    /// Store a copy of the current `X` and `var` under `/raw`, replacing any
+    /// existing raw data. Call this before filtering variables to keep the
+    /// full matrix.
+    pub fn freeze_raw(&self) -> Result<()> {
+        self.ensure_writable()?;
+        ensure!(!self.x.is_none(), "cannot freeze raw data: X is empty");
+        self.del_raw()?;
+        let group = new_mapping(&self.file, "raw")?;
+        self.x.inner().export::<B, _>(&group, "X")?;
+        self.var
+            .lock()
+            .as_ref()
+            .map(|x| x.export::<B, _>(&group, "var"))
+            .transpose()?;
+        self.raw.insert(Raw::open(&group)?);
+        Ok(())
+    }
+
+    /// Read a slice of the raw matrix. Returns `None` if there is no raw data.
+    pub fn read_raw_x_slice<D, S>(&self, selection: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+        S: AsRef<[SelectInfoElem]>,
+    {
+        self.raw
+            .lock()
+            .as_ref()
+            .map_or(Ok(None), |raw| raw.x.slice(selection))
+    }
+
+    /// Read the variable annotations of the raw data.
+    pub fn read_raw_var(&self) -> Result<Option<DataFrame>> {
+        self.raw
+            .lock()
+            .as_ref()
+            .and_then(|raw| raw.var.lock().as_mut().map(|x| x.data().cloned()))
+            .transpose()
+    }
+
+    /// Delete the raw data.
+    pub fn del_raw(&self) -> Result<()> {
+        self.ensure_writable()?;
+        if let Some(raw) = self.raw.extract() {
+            raw.drop();
+        }
+        if self.file.exists("raw")? {
+            self.file.delete("raw")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    anndata::{loom, AnnData},
+    backend::Backend,
+    container::AxisArrays,
+    data::*,
+    traits::{AnnDataOp, ArrayElemOp, AxisArraysOp},
+};
+
+use anyhow::{bail, ensure, Result};
+use nalgebra_sparse::{CscMatrix, CsrMatrix};
+use polars::prelude::DataFrame;
+
+/// A transposed handle to an [`AnnData`] object, in which the variables are
+/// the observations and vice versa.
+///
+/// Nothing is copied: `obs` and `var`, `obsm` and `varm`, and `obsp` and
+/// `varp` are exchanged, and the selected part of `X` or a layer is read
+/// and transposed on demand. A CSR matrix is transposed into a CSC matrix
+/// that shares its buffers, so sparse data is never densified.
+pub struct TransposedAnnData<'a, B: Backend> {
+    adata: &'a AnnData<B>,
+}
+
+impl<B: Backend> std::fmt::Display for TransposedAnnData<'_, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Transposed AnnData object with n_obs x n_vars = {} x {} backed at '{}'",
+            self.n_obs(),
+            self.n_vars(),
+            self.adata.filename().display(),
+        )
+    }
+}
+
+impl<B: Backend> AnnData<B> {
+    /// Create a transposed handle, which treats variables as observations.
+    pub fn transposed_view(&self) -> TransposedAnnData<'_, B> {
+        TransposedAnnData { adata: self }
+    }
+}
+
+impl<'a, B: Backend> TransposedAnnData<'a, B> {
+    /// The AnnData object this handle refers to.
+    pub fn parent(&self) -> &'a AnnData<B> {
+        self.adata
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.adata.n_vars()
+    }
+
+    pub fn n_vars(&self) -> usize {
+        self.adata.n_obs()
+    }
+
+    /// Read the transposed `X`.
+    pub fn read_x<D>(&self) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.read_x_slice([SelectInfoElem::full(), SelectInfoElem::full()])
+    }
+
+    /// Read a slice of the transposed `X`. The selection refers to the
+    /// transposed axes, i.e., the variables first.
+    pub fn read_x_slice<D, S>(&self, selection: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+        S: AsRef<[SelectInfoElem]>,
+    {
+        read_transposed(&self.adata.x(), selection.as_ref())
+    }
+
+    /// Read a slice of a transposed layer. Returns `None` if the layer does
+    /// not exist.
+    pub fn read_layer_slice<D, S>(&self, key: &str, selection: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+        S: AsRef<[SelectInfoElem]>,
+    {
+        self.adata
+            .layers()
+            .get(key)
+            .map_or(Ok(None), |x| read_transposed(&x, selection.as_ref()))
+    }
+
+    /// Read the variable annotations of the parent.
+    pub fn read_obs(&self) -> Result<DataFrame> {
+        self.adata.read_var()
+    }
+
+    /// Read the observation annotations of the parent.
+    pub fn read_var(&self) -> Result<DataFrame> {
+        self.adata.read_obs()
+    }
+
+    pub fn obs_names(&self) -> DataFrameIndex {
+        self.adata.var_names()
+    }
+
+    pub fn var_names(&self) -> DataFrameIndex {
+        self.adata.obs_names()
+    }
+
+    /// The `varm` of the parent.
+    pub fn obsm(&self) -> &'a AxisArrays<B> {
+        self.adata.varm()
+    }
+
+    /// The `varp` of the parent.
+    pub fn obsp(&self) -> &'a AxisArrays<B> {
+        self.adata.varp()
+    }
+
+    /// The `obsm` of the parent.
+    pub fn varm(&self) -> &'a AxisArrays<B> {
+        self.adata.obsm()
+    }
+
+    /// The `obsp` of the parent.
+    pub fn varp(&self) -> &'a AxisArrays<B> {
+        self.adata.obsp()
+    }
+}
+
+fn read_transposed<A, D>(elem: &A, selection: &[SelectInfoElem]) -> Result<Option<D>>
+where
+    A: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+{
+    ensure!(
+        selection.len() == 2,
+        "transposed reads only support 2D selections, got {}",
+        selection.len()
+    );
+    elem.slice::<ArrayData, _>([selection[1].clone(), selection[0].clone()])?
+        .map(|x| transpose(x)?.try_into().map_err(Into::into))
+        .transpose()
+}
+
+/// Transpose a two-dimensional array. Sparse matrices switch between the CSR
+/// and CSC formats without copying.
+fn transpose(data: ArrayData) -> Result<ArrayData> {
+    macro_rules! csr_to_csc {
+        ($variant:ident, $x:expr) => {{
+            let (nrows, ncols) = ($x.nrows(), $x.ncols());
+            let (offsets, indices, values) = $x.disassemble();
+            DynCscMatrix::$variant(
+                CscMatrix::try_from_csc_data(ncols, nrows, offsets, indices, values).unwrap(),
+            )
+        }};
+    }
+    macro_rules! csc_to_csr {
+        ($variant:ident, $x:expr) => {{
+            let (nrows, ncols) = ($x.nrows(), $x.ncols());
+            let (offsets, indices, values) = $x.disassemble();
+            DynCsrMatrix::$variant(
+                CsrMatrix::try_from_csr_data(ncols, nrows, offsets, indices, values).unwrap(),
+            )
+        }};
+    }
+    let data = match data {
+        ArrayData::Array(x) => ArrayData::Array(loom::transpose(x)),
+        ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr_to_csc).into(),
+        ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, csc_to_csr).into(),
+        ArrayData::CsrNonCanonical(x) => match x.canonicalize() {
+            Ok(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr_to_csc).into(),
+            Err(_) => bail!("cannot transpose a csr matrix with duplicated entries"),
+        },
+        ArrayData::DataFrame(_) => bail!("cannot transpose a DataFrame"),
+    };
+    Ok(data)
+}
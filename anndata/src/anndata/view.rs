@@ -0,0 +1,181 @@
+use crate::{
+    anndata::AnnData,
+    backend::Backend,
+    data::*,
+    traits::{AnnDataOp, ArrayElemOp, AxisArraysOp},
+};
+
+use anyhow::{anyhow, Result};
+use polars::prelude::DataFrame;
+use std::path::Path;
+
+/// A lazy view of a subset of an [`AnnData`] object.
+///
+/// A view only records the selected rows and columns. Data is read from the
+/// store when one of the `read_*` methods is called, and only the selected
+/// entries are read. Views can be further subsetted with [`AnnDataView::view`],
+/// in which case the selections are composed and the final rows and columns
+/// are read in a single pass.
+pub struct AnnDataView<'a, B: Backend> {
+    adata: &'a AnnData<B>,
+    obs: SelectInfoElem,
+    var: SelectInfoElem,
+    n_obs: usize,
+    n_vars: usize,
+}
+
+impl<B: Backend> std::fmt::Display for AnnDataView<'_, B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "View of AnnData object with n_obs x n_vars = {} x {} backed at '{}'",
+            self.n_obs,
+            self.n_vars,
+            self.adata.filename().display(),
+        )
+    }
+}
+
+impl<B: Backend> AnnData<B> {
+    /// Create a lazy view of the selected observations and variables.
+    /// No data is read until one of the `read_*` methods of the view is called.
+    pub fn view<S>(&self, selection: S) -> Result<AnnDataView<'_, B>>
+    where
+        S: AsRef<[SelectInfoElem]>,
+    {
+        AnnDataView {
+            adata: self,
+            obs: SelectInfoElem::full(),
+            var: SelectInfoElem::full(),
+            n_obs: self.n_obs(),
+            n_vars: self.n_vars(),
+        }
+        .view(selection)
+    }
+}
+
+impl<'a, B: Backend> AnnDataView<'a, B> {
+    /// The AnnData object this view refers to.
+    pub fn parent(&self) -> &'a AnnData<B> {
+        self.adata
+    }
+
+    pub fn n_obs(&self) -> usize {
+        self.n_obs
+    }
+
+    pub fn n_vars(&self) -> usize {
+        self.n_vars
+    }
+
+    /// The selection of the view in terms of the rows and columns of the parent.
+    pub fn selection(&self) -> [&SelectInfoElem; 2] {
+        [&self.obs, &self.var]
+    }
+
+    /// Subset the view further. The selection is relative to the view, not to
+    /// the parent object.
+    pub fn view<S>(&self, selection: S) -> Result<Self>
+    where
+        S: AsRef<[SelectInfoElem]>,
+    {
+        let slice = selection.as_ref();
+        anyhow::ensure!(
+            slice.len() == 2,
+            "view only supports 2D selections, got {}",
+            slice.len()
+        );
+        slice[0]
+            .bound_check(self.n_obs)
+            .map_err(|e| anyhow!("AnnDataView obs {}", e))?;
+        slice[1]
+            .bound_check(self.n_vars)
+            .map_err(|e| anyhow!("AnnDataView var {}", e))?;
+        Ok(Self {
+            adata: self.adata,
+            obs: compose(&self.obs, self.adata.n_obs(), &slice[0]),
+            var: compose(&self.var, self.adata.n_vars(), &slice[1]),
+            n_obs: SelectInfoElemBounds::new(&slice[0], self.n_obs).len(),
+            n_vars: SelectInfoElemBounds::new(&slice[1], self.n_vars).len(),
+        })
+    }
+
+    /// Read the selected part of `X`.
+    pub fn read<D>(&self) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.adata.x().slice([self.obs.clone(), self.var.clone()])
+    }
+
+    pub fn read_obs(&self) -> Result<DataFrame> {
+        self.adata
+            .get_obs()
+            .lock()
+            .as_mut()
+            .map_or(Ok(DataFrame::empty()), |x| x.select_axis(0, &self.obs))
+    }
+
+    pub fn read_var(&self) -> Result<DataFrame> {
+        self.adata
+            .get_var()
+            .lock()
+            .as_mut()
+            .map_or(Ok(DataFrame::empty()), |x| x.select_axis(0, &self.var))
+    }
+
+    pub fn obs_names(&self) -> DataFrameIndex {
+        self.adata.obs_names().select(&self.obs)
+    }
+
+    pub fn var_names(&self) -> DataFrameIndex {
+        self.adata.var_names().select(&self.var)
+    }
+
+    /// Read the selected rows of an `obsm` element.
+    pub fn read_obsm<D>(&self, key: &str) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.adata
+            .obsm()
+            .get(key)
+            .and_then(|x| x.slice_axis(0, &self.obs).transpose())
+            .transpose()
+    }
+
+    /// Read the selected rows and columns of a layer.
+    pub fn read_layer<D>(&self, key: &str) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.adata
+            .layers()
+            .get_item_slice(key, [self.obs.clone(), self.var.clone()])
+    }
+
+    /// Write the view to a new file. See [`AnnData::write_select`].
+    pub fn write<O: Backend, P: AsRef<Path>>(&self, filename: P) -> Result<()> {
+        self.adata
+            .write_select::<O, _, _>([self.obs.clone(), self.var.clone()], filename)
+    }
+}
+
+/// Compose two selections: `outer` selects from an axis of length `len`, and
+/// `inner` selects from the result of `outer`.
+fn compose(outer: &SelectInfoElem, len: usize, inner: &SelectInfoElem) -> SelectInfoElem {
+    if outer.is_full() {
+        inner.clone()
+    } else if inner.is_full() {
+        outer.clone()
+    } else {
+        let outer = SelectInfoElemBounds::new(outer, len);
+        SelectInfoElemBounds::new(inner, outer.len())
+            .iter()
+            .map(|i| outer.index(i))
+            .collect()
+    }
+}
@@ -1,6 +1,6 @@
 mod datatype;
-use crate::data::{ArrayConvert, DynArray, SelectInfo, SelectInfoElem, Shape};
-pub use datatype::{BackendData, DataType, ScalarType};
+use crate::data::{f16, ArrayConvert, DynArray, SelectInfo, SelectInfoElem, Shape};
+pub use datatype::{BackendData, DataType, EncodingType, ScalarType};
 
 use anyhow::{bail, Result};
 use core::fmt::{Debug, Formatter};
@@ -13,12 +13,30 @@ use serde::Deserialize;
 pub enum Compression {
     Gzip(u8),
     Zst(u8),
+    /// LZF compression, which has no level. Fast but with a lower compression ratio.
+    Lzf,
+}
+
+/// Lossy quantization applied to floating point datasets on write.
+///
+/// Quantization is opt-in and only affects `f32` and `f64` datasets; other
+/// data types are written unchanged. The scheme is stored alongside the data
+/// by the backend, so reads transparently return the reconstructed values.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Quantize {
+    /// HDF5 scale-offset filter with decimal scaling. Values are rounded to
+    /// `digits` decimal places before being packed into the minimal number of
+    /// bits, so the absolute error of every element is at most `0.5 * 10^-digits`.
+    /// NaN and infinite values are not preserved by this filter.
+    ScaleOffset { digits: u8 },
 }
 
 #[derive(Debug, Clone)]
 pub struct WriteConfig {
     pub compression: Option<Compression>,
     pub block_size: Option<Shape>,
+    /// Optional lossy quantization for floating point data. Default: `None`.
+    pub quantize: Option<Quantize>,
 }
 
 impl Default for WriteConfig {
@@ -26,6 +44,7 @@ impl Default for WriteConfig {
         Self {
             compression: Some(Compression::Zst(3)),
             block_size: None,
+            quantize: None,
         }
     }
 }
@@ -86,6 +105,11 @@ pub trait GroupOp<B: Backend + ?Sized> {
     /// Check if a group or dataset exists.
     fn exists(&self, name: &str) -> Result<bool>;
 
+    /// Rename a group or dataset of this group without rewriting its data, so
+    /// that its chunking and compression are kept. Fails if `new_name` exists.
+    /// Handles to the element that were opened before are no longer valid.
+    fn rename(&self, name: &str, new_name: &str) -> Result<()>;
+
     fn new_array_dataset<'a, D, Dim>(
         &self,
         name: &str,
@@ -112,6 +136,7 @@ pub trait GroupOp<B: Backend + ?Sized> {
         let new_config = WriteConfig {
             compression: compression,
             block_size: Some(block_size),
+            quantize: config.quantize,
         };
         let dataset = self.new_empty_dataset::<D>(name, &shape.into(), new_config)?;
         dataset.write_array(arr)?;
@@ -152,7 +177,7 @@ pub trait DatasetOp<B: Backend + ?Sized> {
     /// Required methods
 
     fn dtype(&self) -> Result<ScalarType>;
-    fn shape(&self) -> Shape;
+    fn shape(&self) -> Result<Shape>;
     fn reshape(&mut self, shape: &Shape) -> Result<()>;
 
     fn write_array_slice<S, T, D>(&self, arr: CowArray<'_, T, D>, selection: &[S]) -> Result<()>
@@ -168,6 +193,18 @@ pub trait DatasetOp<B: Backend + ?Sized> {
 
     /// Optional methods
 
+    /// The chunk shape, compression and quantization of the dataset, so that
+    /// a copy can be created with the same layout. Settings the backend does
+    /// not record are returned as their defaults.
+    fn write_config(&self) -> Result<WriteConfig> {
+        Ok(WriteConfig::default())
+    }
+
+    /// Whether [`DatasetOp::reshape`] can grow the dataset.
+    fn is_resizable(&self) -> Result<bool> {
+        Ok(true)
+    }
+
     fn read_dyn_array_slice<S>(&self, selection: &[S]) -> Result<DynArray>
     where
         S: AsRef<SelectInfoElem>
@@ -181,6 +218,7 @@ pub trait DatasetOp<B: Backend + ?Sized> {
             ScalarType::U16 => self.read_array_slice::<u16, _, IxDyn>(selection)?.into(),
             ScalarType::U32 => self.read_array_slice::<u32, _, IxDyn>(selection)?.into(),
             ScalarType::U64 => self.read_array_slice::<u64, _, IxDyn>(selection)?.into(),
+            ScalarType::F16 => self.read_array_slice::<f16, _, IxDyn>(selection)?.into(),
             ScalarType::F32 => self.read_array_slice::<f32, _, IxDyn>(selection)?.into(),
             ScalarType::F64 => self.read_array_slice::<f64, _, IxDyn>(selection)?.into(),
             ScalarType::Bool => self.read_array_slice::<bool, _, IxDyn>(selection)?.into(),
@@ -202,11 +240,11 @@ pub trait DatasetOp<B: Backend + ?Sized> {
     where
         D: Dimension,
     {
-        self.read_array_slice(SelectInfo::full_slice(self.shape().ndim()).as_ref())
+        self.read_array_slice(SelectInfo::full_slice(self.shape()?.ndim()).as_ref())
     }
 
     fn read_dyn_array(&self) -> Result<DynArray> {
-        self.read_dyn_array_slice(SelectInfo::full_slice(self.shape().ndim()).as_ref())
+        self.read_dyn_array_slice(SelectInfo::full_slice(self.shape()?.ndim()).as_ref())
     }
 
     fn read_array_cast<T, D>(&self) -> Result<Array<T, D>>
@@ -311,34 +349,61 @@ impl<B: Backend> DataContainer<B> {
             .delete(&container.path().to_string_lossy())
     }
 
-    pub fn encoding_type(&self) -> Result<DataType> {
+    /// Read the `encoding-type` attribute. Groups without the attribute are
+    /// treated as dicts, and datasets as numeric scalars. Sparse matrices
+    /// written by anndata before 0.7 only carry an `h5sparse_format` attribute,
+    /// which is used when `encoding-type` is absent.
+    pub fn encoding(&self) -> Result<EncodingType> {
         let enc = match self {
-            DataContainer::Group(group) => group
-                .get_attr("encoding-type")
-                .unwrap_or("dict".to_string()),
-            DataContainer::Dataset(dataset) => dataset
-                .get_attr("encoding-type")
-                .unwrap_or("numeric-scalar".to_string()),
+            DataContainer::Group(group) => group.get_attr::<String>("encoding-type").ok(),
+            DataContainer::Dataset(dataset) => dataset.get_attr::<String>("encoding-type").ok(),
             DataContainer::Null => bail!("Null container"),
         };
-        let ty = match enc.as_str() {
-            "string" => DataType::Scalar(ScalarType::String),
-            "numeric-scalar" => DataType::Scalar(self.as_dataset()?.dtype()?),
-            "categorical" => DataType::Categorical,
-            "string-array" => DataType::Array(ScalarType::String),
-            "array" => DataType::Array(self.as_dataset()?.dtype()?),
-            "csc_matrix" => {
+        match enc {
+            Some(enc) => enc.parse(),
+            None if matches!(self, DataContainer::Group(_)) => {
+                match self.get_attr::<String>("h5sparse_format").ok().as_deref() {
+                    Some("csr") => Ok(EncodingType::CsrMatrix),
+                    Some("csc") => Ok(EncodingType::CscMatrix),
+                    Some(format) => bail!("unsupported legacy sparse format '{}'", format),
+                    None => Ok(EncodingType::Dict),
+                }
+            }
+            // Datasets written without anndata attributes, e.g. directly with h5py.
+            None => {
+                let dataset = self.as_dataset()?;
+                let is_string = dataset.dtype()? == ScalarType::String;
+                let enc = match (dataset.shape()?.ndim(), is_string) {
+                    (0, true) => EncodingType::String,
+                    (0, false) => EncodingType::NumericScalar,
+                    (_, true) => EncodingType::StringArray,
+                    (_, false) => EncodingType::Array,
+                };
+                Ok(enc)
+            }
+        }
+    }
+
+    pub fn encoding_type(&self) -> Result<DataType> {
+        let ty = match self.encoding()? {
+            EncodingType::String => DataType::Scalar(ScalarType::String),
+            EncodingType::NumericScalar => DataType::Scalar(self.as_dataset()?.dtype()?),
+            EncodingType::Categorical => DataType::Categorical,
+            EncodingType::StringArray => DataType::Array(ScalarType::String),
+            EncodingType::Array => DataType::Array(self.as_dataset()?.dtype()?),
+            EncodingType::CscMatrix => {
                 let ty = self.as_group()?.open_dataset("data")?.dtype()?;
                 DataType::CscMatrix(ty)
             }
-            "csr_matrix" => {
+            EncodingType::CsrMatrix => {
                 let ty = self.as_group()?.open_dataset("data")?.dtype()?;
                 DataType::CsrMatrix(ty)
             }
-            "dataframe" => DataType::DataFrame,
-            "mapping" | "dict" => DataType::Mapping,
-            "nullable-integer" | "nullable-boolean" => DataType::NullableArray,
-            ty => bail!("Unsupported type '{}'", ty),
+            EncodingType::DataFrame => DataType::DataFrame,
+            EncodingType::Dict => DataType::Mapping,
+            EncodingType::NullableInteger
+            | EncodingType::NullableBoolean
+            | EncodingType::NullableStringArray => DataType::NullableArray,
         };
         Ok(ty)
     }
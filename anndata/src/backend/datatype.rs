@@ -1,7 +1,8 @@
-use crate::data::{DynArray, DynCowArray, DynScalar};
+use crate::data::{f16, DynArray, DynCowArray, DynScalar};
 
 use anyhow::{bail, Result};
 use core::fmt::{Display, Formatter, Debug};
+use core::str::FromStr;
 use ndarray::{ArrayD, CowArray, IxDyn};
 use serde::{Serialize, Deserialize};
 
@@ -45,6 +46,85 @@ impl Display for DataType {
     }
 }
 
+/// The `encoding-type` attribute of a stored element, as defined by the
+/// anndata on-disk format.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EncodingType {
+    DataFrame,
+    CsrMatrix,
+    CscMatrix,
+    Array,
+    StringArray,
+    Categorical,
+    NullableInteger,
+    NullableBoolean,
+    NullableStringArray,
+    /// A group of elements. Also read from the legacy name `mapping`.
+    Dict,
+    String,
+    NumericScalar,
+}
+
+impl EncodingType {
+    /// All supported encodings.
+    pub const ALL: [EncodingType; 12] = [
+        EncodingType::DataFrame,
+        EncodingType::CsrMatrix,
+        EncodingType::CscMatrix,
+        EncodingType::Array,
+        EncodingType::StringArray,
+        EncodingType::Categorical,
+        EncodingType::NullableInteger,
+        EncodingType::NullableBoolean,
+        EncodingType::NullableStringArray,
+        EncodingType::Dict,
+        EncodingType::String,
+        EncodingType::NumericScalar,
+    ];
+
+    /// The value of the `encoding-type` attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EncodingType::DataFrame => "dataframe",
+            EncodingType::CsrMatrix => "csr_matrix",
+            EncodingType::CscMatrix => "csc_matrix",
+            EncodingType::Array => "array",
+            EncodingType::StringArray => "string-array",
+            EncodingType::Categorical => "categorical",
+            EncodingType::NullableInteger => "nullable-integer",
+            EncodingType::NullableBoolean => "nullable-boolean",
+            EncodingType::NullableStringArray => "nullable-string-array",
+            EncodingType::Dict => "dict",
+            EncodingType::String => "string",
+            EncodingType::NumericScalar => "numeric-scalar",
+        }
+    }
+}
+
+impl Display for EncodingType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for EncodingType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "mapping" {
+            return Ok(EncodingType::Dict);
+        }
+        match EncodingType::ALL.iter().find(|x| x.as_str() == s) {
+            Some(x) => Ok(*x),
+            None => bail!(
+                "unsupported encoding type '{}', expecting one of: {}",
+                s,
+                EncodingType::ALL.map(|x| x.as_str()).join(", ")
+            ),
+        }
+    }
+}
+
 /// All scalar types that are supported in an AnnData object.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ScalarType {
@@ -56,6 +136,11 @@ pub enum ScalarType {
     U16,
     U32,
     U64,
+    /// IEEE 754 half-precision float, the layout of numpy's `float16`, which
+    /// Python anndata reads and writes as is. It keeps about three significant
+    /// decimal digits and overflows to infinity above 65504, so it suits
+    /// counts and normalized values that tolerate rounding.
+    F16,
     F32,
     F64,
     Bool,
@@ -73,6 +158,7 @@ impl Display for ScalarType {
             ScalarType::U16 => write!(f, "u16"),
             ScalarType::U32 => write!(f, "u32"),
             ScalarType::U64 => write!(f, "u64"),
+            ScalarType::F16 => write!(f, "f16"),
             ScalarType::F32 => write!(f, "f32"),
             ScalarType::F64 => write!(f, "f64"),
             ScalarType::Bool => write!(f, "bool"),
@@ -322,6 +408,34 @@ impl BackendData for u64 {
     }
 }
 
+impl BackendData for f16 {
+    const DTYPE: ScalarType = ScalarType::F16;
+
+    fn into_dyn(&self) -> DynScalar {
+        DynScalar::F16(*self)
+    }
+
+    fn into_dyn_arr<'a>(arr: CowArray<'a, Self, IxDyn>) -> DynCowArray<'a> {
+        DynCowArray::F16(arr)
+    }
+
+    fn from_dyn(x: DynScalar) -> Result<Self> {
+        if let DynScalar::F16(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting f16")
+        }
+    }
+
+    fn from_dyn_arr(x: DynArray) -> Result<ArrayD<Self>> {
+        if let DynArray::F16(x) = x {
+            Ok(x)
+        } else {
+            bail!("Expecting f16 array")
+        }
+    }
+}
+
 impl BackendData for f32 {
     const DTYPE: ScalarType = ScalarType::F32;
 
@@ -432,4 +546,19 @@ impl BackendData for bool {
             bail!("Expecting bool array")
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_type() {
+        for enc in EncodingType::ALL {
+            assert_eq!(enc.to_string().parse::<EncodingType>().unwrap(), enc);
+        }
+        assert_eq!("mapping".parse::<EncodingType>().unwrap(), EncodingType::Dict);
+        let err = "awkward-array".parse::<EncodingType>().unwrap_err().to_string();
+        assert!(err.contains("'awkward-array'") && err.contains("csr_matrix"));
+    }
+}
@@ -1,10 +1,11 @@
 use crate::backend::ScalarType;
 use crate::data::utils::{array_major_minor_index_default, cs_major_minor_index2};
-use crate::data::{DataFrameIndex, DynCsrMatrix};
+use crate::data::{f16, DataFrameIndex, DynCscMatrix, DynCsrMatrix};
 use crate::{AnnDataOp, ArrayElemOp};
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use indexmap::IndexSet;
 use itertools::Itertools;
+use nalgebra_sparse::csc::CscMatrix;
 use nalgebra_sparse::csr::CsrMatrix;
 use nalgebra_sparse::pattern::SparsityPattern;
 use polars::frame::DataFrame;
@@ -31,6 +32,8 @@ where
     O: AnnDataOp,
     S: ToString,
 {
+    ensure!(!adatas.is_empty(), "cannot concatenate an empty list of AnnData objects");
+
     // Concatenate var_names
     let common_vars = adatas
         .iter()
@@ -40,6 +43,13 @@ where
         JoinType::Outer => common_vars.reduce(|a, b| a.union(&b).cloned().collect()),
     }
     .unwrap();
+    if let JoinType::Inner = join {
+        ensure!(
+            !common_vars.is_empty() || adatas.iter().all(|x| x.n_vars() == 0),
+            "var names are incompatible: the inner join of {} AnnData objects has no var names in common",
+            adatas.len(),
+        );
+    }
     out.set_var_names(common_vars.iter().cloned().collect())?;
 
     // Concatenate vars
@@ -108,6 +118,9 @@ where
                 let var_names = adata.var_names();
 
                 macro_rules! fun {
+                    (f16) => {
+                        ndarray::Array2::<f16>::zeros((n_obs, n_vars)).into()
+                    };
                     ($variant:ident) => {
                         CsrMatrix::<$variant>::zeros(n_obs, n_vars).into()
                     };
@@ -130,9 +143,9 @@ where
                                 .collect::<Vec<_>>(),
                         )
                     })
-                    .unwrap_or_else(|| crate::macros::dyn_match!(dtype, ScalarType, fun))
+                    .unwrap_or_else(|| Ok(crate::macros::dyn_match!(dtype, ScalarType, fun)))
             });
-            out.set_x_from_iter(x_arr)?;
+            itertools::process_results(x_arr, |iter| out.set_x_from_iter(iter))??;
         }
     }
 
@@ -230,11 +243,14 @@ fn align_series(
     Ok(new_series.into())
 }
 
-fn index_array(
+/// Reindex the rows and columns of an array. `None` entries are filled with zeros.
+/// Non-canonical CSR matrices are canonicalized first, which fails if they contain
+/// duplicated entries.
+pub(crate) fn index_array(
     arr: ArrayData,
     row_indices: &[Option<usize>],
     col_indices: &[Option<usize>],
-) -> ArrayData {
+) -> Result<ArrayData> {
     macro_rules! fun_array {
         ($variant:ident, $value:expr) => {
             array_major_minor_index_default(
@@ -271,9 +287,40 @@ fn index_array(
         }};
     }
 
-    match arr {
+    macro_rules! fun_csc {
+        ($variant:ident, $value:expr) => {{
+            let (offsets, indices, data) = $value.csc_data();
+            let (new_col_offsets, new_row_indices, new_data) = cs_major_minor_index2(
+                col_indices,
+                row_indices,
+                $value.nrows(),
+                offsets,
+                indices,
+                data,
+            );
+            let pattern = unsafe {
+                SparsityPattern::from_offset_and_indices_unchecked(
+                    col_indices.len(),
+                    row_indices.len(),
+                    new_col_offsets,
+                    new_row_indices,
+                )
+            };
+            CscMatrix::try_from_pattern_and_values(pattern, new_data)
+                .unwrap()
+                .into()
+        }};
+    }
+
+    let arr = match arr {
         ArrayData::Array(x) => crate::macros::dyn_map!(x, DynArray, fun_array),
         ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, fun_csr),
-        _ => todo!(),
-    }
+        ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, fun_csc),
+        ArrayData::CsrNonCanonical(x) => match x.canonicalize() {
+            Ok(x) => crate::macros::dyn_map!(x, DynCsrMatrix, fun_csr),
+            Err(_) => bail!("cannot reindex a csr matrix with duplicated entries"),
+        },
+        ArrayData::DataFrame(_) => bail!("cannot reindex a dataframe as an array"),
+    };
+    Ok(arr)
 }
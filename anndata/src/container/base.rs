@@ -1,30 +1,46 @@
 use crate::{
-    backend::{AttributeOp, Backend, DataContainer, DataType, GroupOp},
-    data::index::VecVecIndex,
+    backend::{
+        AttributeOp, Backend, BackendData, DataContainer, DataType, DatasetOp, EncodingType,
+        GroupOp, ScalarType, StoreOp, WriteConfig,
+    },
+    data::array::dataframe::{
+        check_series_dtypes, read_series, read_series_dtype, schema_of, write_series,
+    },
+    data::index::{reverse_mapping, VecVecIndex},
     data::*,
+    error::AnnDataError,
+    traits::ArrayElemOp,
 };
 
-use anyhow::{bail, ensure, Result};
-use indexmap::set::IndexSet;
+use anyhow::{bail, ensure, Context, Result};
+use indexmap::{set::IndexSet, IndexMap};
 use itertools::Itertools;
+use nalgebra_sparse::CsrMatrix;
+use ndarray::{Array1, Array2, ArrayView1, Ix1};
 use num::integer::div_rem;
-use parking_lot::{Mutex, MutexGuard};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 use polars::{
     frame::DataFrame,
-    prelude::{concat, Column, IntoLazy, UnionArgs},
-    series::IntoSeries,
+    prelude::{concat, Column, IntoColumn, IntoLazy, UnionArgs},
+    series::{IntoSeries, Series},
 };
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use smallvec::SmallVec;
 use std::{
+    borrow::Cow,
+    collections::HashMap,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
 
-/// Slot stores an optional object wrapped by Arc and Mutex.
+/// Slot stores an optional object wrapped by Arc and RwLock.
 /// Encapsulating an object inside a slot allows us to drop the object from all references.
+///
+/// [`Slot::read`] gives shared access, so any number of threads can read the
+/// object at the same time. [`Slot::lock`] and [`Slot::inner`] give exclusive
+/// access and wait until all readers are done.
 #[derive(Debug)]
-pub struct Slot<T>(pub(crate) Arc<Mutex<Option<T>>>);
+pub struct Slot<T>(pub(crate) Arc<RwLock<Option<T>>>);
 
 impl<T> Clone for Slot<T> {
     fn clone(&self) -> Self {
@@ -37,10 +53,9 @@ where
     T: std::fmt::Display,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.is_none() {
-            write!(f, "Empty or closed slot")
-        } else {
-            write!(f, "{}", self.inner().deref())
+        match self.read().as_ref() {
+            None => write!(f, "Empty or closed slot"),
+            Some(x) => write!(f, "{}", x),
         }
     }
 }
@@ -48,34 +63,40 @@ where
 impl<T> Slot<T> {
     /// Create a slot from data.
     pub fn new(x: T) -> Self {
-        Slot(Arc::new(Mutex::new(Some(x))))
+        Slot(Arc::new(RwLock::new(Some(x))))
     }
 
     /// Create an empty slot.
     pub fn none() -> Self {
-        Slot(Arc::new(Mutex::new(None)))
+        Slot(Arc::new(RwLock::new(None)))
     }
 
     pub fn is_none(&self) -> bool {
-        self.0.lock().is_none()
+        self.0.read().is_none()
+    }
+
+    /// Acquire exclusive access to the slot.
+    pub fn lock(&self) -> RwLockWriteGuard<'_, Option<T>> {
+        self.0.write()
     }
 
-    pub fn lock(&self) -> MutexGuard<'_, Option<T>> {
-        self.0.lock()
+    /// Acquire shared access to the slot. Other readers are not blocked.
+    pub fn read(&self) -> RwLockReadGuard<'_, Option<T>> {
+        self.0.read()
     }
 
     pub fn inner(&self) -> Inner<'_, T> {
-        Inner(self.0.lock())
+        Inner(self.0.write())
     }
 
     /// Insert data to the slot, and return the old data.
     pub fn insert(&self, data: T) -> Option<T> {
-        std::mem::replace(self.0.lock().deref_mut(), Some(data))
+        std::mem::replace(self.0.write().deref_mut(), Some(data))
     }
 
     /// Extract the data from the slot. The slot becomes empty after this operation.
     pub fn extract(&self) -> Option<T> {
-        std::mem::replace(self.0.lock().deref_mut(), None)
+        std::mem::replace(self.0.write().deref_mut(), None)
     }
 
     /// Remove the data from the slot.
@@ -84,13 +105,13 @@ impl<T> Slot<T> {
     }
 
     pub fn swap(&self, other: &Self) {
-        let mut self_lock = self.0.lock();
-        let mut other_lock = other.0.lock();
+        let mut self_lock = self.0.write();
+        let mut other_lock = other.0.write();
         std::mem::swap(self_lock.deref_mut(), other_lock.deref_mut());
     }
 }
 
-pub struct Inner<'a, T>(pub MutexGuard<'a, Option<T>>);
+pub struct Inner<'a, T>(pub RwLockWriteGuard<'a, Option<T>>);
 
 impl<T> Deref for Inner<'_, T> {
     type Target = T;
@@ -118,6 +139,7 @@ pub struct InnerDataFrameElem<B: Backend> {
     container: DataContainer<B>,
     column_names: IndexSet<String>,
     pub index: DataFrameIndex,
+    read_only: bool,
 }
 
 impl<B: Backend> InnerDataFrameElem<B> {
@@ -145,6 +167,7 @@ impl<B: Backend> InnerDataFrameElem<B> {
             container,
             column_names,
             index,
+            read_only: false,
         })
     }
 }
@@ -172,12 +195,9 @@ impl<B: Backend> InnerDataFrameElem<B> {
         &self.column_names
     }
 
-    /// Set a column with a Series.
-    //TODO: this is not efficient. We should be able to replace a column without reading the whole dataframe.
+    /// Set a column with a Series. See [`InnerDataFrameElem::add_column`].
     pub fn set_column<S: IntoSeries>(&mut self, name: &str, new_col: S) -> Result<()> {
-        let mut df = self.data()?.clone();
-        df.replace_or_add(name.into(), new_col)?;
-        self.save(df)
+        self.add_column(name, new_col.into_series())
     }
 
     pub fn set_index(&mut self, index: DataFrameIndex) -> Result<()> {
@@ -201,12 +221,163 @@ impl<B: Backend> InnerDataFrameElem<B> {
         }
     }
 
+    /// Read a single column without loading the whole dataframe.
+    /// Returns `None` if the column does not exist.
+    pub fn read_column(&self, name: &str) -> Result<Option<Series>> {
+        if !self.column_names.contains(name) {
+            return Ok(None);
+        }
+        let series = match self.element.as_ref() {
+            Some(df) => df.column(name)?.as_materialized_series().clone(),
+            None => {
+                let mut series = read_series(&self.open_column(name)?)
+                    .with_context(|| format!("Failed to read series: {}", name))?;
+                series.rename(name.into());
+                series
+            }
+        };
+        Ok(Some(series))
+    }
+
+    /// Returns the name and dtype of each column. Only the attributes of the
+    /// columns are read if the dataframe is not in memory.
+    pub fn schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        match self.element.as_ref() {
+            Some(df) => Ok(schema_of(df)),
+            None => self
+                .column_names
+                .iter()
+                .map(|name| {
+                    let dtype = read_series_dtype(&self.open_column(name)?)
+                        .with_context(|| format!("Failed to read dtype of series: {}", name))?;
+                    Ok((name.clone(), dtype))
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the user-defined attributes attached to a column.
+    pub fn get_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        let container = self.open_column(column)?;
+        read_column_attrs(&container)
+    }
+
+    /// Add `series` as the column `name`, or replace the column if it exists.
+    /// Only this column is written, and a replaced column keeps its position
+    /// and its attributes.
+    pub fn add_column(&mut self, name: &str, mut series: Series) -> Result<()> {
+        ensure!(
+            series.len() == self.height(),
+            AnnDataError::DimMismatch {
+                expected: self.height(),
+                found: series.len(),
+            }
+        );
+        ensure!(
+            name != self.index.index_name,
+            "cannot add a column with the name of the index: '{}'",
+            name
+        );
+        series.rename(name.into());
+        check_series_dtypes(&DataFrame::new(vec![series.clone().into_column()])?)?;
+
+        let group = self.container.as_group()?;
+        let attrs = if self.column_names.contains(name) {
+            let attrs = read_column_attrs(&self.open_column(name)?)?;
+            group.delete(name)?;
+            attrs
+        } else {
+            IndexMap::new()
+        };
+        let mut container = write_series::<B, _>(&series, group, name)?;
+        if !attrs.is_empty() {
+            write_column_attrs(&mut container, &attrs)?;
+        }
+        self.column_names.insert(name.to_string());
+        self.container
+            .new_attr("column-order", self.column_names.iter().cloned().collect::<Vec<_>>())?;
+        if let Some(df) = self.element.as_mut() {
+            df.replace_or_add(name.into(), series)?;
+        }
+        Ok(())
+    }
+
+    /// Attaches a string attribute (e.g., a description or unit) to a column.
+    /// The attribute is stored on the column's container and is preserved when
+    /// the dataframe is updated or exported.
+    pub fn set_column_attr(&mut self, column: &str, key: &str, value: &str) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        let mut container = self.open_column(column)?;
+        let mut attrs = read_column_attrs(&container)?;
+        ensure!(
+            key != COLUMN_ATTRS
+                && (attrs.contains_key(key) || container.get_json_attr(key).is_err()),
+            "'{}' is a reserved attribute name",
+            key
+        );
+        attrs.insert(key.to_string(), value.to_string());
+        write_column_attrs(&mut container, &attrs)
+    }
+
+    /// Rename columns according to `mapping`, which maps old names to new
+    /// names. The renamed columns are moved within the store without being
+    /// rewritten; the other columns and the index are left untouched.
+    pub fn rename_columns(&mut self, mapping: &HashMap<String, String>) -> Result<()> {
+        let new_names = renamed_columns(self.column_names.iter(), mapping)?;
+        ensure!(
+            !new_names.contains(&self.index.index_name),
+            "cannot rename a column to the name of the index: '{}'",
+            self.index.index_name
+        );
+        // Move all renamed columns to temporary names first, so that names can be swapped.
+        let renamed: Vec<_> = mapping.iter().filter(|(old, new)| old != new).collect();
+        let tmp_name = |i: usize| format!("__renamed_column_{}", i);
+        let moves: Vec<(String, String)> = renamed
+            .iter()
+            .enumerate()
+            .map(|(i, (old, _))| (old.to_string(), tmp_name(i)))
+            .chain(renamed.iter().enumerate().map(|(i, (_, new))| (tmp_name(i), new.to_string())))
+            .collect();
+        let group = self.container.as_group()?;
+        for (i, (from, to)) in moves.iter().enumerate() {
+            if let Err(e) = group.rename(from, to) {
+                // Undo the moves done so far, so that no column is left behind.
+                for (from, to) in moves[..i].iter().rev() {
+                    group.rename(to, from)?;
+                }
+                return Err(e);
+            }
+        }
+        self.container
+            .new_attr("column-order", new_names.iter().cloned().collect::<Vec<_>>())?;
+        self.column_names = new_names;
+        self.element = None;
+        Ok(())
+    }
+
+    fn open_column(&self, column: &str) -> Result<DataContainer<B>> {
+        ensure!(
+            self.column_names.contains(column),
+            AnnDataError::key_not_found(column, "columns")
+        );
+        DataContainer::open(self.container.as_group()?, column)
+    }
+
+    fn all_column_attrs(&self) -> Result<Vec<(String, IndexMap<String, String>)>> {
+        self.column_names
+            .iter()
+            .map(|name| Ok((name.clone(), self.get_column_attrs(name)?)))
+            .filter(|x| x.as_ref().map_or(true, |(_, attrs)| !attrs.is_empty()))
+            .collect()
+    }
+
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
         let df = match self.element {
             Some(ref df) => df.clone(),
             None => DataFrame::read(&self.container)?,
         };
         let mut container = df.write(location, name)?;
+        restore_column_attrs(&container, self.all_column_attrs()?)?;
         self.index.overwrite(&mut container)
     }
 
@@ -224,6 +395,7 @@ impl<B: Backend> InnerDataFrameElem<B> {
             self.export::<O, _>(location, name)
         } else {
             let mut container = self.select(selection)?.write(location, name)?;
+            restore_column_attrs(&container, self.all_column_attrs()?)?;
             self.index.select(&selection[0]).overwrite(&mut container)
         }
     }
@@ -245,11 +417,16 @@ impl<B: Backend> InnerDataFrameElem<B> {
         self.export_select(slice.as_slice(), location, name)
     }
 
+    /// Select rows and columns. If the dataframe is not in memory, only the
+    /// selection is read and the dataframe is not cached.
     pub fn select<S>(&mut self, selection: &[S]) -> Result<DataFrame>
     where
         S: AsRef<SelectInfoElem>,
     {
-        Ok(Selectable::select(self.data()?, selection))
+        match self.element.as_ref() {
+            Some(df) => Ok(Selectable::select(df, selection)),
+            None => DataFrame::read_select(&self.container, selection),
+        }
     }
 
     pub fn select_axis<S>(&mut self, axis: usize, selection: S) -> Result<DataFrame>
@@ -265,8 +442,12 @@ impl<B: Backend> InnerDataFrameElem<B> {
             num_recs == 0 || self.index.len() == num_recs,
             "cannot update dataframe as lengths differ"
         );
+        // Check before the container is taken, so that it stays valid on error.
+        check_series_dtypes(&data)?;
+        let attrs = self.all_column_attrs()?;
         let new = data.overwrite(std::mem::take(&mut self.container))?;
         let _ = std::mem::replace(&mut self.container, new);
+        restore_column_attrs(&self.container, attrs)?;
         self.column_names = data
             .get_column_names()
             .into_iter()
@@ -278,6 +459,19 @@ impl<B: Backend> InnerDataFrameElem<B> {
         Ok(())
     }
 
+    /// Replace both the index and the data, e.g. with the result of
+    /// [`append_to_dataframe`].
+    pub(crate) fn replace(&mut self, index: DataFrameIndex, data: DataFrame) -> Result<()> {
+        ensure!(
+            data.width() == 0 || data.height() == index.len(),
+            "cannot update dataframe as lengths differ"
+        );
+        check_series_dtypes(&data)?;
+        self.index = index;
+        self.index.overwrite(&mut self.container)?;
+        self.save(data)
+    }
+
     /// inplace subsetting the DataFrameElem.
     pub fn subset<S>(&mut self, selection: &[S]) -> Result<()>
     where
@@ -299,6 +493,68 @@ impl<B: Backend> InnerDataFrameElem<B> {
     }
 }
 
+/// Apply a renaming to a list of column names. Fails if a column in `mapping`
+/// does not exist or if the new names are not unique.
+pub(crate) fn renamed_columns<I, S>(
+    names: I,
+    mapping: &HashMap<String, String>,
+) -> Result<IndexSet<String>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let names: Vec<String> = names.into_iter().map(|x| x.as_ref().to_string()).collect();
+    if let Some(old) = mapping.keys().find(|x| !names.contains(x)) {
+        bail!(AnnDataError::key_not_found(old.as_str(), "columns"));
+    }
+    let new_names: IndexSet<String> = names
+        .iter()
+        .map(|x| mapping.get(x).unwrap_or(x).clone())
+        .collect();
+    ensure!(
+        new_names.len() == names.len(),
+        "cannot rename columns as the new names are not unique"
+    );
+    Ok(new_names)
+}
+
+/// Name of the attribute listing the user-defined attributes of a column.
+const COLUMN_ATTRS: &str = "column-attrs";
+
+fn read_column_attrs<B: Backend>(container: &DataContainer<B>) -> Result<IndexMap<String, String>> {
+    let keys: Vec<String> = container.get_attr(COLUMN_ATTRS).unwrap_or_default();
+    keys.into_iter()
+        .map(|k| {
+            let v = container.get_attr(&k)?;
+            Ok((k, v))
+        })
+        .collect()
+}
+
+fn write_column_attrs<B: Backend>(
+    container: &mut DataContainer<B>,
+    attrs: &IndexMap<String, String>,
+) -> Result<()> {
+    for (k, v) in attrs {
+        container.new_attr(k, v.as_str())?;
+    }
+    container.new_attr(COLUMN_ATTRS, attrs.keys().cloned().collect::<Vec<_>>())
+}
+
+/// Write column attributes back to the columns of `df_container` that still exist.
+fn restore_column_attrs<B: Backend>(
+    df_container: &DataContainer<B>,
+    attrs: Vec<(String, IndexMap<String, String>)>,
+) -> Result<()> {
+    let group = df_container.as_group()?;
+    for (column, attrs) in attrs {
+        if group.exists(&column)? {
+            write_column_attrs(&mut DataContainer::open(group, &column)?, &attrs)?;
+        }
+    }
+    Ok(())
+}
+
 pub type DataFrameElem<B> = Slot<InnerDataFrameElem<B>>;
 
 impl<B: Backend> TryFrom<DataContainer<B>> for DataFrameElem<B> {
@@ -318,6 +574,7 @@ impl<B: Backend> TryFrom<DataContainer<B>> for DataFrameElem<B> {
                     container,
                     column_names,
                     index,
+                    read_only: false,
                 };
                 Ok(Slot::new(df))
             }
@@ -327,6 +584,14 @@ impl<B: Backend> TryFrom<DataContainer<B>> for DataFrameElem<B> {
 }
 
 impl<B: Backend> DataFrameElem<B> {
+    /// Make subsequent modifications of the element fail with
+    /// [`AnnDataError::ReadOnly`].
+    pub(crate) fn set_read_only(&self) {
+        if let Some(x) = self.lock().as_mut() {
+            x.read_only = true;
+        }
+    }
+
     /// Delete and Remove the data from the element.
     pub fn clear(&self) -> Result<()> {
         if let Some(elem) = self.extract() {
@@ -441,6 +706,7 @@ pub struct InnerArrayElem<B: Backend> {
     cache_enabled: bool,
     container: DataContainer<B>,
     element: Option<ArrayData>,
+    read_only: bool,
 }
 
 impl<B: Backend> std::fmt::Display for InnerArrayElem<B> {
@@ -489,12 +755,13 @@ impl<B: Backend> InnerArrayElem<B> {
     }
 
     pub(crate) fn save(&mut self, data: ArrayData) -> Result<()> {
+        self.element = None;
         let new = data.overwrite(std::mem::take(&mut self.container))?;
         let _ = std::mem::replace(&mut self.container, new);
         self.dtype = data.data_type();
         self.shape = data.shape();
-        if self.element.is_some() {
-            self.element = Some(data.into());
+        if self.cache_enabled {
+            self.element = Some(data);
         }
         Ok(())
     }
@@ -507,12 +774,87 @@ impl<B: Backend> InnerArrayElem<B> {
         Ok(())
     }
 
+    /// The chunk shape and compression of the datasets backing the element.
+    /// For sparse matrices this is the configuration of the `data` dataset.
+    pub fn write_config(&self) -> Result<WriteConfig> {
+        match &self.container {
+            DataContainer::Dataset(dataset) => dataset.write_config(),
+            DataContainer::Group(group) if group.exists("data")? => {
+                group.open_dataset("data")?.write_config()
+            }
+            _ => Ok(WriteConfig::default()),
+        }
+    }
+
+    /// Export the element, creating its datasets with the given configuration.
+    pub fn export_with_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<()> {
+        match self.element.as_ref() {
+            Some(data) => data.write_with_config(location, name, config)?,
+            None => ArrayData::read(&self.container)?.write_with_config(location, name, config)?,
+        };
+        Ok(())
+    }
+
+    /// Export the element with the chunk shape and compression of its own
+    /// datasets. Quantization is not carried over, as the values are already
+    /// rounded.
+    pub fn export_with_source_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+    ) -> Result<()> {
+        let config = WriteConfig {
+            quantize: None,
+            ..self.write_config()?
+        };
+        self.export_with_config::<O, _>(location, name, &config)
+    }
+
+    /// Whether reading the data stores it in the cache, which needs exclusive
+    /// access to the element.
+    pub(crate) fn needs_caching(&self) -> bool {
+        self.cache_enabled && self.element.is_none()
+    }
+
+    /// Same as [`InnerArrayElem::data`], but the cache is never filled, so only
+    /// shared access is needed.
+    pub(crate) fn read_data(&self) -> Result<ArrayData> {
+        match self.element.as_ref() {
+            Some(data) => Ok(data.clone()),
+            None => ArrayData::read(&self.container),
+        }
+    }
+
     pub fn select<S>(&mut self, selection: &[S]) -> Result<ArrayData>
     where
         S: AsRef<SelectInfoElem>,
     {
-        if selection.as_ref().iter().all(|x| x.as_ref().is_full()) {
+        if self.needs_caching() && selection.iter().all(|x| x.as_ref().is_full()) {
             self.data()
+        } else {
+            self.read_select(selection)
+        }
+    }
+
+    /// Same as [`InnerArrayElem::select`], but the cache is never filled.
+    pub(crate) fn read_select<S>(&self, selection: &[S]) -> Result<ArrayData>
+    where
+        S: AsRef<SelectInfoElem>,
+    {
+        let selection = self.resolve(selection);
+        let selection = selection.as_slice();
+        selection
+            .iter()
+            .zip(self.shape.as_ref())
+            .filter(|(x, _)| matches!(x.as_ref(), SelectInfoElem::Mask(_)))
+            .try_for_each(|(x, n)| x.as_ref().bound_check(*n))?;
+        if selection.iter().all(|x| x.as_ref().is_full()) {
+            self.read_data()
         } else {
             match self.element.as_ref() {
                 Some(data) => Ok(data.select(selection).try_into()?),
@@ -532,6 +874,22 @@ impl<B: Backend> InnerArrayElem<B> {
         self.select(slice.as_slice())
     }
 
+    /// Resolve negative and out-of-range slice bounds against the shape, so
+    /// that readers only see bounds within the array.
+    fn resolve<'a, S>(&self, selection: &'a [S]) -> SmallVec<[Cow<'a, SelectInfoElem>; 3]>
+    where
+        S: AsRef<SelectInfoElem>,
+    {
+        selection
+            .iter()
+            .enumerate()
+            .map(|(i, x)| match self.shape.as_ref().get(i) {
+                Some(n) => x.as_ref().resolve(*n),
+                None => Cow::Borrowed(x.as_ref()),
+            })
+            .collect()
+    }
+
     pub fn export_select<O, G>(
         &mut self,
         selection: &[&SelectInfoElem],
@@ -572,7 +930,9 @@ impl<B: Backend> InnerArrayElem<B> {
     where
         S: AsRef<SelectInfoElem>,
     {
-        let data = match self.element.as_ref() {
+        let selection = self.resolve(selection);
+        let selection = selection.as_slice();
+        let data = match self.element.take() {
             Some(data) => data.select(selection),
             None => ArrayData::read_select(&self.container, selection)?,
         };
@@ -580,7 +940,7 @@ impl<B: Backend> InnerArrayElem<B> {
         self.shape = data.shape();
         let new = data.overwrite(std::mem::take(&mut self.container))?;
         let _ = std::mem::replace(&mut self.container, new);
-        if self.element.is_some() {
+        if self.cache_enabled {
             self.element = Some(data);
         }
         Ok(())
@@ -596,6 +956,379 @@ impl<B: Backend> InnerArrayElem<B> {
             .set_axis(axis, self.shape().ndim(), &full);
         self.subset(slice.as_slice())
     }
+
+    pub(crate) fn astype(&mut self, dtype: ScalarType, saturating: bool) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        ensure!(
+            !matches!(dtype, ScalarType::Bool | ScalarType::String),
+            "cannot convert to {}, the target type must be numeric",
+            dtype
+        );
+        let sparse = match self.dtype {
+            DataType::Array(ty) | DataType::CsrMatrix(ty) | DataType::CscMatrix(ty)
+                if ty == dtype =>
+            {
+                return Ok(())
+            }
+            DataType::Array(_) => false,
+            DataType::CsrMatrix(_) | DataType::CscMatrix(_) => {
+                ensure!(
+                    dtype != ScalarType::F16,
+                    AnnDataError::f16_sparse()
+                );
+                true
+            }
+            ty => bail!("cannot convert {} element to {}", ty, dtype),
+        };
+        self.element = None;
+
+        let file = self.container.store()?;
+        let path = self.container.path();
+        let parent = file.open_group(path.parent().unwrap().to_str().unwrap())?;
+        let name = path.file_name().unwrap().to_str().unwrap();
+        if sparse {
+            // Only the stored values change, the indices are kept as they are.
+            astype_dataset::<B>(self.container.as_group()?, "data", dtype, saturating)?;
+            self.container = DataContainer::open(&parent, name)?;
+        } else {
+            astype_dataset::<B>(&parent, name, dtype, saturating)?;
+            self.container = DataContainer::open(&parent, name)?;
+            self.container.new_attr("encoding-type", EncodingType::Array.as_str())?;
+            self.container.new_attr("encoding-version", "0.2.0")?;
+        }
+        self.dtype = self.container.encoding_type()?;
+        Ok(())
+    }
+
+    /// Whether the datasets of the element can grow in place. HDF5 datasets
+    /// written by other tools without resizable dimensions cannot.
+    pub(crate) fn is_resizable(&self) -> Result<bool> {
+        match &self.container {
+            DataContainer::Dataset(dataset) => dataset.is_resizable(),
+            DataContainer::Group(group) => ["data", "indices", "indptr"]
+                .into_iter()
+                .map(|name| group.open_dataset(name)?.is_resizable())
+                .fold_ok(true, |acc, x| acc && x),
+            DataContainer::Null => Ok(false),
+        }
+    }
+
+    /// Append `data` below the existing rows. The datasets are resized in
+    /// place, so the existing rows are not rewritten, and are truncated back
+    /// to their original length if writing fails. Only dense arrays and CSR
+    /// matrices stored in resizable datasets can grow along the first axis.
+    pub(crate) fn append_rows(&mut self, data: ArrayData) -> Result<()> {
+        let shape = data.shape();
+        ensure!(
+            shape.ndim() == self.shape.ndim() && shape.as_ref()[1..] == self.shape.as_ref()[1..],
+            "cannot append an array of shape {} to an element of shape {}",
+            shape,
+            self.shape,
+        );
+        ensure!(
+            data.data_type() == self.dtype,
+            "cannot append {} to {} element",
+            data.data_type(),
+            self.dtype,
+        );
+        ensure!(
+            matches!(self.dtype, DataType::Array(_) | DataType::CsrMatrix(_)),
+            "cannot append rows to {} element",
+            self.dtype,
+        );
+        ensure!(
+            self.is_resizable()?,
+            "cannot append rows as the datasets of the element are not resizable"
+        );
+        let n = self.shape[0];
+        if let Err(e) = self.write_rows(data) {
+            self.truncate_rows(n)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Shrink the element to its first `n` rows in place.
+    pub(crate) fn truncate_rows(&mut self, n: usize) -> Result<()> {
+        let mut new_shape = self.shape.clone();
+        new_shape[0] = n;
+        self.element = None;
+        match &mut self.container {
+            DataContainer::Dataset(dataset) => dataset.reshape(&new_shape)?,
+            DataContainer::Group(group) => {
+                let mut indptr = group.open_dataset("indptr")?;
+                let nnz = indptr.read_array_slice_cast::<i64, Ix1, _>(&[SelectInfoElem::from(n..n + 1)])?[0];
+                indptr.reshape(&(n + 1).into())?;
+                for name in ["data", "indices"] {
+                    group.open_dataset(name)?.reshape(&(nnz as usize).into())?;
+                }
+            }
+            DataContainer::Null => bail!("cannot truncate an empty element"),
+        }
+        if let DataContainer::Group(_) = self.container {
+            self.container.new_attr("shape", new_shape.clone())?;
+        }
+        self.shape = new_shape;
+        Ok(())
+    }
+
+    fn write_rows(&mut self, data: ArrayData) -> Result<()> {
+        let shape = data.shape();
+        let n = self.shape[0];
+        let m = shape[0];
+        let mut new_shape = self.shape.clone();
+        new_shape[0] = n + m;
+        self.element = None;
+
+        match data {
+            ArrayData::Array(x) => {
+                let DataContainer::Dataset(dataset) = &mut self.container else {
+                    bail!("expecting a dataset");
+                };
+                dataset.reshape(&new_shape)?;
+                let full = SelectInfoElem::full();
+                let selection = SelectInfoElem::from(n..n + m).set_axis(0, shape.ndim(), &full);
+                macro_rules! write {
+                    ($variant:ident, $x:expr) => {
+                        dataset.write_array_slice($x.view().into(), selection.as_slice())?
+                    };
+                }
+                crate::macros::dyn_map!(x, DynArray, write);
+            }
+            ArrayData::CsrMatrix(x) => {
+                let group = self.container.as_group()?;
+                macro_rules! append {
+                    ($variant:ident, $x:expr) => {{
+                        let (offsets, indices, values) = $x.csr_data();
+                        append_csr::<B, _>(group, offsets, indices, values)?
+                    }};
+                }
+                crate::macros::dyn_map!(x, DynCsrMatrix, append);
+            }
+            ArrayData::CsrNonCanonical(x) => {
+                let group = self.container.as_group()?;
+                macro_rules! append {
+                    ($variant:ident, $x:expr) => {{
+                        let (offsets, indices, values) = $x.csr_data();
+                        append_csr::<B, _>(group, offsets, indices, values)?
+                    }};
+                }
+                crate::macros::dyn_map!(x, DynCsrNonCanonical, append);
+            }
+            ArrayData::CscMatrix(_) => bail!("cannot append rows to a CSC matrix"),
+            ArrayData::DataFrame(_) => bail!("cannot append rows to a DataFrame element"),
+        }
+        if let DataContainer::Group(_) = self.container {
+            self.container.new_attr("shape", new_shape.clone())?;
+        }
+        self.shape = new_shape;
+        Ok(())
+    }
+}
+
+/// Returns `index` and `df` with `m` new rows appended. The names of the new
+/// rows are read from the column of `rows` named after the index, or are their
+/// positions if there is no such column. The other columns of `rows` must
+/// match those of `df`, and `rows` may have no other columns when `df` has
+/// none.
+pub(crate) fn append_to_dataframe(
+    index: DataFrameIndex,
+    mut df: DataFrame,
+    mut rows: DataFrame,
+    m: usize,
+) -> Result<(DataFrameIndex, DataFrame)> {
+    let n = index.len();
+    let names = match rows.drop_in_place(&index.index_name) {
+        Ok(column) => DataFrameIndex::try_from(column.as_materialized_series())?,
+        Err(_) => (n..n + m).into(),
+    };
+    ensure!(
+        names.len() == m,
+        "expecting {} observation names, found {}",
+        m,
+        names.len(),
+    );
+    ensure!(
+        rows.width() == 0 || rows.height() == m,
+        "cannot append {} rows with {} names",
+        rows.height(),
+        m,
+    );
+    ensure!(
+        df.get_column_names() == rows.get_column_names(),
+        "cannot append rows with columns '{}' to a dataframe with columns '{}'",
+        rows.get_column_names().into_iter().join("', '"),
+        df.get_column_names().into_iter().join("', '"),
+    );
+    if df.width() > 0 {
+        df.vstack_mut(&rows)?;
+        df.align_chunks();
+        check_series_dtypes(&df)?;
+    }
+    Ok((index.concat(names), df))
+}
+
+/// Append the rows of a CSR matrix to the `data`, `indices` and `indptr`
+/// datasets of `group`. The new row offsets are shifted by the number of
+/// stored entries. The indices are converted before anything is written.
+fn append_csr<B: Backend, T: BackendData>(
+    group: &B::Group,
+    offsets: &[usize],
+    indices: &[usize],
+    values: &[T],
+) -> Result<()> {
+    let nnz = group.open_dataset("data")?.shape()?[0];
+    let indices = IndexValues::new::<B>(group, "indices", indices.iter().copied())?;
+    let indptr = IndexValues::new::<B>(group, "indptr", offsets[1..].iter().map(|x| x + nnz))?;
+    extend_dataset::<B, _>(group, "data", ArrayView1::from(values))?;
+    indices.extend::<B>(group, "indices")?;
+    indptr.extend::<B>(group, "indptr")
+}
+
+/// Append `values` to the one-dimensional dataset `name`.
+fn extend_dataset<B: Backend, T: BackendData>(
+    group: &B::Group,
+    name: &str,
+    values: ArrayView1<'_, T>,
+) -> Result<()> {
+    let mut dataset = group.open_dataset(name)?;
+    let n = dataset.shape()?[0];
+    dataset.reshape(&(n + values.len()).into())?;
+    dataset.write_array_slice(values.into(), &[SelectInfoElem::from(n..n + values.len())])
+}
+
+/// Integers converted to the integer type of the index dataset they are
+/// appended to.
+enum IndexValues {
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+}
+
+impl IndexValues {
+    fn new<B: Backend>(
+        group: &B::Group,
+        name: &str,
+        values: impl Iterator<Item = usize>,
+    ) -> Result<Self> {
+        macro_rules! convert {
+            ($variant:ident, $ty:ty) => {{
+                let values = values
+                    .map(<$ty>::try_from)
+                    .collect::<Result<_, _>>()
+                    .with_context(|| {
+                        format!("cannot append to '{}' as it overflows {}", name, stringify!($ty))
+                    })?;
+                Ok(IndexValues::$variant(values))
+            }};
+        }
+        match group.open_dataset(name)?.dtype()? {
+            ScalarType::I32 => convert!(I32, i32),
+            ScalarType::I64 => convert!(I64, i64),
+            ScalarType::U32 => convert!(U32, u32),
+            ScalarType::U64 => convert!(U64, u64),
+            ty => bail!("cannot append to '{}' of type {}", name, ty),
+        }
+    }
+
+    fn extend<B: Backend>(&self, group: &B::Group, name: &str) -> Result<()> {
+        match self {
+            IndexValues::I32(x) => extend_dataset::<B, _>(group, name, ArrayView1::from(x)),
+            IndexValues::I64(x) => extend_dataset::<B, _>(group, name, ArrayView1::from(x)),
+            IndexValues::U32(x) => extend_dataset::<B, _>(group, name, ArrayView1::from(x)),
+            IndexValues::U64(x) => extend_dataset::<B, _>(group, name, ArrayView1::from(x)),
+        }
+    }
+}
+
+/// Fails if the element or collection stored in `container` belongs to a file
+/// that was opened read-only.
+pub(crate) fn ensure_writable<B: Backend>(
+    read_only: bool,
+    container: &impl AttributeOp<B>,
+) -> Result<()> {
+    if read_only {
+        let file = container.store()?.filename();
+        bail!(AnnDataError::ReadOnly(file.display().to_string()));
+    }
+    Ok(())
+}
+
+/// Number of values converted at a time by [`ArrayElem::astype`].
+const ASTYPE_BUFFER_SIZE: usize = 1 << 20;
+
+/// Convert the dataset `name` to `dtype`. The converted values are written to
+/// a temporary dataset with the chunk shape and compression of the original,
+/// which is then renamed to `name`. The original is kept until the renaming
+/// has succeeded, so it is left untouched when a value cannot be converted.
+fn astype_dataset<B: Backend>(
+    group: &B::Group,
+    name: &str,
+    dtype: ScalarType,
+    saturating: bool,
+) -> Result<()> {
+    let tmp = format!("{}.astype", name);
+    let old = format!("{}.astype_old", name);
+    let dataset = group.open_dataset(name)?;
+    let config = WriteConfig {
+        quantize: None,
+        ..dataset.write_config()?
+    };
+    if let Err(e) = copy_dataset::<B>(&dataset, group, &tmp, dtype, config, |x| {
+        x.astype(dtype, saturating)
+    }) {
+        if group.exists(&tmp)? {
+            group.delete(&tmp)?;
+        }
+        return Err(e);
+    }
+    drop(dataset);
+    if let Err(e) = group.rename(name, &old) {
+        group.delete(&tmp)?;
+        return Err(e);
+    }
+    if let Err(e) = group.rename(&tmp, name) {
+        group.rename(&old, name)?;
+        group.delete(&tmp)?;
+        return Err(e);
+    }
+    group.delete(&old)
+}
+
+/// Copy `src` to a new dataset of type `dtype` created with `config`, one
+/// chunk of rows at a time, transforming each chunk with `f`.
+fn copy_dataset<B: Backend>(
+    src: &B::Dataset,
+    group: &B::Group,
+    name: &str,
+    dtype: ScalarType,
+    config: WriteConfig,
+    f: impl Fn(DynArray) -> Result<DynArray>,
+) -> Result<B::Dataset> {
+    let shape = src.shape()?;
+    macro_rules! create {
+        ($ty:ty) => {
+            group.new_empty_dataset::<$ty>(name, &shape, config)?
+        };
+    }
+    let dataset = crate::macros::dyn_match!(dtype, ScalarType, create);
+
+    let row_size = shape.as_ref()[1..].iter().product::<usize>().max(1);
+    let step = (ASTYPE_BUFFER_SIZE / row_size).max(1);
+    let full = SelectInfoElem::full();
+    for start in (0..shape[0]).step_by(step) {
+        let rows = SelectInfoElem::from(start..(start + step).min(shape[0]));
+        let selection = rows.set_axis(0, shape.ndim(), &full);
+        let chunk = f(src.read_dyn_array_slice(selection.as_slice())?)?;
+        macro_rules! write {
+            ($variant:ident, $x:expr) => {
+                dataset.write_array_slice($x.view().into(), selection.as_slice())?
+            };
+        }
+        crate::macros::dyn_map!(chunk, DynArray, write);
+    }
+    Ok(dataset)
 }
 
 pub type ArrayElem<B> = Slot<InnerArrayElem<B>>;
@@ -612,12 +1345,42 @@ impl<B: Backend> TryFrom<DataContainer<B>> for ArrayElem<B> {
             cache_enabled: false,
             element: None,
             container,
+            read_only: false,
         };
         Ok(Slot::new(elem))
     }
 }
 
 impl<B: Backend> ArrayElem<B> {
+    /// Make subsequent modifications of the element fail with
+    /// [`AnnDataError::ReadOnly`].
+    pub(crate) fn set_read_only(&self) {
+        if let Some(x) = self.lock().as_mut() {
+            x.read_only = true;
+        }
+    }
+
+    /// Keep the data in memory once it has been read in full, so that later
+    /// reads do not go to the store. The cached data is replaced when the
+    /// element is saved or subsetted, and dropped when it is cleared.
+    pub fn enable_cache(&self) {
+        if let Some(x) = self.lock().as_mut() {
+            x.enable_cache();
+        }
+    }
+
+    /// Stop caching and release the cached data.
+    pub fn disable_cache(&self) {
+        if let Some(x) = self.lock().as_mut() {
+            x.disable_cache();
+        }
+    }
+
+    /// Whether the data is currently held in memory.
+    pub fn is_cached(&self) -> bool {
+        self.lock().as_ref().map_or(false, |x| x.element.is_some())
+    }
+
     /// Delete and Remove the data from the element.
     pub fn clear(&self) -> Result<()> {
         if let Some(elem) = self.extract() {
@@ -632,6 +1395,44 @@ impl<B: Backend> ArrayElem<B> {
     {
         ChunkedArrayElem::new(self.clone(), chunk_size)
     }
+
+    /// Rewrite the stored values as `dtype` in place, one chunk of rows at a
+    /// time, e.g. to turn integer counts into `f32` before normalization.
+    /// Values that would overflow the new type are rejected, unless `saturating`
+    /// is set, in which case they are clamped (see [`DynArray::astype`]).
+    /// For sparse matrices only the values are converted.
+    pub fn astype(&self, dtype: ScalarType, saturating: bool) -> Result<()> {
+        self.lock()
+            .as_mut()
+            .context("cannot convert an empty element")?
+            .astype(dtype, saturating)
+    }
+
+    /// Iterate over vertical slabs of `chunk_size` columns. Each item holds the
+    /// data of the slab and its start and end column.
+    pub fn chunked_cols<D>(&self, chunk_size: usize) -> ChunkedColsArrayElem<Self, D>
+    where
+        D: TryFrom<ArrayData>,
+    {
+        ChunkedColsArrayElem::new(self.clone(), chunk_size)
+    }
+
+    /// Iterate over the rows of a numeric two-dimensional array, reading
+    /// `chunk_size` rows at a time. Each row gives the column indices and
+    /// values of its entries as slices, e.g. the genes detected in a cell.
+    /// The rows of a CSR chunk are views into its own buffers. See [`SparseRow`].
+    pub fn iter_sparse_rows(
+        &self,
+        chunk_size: usize,
+    ) -> Result<SparseRows<ChunkedArrayElem<B, ArrayData>>> {
+        let (dtype, shape) = self
+            .lock()
+            .as_ref()
+            .map(|x| (x.dtype(), x.shape().clone()))
+            .context("cannot iterate over the rows of an empty element")?;
+        ensure_numeric_matrix(dtype, &shape)?;
+        Ok(SparseRows::new(self.chunked(chunk_size)))
+    }
 }
 
 /// Horizontal concatenated dataframe elements.
@@ -671,39 +1472,58 @@ impl<B: Backend> StackedDataFrame<B> {
     }
 
     pub fn height(&self) -> usize {
-        self.elems.iter().map(|x| x.inner().height()).sum()
+        self.index.len()
     }
 
     pub fn new(elems: Vec<DataFrameElem<B>>) -> Result<Self> {
-        let index = elems
+        ensure!(
+            elems.iter().all(|x| x.is_none()) || elems.iter().all(|x| !x.is_none()),
+            "slots must be either all empty or all full"
+        );
+        let heights = elems
             .iter()
             .map(|x| x.lock().as_ref().map(|x| x.height()).unwrap_or(0))
             .collect();
-        if elems.iter().all(|x| x.is_none()) {
-            Ok(Self {
-                column_names: IndexSet::new(),
-                elems: Arc::new(elems),
-                index,
-            })
-        } else if elems.iter().all(|x| !x.is_none()) {
-            let column_names = elems
-                .iter()
-                .map(|x| x.inner().get_column_names().clone())
-                .reduce(|shared_keys, next_keys| {
-                    shared_keys
-                        .intersection(&next_keys)
-                        .map(|x| x.to_owned())
-                        .collect()
-                })
-                .unwrap_or(IndexSet::new());
-            Ok(Self {
-                column_names,
-                elems: Arc::new(elems),
-                index,
-            })
-        } else {
-            bail!("slots must be either all empty or all full");
+        Self::with_heights(elems, heights)
+    }
+
+    /// Stack dataframe elements whose slots may be empty. `heights` gives the
+    /// number of rows of each component, and the rows of a component without
+    /// a dataframe are read as nulls. The columns are those shared by all
+    /// components that have a dataframe.
+    pub(crate) fn with_heights(elems: Vec<DataFrameElem<B>>, heights: Vec<usize>) -> Result<Self> {
+        ensure!(
+            elems.len() == heights.len(),
+            "expecting {} heights, found {}",
+            elems.len(),
+            heights.len()
+        );
+        for (el, &height) in elems.iter().zip(heights.iter()) {
+            if let Some(df) = el.lock().as_ref() {
+                ensure!(
+                    df.height() == height,
+                    "dataframe has {} rows, expecting {}",
+                    df.height(),
+                    height
+                );
+            }
         }
+        let column_names = elems
+            .iter()
+            .filter(|x| !x.is_none())
+            .map(|x| x.inner().get_column_names().clone())
+            .reduce(|shared_keys, next_keys| {
+                shared_keys
+                    .intersection(&next_keys)
+                    .map(|x| x.to_owned())
+                    .collect()
+            })
+            .unwrap_or(IndexSet::new());
+        Ok(Self {
+            column_names,
+            elems: Arc::new(elems),
+            index: heights.into_iter().collect(),
+        })
     }
 
     pub fn get_column_names(&self) -> &IndexSet<String> {
@@ -715,52 +1535,125 @@ impl<B: Backend> StackedDataFrame<B> {
             DataFrame::empty()
         } else {
             let _sc = polars::datatypes::string_cache::StringCacheHolder::hold();
-            let mut elems = self.elems.iter();
-            let mut columns = elems
-                .next()
-                .unwrap()
-                .inner()
-                .data()?
-                .columns(self.column_names.iter())?
-                .into_iter()
-                .cloned()
-                .collect::<Vec<_>>();
-            elems.try_for_each(|el| {
-                let mut inner = el.inner();
-                let col = inner.data()?.columns(self.column_names.iter())?;
-                columns
-                    .iter_mut()
-                    .zip(col.into_iter())
-                    .try_for_each(|(a, b)| {
-                        a.append(b)?;
-                        Ok::<_, anyhow::Error>(())
-                    })
-            })?;
+            let dfs = self
+                .elems
+                .iter()
+                .map(|el| match el.lock().as_mut() {
+                    Some(x) => x.data().cloned().map(Some),
+                    None => Ok(None),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let columns = self
+                .column_names
+                .iter()
+                .map(|name| {
+                    let series = dfs
+                        .iter()
+                        .map(|df| {
+                            df.as_ref()
+                                .map(|df| {
+                                    Ok::<_, anyhow::Error>(
+                                        df.column(name)?.as_materialized_series().clone(),
+                                    )
+                                })
+                                .transpose()
+                        })
+                        .collect::<Result<Vec<Option<Series>>>>()?;
+                    Ok(self.vstack_with_nulls(name, series)?.into_column())
+                })
+                .collect::<Result<Vec<Column>>>()?;
             DataFrame::new(columns)?
         };
         Ok(df)
     }
 
+    /// Read the column `name` of each component and stack them. Only this
+    /// column is read, and the dataframes of the components are not kept in
+    /// memory. Returns `None` if the column is not shared by all components
+    /// that have a dataframe.
+    pub fn read_column(&self, name: &str) -> Result<Option<Series>> {
+        if self.column_names.contains(name) {
+            self.stack_column(name).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn stack_column(&self, name: &str) -> Result<Series> {
+        // Categories of different components must share the string cache.
+        let _sc = polars::datatypes::string_cache::StringCacheHolder::hold();
+        let series = self
+            .elems
+            .iter()
+            .map(|el| match el.lock().as_ref() {
+                Some(x) => x
+                    .read_column(name)?
+                    .with_context(|| format!("column '{}' is missing from a component", name))
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.vstack_with_nulls(name, series)
+    }
+
+    /// Stack the series of the components, filling the rows of components
+    /// without a dataframe with nulls.
+    fn vstack_with_nulls(&self, name: &str, series: Vec<Option<Series>>) -> Result<Series> {
+        let dtype = series
+            .iter()
+            .flatten()
+            .next()
+            .map(|x| x.dtype().clone())
+            .with_context(|| format!("column '{}' is missing from all components", name))?;
+        let series = series
+            .into_iter()
+            .zip(self.index.lengths())
+            .map(|(x, n)| x.unwrap_or_else(|| Series::full_null(name.into(), n, &dtype)));
+        let mut series = Stackable::vstack(series)?;
+        series.rename(name.into());
+        Ok(series)
+    }
+
     pub fn select<S>(&self, selection: &[S]) -> Result<DataFrame>
     where
         S: AsRef<SelectInfoElem>,
     {
         let (indices, mapping) = self.index.split_select(selection.as_ref()[0].as_ref());
-        let dfs = self
+        // Components without a dataframe give `Err(n)`, the number of selected rows.
+        let parts = self
             .elems
             .iter()
+            .zip(self.index.lengths())
             .enumerate()
-            .flat_map(|(i, el)| {
-                indices.get(&i).and_then(|idx| {
+            .flat_map(|(i, (el, len))| {
+                indices.get(&i).map(|idx| {
                     let select: SmallVec<[_; 3]> = std::iter::once(idx)
                         .chain(selection.as_ref()[1..].iter().map(|x| x.as_ref()))
                         .collect();
-                    el.lock()
-                        .as_mut()
-                        .map(|x| x.select(select.as_slice()).unwrap().lazy())
+                    match el.lock().as_mut() {
+                        Some(x) => Ok(Ok(x.select(select.as_slice())?)),
+                        None => Ok(Err(SelectInfoElemBounds::new(idx, len).len())),
+                    }
                 })
             })
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>>>()?;
+        let schema = parts.iter().find_map(|x| x.as_ref().ok()).map(schema_of);
+        let dfs = parts
+            .into_iter()
+            .map(|x| match x {
+                Ok(df) => Ok(df.lazy()),
+                Err(n) => {
+                    let columns = schema
+                        .iter()
+                        .flatten()
+                        .map(|(name, dtype)| {
+                            Series::full_null(name.as_str().into(), n, dtype).into_column()
+                        })
+                        .collect();
+                    Ok(DataFrame::new(columns)?.lazy())
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
         let df = concat(
             &dfs,
             UnionArgs {
@@ -780,12 +1673,24 @@ impl<B: Backend> StackedDataFrame<B> {
         }
     }
 
-    // TODO: this is not efficient, we should use the index to select the columns
     pub fn column(&self, name: &str) -> Result<Column> {
-        if self.column_names.contains(name) {
-            Ok(self.data()?.column(name)?.clone())
-        } else {
-            bail!("key is not present");
+        match self.read_column(name)? {
+            Some(series) => Ok(series.into_column()),
+            None => bail!(AnnDataError::key_not_found(name, "columns")),
+        }
+    }
+}
+
+/// Positions of the stacked columns in a component array. `None` marks a
+/// column that is absent from the component and is filled with zeros.
+pub(crate) type ColumnMap = Arc<Vec<Option<usize>>>;
+
+fn reindex_columns(data: ArrayData, columns: &Option<ColumnMap>) -> Result<ArrayData> {
+    match columns {
+        None => Ok(data),
+        Some(columns) => {
+            let rows: Vec<_> = (0..data.shape()[0]).map(Some).collect();
+            crate::concat::index_array(data, &rows, columns)
         }
     }
 }
@@ -793,12 +1698,22 @@ impl<B: Backend> StackedDataFrame<B> {
 pub struct InnerStackedArrayElem<B: Backend> {
     pub(crate) shape: Option<Shape>,
     pub(crate) elems: SmallVec<[ArrayElem<B>; 96]>,
+    columns: Vec<Option<ColumnMap>>,
     index: VecVecIndex,
+    /// Keys of the components that could not be opened. Reading fails while
+    /// this is not empty.
+    missing: Vec<String>,
 }
 
 impl<B: Backend> std::fmt::Display for InnerStackedArrayElem<B> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.elems.len() == 0 {
+        if !self.missing.is_empty() {
+            write!(
+                f,
+                "stacked elements with missing components: '{}'",
+                self.missing.join("', '")
+            )
+        } else if self.elems.len() == 0 {
             write!(f, "empty stacked elements")
         } else {
             write!(
@@ -821,18 +1736,39 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         &self.shape
     }
 
+    /// Whether the columns of any element are mapped to a different order.
+    pub fn is_reindexed(&self) -> bool {
+        self.columns.iter().any(|x| x.is_some())
+    }
+
+    /// Fails with [`AnnDataError::MissingChildren`] if some components could
+    /// not be opened.
+    pub fn ensure_complete(&self) -> Result<()> {
+        ensure!(
+            self.missing.is_empty(),
+            AnnDataError::MissingChildren(self.missing.clone())
+        );
+        Ok(())
+    }
+
     pub fn data<D>(&self) -> Result<Option<D>>
     where
         D: TryFrom<ArrayData>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        self.ensure_complete()?;
         let data = if self.is_none() {
             None
         } else {
             let array = self
                 .elems
                 .iter()
-                .flat_map(|x| x.lock().as_mut().map(|i| i.data()))
+                .zip(self.columns.iter())
+                .flat_map(|(x, c)| {
+                    x.lock()
+                        .as_mut()
+                        .map(|i| i.data().and_then(|d| reindex_columns(d, c)))
+                })
                 .process_results(|x| Stackable::vstack(x).unwrap())?;
             Some(array.try_into().map_err(Into::into)?)
         };
@@ -844,13 +1780,19 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         D: TryFrom<ArrayData>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        self.ensure_complete()?;
         let data = if self.is_none() {
             None
         } else {
             let array = self
                 .elems
                 .par_iter()
-                .flat_map(|x| x.lock().as_mut().map(|i| i.data()))
+                .zip(self.columns.par_iter())
+                .flat_map(|(x, c)| {
+                    x.lock()
+                        .as_mut()
+                        .map(|i| i.data().and_then(|d| reindex_columns(d, c)))
+                })
                 .collect::<Vec<_>>()
                 .into_iter()
                 .process_results(|x| Stackable::vstack(x).unwrap())?;
@@ -865,6 +1807,7 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         S: AsRef<SelectInfoElem>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        self.ensure_complete()?;
         self.shape
             .as_ref()
             .map(|s| {
@@ -882,6 +1825,7 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         S: AsRef<SelectInfoElem>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        self.ensure_complete()?;
         let data = if self.is_none() {
             None
         } else {
@@ -892,23 +1836,17 @@ impl<B: Backend> InnerStackedArrayElem<B> {
                 .enumerate()
                 .map(|(i, el)| {
                     if let Some(idx) = indices.get(&i) {
-                        let select: SmallVec<[_; 3]> = std::iter::once(idx)
-                            .chain(selection.as_ref()[1..].iter().map(|x| x.as_ref()))
-                            .collect();
-                        el.inner().select(select.as_slice())
+                        self.select_elem(el, &self.columns[i], idx, &selection.as_ref()[1..])
                     } else {
                         let idx = SelectInfoElem::empty();
-                        let select: SmallVec<[_; 3]> = std::iter::once(&idx)
-                            .chain(selection.as_ref()[1..].iter().map(|x| x.as_ref()))
-                            .collect();
-                        el.inner().select(select.as_slice())
+                        self.select_elem(el, &self.columns[i], &idx, &selection.as_ref()[1..])
                     }
                 })
                 .process_results(|x| Stackable::vstack(x).unwrap())?;
             if let Some(m) = mapping {
                 Some(
                     array
-                        .select_axis(0, SelectInfoElem::from(reverse_mapping(m)))
+                        .select_axis(0, SelectInfoElem::from(reverse_mapping(&m)))
                         .try_into()
                         .map_err(Into::into)?,
                 )
@@ -925,6 +1863,7 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         S: AsRef<SelectInfoElem> + Sync,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        self.ensure_complete()?;
         let data = if self.is_none() {
             None
         } else {
@@ -935,10 +1874,7 @@ impl<B: Backend> InnerStackedArrayElem<B> {
                 .enumerate()
                 .flat_map(|(i, el)| {
                     indices.get(&i).map(|idx| {
-                        let select: SmallVec<[_; 3]> = std::iter::once(idx)
-                            .chain(selection.as_ref()[1..].iter().map(|x| x.as_ref()))
-                            .collect();
-                        el.inner().select(select.as_slice())
+                        self.select_elem(el, &self.columns[i], idx, &selection.as_ref()[1..])
                     })
                 })
                 .collect::<Vec<_>>()
@@ -947,7 +1883,7 @@ impl<B: Backend> InnerStackedArrayElem<B> {
             if let Some(m) = mapping {
                 Some(
                     array
-                        .select_axis(0, SelectInfoElem::from(reverse_mapping(m)))
+                        .select_axis(0, SelectInfoElem::from(reverse_mapping(&m)))
                         .try_into()
                         .map_err(Into::into)?,
                 )
@@ -958,6 +1894,33 @@ impl<B: Backend> InnerStackedArrayElem<B> {
         Ok(data)
     }
 
+    /// Select rows from a component, aligning its columns to the stacked columns.
+    fn select_elem<S>(
+        &self,
+        el: &ArrayElem<B>,
+        columns: &Option<ColumnMap>,
+        rows: &SelectInfoElem,
+        rest: &[S],
+    ) -> Result<ArrayData>
+    where
+        S: AsRef<SelectInfoElem>,
+    {
+        if columns.is_none() {
+            let select: SmallVec<[_; 3]> = std::iter::once(rows)
+                .chain(rest.iter().map(|x| x.as_ref()))
+                .collect();
+            el.inner().select(select.as_slice())
+        } else {
+            let full = SelectInfoElem::full();
+            let select: SmallVec<[_; 3]> = std::iter::once(rows)
+                .chain(std::iter::once(&full))
+                .chain(rest[1..].iter().map(|x| x.as_ref()))
+                .collect();
+            let data = reindex_columns(el.inner().select(select.as_slice())?, columns)?;
+            Ok(data.select_axis(1, rest[0].as_ref()))
+        }
+    }
+
     /// Activate the cache for all elements.
     pub fn enable_cache(&self) {
         for el in self.elems.iter() {
@@ -1003,11 +1966,39 @@ impl<B: Backend> StackedArrayElem<B> {
         Self(Arc::new(InnerStackedArrayElem {
             shape: None,
             elems: SmallVec::new(),
+            columns: Vec::new(),
+            index: std::iter::empty().collect(),
+            missing: Vec::new(),
+        }))
+    }
+
+    /// Placeholder for elements of which the components `keys` could not be
+    /// opened. Reading it fails with [`AnnDataError::MissingChildren`].
+    pub(crate) fn missing(keys: Vec<String>) -> Self {
+        Self(Arc::new(InnerStackedArrayElem {
+            shape: None,
+            elems: SmallVec::new(),
+            columns: Vec::new(),
             index: std::iter::empty().collect(),
+            missing: keys,
         }))
     }
 
     pub(crate) fn new(elems: SmallVec<[ArrayElem<B>; 96]>) -> Result<Self> {
+        let columns = vec![None; elems.len()];
+        Self::new_reindexed(elems, columns)
+    }
+
+    /// Stack the elements after mapping their columns to a common column order.
+    /// An element with a `None` mapping is used as is.
+    pub(crate) fn new_reindexed(
+        elems: SmallVec<[ArrayElem<B>; 96]>,
+        columns: Vec<Option<ColumnMap>>,
+    ) -> Result<Self> {
+        ensure!(
+            elems.len() == columns.len(),
+            "the number of column mappings must match the number of elements"
+        );
         ensure!(
             elems
                 .iter()
@@ -1018,7 +2009,16 @@ impl<B: Backend> StackedArrayElem<B> {
 
         let shapes: Vec<_> = elems
             .iter()
-            .map(|x| x.lock().as_ref().map(|x| x.shape().clone()))
+            .zip(columns.iter())
+            .map(|(x, c)| {
+                x.lock().as_ref().map(|x| {
+                    let mut shape = x.shape().clone();
+                    if let Some(c) = c {
+                        shape[1] = c.len();
+                    }
+                    shape
+                })
+            })
             .collect();
         ensure!(
             shapes
@@ -1038,15 +2038,339 @@ impl<B: Backend> StackedArrayElem<B> {
         Ok(Self(Arc::new(InnerStackedArrayElem {
             shape,
             elems,
+            columns,
             index,
+            missing: Vec::new(),
         })))
     }
 
+    /// Iterate over chunks of `chunk_size` rows, stacking the rows of all
+    /// elements. Each chunk keeps the storage format of its element, so sparse
+    /// elements yield sparse chunks and only one chunk is held in memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some components could not be opened, see
+    /// [`StackedArrayElem::try_chunked`].
     pub fn chunked<D>(&self, chunk_size: usize) -> StackedChunkedArrayElem<B, D>
     where
         D: TryFrom<ArrayData>,
     {
-        StackedChunkedArrayElem::new(self.elems.iter().map(|x| x.clone()), chunk_size)
+        self.try_chunked(chunk_size).unwrap()
+    }
+
+    /// Like [`StackedArrayElem::chunked`], but fails with
+    /// [`AnnDataError::MissingChildren`] if some components could not be opened.
+    pub fn try_chunked<D>(&self, chunk_size: usize) -> Result<StackedChunkedArrayElem<B, D>>
+    where
+        D: TryFrom<ArrayData>,
+    {
+        self.ensure_complete()?;
+        Ok(StackedChunkedArrayElem::new(
+            self.elems.iter().cloned().zip(self.columns.iter().cloned()),
+            chunk_size,
+        ))
+    }
+
+    /// Iterate over vertical slabs of `chunk_size` columns, stacking the rows
+    /// of all elements. See [`ArrayElem::chunked_cols`].
+    pub fn chunked_cols<D>(&self, chunk_size: usize) -> ChunkedColsArrayElem<Self, D>
+    where
+        D: TryFrom<ArrayData>,
+    {
+        ChunkedColsArrayElem::new(self.clone(), chunk_size)
+    }
+
+    /// Iterate over the rows of all elements, numbered across elements. See
+    /// [`ArrayElem::iter_sparse_rows`].
+    pub fn iter_sparse_rows(
+        &self,
+        chunk_size: usize,
+    ) -> Result<SparseRows<StackedChunkedArrayElem<B, ArrayData>>> {
+        if let Some(shape) = self.shape() {
+            for elem in self.elems.iter() {
+                ensure_numeric_matrix(elem.inner().dtype(), &shape)?;
+            }
+        }
+        Ok(SparseRows::new(self.try_chunked(chunk_size)?))
+    }
+
+    /// Sum of each column. The rows are read one chunk at a time, so only one
+    /// chunk is held in memory, and sparse chunks only visit their stored entries.
+    pub fn column_sum(&self) -> Result<Array1<f64>> {
+        let mut sum = Array1::zeros(self.dims()?.1);
+        self.for_each_entry(|_, j, v| sum[j] += v)?;
+        Ok(sum)
+    }
+
+    /// Mean of each column. See [`StackedArrayElem::column_sum`].
+    pub fn column_mean(&self) -> Result<Array1<f64>> {
+        let n = self.dims()?.0;
+        Ok(self.column_sum()? / n as f64)
+    }
+
+    /// Total of each row, e.g. the total counts of each cell. Computed one
+    /// chunk of rows at a time, see [`StackedArrayElem::column_sum`].
+    pub fn row_sum(&self) -> Result<Array1<f64>> {
+        let mut sum = Array1::zeros(self.dims()?.0);
+        self.for_each_entry(|i, _, v| sum[i] += v)?;
+        Ok(sum)
+    }
+
+    /// Number of nonzero entries of each row, e.g. the number of genes detected
+    /// in each cell. Explicitly stored zeros are not counted.
+    pub fn row_nnz(&self) -> Result<Array1<usize>> {
+        let mut nnz = Array1::zeros(self.dims()?.0);
+        self.for_each_entry(|i, _, v| {
+            if v != 0.0 {
+                nnz[i] += 1;
+            }
+        })?;
+        Ok(nnz)
+    }
+
+    fn dims(&self) -> Result<(usize, usize)> {
+        self.ensure_complete()?;
+        match self.shape() {
+            None => Ok((0, 0)),
+            Some(shape) if shape.ndim() == 2 => Ok((shape[0], shape[1])),
+            Some(shape) => bail!("expected a two-dimensional array, found shape {}", shape),
+        }
+    }
+
+    /// Call `f(row, column, value)` for the entries of each chunk of rows, see
+    /// [`visit_entries`].
+    fn for_each_entry<F: FnMut(usize, usize, f64)>(&self, mut f: F) -> Result<()> {
+        if self.dims()?.0 == 0 {
+            return Ok(());
+        }
+        for (chunk, start, _) in self.chunked::<ArrayData>(REDUCTION_CHUNK_SIZE) {
+            visit_entries(chunk, |i, j, v| f(start + i, j, v))?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of rows read at a time by the reductions of [`StackedArrayElem`].
+const REDUCTION_CHUNK_SIZE: usize = 500;
+
+/// Call `f(row, column, value)` for the entries of a two-dimensional array.
+/// Dense arrays visit every entry, sparse matrices only their stored entries.
+fn visit_entries<F: FnMut(usize, usize, f64)>(data: ArrayData, mut f: F) -> Result<()> {
+    macro_rules! visit {
+        (String, $x:expr, $data:ident, $transposed:expr) => {
+            bail!("cannot compute statistics of strings")
+        };
+        (Bool, $x:expr, $data:ident, $transposed:expr) => {{
+            let (offsets, indices, values) = $x.$data();
+            let values: Vec<u8> = values.iter().map(|v| u8::from(*v)).collect();
+            visit_compressed(offsets, indices, &values, $transposed, &mut f)
+        }};
+        ($variant:ident, $x:expr, $data:ident, $transposed:expr) => {{
+            let (offsets, indices, values) = $x.$data();
+            visit_compressed(offsets, indices, values, $transposed, &mut f)
+        }};
+    }
+    macro_rules! csr {
+        ($variant:ident, $x:expr) => {
+            visit!($variant, $x, csr_data, false)
+        };
+    }
+    macro_rules! csc {
+        ($variant:ident, $x:expr) => {
+            visit!($variant, $x, csc_data, true)
+        };
+    }
+    match data {
+        ArrayData::Array(x) => {
+            let x: Array2<f64> = x.try_convert()?;
+            x.indexed_iter().for_each(|((i, j), v)| f(i, j, *v));
+        }
+        ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr),
+        ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, csc),
+        ArrayData::CsrNonCanonical(x) => crate::macros::dyn_map!(x, DynCsrNonCanonical, csr),
+        ArrayData::DataFrame(_) => bail!("cannot compute statistics of a DataFrame"),
+    }
+    Ok(())
+}
+
+/// Visit the entries of a compressed sparse matrix. The rows are the major
+/// axis, or the columns if `transposed` is set.
+fn visit_compressed<T, F>(
+    offsets: &[usize],
+    indices: &[usize],
+    values: &[T],
+    transposed: bool,
+    f: &mut F,
+) where
+    T: num::ToPrimitive,
+    F: FnMut(usize, usize, f64),
+{
+    for (i, range) in offsets.windows(2).enumerate() {
+        for k in range[0]..range[1] {
+            let v = values[k].to_f64().unwrap_or(f64::NAN);
+            if transposed {
+                f(indices[k], i, v);
+            } else {
+                f(i, indices[k], v);
+            }
+        }
+    }
+}
+
+/// Fail unless an element of type `dtype` and shape `shape` is a numeric
+/// two-dimensional array whose rows can be iterated by [`SparseRows`].
+fn ensure_numeric_matrix(dtype: DataType, shape: &Shape) -> Result<()> {
+    ensure!(shape.ndim() == 2, "expected a two-dimensional array, found shape {}", shape);
+    ensure!(
+        matches!(dtype.scalar_type(), Some(t) if t != ScalarType::String),
+        AnnDataError::UnsupportedDtype(format!("rows of {}", dtype))
+    );
+    Ok(())
+}
+
+/// The rows of a chunk in compressed sparse row layout.
+#[derive(Debug)]
+struct RowChunk {
+    offsets: Vec<usize>,
+    indices: Vec<usize>,
+    values: Vec<f64>,
+}
+
+impl RowChunk {
+    /// The offsets and column indices of CSR chunks are taken over as they
+    /// are. CSC chunks are converted to CSR and dense chunks list every column.
+    fn new(data: ArrayData) -> Result<Self> {
+        macro_rules! csr {
+            (String, $x:expr) => {
+                bail!(AnnDataError::UnsupportedDtype("rows of String".to_string()))
+            };
+            (Bool, $x:expr) => {{
+                let (offsets, indices, values) = $x.disassemble();
+                let values = values.into_iter().map(|v| f64::from(u8::from(v))).collect();
+                Self { offsets, indices, values }
+            }};
+            (F64, $x:expr) => {{
+                let (offsets, indices, values) = $x.disassemble();
+                Self { offsets, indices, values }
+            }};
+            ($variant:ident, $x:expr) => {{
+                let (offsets, indices, values) = $x.disassemble();
+                let values = values
+                    .into_iter()
+                    .map(|v| num::ToPrimitive::to_f64(&v).unwrap_or(f64::NAN))
+                    .collect();
+                Self { offsets, indices, values }
+            }};
+        }
+        macro_rules! csc {
+            (String, $x:expr) => {
+                csr!(String, $x)
+            };
+            ($variant:ident, $x:expr) => {
+                csr!($variant, CsrMatrix::from(&$x))
+            };
+        }
+        let chunk = match data {
+            ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr),
+            ArrayData::CsrNonCanonical(x) => {
+                crate::macros::dyn_map!(x, DynCsrNonCanonical, csr)
+            }
+            ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, csc),
+            ArrayData::Array(x) => {
+                let x: Array2<f64> = x.try_convert()?;
+                let (n_rows, n_cols) = x.dim();
+                Self {
+                    offsets: (0..=n_rows).map(|i| i * n_cols).collect(),
+                    indices: (0..n_rows).flat_map(|_| 0..n_cols).collect(),
+                    values: x.iter().copied().collect(),
+                }
+            }
+            ArrayData::DataFrame(_) => bail!("cannot iterate over the rows of a DataFrame"),
+        };
+        Ok(chunk)
+    }
+
+    fn n_rows(&self) -> usize {
+        self.offsets.len() - 1
+    }
+}
+
+/// A row of a two-dimensional array, given by the column indices and values
+/// of its entries in the order they are stored, which is increasing unless
+/// the matrix is non-canonical. Rows of sparse matrices hold their stored
+/// entries and rows of dense arrays hold every column. The rows of a chunk
+/// share its buffers, so a row is a view that is cheap to clone.
+#[derive(Debug, Clone)]
+pub struct SparseRow {
+    chunk: Arc<RowChunk>,
+    row: usize,
+}
+
+impl SparseRow {
+    pub fn indices(&self) -> &[usize] {
+        &self.chunk.indices[self.range()]
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.chunk.values[self.range()]
+    }
+
+    /// Number of entries in the row.
+    pub fn nnz(&self) -> usize {
+        self.range().len()
+    }
+
+    fn range(&self) -> std::ops::Range<usize> {
+        self.chunk.offsets[self.row]..self.chunk.offsets[self.row + 1]
+    }
+}
+
+/// Iterator over the rows of an array, yielding the index of each row and
+/// the row, or the error of reading a chunk. The rows are read one chunk at
+/// a time, so only one chunk is held in memory. See
+/// [`ArrayElem::iter_sparse_rows`].
+pub struct SparseRows<I> {
+    chunks: I,
+    current: Option<(Arc<RowChunk>, usize)>,
+    row: usize,
+}
+
+impl<I> SparseRows<I> {
+    fn new(chunks: I) -> Self {
+        Self {
+            chunks,
+            current: None,
+            row: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = (ArrayData, usize, usize)>> Iterator for SparseRows<I> {
+    type Item = Result<(usize, SparseRow)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((chunk, start)) = self.current.as_ref() {
+                if self.row < chunk.n_rows() {
+                    let row = SparseRow {
+                        chunk: chunk.clone(),
+                        row: self.row,
+                    };
+                    self.row += 1;
+                    return Some(Ok((start + row.row, row)));
+                }
+            }
+            let (data, start, _) = self.chunks.next()?;
+            match RowChunk::new(data) {
+                Ok(chunk) => self.current = Some((Arc::new(chunk), start)),
+                Err(e) => {
+                    self.current = None;
+                    return Some(Err(e));
+                }
+            }
+            self.row = 0;
+        }
     }
 }
 
@@ -1058,20 +2382,44 @@ pub struct ChunkedArrayElem<B: Backend, D> {
     chunk_size: usize,
     num_items: usize,
     current_position: usize,
+    /// Optional column mapping applied to each chunk.
+    columns: Option<ColumnMap>,
     phantom: std::marker::PhantomData<D>,
 }
 
 impl<B: Backend, D> ChunkedArrayElem<B, D> {
     pub fn new(elem: ArrayElem<B>, chunk_size: usize) -> Self {
+        Self::new_reindexed(elem, chunk_size, None)
+    }
+
+    pub(crate) fn new_reindexed(
+        elem: ArrayElem<B>,
+        chunk_size: usize,
+        columns: Option<ColumnMap>,
+    ) -> Self {
         let num_items = elem.inner().shape()[0];
         Self {
             elem,
             chunk_size,
             num_items,
             current_position: 0,
+            columns,
             phantom: std::marker::PhantomData,
         }
     }
+
+    fn read_chunk(&self, i: usize, j: usize) -> ArrayData {
+        let data = self
+            .elem
+            .inner()
+            .select_axis(0, SelectInfoElem::from(i..j))
+            .unwrap();
+        reindex_columns(data, &self.columns).unwrap()
+    }
+
+    fn read_all(&self) -> ArrayData {
+        reindex_columns(self.elem.inner().data().unwrap(), &self.columns).unwrap()
+    }
 }
 
 impl<B, D> Iterator for ChunkedArrayElem<B, D>
@@ -1087,7 +2435,7 @@ where
             if self.current_position == 0 {
                 // return an empty array
                 self.current_position = 1;
-                Some((self.elem.inner().data().unwrap().try_into().unwrap(), 0, 0))
+                Some((self.read_all().try_into().unwrap(), 0, 0))
             } else {
                 None
             }
@@ -1095,13 +2443,7 @@ where
             let i = self.current_position;
             let j = std::cmp::min(self.num_items, self.current_position + self.chunk_size);
             self.current_position = j;
-            let data = self
-                .elem
-                .inner()
-                .select_axis(0, SelectInfoElem::from(i..j))
-                .unwrap()
-                .try_into()
-                .unwrap();
+            let data = self.read_chunk(i, j).try_into().unwrap();
             Some((data, i, j))
         }
     }
@@ -1130,10 +2472,13 @@ pub struct StackedChunkedArrayElem<B: Backend, D> {
 }
 
 impl<B: Backend, D> StackedChunkedArrayElem<B, D> {
-    pub(crate) fn new<I: Iterator<Item = ArrayElem<B>>>(elems: I, chunk_size: usize) -> Self {
+    pub(crate) fn new<I>(elems: I, chunk_size: usize) -> Self
+    where
+        I: Iterator<Item = (ArrayElem<B>, Option<ColumnMap>)>,
+    {
         Self {
             arrays: elems
-                .map(|x| ChunkedArrayElem::new(x, chunk_size))
+                .map(|(x, c)| ChunkedArrayElem::new_reindexed(x, chunk_size, c))
                 .collect(),
             current_position: 0,
             current_array: 0,
@@ -1164,17 +2509,7 @@ where
             if self.current_position == 0 {
                 // return an empty array
                 self.current_position = 1;
-                Some((
-                    self.arrays[0]
-                        .elem
-                        .inner()
-                        .data()
-                        .unwrap()
-                        .try_into()
-                        .unwrap(),
-                    0,
-                    0,
-                ))
+                Some((self.arrays[0].read_all().try_into().unwrap(), 0, 0))
             } else {
                 None
             }
@@ -1193,10 +2528,124 @@ where
     }
 }
 
-fn reverse_mapping(mapping: Vec<usize>) -> Vec<usize> {
-    let mut res = vec![0; mapping.len()];
-    for (i, x) in mapping.into_iter().enumerate() {
-        res[x] = i;
+/// Column-wise chunks of a two-dimensional array element.
+///
+/// Each chunk is read with a column selection, which only touches the
+/// selected columns for dense and CSC arrays. CSR arrays are read row by row
+/// and filtered, so column chunks of large CSR arrays are more expensive than
+/// row chunks.
+pub struct ChunkedColsArrayElem<A, D> {
+    elem: A,
+    chunk_size: usize,
+    num_items: usize,
+    current_position: usize,
+    phantom: std::marker::PhantomData<D>,
+}
+
+impl<A: ArrayElemOp, D> ChunkedColsArrayElem<A, D> {
+    pub(crate) fn new(elem: A, chunk_size: usize) -> Self {
+        let num_items = elem.shape().map_or(0, |shape| shape[1]);
+        Self {
+            elem,
+            chunk_size,
+            num_items,
+            current_position: 0,
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    fn read_chunk(&self, i: usize, j: usize) -> ArrayData {
+        self.elem
+            .slice::<ArrayData, _>([SelectInfoElem::full(), (i..j).into()])
+            .unwrap()
+            .unwrap()
+    }
+}
+
+impl<A, D> Iterator for ChunkedColsArrayElem<A, D>
+where
+    A: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    type Item = (D, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_position >= self.num_items {
+            if self.current_position == 0 {
+                // return an empty array
+                self.current_position = 1;
+                Some((self.read_chunk(0, 0).try_into().unwrap(), 0, 0))
+            } else {
+                None
+            }
+        } else {
+            let i = self.current_position;
+            let j = std::cmp::min(self.num_items, self.current_position + self.chunk_size);
+            self.current_position = j;
+            let data = self.read_chunk(i, j).try_into().unwrap();
+            Some((data, i, j))
+        }
+    }
+}
+
+impl<A, D> ExactSizeIterator for ChunkedColsArrayElem<A, D>
+where
+    A: ArrayElemOp,
+    D: TryFrom<ArrayData>,
+    <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+{
+    fn len(&self) -> usize {
+        let (n, remain) = div_rem(self.num_items, self.chunk_size);
+        if remain == 0 {
+            n
+        } else {
+            n + 1
+        }
+    }
+}
+
+/// Row chunks of an array paired with the same rows of other arrays, e.g.,
+/// chunks of `X` together with the matching rows of `obsm` elements.
+pub struct ChunkedWithObsm<I, A> {
+    iter: I,
+    obsm: Vec<A>,
+}
+
+impl<I, A> ChunkedWithObsm<I, A> {
+    pub(crate) fn new(iter: I, obsm: Vec<A>) -> Self {
+        Self { iter, obsm }
+    }
+}
+
+impl<I, A, D> Iterator for ChunkedWithObsm<I, A>
+where
+    I: Iterator<Item = (D, usize, usize)>,
+    A: ArrayElemOp,
+{
+    type Item = (D, Vec<ArrayData>, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (data, start, end) = self.iter.next()?;
+        let obsm = self
+            .obsm
+            .iter()
+            .map(|x| {
+                x.slice_axis::<ArrayData, _>(0, SelectInfoElem::from(start..end))
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        Some((data, obsm, start, end))
+    }
+}
+
+impl<I, A, D> ExactSizeIterator for ChunkedWithObsm<I, A>
+where
+    I: ExactSizeIterator<Item = (D, usize, usize)>,
+    A: ArrayElemOp,
+{
+    fn len(&self) -> usize {
+        self.iter.len()
     }
-    res
 }
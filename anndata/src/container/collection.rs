@@ -1,22 +1,30 @@
 use crate::{
-    anndata::new_mapping, backend::{iter_containers, AttributeOp, Backend, GroupOp}, container::base::*, data::*, ElemCollectionOp
+    anndata::new_mapping, backend::{
+        iter_containers, AttributeOp, Backend, DataContainer, DatasetOp, EncodingType, GroupOp,
+        StoreOp, WriteConfig,
+    }, container::base::*, data::*, AnnDataError,
+    ElemCollectionOp
 };
 
-use anyhow::{bail, ensure, Result};
+use anyhow::{bail, ensure, Context, Result};
+use indexmap::IndexMap;
 use itertools::Itertools;
 use log::warn;
 use parking_lot::{Mutex, MutexGuard};
 use smallvec::{smallvec, SmallVec};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashSet,
     fmt::Display,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
 
+/// The elements are kept in insertion order. Elements read from a file come
+/// first, ordered by their keys.
 pub struct InnerElemCollection<B: Backend> {
     container: B::Group,
-    data: HashMap<String, Elem<B>>,
+    data: IndexMap<String, Elem<B>>,
+    read_only: bool,
 }
 
 impl<B: Backend> std::fmt::Debug for InnerElemCollection<B> {
@@ -26,7 +34,7 @@ impl<B: Backend> std::fmt::Debug for InnerElemCollection<B> {
 }
 
 impl<B: Backend> Deref for InnerElemCollection<B> {
-    type Target = HashMap<String, Elem<B>>;
+    type Target = IndexMap<String, Elem<B>>;
 
     fn deref(&self) -> &Self::Target {
         &self.data
@@ -52,6 +60,12 @@ impl<B: Backend> std::fmt::Display for InnerElemCollection<B> {
 
 impl<B: Backend> InnerElemCollection<B> {
     pub fn add_data(&mut self, key: &str, data: Data) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        if let Some((key, path)) = key.split_once('/') {
+            let mut mapping = self.read_mapping(key)?.unwrap_or_default();
+            mapping.insert_path(path, data)?;
+            return self.add_data(key, mapping.into());
+        }
         match self.get_mut(key) {
             None => {
                 let container = data.write(&self.container, key)?;
@@ -63,10 +77,38 @@ impl<B: Backend> InnerElemCollection<B> {
     }
 
     pub fn remove_data(&mut self, key: &str) -> Result<()> {
-        self.remove(key).map(|x| x.clear()).transpose()?;
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        if let Some((key, path)) = key.split_once('/') {
+            if let Some(mut mapping) = self.read_mapping(key)? {
+                if mapping.remove_path(path).is_some() {
+                    self.add_data(key, mapping.into())?;
+                }
+            }
+            return Ok(());
+        }
+        self.shift_remove(key).map(|x| x.clear()).transpose()?;
         Ok(())
     }
 
+    /// Read an entry, which may be nested and given by a `/`-separated path.
+    fn get_data(&mut self, key: &str) -> Result<Option<Data>> {
+        match key.split_once('/') {
+            None => self.get_mut(key).map(|x| x.inner().data()).transpose(),
+            Some((key, path)) => Ok(self
+                .read_mapping(key)?
+                .and_then(|mut mapping| mapping.remove_path(path))),
+        }
+    }
+
+    fn read_mapping(&mut self, key: &str) -> Result<Option<Mapping>> {
+        self.get_mut(key)
+            .map(|x| {
+                let data = x.inner().data()?;
+                Mapping::try_from(data).with_context(|| format!("'{}' is not a mapping", key))
+            })
+            .transpose()
+    }
+
     pub fn export<O: Backend, G: GroupOp<O>>(&self, location: &G, name: &str) -> Result<()> {
         let group = new_mapping(location, name)?;
         for (key, val) in self.iter() {
@@ -76,6 +118,9 @@ impl<B: Backend> InnerElemCollection<B> {
     }
 }
 
+/// A collection of named elements, such as `uns`. Entries of nested mappings
+/// can be read, added and removed with `/`-separated keys like
+/// `"neighbors/params"`. Changing a nested entry rewrites its top-level mapping.
 #[derive(Debug)]
 pub struct ElemCollection<B: Backend>(Slot<InnerElemCollection<B>>);
 
@@ -120,10 +165,9 @@ impl<B: Backend> ElemCollectionOp for &ElemCollection<B> {
         <D as TryFrom<Data>>::Error: Into<anyhow::Error>,
     {
         let mut lock = self.lock();
-        if let Some(elem) = lock.as_mut().and_then(|x| x.get_mut(key)) {
-            Ok(Some(elem.inner().data()?.try_into().map_err(Into::into)?))
-        } else {
-            Ok(None)
+        match lock.as_mut().map(|x| x.get_data(key)).transpose()?.flatten() {
+            Some(data) => Ok(Some(data.try_into().map_err(Into::into)?)),
+            None => Ok(None),
         }
     }
 
@@ -145,23 +189,44 @@ impl<B: Backend> ElemCollection<B> {
         self.is_none() || self.inner().data.is_empty()
     }
 
+    /// Returns the key, encoding and shape of each entry, without reading the
+    /// data. Entries of nested mappings follow their mapping under
+    /// `/`-separated keys.
+    pub fn entries(&self) -> Result<Vec<(String, EncodingType, Shape)>> {
+        let mut entries = Vec::new();
+        if let Some(x) = self.0.read().as_ref() {
+            collect_entries::<B>(&x.container, "", &mut entries)?;
+        }
+        Ok(entries)
+    }
+
     pub fn new(container: B::Group) -> Result<Self> {
-        let data: Result<HashMap<_, _>> = iter_containers(&container)
+        let data: Result<IndexMap<_, _>> = iter_containers(&container)
+            .sorted_by(|a, b| a.0.cmp(&b.0))
             .map(|(k, v)| Ok((k, Elem::try_from(v)?)))
             .collect();
         let collection = InnerElemCollection {
             container,
             data: data?,
+            read_only: false,
         };
         Ok(Self(Slot::new(collection)))
     }
 
+    /// Make all subsequent modifications fail with [`AnnDataError::ReadOnly`].
+    pub(crate) fn set_read_only(&self) {
+        if let Some(x) = self.0.lock().as_mut() {
+            x.read_only = true;
+        }
+    }
+
     pub fn clear(&self) -> Result<()> {
         self.0
             .lock()
             .as_ref()
             .map(|x| {
                 let g = &x.container;
+                ensure_writable::<B>(x.read_only, g)?;
                 g.store()?.delete(&g.path().to_string_lossy())
             })
             .transpose()?;
@@ -170,6 +235,34 @@ impl<B: Backend> ElemCollection<B> {
     }
 }
 
+fn collect_entries<B: Backend>(
+    group: &B::Group,
+    prefix: &str,
+    entries: &mut Vec<(String, EncodingType, Shape)>,
+) -> Result<()> {
+    for key in group.list()?.into_iter().sorted() {
+        let container = DataContainer::<B>::open(group, &key)?;
+        let encoding = container.encoding()?;
+        let shape = match encoding {
+            EncodingType::String | EncodingType::NumericScalar => Shape::from(Vec::new()),
+            EncodingType::Dict => container.as_group()?.list()?.len().into(),
+            EncodingType::Categorical => CategoricalArray::get_shape(&container)?,
+            EncodingType::NullableInteger
+            | EncodingType::NullableBoolean
+            | EncodingType::NullableStringArray => {
+                container.as_group()?.open_dataset("values")?.shape()?
+            }
+            _ => ArrayData::get_shape(&container)?,
+        };
+        let path = format!("{}{}", prefix, key);
+        entries.push((path.clone(), encoding, shape));
+        if encoding == EncodingType::Dict {
+            collect_entries::<B>(container.as_group()?, &format!("{}/", path), entries)?;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Axis {
     Row,       // Can perform row-wise operations.
@@ -233,11 +326,10 @@ impl DimLock<'_> {
 
     pub fn try_set(&mut self, n: usize) -> Result<()> {
         if self.0.is_some() && self.0.unwrap() != n {
-            bail!(
-                "dimension cannot be changed from {} to {}",
-                self.0.unwrap(),
-                n
-            );
+            bail!(AnnDataError::DimMismatch {
+                expected: self.0.unwrap(),
+                found: n,
+            });
         } else {
             *self.0 = Some(n);
         }
@@ -247,14 +339,22 @@ impl DimLock<'_> {
     pub(crate) fn set(&mut self, n: usize) {
         *self.0 = Some(n);
     }
+
+    /// Unset the dimension, e.g. when the arrays that set it are rolled back.
+    pub(crate) fn reset(&mut self) {
+        *self.0 = None;
+    }
 }
 
+/// The arrays are kept in insertion order. Arrays read from a file come first,
+/// ordered by their keys.
 pub struct InnerAxisArrays<B: Backend> {
     pub axis: Axis,
     pub(crate) container: B::Group,
     pub(crate) dim1: Dim,
     pub(crate) dim2: Option<Dim>,
-    data: HashMap<String, ArrayElem<B>>,
+    data: IndexMap<String, ArrayElem<B>>,
+    read_only: bool,
 }
 
 impl<B: Backend> std::fmt::Debug for InnerAxisArrays<B> {
@@ -264,7 +364,7 @@ impl<B: Backend> std::fmt::Debug for InnerAxisArrays<B> {
 }
 
 impl<B: Backend> Deref for InnerAxisArrays<B> {
-    type Target = HashMap<String, ArrayElem<B>>;
+    type Target = IndexMap<String, ArrayElem<B>>;
 
     fn deref(&self) -> &Self::Target {
         &self.data
@@ -298,27 +398,18 @@ impl<B: Backend> InnerAxisArrays<B> {
         self.dim1.get()
     }
 
+    /// Returns the shape of the array `key`, which is read from the stored
+    /// attributes when the array is opened. Returns `None` if the key does not
+    /// exist.
+    pub fn shape_of(&self, key: &str) -> Option<Shape> {
+        self.get(key)?.read().as_ref().map(|x| x.shape().clone())
+    }
+
     pub fn add_data<D: Into<ArrayData>>(&mut self, key: &str, data: D) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
         // Check if the data is compatible with the current size
         let data = data.into();
-        let shape = data.shape();
-        match self.axis {
-            Axis::Row => {
-                self.dim1.try_set(shape[0])?;
-            }
-            Axis::RowColumn => {
-                self.dim1.try_set(shape[0])?;
-                self.dim2.as_ref().unwrap().try_set(shape[1])?;
-            }
-            Axis::Pairwise => {
-                ensure!(
-                    shape[0] == shape[1],
-                    "expecting a square array, but receive a {:?} array",
-                    shape
-                );
-                self.dim1.try_set(shape[0])?;
-            }
-        }
+        self.check_and_set_dims(&[data.shape()])?;
 
         match self.get_mut(key) {
             None => {
@@ -331,11 +422,129 @@ impl<B: Backend> InnerAxisArrays<B> {
         Ok(())
     }
 
+    /// Add array data, creating its datasets with the given configuration. An
+    /// existing element with the same key is replaced.
+    pub fn add_data_with_config<D: Into<ArrayData>>(
+        &mut self,
+        key: &str,
+        data: D,
+        config: &WriteConfig,
+    ) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        let data = data.into();
+        self.check_and_set_dims(&[data.shape()])?;
+
+        if let Some(elem) = self.get(key) {
+            elem.clear()?;
+        }
+        let container = data.write_with_config(&self.container, key, config)?;
+        let elem = container.try_into()?;
+        self.insert(key.to_string(), elem);
+        Ok(())
+    }
+
+    /// Add several arrays while the collection stays locked. The shapes of
+    /// all arrays are checked before anything is written. If writing fails,
+    /// the arrays written so far are removed and the arrays they replaced are
+    /// restored with their chunk shape and compression, so the collection is
+    /// left as it was. To this end, arrays that are replaced are read into
+    /// memory first. Dimensions set by the batch are unset again.
+    pub fn add_batch(&mut self, data: Vec<(String, ArrayData)>) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        let shapes: Vec<Shape> = data.iter().map(|(_, arr)| arr.shape()).collect();
+        let unset_dim1 = self.dim1.lock().is_empty();
+        let unset_dim2 = match self.axis {
+            Axis::RowColumn => self.dim2.as_ref().map_or(false, |x| x.lock().is_empty()),
+            _ => false,
+        };
+        self.check_and_set_dims(&shapes)?;
+
+        let result = add_with_rollback(
+            self,
+            data,
+            |this, key| {
+                this.get(key)
+                    .map(|elem| {
+                        let mut elem = elem.inner();
+                        // Quantization is not applied again to the rounded values.
+                        let config = WriteConfig {
+                            quantize: None,
+                            ..elem.write_config()?
+                        };
+                        Ok::<_, anyhow::Error>((elem.data()?, config))
+                    })
+                    .transpose()
+            },
+            |this, key, arr| this.add_data(key, arr),
+            |this, key, old| match old {
+                Some((old, config)) => this.add_data_with_config(key, old, &config),
+                None => this.remove_data(key),
+            },
+        );
+        if result.is_err() {
+            if unset_dim1 {
+                self.dim1.lock().reset();
+            }
+            if let (true, Some(dim2)) = (unset_dim2, self.dim2.as_ref()) {
+                dim2.lock().reset();
+            }
+        }
+        result
+    }
+
+    /// Check the shapes against the dimensions of the collection and each
+    /// other, and set the dimensions that are not yet known. Nothing is
+    /// changed if the check fails.
+    fn check_and_set_dims(&self, shapes: &[Shape]) -> Result<()> {
+        let mut dim1 = self.dim1.lock();
+        let mut dim2 = match self.axis {
+            Axis::RowColumn => self.dim2.as_ref().map(|x| x.lock()),
+            _ => None,
+        };
+        let mut sizes: SmallVec<[Option<usize>; 2]> = std::iter::once(&dim1)
+            .chain(dim2.as_ref())
+            .map(|dim| if dim.is_empty() { None } else { Some(dim.get()) })
+            .collect();
+        for shape in shapes {
+            if let Axis::Pairwise = self.axis {
+                ensure!(
+                    shape.ndim() >= 2 && shape[0] == shape[1],
+                    "expecting a square array, but receive a {} array",
+                    shape
+                );
+            }
+            ensure!(
+                shape.ndim() >= sizes.len(),
+                "expecting an array with at least {} dimensions, but receive a {} array",
+                sizes.len(),
+                shape
+            );
+            for (i, size) in sizes.iter_mut().enumerate() {
+                let n = *size.get_or_insert(shape[i]);
+                ensure!(
+                    n == shape[i],
+                    "expecting an array with {} elements along axis {}, but receive a {} array",
+                    n,
+                    i,
+                    shape
+                );
+            }
+        }
+        if let Some(n) = sizes[0] {
+            dim1.set(n);
+        }
+        if let (Some(dim2), Some(n)) = (dim2.as_mut(), sizes.get(1).copied().flatten()) {
+            dim2.set(n);
+        }
+        Ok(())
+    }
+
     pub fn add_data_from_iter<I, D>(&mut self, key: &str, data: I) -> Result<()>
     where
         I: Iterator<Item = D>,
         D: ArrayChunk,
     {
+        ensure_writable::<B>(self.read_only, &self.container)?;
         if let Some(elem) = self.get(key) {
             elem.clear()?;
         }
@@ -381,7 +590,27 @@ impl<B: Backend> InnerAxisArrays<B> {
     }
 
     pub fn remove_data(&mut self, key: &str) -> Result<()> {
-        self.remove(key).map(|x| x.clear()).transpose()?;
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        self.shift_remove(key).map(|x| x.clear()).transpose()?;
+        Ok(())
+    }
+
+    /// Rename the array `old` to `new`, which takes the position of `old`.
+    /// The array is moved in the backend, so its data is not copied.
+    pub fn rename_data(&mut self, old: &str, new: &str) -> Result<()> {
+        ensure_writable::<B>(self.read_only, &self.container)?;
+        if old == new {
+            return Ok(());
+        }
+        ensure!(!self.contains_key(new), "key '{}' already exists", new);
+        let i = self
+            .get_index_of(old)
+            .ok_or_else(|| AnnDataError::key_not_found(old, "axis arrays"))?;
+        self.container.rename(old, new)?;
+        // The element of `old` refers to the dataset before it was moved.
+        self.shift_remove(old);
+        let renamed = ArrayElem::try_from(DataContainer::open(&self.container, new)?)?;
+        self.shift_insert(i, new.to_string(), renamed);
         Ok(())
     }
 
@@ -393,6 +622,34 @@ impl<B: Backend> InnerAxisArrays<B> {
         Ok(())
     }
 
+    /// Export all arrays, creating their datasets with the given configuration.
+    pub fn export_with_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<()> {
+        let group = new_mapping(location, name)?;
+        for (key, val) in self.iter() {
+            val.inner().export_with_config::<O, _>(&group, key, config)?;
+        }
+        Ok(())
+    }
+
+    /// Export all arrays, creating each dataset with the chunk shape and
+    /// compression of its source.
+    pub fn export_with_source_config<O: Backend, G: GroupOp<O>>(
+        &self,
+        location: &G,
+        name: &str,
+    ) -> Result<()> {
+        let group = new_mapping(location, name)?;
+        for (key, val) in self.iter() {
+            val.inner().export_with_source_config::<O, _>(&group, key)?;
+        }
+        Ok(())
+    }
+
     pub fn export_select<O, G>(
         &self,
         selection: &[&SelectInfoElem],
@@ -466,12 +723,32 @@ impl<B: Backend> InnerAxisArrays<B> {
                 if selection.len() != 1 {
                     bail!("selection dimension must be 1 for pairwise AxisArrays");
                 }
-                self.values().try_for_each(|x| {
+                // Both axes are subset with the same selection, so the arrays,
+                // dense or sparse, must still be square afterwards.
+                self.iter().try_for_each(|(key, x)| {
+                    let mut x = x.inner();
+                    let shape = x.shape().clone();
+                    ensure!(
+                        shape.ndim() >= 2 && shape[0] == shape[1],
+                        "pairwise array '{}' is not square: {}",
+                        key,
+                        shape
+                    );
                     let full = SelectInfoElem::full();
-                    let mut slice: SmallVec<[_; 3]> = smallvec![&full; x.inner().shape().ndim()];
+                    let mut slice: SmallVec<[_; 3]> = smallvec![&full; shape.ndim()];
                     slice[0] = selection[0];
                     slice[1] = selection[0];
-                    x.inner().subset(slice.as_slice())
+                    x.subset(slice.as_slice())?;
+                    let n = SelectInfoElemBounds::new(selection[0], shape[0]).len();
+                    ensure!(
+                        x.shape()[0] == n && x.shape()[1] == n,
+                        "pairwise array '{}' has shape {} after subsetting, expecting {} x {}",
+                        key,
+                        x.shape(),
+                        n,
+                        n
+                    );
+                    Ok(())
                 })?;
                 if let Some(mut lock) = self.dim1.try_lock() {
                     lock.set(SelectInfoElemBounds::new(selection[0], lock.get()).len());
@@ -517,11 +794,12 @@ impl<B: Backend> AxisArrays<B> {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.is_none() || self.inner().data.is_empty()
+        self.read().as_ref().map_or(true, |x| x.data.is_empty())
     }
 
     pub fn new(group: B::Group, axis: Axis, dim1: &Dim, dim2: Option<&Dim>) -> Result<Self> {
-        let data: HashMap<_, _> = iter_containers::<B>(&group)
+        let data: IndexMap<_, _> = iter_containers::<B>(&group)
+            .sorted_by(|a, b| a.0.cmp(&b.0))
             .map(|(k, v)| (k, ArrayElem::try_from(v).unwrap()))
             .collect();
 
@@ -562,28 +840,48 @@ impl<B: Backend> AxisArrays<B> {
             dim2: dim2.cloned(),
             axis,
             data,
+            read_only: false,
         };
         Ok(Self(Slot::new(arrays)))
     }
 
+    /// Make all subsequent modifications of the collection and its arrays
+    /// fail with [`AnnDataError::ReadOnly`].
+    pub(crate) fn set_read_only(&self) {
+        if let Some(x) = self.0.lock().as_mut() {
+            x.read_only = true;
+            x.data.values().for_each(|elem| elem.set_read_only());
+        }
+    }
+
     pub fn clear(&self) -> Result<()> {
         self.0
             .lock()
             .as_ref()
             .map(|x| {
                 let g = &x.container;
+                ensure_writable::<B>(x.read_only, g)?;
                 g.store()?.delete(&g.path().to_string_lossy())
             })
             .transpose()?;
         self.0.drop();
         Ok(())
     }
+
+    /// Rename the array `old` to `new`. Fails if `old` does not exist or `new`
+    /// is already taken.
+    pub fn rename(&self, old: &str, new: &str) -> Result<()> {
+        match self.lock().as_mut() {
+            Some(x) => x.rename_data(old, new),
+            None => bail!(AnnDataError::key_not_found(old, "axis arrays")),
+        }
+    }
 }
 
 /// Stacked axis arrays, providing Read-only access to the data.
 pub struct StackedAxisArrays<B: Backend> {
     axis: Axis,
-    pub(crate) data: Arc<HashMap<String, StackedArrayElem<B>>>,
+    pub(crate) data: Arc<IndexMap<String, StackedArrayElem<B>>>,
 }
 
 impl<B: Backend> Clone for StackedAxisArrays<B> {
@@ -596,7 +894,7 @@ impl<B: Backend> Clone for StackedAxisArrays<B> {
 }
 
 impl<B: Backend> Deref for StackedAxisArrays<B> {
-    type Target = HashMap<String, StackedArrayElem<B>>;
+    type Target = IndexMap<String, StackedArrayElem<B>>;
 
     fn deref(&self) -> &Self::Target {
         &self.data
@@ -624,7 +922,7 @@ impl<B: Backend> StackedAxisArrays<B> {
     pub fn empty(axis: Axis) -> Self {
         Self {
             axis,
-            data: Arc::new(HashMap::new()),
+            data: Arc::new(IndexMap::new()),
         }
     }
 
@@ -638,11 +936,15 @@ impl<B: Backend> StackedAxisArrays<B> {
             "Axis mismatch"
         );
 
-        let shared_keys: HashSet<String> = arrays
+        // The keys shared by all arrays, in the order of the first one.
+        let shared_keys: Vec<String> = arrays
             .iter()
-            .map(|x| x.inner().keys().cloned().collect::<HashSet<_>>())
-            .reduce(|a, b| a.intersection(&b).cloned().collect())
-            .unwrap_or(HashSet::new());
+            .map(|x| x.inner().keys().cloned().collect::<Vec<_>>())
+            .reduce(|a, b| {
+                let b: HashSet<_> = b.into_iter().collect();
+                a.into_iter().filter(|k| b.contains(k)).collect()
+            })
+            .unwrap_or_default();
 
         let mut ignore_keys = Vec::new();
         let data = shared_keys
@@ -659,7 +961,7 @@ impl<B: Backend> StackedAxisArrays<B> {
                     None
                 }
             })
-            .collect::<HashMap<_, _>>();
+            .collect::<IndexMap<_, _>>();
         if !ignore_keys.is_empty() {
             warn!(
                 "Unable to create stacked arrays for these keys: {}",
@@ -676,3 +978,32 @@ impl<B: Backend> StackedAxisArrays<B> {
         self.data.contains_key(key)
     }
 }
+
+/// Add the arrays in `data` one by one. `save` is called before an array is
+/// added and returns what `restore` needs to undo adding it, e.g. the array
+/// it replaces. If an array cannot be added, the arrays added so far are
+/// rolled back in reverse order, and failures to roll back are logged.
+pub(crate) fn add_with_rollback<S, T>(
+    state: &mut S,
+    data: Vec<(String, ArrayData)>,
+    save: impl Fn(&mut S, &str) -> Result<T>,
+    add: impl Fn(&mut S, &str, ArrayData) -> Result<()>,
+    restore: impl Fn(&mut S, &str, T) -> Result<()>,
+) -> Result<()> {
+    let mut added: Vec<(String, T)> = Vec::with_capacity(data.len());
+    for (key, arr) in data {
+        let result = save(state, &key).and_then(|old| {
+            added.push((key.clone(), old));
+            add(state, &key, arr)
+        });
+        if let Err(e) = result {
+            for (key, old) in added.into_iter().rev() {
+                if let Err(err) = restore(state, &key, old) {
+                    warn!("failed to roll back '{}': {}", key, err);
+                }
+            }
+            return Err(e.context(format!("cannot add '{}'", key)));
+        }
+    }
+    Ok(())
+}
@@ -4,5 +4,6 @@ pub(crate) mod collection;
 pub use base::{
     InnerDataFrameElem, DataFrameElem, Elem, Inner, ArrayElem, Slot,
     StackedDataFrame, StackedArrayElem, ChunkedArrayElem, StackedChunkedArrayElem,
+    ChunkedColsArrayElem, ChunkedWithObsm, SparseRow, SparseRows,
 };
 pub use collection::{Dim, Axis, AxisArrays, ElemCollection, StackedAxisArrays};
\ No newline at end of file
@@ -6,6 +6,7 @@ pub mod mapping;
 pub use array::*;
 pub use data_traits::*;
 pub use mapping::*;
+pub use half::f16;
 
 use crate::backend::{Backend, DataContainer, DataType, GroupOp};
 
@@ -73,6 +74,18 @@ impl_into_data!(f64, F64);
 impl_into_data!(bool, Bool);
 impl_into_data!(String, String);
 
+// Half-precision floats only exist as scalars and dense arrays.
+impl From<f16> for Data {
+    fn from(data: f16) -> Self {
+        Data::Scalar(DynScalar::F16(data))
+    }
+}
+impl<D: RemoveAxis> From<Array<f16, D>> for Data {
+    fn from(data: Array<f16, D>) -> Self {
+        Data::ArrayData(ArrayData::Array(DynArray::F16(data.into_dyn())))
+    }
+}
+
 macro_rules! impl_into_data2 {
     ($from_type:ty, $to_type:ident) => {
         impl From<$from_type> for Data {
@@ -114,8 +127,8 @@ macro_rules! impl_try_from_for_scalar {
 }
 
 impl_try_from_for_scalar!(
-    I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F32, f32, F64, f64,
-    Bool, bool, String, String
+    I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F16, f16, F32, f32,
+    F64, f64, Bool, bool, String, String
 );
 
 impl TryFrom<Data> for DataFrame {
@@ -129,6 +142,17 @@ impl TryFrom<Data> for DataFrame {
     }
 }
 
+impl TryFrom<Data> for DynScalar {
+    type Error = anyhow::Error;
+
+    fn try_from(v: Data) -> Result<Self> {
+        match v {
+            Data::Scalar(data) => Ok(data),
+            _ => bail!("Cannot convert data to DynScalar"),
+        }
+    }
+}
+
 impl TryFrom<Data> for Mapping {
     type Error = anyhow::Error;
 
@@ -8,15 +8,16 @@ pub mod utils;
 pub use chunks::ArrayChunk;
 pub use dataframe::DataFrameIndex;
 pub use dense::{ArrayConvert, CategoricalArray, DynArray, DynCowArray, DynScalar};
-pub use slice::{SelectInfo, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape};
+pub use slice::{Mask, SelectInfo, SelectInfoBounds, SelectInfoElem, SelectInfoElemBounds, Shape};
 pub use sparse::{CsrNonCanonical, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
 
 use crate::backend::*;
-use crate::data::utils::from_csr_data;
-use crate::data::{data_traits::*, DataType};
+use crate::data::utils::{from_csr_data, sparse_shape};
+use crate::data::{data_traits::*, f16, DataType};
+use crate::error::AnnDataError;
 
-use ::ndarray::{Array, Ix1, RemoveAxis};
-use anyhow::{bail, Result};
+use ::ndarray::{Array, ArrayD, Ix1, RemoveAxis};
+use anyhow::{anyhow, bail, ensure, Result};
 use nalgebra_sparse::csc::CscMatrix;
 use nalgebra_sparse::csr::CsrMatrix;
 use polars::prelude::DataFrame;
@@ -64,6 +65,233 @@ impl From<DynCscMatrix> for ArrayData {
     }
 }
 
+impl ArrayData {
+    /// Binarize the array: entries greater than `threshold` become 1.0 and all
+    /// other entries become 0.0. The result is stored as `f32`. For sparse
+    /// matrices the entries that become zero are removed, so `threshold` must
+    /// not be negative.
+    pub fn binarize(self, threshold: f64) -> Result<Self> {
+        self.binarize_as(threshold, 1.0f32, 0.0)
+    }
+
+    /// Convert the array to a boolean mask of the entries greater than
+    /// `threshold`, e.g. to store a neighbor graph as its adjacency. Sparse
+    /// matrices stay sparse with `bool` values, which take an eighth of the
+    /// space of `f64` values. As in [`ArrayData::binarize`], `threshold` must
+    /// not be negative for sparse matrices.
+    pub fn to_mask(self, threshold: f64) -> Result<Self> {
+        self.binarize_as(threshold, true, false)
+    }
+
+    /// Convert a sparse matrix to a dense array with the same data type. Dense
+    /// arrays are returned unchanged.
+    pub fn to_dense(self) -> Result<Self> {
+        macro_rules! fun {
+            ($variant:ident, $x:expr) => {{
+                let mut arr = ::ndarray::Array2::default(($x.nrows(), $x.ncols()));
+                $x.triplet_iter().for_each(|(i, j, v)| arr[[i, j]] = v.clone());
+                DynArray::$variant(arr.into_dyn())
+            }};
+        }
+        let arr = match self {
+            ArrayData::Array(x) => x,
+            ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, fun),
+            ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, fun),
+            ArrayData::CsrNonCanonical(x) => match x.canonicalize() {
+                Ok(x) => crate::macros::dyn_map!(x, DynCsrMatrix, fun),
+                Err(_) => bail!("cannot convert a csr matrix with duplicated entries to dense"),
+            },
+            ArrayData::DataFrame(_) => bail!("cannot convert a DataFrame to a dense array"),
+        };
+        Ok(arr.into())
+    }
+
+    /// Convert a two-dimensional array to a CSR matrix with the same data
+    /// type, keeping the entries whose absolute value is greater than
+    /// `threshold`. A threshold of 0.0 drops exact zeros only. For boolean
+    /// arrays the `true` entries are kept, for string arrays the non-empty
+    /// ones. Sparse matrices keep their format and only lose the entries below
+    /// the threshold, including explicitly stored zeros.
+    pub fn to_sparse(self, threshold: f64) -> Result<Self> {
+        ensure!(threshold >= 0.0, "the threshold must not be negative");
+        macro_rules! csr {
+            ($variant:ident, $x:expr) => {{
+                let (nrows, ncols) = ($x.nrows(), $x.ncols());
+                let (offsets, indices, values) = prune_cs($x.disassemble(), threshold);
+                CsrMatrix::try_from_csr_data(nrows, ncols, offsets, indices, values)
+                    .map_err(|e| anyhow!("{}", e))?
+                    .into()
+            }};
+        }
+        macro_rules! csc {
+            ($variant:ident, $x:expr) => {{
+                let (nrows, ncols) = ($x.nrows(), $x.ncols());
+                let (offsets, indices, values) = prune_cs($x.disassemble(), threshold);
+                CscMatrix::try_from_csc_data(nrows, ncols, offsets, indices, values)
+                    .map_err(|e| anyhow!("{}", e))?
+                    .into()
+            }};
+        }
+        macro_rules! dense {
+            ($arr:expr, [$($variant:ident),*]) => {
+                match $arr {
+                    $(DynArray::$variant(x) => dense_to_csr(x, threshold)?.into(),)*
+                    DynArray::F16(_) => bail!(AnnDataError::f16_sparse()),
+                }
+            };
+        }
+        let data: ArrayData = match self {
+            ArrayData::Array(arr) => dense!(arr, [
+                I8, I16, I32, I64, U8, U16, U32, U64, F32, F64, Bool, String
+            ]),
+            ArrayData::CsrMatrix(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr),
+            ArrayData::CscMatrix(x) => crate::macros::dyn_map!(x, DynCscMatrix, csc),
+            ArrayData::CsrNonCanonical(x) => match x.canonicalize() {
+                Ok(x) => crate::macros::dyn_map!(x, DynCsrMatrix, csr),
+                Err(_) => bail!("cannot sparsify a csr matrix with duplicated entries"),
+            },
+            ArrayData::DataFrame(_) => bail!("cannot convert a DataFrame to a sparse matrix"),
+        };
+        Ok(data)
+    }
+
+    fn binarize_as<T: Clone>(self, threshold: f64, one: T, zero: T) -> Result<Self>
+    where
+        ArrayD<T>: Into<ArrayData>,
+        CsrMatrix<T>: Into<ArrayData>,
+        CscMatrix<T>: Into<ArrayData>,
+    {
+        let data = match self {
+            ArrayData::Array(arr) => {
+                let arr: ArrayD<f64> = arr.try_convert()?;
+                arr.mapv(|x| if x > threshold { one.clone() } else { zero.clone() }).into()
+            }
+            ArrayData::CsrMatrix(csr) => {
+                ensure!(threshold >= 0.0, "sparse matrices require a non-negative threshold");
+                let csr: CsrMatrix<f64> = csr.try_convert()?;
+                let (nrows, ncols) = (csr.nrows(), csr.ncols());
+                let (offsets, indices, values) = csr.disassemble();
+                let (offsets, indices) = binarize_cs(&offsets, &indices, &values, threshold);
+                let values = vec![one; indices.len()];
+                CsrMatrix::try_from_csr_data(nrows, ncols, offsets, indices, values)
+                    .map_err(|e| anyhow!("{}", e))?
+                    .into()
+            }
+            ArrayData::CscMatrix(csc) => {
+                ensure!(threshold >= 0.0, "sparse matrices require a non-negative threshold");
+                let csc: CscMatrix<f64> = csc.try_convert()?;
+                let (nrows, ncols) = (csc.nrows(), csc.ncols());
+                let (offsets, indices, values) = csc.disassemble();
+                let (offsets, indices) = binarize_cs(&offsets, &indices, &values, threshold);
+                let values = vec![one; indices.len()];
+                CscMatrix::try_from_csc_data(nrows, ncols, offsets, indices, values)
+                    .map_err(|e| anyhow!("{}", e))?
+                    .into()
+            }
+            ArrayData::CsrNonCanonical(csr) => match csr.canonicalize() {
+                Ok(csr) => ArrayData::CsrMatrix(csr).binarize_as(threshold, one, zero)?,
+                Err(_) => bail!("cannot binarize a csr matrix with duplicated entries"),
+            },
+            ArrayData::DataFrame(_) => bail!("cannot binarize a DataFrame"),
+        };
+        Ok(data)
+    }
+}
+
+/// Keep the entries of a compressed sparse matrix whose values exceed `threshold`.
+fn binarize_cs(
+    offsets: &[usize],
+    indices: &[usize],
+    values: &[f64],
+    threshold: f64,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut new_offsets = Vec::with_capacity(offsets.len());
+    let mut new_indices = Vec::new();
+    new_offsets.push(0);
+    offsets.windows(2).for_each(|w| {
+        (w[0]..w[1]).for_each(|i| {
+            if values[i] > threshold {
+                new_indices.push(indices[i]);
+            }
+        });
+        new_offsets.push(new_indices.len());
+    });
+    (new_offsets, new_indices)
+}
+
+/// Values that [`ArrayData::to_sparse`] keeps if they exceed a threshold.
+trait SparseValue {
+    fn exceeds(&self, threshold: f64) -> bool;
+}
+
+macro_rules! impl_sparse_value {
+    ($($ty:ty),*) => {
+        $(impl SparseValue for $ty {
+            fn exceeds(&self, threshold: f64) -> bool {
+                (*self as f64).abs() > threshold
+            }
+        })*
+    };
+}
+
+impl_sparse_value!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl SparseValue for bool {
+    fn exceeds(&self, _threshold: f64) -> bool {
+        *self
+    }
+}
+
+impl SparseValue for String {
+    fn exceeds(&self, _threshold: f64) -> bool {
+        !self.is_empty()
+    }
+}
+
+fn dense_to_csr<T: Clone + SparseValue>(arr: ArrayD<T>, threshold: f64) -> Result<CsrMatrix<T>> {
+    ensure!(
+        arr.ndim() == 2,
+        "only two-dimensional arrays can be converted to sparse matrices, got {} dimensions",
+        arr.ndim()
+    );
+    let (nrows, ncols) = (arr.shape()[0], arr.shape()[1]);
+    let mut offsets = Vec::with_capacity(nrows + 1);
+    let mut indices = Vec::new();
+    let mut values = Vec::new();
+    offsets.push(0);
+    for row in arr.outer_iter() {
+        for (j, x) in row.iter().enumerate().filter(|(_, x)| x.exceeds(threshold)) {
+            indices.push(j);
+            values.push(x.clone());
+        }
+        offsets.push(indices.len());
+    }
+    CsrMatrix::try_from_csr_data(nrows, ncols, offsets, indices, values)
+        .map_err(|e| anyhow!("{}", e))
+}
+
+/// Remove the entries of a compressed sparse matrix that do not exceed `threshold`.
+fn prune_cs<T: SparseValue>(
+    (offsets, indices, values): (Vec<usize>, Vec<usize>, Vec<T>),
+    threshold: f64,
+) -> (Vec<usize>, Vec<usize>, Vec<T>) {
+    let mut new_offsets = Vec::with_capacity(offsets.len());
+    let mut new_indices = Vec::with_capacity(indices.len());
+    let mut new_values = Vec::with_capacity(values.len());
+    new_offsets.push(0);
+    let mut entries = indices.into_iter().zip(values);
+    offsets.windows(2).for_each(|w| {
+        entries.by_ref().take(w[1] - w[0]).for_each(|(i, x)| {
+            if x.exceeds(threshold) {
+                new_indices.push(i);
+                new_values.push(x);
+            }
+        });
+        new_offsets.push(new_indices.len());
+    });
+    (new_offsets, new_indices, new_values)
+}
+
 impl TryFrom<ArrayData> for DynArray {
     type Error = anyhow::Error;
     fn try_from(value: ArrayData) -> Result<Self, Self::Error> {
@@ -192,6 +420,12 @@ macro_rules! impl_arraydata_traits {
 
 impl_arraydata_traits!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64, bool, String);
 
+impl<D: RemoveAxis> From<Array<f16, D>> for ArrayData {
+    fn from(data: Array<f16, D>) -> Self {
+        ArrayData::Array(data.into())
+    }
+}
+
 impl Readable for ArrayData {
     fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
         match container.encoding_type()? {
@@ -242,6 +476,21 @@ impl Writable for ArrayData {
             ArrayData::DataFrame(data) => data.write(location, name),
         }
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        match self {
+            ArrayData::Array(data) => data.write_with_config(location, name, config),
+            ArrayData::CsrMatrix(data) => data.write_with_config(location, name, config),
+            ArrayData::CsrNonCanonical(data) => data.write_with_config(location, name, config),
+            ArrayData::CscMatrix(data) => data.write_with_config(location, name, config),
+            ArrayData::DataFrame(data) => data.write(location, name),
+        }
+    }
 }
 
 impl HasShape for ArrayData {
@@ -302,7 +551,9 @@ impl Stackable for ArrayData {
             ArrayData::CsrNonCanonical(_) => {
                 DynCsrNonCanonical::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
             }
-            ArrayData::CscMatrix(_) => todo!(),
+            ArrayData::CscMatrix(_) => {
+                DynCscMatrix::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
             ArrayData::DataFrame(_) => {
                 <DataFrame as Stackable>::vstack(iter.map(|x| x.try_into().unwrap()))
                     .map(|x| x.into())
@@ -355,7 +606,7 @@ fn read_csr<B: Backend>(container: &DataContainer<B>) -> Result<ArrayData> {
         CsrNonCanonical<T>: Into<ArrayData>,
     {
         let group = container.as_group()?;
-        let shape: Vec<u64> = group.get_attr("shape")?;
+        let shape = sparse_shape::<B>(group)?;
         let data = group
             .open_dataset("data")?
             .read_array::<_, Ix1>()?
@@ -371,7 +622,7 @@ fn read_csr<B: Backend>(container: &DataContainer<B>) -> Result<ArrayData> {
             .read_array_cast::<_, Ix1>()?
             .into_raw_vec_and_offset()
             .0;
-        from_csr_data::<T>(shape[0] as usize, shape[1] as usize, indptr, indices, data)
+        from_csr_data::<T>(shape[0], shape[1], indptr, indices, data)
     }
 
     match container {
@@ -384,6 +635,7 @@ fn read_csr<B: Backend>(container: &DataContainer<B>) -> Result<ArrayData> {
             ScalarType::U16 => _read_csr::<B, u16>(container),
             ScalarType::U32 => _read_csr::<B, u32>(container),
             ScalarType::U64 => _read_csr::<B, u64>(container),
+            ScalarType::F16 => bail!(AnnDataError::f16_sparse()),
             ScalarType::F32 => _read_csr::<B, f32>(container),
             ScalarType::F64 => _read_csr::<B, f64>(container),
             ScalarType::Bool => _read_csr::<B, bool>(container),
@@ -417,7 +669,7 @@ where
 
         let data = if let SelectInfoElem::Slice(s) = info[0].as_ref() {
             let group = container.as_group()?;
-            let shape: Vec<u64> = group.get_attr("shape")?;
+            let shape = sparse_shape::<B>(group)?;
             let indptr_slice = if let Some(end) = s.end {
                 SelectInfoElem::from(s.start..end + 1)
             } else {
@@ -439,7 +691,7 @@ where
                 .to_vec();
             indptr.iter_mut().for_each(|x| *x -= lo);
 
-            from_csr_data::<T>(indptr.len() - 1, shape[1] as usize, indptr, indices, data)
+            from_csr_data::<T>(indptr.len() - 1, shape[1], indptr, indices, data)
                 .unwrap()
                 .select_axis(1, info[1].as_ref())
         } else {
@@ -458,6 +710,7 @@ where
             ScalarType::U16 => _read_csr::<B, u16, _>(container, info),
             ScalarType::U32 => _read_csr::<B, u32, _>(container, info),
             ScalarType::U64 => _read_csr::<B, u64, _>(container, info),
+            ScalarType::F16 => bail!(AnnDataError::f16_sparse()),
             ScalarType::F32 => _read_csr::<B, f32, _>(container, info),
             ScalarType::F64 => _read_csr::<B, f64, _>(container, info),
             ScalarType::Bool => _read_csr::<B, bool, _>(container, info),
@@ -1,21 +1,29 @@
 use std::collections::HashMap;
 
-use crate::backend::{AttributeOp, Backend, DataContainer, DatasetOp, GroupOp, ScalarType};
+use crate::backend::{
+    AttributeOp, Backend, DataContainer, DatasetOp, EncodingType, GroupOp, ScalarType,
+};
 use crate::data::array::{
     slice::{SelectInfoElem, Shape},
     CategoricalArray, DynArray,
 };
 use crate::data::data_traits::*;
 use crate::data::index::{Index, Interval};
+use crate::error::AnnDataError;
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use log::warn;
 use ndarray::{Array1, Ix1};
 use polars::chunked_array::ChunkedArray;
 use polars::datatypes::DataType;
-use polars::prelude::{DataFrame, Series};
+use polars::prelude::{create_enum_dtype, DataFrame, NamedFrom, Series, TimeUnit};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use super::{BackendData, SelectInfoBounds, SelectInfoElemBounds};
+use super::ArrayChunk;
+
+/// Number of names written at a time for a range index.
+const RANGE_CHUNK_SIZE: usize = 100_000;
 
 impl Element for DataFrame {
     fn data_type(&self) -> crate::backend::DataType {
@@ -32,7 +40,7 @@ impl Element for DataFrame {
             .collect();
         metadata.insert("column-order".to_string(), columns.into());
 
-        MetaData::new("dataframe", "0.2.0", Some(metadata))
+        MetaData::new(EncodingType::DataFrame, "0.2.0", Some(metadata))
     }
 }
 
@@ -42,6 +50,7 @@ impl Writable for DataFrame {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
+        check_series_dtypes(self)?;
         let mut group = if location.exists(name)? {
             location.open_group(name)?
         } else {
@@ -57,28 +66,23 @@ impl Writable for DataFrame {
         let mut container = DataContainer::Group(group);
 
         // Create an index as the python anndata package enforce it. This is not used by this library
-        DataFrameIndex::from(self.height()).overwrite(&mut container)?;
+        write_default_index(&mut container, self.height())?;
 
         Ok(container)
     }
 
-    /// Overwrite the data inplace.
+    /// Overwrite the data inplace. The dtypes of all columns are checked before
+    /// anything is deleted, so an unsupported column leaves the old data intact.
     fn overwrite<B: Backend>(&self, mut container: DataContainer<B>) -> Result<DataContainer<B>> {
-        if let Ok(index_name) = container.get_attr::<String>("_index") {
-            for obj in container.as_group()?.list()? {
-                if obj != index_name {
-                    container.as_group()?.delete(&obj)?;
-                }
-            }
-            let n = self.height();
-            if n != 0 && n != container.as_group()?.open_dataset(&index_name)?.shape()[0] {
-                DataFrameIndex::from(self.height()).overwrite(&mut container)?;
-            }
-        } else {
-            for obj in container.as_group()?.list()? {
+        check_series_dtypes(self)?;
+        let index_name = container.get_attr::<String>("_index").ok();
+        for obj in container.as_group()?.list()? {
+            if Some(&obj) != index_name.as_ref() {
                 container.as_group()?.delete(&obj)?;
             }
-            DataFrameIndex::from(self.height()).overwrite(&mut container)?;
+        }
+        if index_name.is_none() || self.height() != 0 {
+            write_default_index(&mut container, self.height())?;
         }
 
         self.iter().try_for_each(|x| {
@@ -91,20 +95,27 @@ impl Writable for DataFrame {
     }
 }
 
+/// Columns are read in parallel on the rayon thread pool. Backend handles are
+/// `Send + Sync`, and backends that are not thread-safe internally, like HDF5,
+/// serialize their calls, so this is always safe.
 impl Readable for DataFrame {
     fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
         let columns: Vec<String> = container.get_attr("column-order")?;
-        columns
-            .into_iter()
+        check_column_order(container)?;
+        let group = container.as_group()?;
+        let series = columns
+            .par_iter()
             .map(|name| {
                 let name = name.as_str();
-                let series_container = DataContainer::<B>::open(container.as_group()?, name)?;
+                let series_container = DataContainer::<B>::open(group, name)?;
                 let mut series = read_series::<B>(&series_container)
                     .with_context(|| format!("Failed to read series: {}", name))?;
                 series.rename(name.into());
                 Ok(series)
             })
-            .collect()
+            .collect::<Result<Vec<_>>>()?;
+        // The parallel collect keeps the order given by "column-order".
+        Ok(series.into_iter().collect())
     }
 }
 
@@ -148,37 +159,133 @@ impl Stackable for DataFrame {
     }
 }
 
+impl HasShape for Series {
+    fn shape(&self) -> Shape {
+        self.len().into()
+    }
+}
+
+/// Concatenate series of compatible dtypes. Integer and float widths are
+/// promoted, and categorical dictionaries are merged. Categorical and string
+/// series are stacked into a string series. As no integer type holds both
+/// `u64` and signed values, they are stacked into `f64`, like numpy does.
+impl Stackable for Series {
+    fn vstack<I: Iterator<Item = Self>>(iter: I) -> Result<Self> {
+        let series: Vec<Series> = iter.collect();
+        let Some(first) = series.first() else {
+            return Ok(Series::new_empty("".into(), &DataType::Null));
+        };
+        let name = first.name().clone();
+        let dtype = series
+            .iter()
+            .skip(1)
+            .try_fold(first.dtype().clone(), |acc, x| {
+                stacked_dtype(&acc, x.dtype()).with_context(|| {
+                    format!(
+                        "cannot stack column '{}': incompatible dtypes {} and {}",
+                        name,
+                        acc,
+                        x.dtype()
+                    )
+                })
+            })?;
+
+        // Categorical series are stacked as strings and re-encoded, as their
+        // dictionaries may differ.
+        let (target, ordering) = match &dtype {
+            DataType::Categorical(_, ordering) => (DataType::String, Some(*ordering)),
+            dtype => (dtype.clone(), None),
+        };
+        let mut iter = series.iter().map(|x| x.strict_cast(&target));
+        let mut result = iter.next().unwrap()?;
+        for x in iter {
+            result.append(&x?)?;
+        }
+        if let Some(ordering) = ordering {
+            result = result.cast(&DataType::Categorical(None, ordering))?;
+        }
+        result.rename(name);
+        Ok(result)
+    }
+}
+
+/// The dtype that two series of dtypes `a` and `b` are stacked into.
+fn stacked_dtype(a: &DataType, b: &DataType) -> Option<DataType> {
+    fn int_bits(dtype: &DataType) -> usize {
+        match dtype {
+            DataType::Int8 | DataType::UInt8 => 8,
+            DataType::Int16 | DataType::UInt16 => 16,
+            DataType::Int32 | DataType::UInt32 => 32,
+            _ => 64,
+        }
+    }
+
+    match (a, b) {
+        (DataType::Categorical(_, ordering), DataType::Categorical(..)) => {
+            Some(DataType::Categorical(None, *ordering))
+        }
+        // Free-text columns are not turned into categoricals.
+        (DataType::Categorical(..), DataType::String)
+        | (DataType::String, DataType::Categorical(..)) => Some(DataType::String),
+        _ if a == b => Some(a.clone()),
+        (DataType::Null, _) => Some(b.clone()),
+        (_, DataType::Null) => Some(a.clone()),
+        _ if a.is_float() && b.is_float() => Some(DataType::Float64),
+        (DataType::UInt64, b) | (b, DataType::UInt64) if b.is_signed_integer() => {
+            Some(DataType::Float64)
+        }
+        _ if a.is_integer() && b.is_integer() => {
+            let signed = a.is_signed_integer() || b.is_signed_integer();
+            let bits = if a.is_signed_integer() == b.is_signed_integer() {
+                int_bits(a).max(int_bits(b))
+            } else {
+                let (s, u) = if a.is_signed_integer() { (a, b) } else { (b, a) };
+                if int_bits(s) > int_bits(u) {
+                    int_bits(s)
+                } else {
+                    (int_bits(u) * 2).min(64)
+                }
+            };
+            let dtype = match (signed, bits) {
+                (true, 8) => DataType::Int8,
+                (true, 16) => DataType::Int16,
+                (true, 32) => DataType::Int32,
+                (true, _) => DataType::Int64,
+                (false, 8) => DataType::UInt8,
+                (false, 16) => DataType::UInt16,
+                (false, 32) => DataType::UInt32,
+                (false, _) => DataType::UInt64,
+            };
+            Some(dtype)
+        }
+        _ => None,
+    }
+}
+
 impl ReadableArray for DataFrame {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
         let group = container.as_group()?;
         let index: String = group.get_attr("_index")?;
-        let nrows = group.open_dataset(&index)?.shape()[0];
+        let nrows = group.open_dataset(&index)?.shape()?[0];
         let columns: Vec<String> = container.get_attr("column-order")?;
         Ok((nrows, columns.len()).into())
     }
 
+    /// Only the selected rows of each column are read, see [`read_series_select`].
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
     where
         B: Backend,
         S: AsRef<SelectInfoElem>,
     {
         let columns: Vec<String> = container.get_attr("column-order")?;
-        SelectInfoElemBounds::new(&info.as_ref()[1], columns.len())
+        SelectInfoElemBounds::try_new(&info.as_ref()[1], columns.len())?
             .iter()
             .map(|i| {
                 let name = &columns[i];
-                let series = container
-                    .as_group()?
-                    .open_dataset(name)
-                    .map(DataContainer::Dataset)
-                    .and_then(|x| read_series::<B>(&x))
+                // Categorical and nullable columns are stored as groups.
+                let mut series = DataContainer::<B>::open(container.as_group()?, name)
+                    .and_then(|x| read_series_select::<B>(&x, info[0].as_ref()))
                     .with_context(|| format!("Failed to read series: {}", name))?;
-
-                let indices: Vec<u32> = SelectInfoElemBounds::new(&info[0], series.len())
-                    .iter()
-                    .map(|x| x.try_into().unwrap())
-                    .collect();
-                let mut series = series.take_slice(indices.as_slice())?;
                 series.rename(name.into());
                 Ok(series)
             })
@@ -216,14 +323,49 @@ impl DataFrameIndex {
         self.index.len()
     }
 
+    /// Sets the name of the index, which is stored in the `_index` attribute
+    /// and becomes the name of the index in Python anndata, e.g. "cell_id".
+    /// Indices created by this library are named "index".
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.index_name = name.into();
+        self
+    }
+
+    /// Returns the position of `k`. If `k` occurs more than once, any one of
+    /// its positions may be returned; use [`DataFrameIndex::get_indices`] to
+    /// get all of them.
     pub fn get_index(&self, k: &str) -> Option<usize> {
         self.index.get_index(k)
     }
 
+    /// Returns all positions of `k`, in increasing order.
+    pub fn get_indices(&self, k: &str) -> Vec<usize> {
+        self.index.get_indices(k)
+    }
+
+    /// Returns the positions of the entries overlapping the genomic region
+    /// `chrom:start-end`, with `end` exclusive. See [`Index::query_overlap`].
+    pub fn query_overlap(&self, chrom: &str, start: usize, end: usize) -> Vec<usize> {
+        self.index.query_overlap(chrom, start, end)
+    }
+
+    /// Whether some names occur more than once.
+    pub fn contain_duplicates(&self) -> bool {
+        self.index.contain_duplicates()
+    }
+
     pub fn into_vec(self) -> Vec<String> {
         self.index.into_vec()
     }
 
+    /// Append the names of `other`, keeping the name of this index.
+    pub fn concat(self, other: DataFrameIndex) -> Self {
+        Self {
+            index_name: self.index_name,
+            index: self.index.concat(other.index),
+        }
+    }
+
     pub fn select(&self, select: &SelectInfoElem) -> Self {
         let index = self.index.select(select);
         Self {
@@ -249,7 +391,7 @@ impl DataFrameIndex {
             "intervals" => {
                 let keys: Vec<String> = dataset.get_attr("names")?;
                 let values: Vec<Vec<u64>> = dataset.get_attr("intervals")?;
-                Ok(keys
+                let index: DataFrameIndex = keys
                     .into_iter()
                     .zip(values.into_iter().map(|row| Interval {
                         start: row[0] as usize,
@@ -257,28 +399,46 @@ impl DataFrameIndex {
                         size: row[2] as usize,
                         step: row[3] as usize,
                     }))
-                    .collect())
+                    .collect();
+                Ok(index.with_name(index_name))
             }
             "range" => {
                 let start: u64 = dataset.get_attr("start")?;
                 let end: u64 = dataset.get_attr("end")?;
-                Ok((start as usize..end as usize).into())
+                let mut index: DataFrameIndex = (start as usize..end as usize).into();
+                index.index_name = index_name;
+                Ok(index)
             }
             x => bail!("Unknown index type: {}", x),
         }
     }
 
     /// Overwrite the index inplace.
+    ///
+    /// A range index is stored as the `range` index type: the bounds are kept
+    /// in the attributes, from which the index is read back, and the names are
+    /// written in chunks for Python anndata without collecting all of them.
     pub(crate) fn overwrite<B: Backend>(&self, container: &mut DataContainer<B>) -> Result<()> {
         if let Ok(index_name) = container.get_attr::<String>("_index") {
             container.as_group()?.delete(&index_name)?;
         }
         container.new_attr("_index", self.index_name.clone())?;
         let group = container.as_group()?;
+        if let Index::Range(range) = &self.index {
+            let chunks = range.clone().step_by(RANGE_CHUNK_SIZE).map(|start| {
+                let end = (start + RANGE_CHUNK_SIZE).min(range.end);
+                (start..end).map(|i| i.to_string()).collect::<Array1<String>>()
+            });
+            let mut data = Array1::write_by_chunk::<B, _, _>(chunks, group, &self.index_name)?;
+            data.new_attr("index_type", "range")?;
+            data.new_attr("start", range.start as u64)?;
+            data.new_attr("end", range.end as u64)?;
+            return Ok(());
+        }
         let arr: Array1<String> = self.clone().into_iter().collect();
         let mut data = arr.write(group, &self.index_name)?;
         match &self.index {
-            Index::List(_) => {
+            Index::List(_) | Index::Range(_) => {
                 data.new_attr("index_type", "list")?;
             }
             Index::Intervals(intervals) => {
@@ -297,11 +457,6 @@ impl DataFrameIndex {
                     data.new_attr("index_type", "intervals")?;
                 }
             }
-            Index::Range(range) => {
-                data.new_attr("index_type", "range")?;
-                data.new_attr("start", range.start as u64)?;
-                data.new_attr("end", range.end as u64)?;
-            }
         }
         Ok(())
     }
@@ -328,6 +483,36 @@ where
     }
 }
 
+/// Build an index from a string or categorical series. Fails if the series
+/// contains null values or duplicated names.
+impl TryFrom<&Series> for DataFrameIndex {
+    type Error = anyhow::Error;
+
+    fn try_from(series: &Series) -> Result<Self> {
+        let series = match series.dtype() {
+            DataType::String => series.clone(),
+            DataType::Categorical(_, _) => series.cast(&DataType::String)?,
+            ty => bail!("cannot build an index from a series of type {}", ty),
+        };
+        ensure!(
+            series.null_count() == 0,
+            "index cannot contain null values, found {} in '{}'",
+            series.null_count(),
+            series.name(),
+        );
+        let index: DataFrameIndex = series
+            .str()?
+            .into_iter()
+            .map(|x| x.unwrap().to_string())
+            .collect();
+        ensure!(
+            !index.index.contain_duplicates(),
+            "index cannot contain duplicated names"
+        );
+        Ok(index)
+    }
+}
+
 impl<D> FromIterator<D> for DataFrameIndex
 where
     Index: FromIterator<D>,
@@ -347,7 +532,104 @@ where
 /// Helper functions
 ////////////////////////////////////////////////////////////////////////////////
 
-fn write_series<B: Backend, G: GroupOp<B>>(
+/// Write a range index of length `n`, unless the dataframe already has an
+/// index of this length. The name given by an existing `_index` attribute is
+/// kept.
+fn write_default_index<B: Backend>(container: &mut DataContainer<B>, n: usize) -> Result<()> {
+    let mut index = DataFrameIndex::from(n);
+    if let Ok(index_name) = container.get_attr::<String>("_index") {
+        let group = container.as_group()?;
+        if group.exists(&index_name)? && group.open_dataset(&index_name)?.shape()?[0] == n {
+            return Ok(());
+        }
+        index.index_name = index_name;
+    }
+    index.overwrite(container)
+}
+
+/// Compare the `column-order` attribute of a dataframe with the stored
+/// datasets. Returns the columns listed but not stored, and the datasets
+/// stored but not listed, other than the index.
+pub(crate) fn column_order_mismatch<B: Backend>(
+    container: &DataContainer<B>,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let columns: Vec<String> = container.get_attr("column-order")?;
+    let index_name = container.get_attr::<String>("_index").ok();
+    let datasets = container.as_group()?.list()?;
+    let missing = columns
+        .iter()
+        .filter(|x| !datasets.contains(x))
+        .cloned()
+        .collect();
+    let extra = datasets
+        .into_iter()
+        .filter(|x| !columns.contains(x) && Some(x) != index_name.as_ref())
+        .collect();
+    Ok((missing, extra))
+}
+
+/// Cross-check the `column-order` attribute against the stored datasets.
+/// Missing columns are an error. Datasets not listed in `column-order` are
+/// ignored with a warning, as in Python anndata.
+fn check_column_order<B: Backend>(container: &DataContainer<B>) -> Result<()> {
+    let (missing, extra) = column_order_mismatch(container)?;
+    ensure!(
+        missing.is_empty(),
+        "dataframe at '{}' is corrupted: columns listed in 'column-order' but not stored: {}{}",
+        container.path().display(),
+        missing.join(", "),
+        if extra.is_empty() {
+            String::new()
+        } else {
+            format!("; stored but not listed: {}", extra.join(", "))
+        },
+    );
+    if !extra.is_empty() {
+        warn!(
+            "ignoring datasets not listed in 'column-order' of '{}': {}",
+            container.path().display(),
+            extra.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Fail if any column has a dtype that [`write_series`] cannot store.
+pub(crate) fn check_series_dtypes(df: &DataFrame) -> Result<()> {
+    for column in df.get_columns() {
+        let supported = matches!(
+            column.dtype(),
+            DataType::UInt8
+                | DataType::UInt16
+                | DataType::UInt32
+                | DataType::UInt64
+                | DataType::Int8
+                | DataType::Int16
+                | DataType::Int32
+                | DataType::Int64
+                | DataType::Float32
+                | DataType::Float64
+                | DataType::Boolean
+                | DataType::String
+                | DataType::Categorical(_, _)
+                | DataType::Enum(_, _)
+                | DataType::Datetime(_, _)
+                | DataType::Duration(_)
+        );
+        ensure!(
+            supported,
+            "Unsupported series data type: {:?} in column '{}'",
+            column.dtype(),
+            column.name()
+        );
+    }
+    Ok(())
+}
+
+/// String series are stored as "string-array" (or "nullable-string-array" if
+/// they have missing values) and read back as strings. Only categorical and
+/// enum series are stored as "categorical".
+pub(crate) fn write_series<B: Backend, G: GroupOp<B>>(
     series: &Series,
     location: &G,
     name: &str,
@@ -374,49 +656,211 @@ fn write_series<B: Backend, G: GroupOp<B>>(
             .collect::<Array1<f64>>()
             .write(location, name),
         DataType::Boolean => write_series_helper(series.bool()?, location, name),
-        DataType::String => {
-            let series = series.str()?;
-            if let Some(str_vec) = series.iter().map(|x| x.map(|s| s.to_string())).collect::<Option<Vec<_>>>() {
-                Array1::from(str_vec).write(location, name)
-            } else {
-                series.into_iter().collect::<CategoricalArray>().write(location, name)
-            }
-        },
+        DataType::String => write_series_helper(
+            series.str()?.into_iter().map(|x| x.map(|s| s.to_string())),
+            location,
+            name,
+        ),
         DataType::Categorical(_, _) => series
             .categorical()?
             .iter_str()
             .collect::<CategoricalArray>()
             .write(location, name),
+        DataType::Enum(_, _) => {
+            let ca = series.categorical()?;
+            let categories = ca
+                .get_rev_map()
+                .get_categories()
+                .values_iter()
+                .map(|x| x.to_string())
+                .collect();
+            let codes = ca.physical().into_iter().collect::<Array1<_>>().into_dyn();
+            CategoricalArray { codes, categories, ordered: true }.write(location, name)
+        }
+        DataType::Datetime(unit, tz) => {
+            let physical = series.to_physical_repr();
+            let mut container = write_series_helper(physical.i64()?, location, name)?;
+            container.new_attr("polars-dtype", "datetime")?;
+            container.new_attr("time-unit", time_unit_str(unit))?;
+            if let Some(tz) = tz {
+                container.new_attr("time-zone", tz.as_str())?;
+            }
+            Ok(container)
+        }
+        DataType::Duration(unit) => {
+            let physical = series.to_physical_repr();
+            let mut container = write_series_helper(physical.i64()?, location, name)?;
+            container.new_attr("polars-dtype", "duration")?;
+            container.new_attr("time-unit", time_unit_str(unit))?;
+            Ok(container)
+        }
         other => bail!("Unsupported series data type: {:?}", other),
     }
 }
 
-fn read_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
-    let ty = container.encoding_type()?;
-    match ty {
-        crate::backend::DataType::Categorical => {
+fn time_unit_str(unit: &TimeUnit) -> &'static str {
+    match unit {
+        TimeUnit::Nanoseconds => "ns",
+        TimeUnit::Microseconds => "us",
+        TimeUnit::Milliseconds => "ms",
+    }
+}
+
+/// Datetime and duration series are stored as int64 with their polars dtype in
+/// the attributes. Cast such series back to their original dtype.
+fn restore_temporal<B: Backend>(container: &DataContainer<B>, series: Series) -> Result<Series> {
+    match temporal_dtype(container)? {
+        Some(dtype) => Ok(series.cast(&dtype)?),
+        None => Ok(series),
+    }
+}
+
+fn temporal_dtype<B: Backend>(container: &DataContainer<B>) -> Result<Option<DataType>> {
+    let Ok(kind) = container.get_attr::<String>("polars-dtype") else {
+        return Ok(None);
+    };
+    let unit = match container.get_attr::<String>("time-unit")?.as_str() {
+        "ns" => TimeUnit::Nanoseconds,
+        "us" => TimeUnit::Microseconds,
+        "ms" => TimeUnit::Milliseconds,
+        unit => bail!("unknown time unit '{}'", unit),
+    };
+    let dtype = match kind.as_str() {
+        "datetime" => {
+            let tz = container.get_attr::<String>("time-zone").ok();
+            DataType::Datetime(unit, tz.map(Into::into))
+        }
+        "duration" => DataType::Duration(unit),
+        kind => bail!("unknown polars dtype '{}'", kind),
+    };
+    Ok(Some(dtype))
+}
+
+pub(crate) fn read_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
+    let series = match container.encoding()? {
+        EncodingType::Categorical => {
             let categories = container.as_group()?.open_dataset("categories")?;
             let s = match categories.dtype()? {
                 ScalarType::String => CategoricalArray::read(container)?.into(),
-                _ => read_cat_as_series(container)?,
+                _ => read_cat_as_series(container, &SelectInfoElem::full())?,
             };
             Ok(s)
         }
-        crate::backend::DataType::Array(_) => Ok(DynArray::read(container)?.into()),
-        crate::backend::DataType::NullableArray => read_nullable(container),
-        _ => bail!("Unsupported data type: {:?}", ty),
+        EncodingType::Array | EncodingType::StringArray => Ok(DynArray::read(container)?.into()),
+        EncodingType::NullableInteger
+        | EncodingType::NullableBoolean
+        | EncodingType::NullableStringArray => read_nullable(container, &SelectInfoElem::full()),
+        enc => bail!("cannot read a series from an element encoded as '{}'", enc),
+    }?;
+    restore_temporal(container, series)
+}
+
+/// Read the rows `select` of a series. Only these rows are read, and for
+/// categorical series all categories with the selected codes, so that the
+/// series keeps the categories of the whole column.
+pub(crate) fn read_series_select<B: Backend>(
+    container: &DataContainer<B>,
+    select: &SelectInfoElem,
+) -> Result<Series> {
+    if select.is_full() {
+        return read_series(container);
     }
+    let series = match container.encoding()? {
+        EncodingType::Categorical => {
+            let categories = container.as_group()?.open_dataset("categories")?;
+            match categories.dtype()? {
+                ScalarType::String => {
+                    Ok(CategoricalArray::read_select(container, &[select])?.into())
+                }
+                _ => read_cat_as_series(container, select),
+            }
+        }
+        EncodingType::Array | EncodingType::StringArray => {
+            Ok(container.as_dataset()?.read_dyn_array_slice(&[select])?.into())
+        }
+        EncodingType::NullableInteger
+        | EncodingType::NullableBoolean
+        | EncodingType::NullableStringArray => read_nullable(container, select),
+        enc => bail!("cannot read a series from an element encoded as '{}'", enc),
+    }?;
+    restore_temporal(container, series)
 }
 
-/// Used to read non-string categorical data into regular arrays. After all, such
-/// data should not be stored as categorical data.
-fn read_cat_as_series<B: Backend>(container: &DataContainer<B>) -> Result<Series> {
+/// Determine the dtype [`read_series`] would return from the encoding and the
+/// attributes of the container, without reading the data.
+pub(crate) fn read_series_dtype<B: Backend>(container: &DataContainer<B>) -> Result<DataType> {
+    let dtype = match container.encoding()? {
+        EncodingType::Categorical => {
+            let group = container.as_group()?;
+            let categories = group.open_dataset("categories")?;
+            match categories.dtype()? {
+                // Ordered categoricals are read as enums, whose dtype includes
+                // the categories. Only the categories are read in this case.
+                ScalarType::String if group.get_attr("ordered").unwrap_or(false) => {
+                    let categories: Array1<String> = categories.read_array()?;
+                    let categories = Series::new("".into(), categories.to_vec());
+                    let categories = categories.str()?.rechunk();
+                    create_enum_dtype(categories.downcast_iter().next().unwrap().clone())
+                }
+                ScalarType::String => {
+                    DataType::Categorical(None, polars::datatypes::CategoricalOrdering::Lexical)
+                }
+                ty => scalar_to_polars(ty),
+            }
+        }
+        EncodingType::Array | EncodingType::StringArray => {
+            scalar_to_polars(container.as_dataset()?.dtype()?)
+        }
+        EncodingType::NullableInteger
+        | EncodingType::NullableBoolean
+        | EncodingType::NullableStringArray => {
+            scalar_to_polars(container.as_group()?.open_dataset("values")?.dtype()?)
+        }
+        enc => bail!("cannot read a series from an element encoded as '{}'", enc),
+    };
+    Ok(temporal_dtype(container)?.unwrap_or(dtype))
+}
+
+pub(crate) fn schema_of(df: &DataFrame) -> Vec<(String, DataType)> {
+    df.get_columns()
+        .iter()
+        .map(|x| (x.name().to_string(), x.dtype().clone()))
+        .collect()
+}
+
+fn scalar_to_polars(ty: ScalarType) -> DataType {
+    match ty {
+        ScalarType::I8 => DataType::Int8,
+        ScalarType::I16 => DataType::Int16,
+        ScalarType::I32 => DataType::Int32,
+        ScalarType::I64 => DataType::Int64,
+        ScalarType::U8 => DataType::UInt8,
+        ScalarType::U16 => DataType::UInt16,
+        ScalarType::U32 => DataType::UInt32,
+        ScalarType::U64 => DataType::UInt64,
+        ScalarType::F16 | ScalarType::F32 => DataType::Float32,
+        ScalarType::F64 => DataType::Float64,
+        ScalarType::Bool => DataType::Boolean,
+        ScalarType::String => DataType::String,
+    }
+}
+
+/// Read the rows `select` of a categorical array with non-string categories
+/// as a series of the category values. Such data should not be stored as
+/// categorical data, so it is read into a regular array.
+fn read_cat_as_series<B: Backend>(
+    container: &DataContainer<B>,
+    select: &SelectInfoElem,
+) -> Result<Series> {
     let group = container.as_group()?;
-    let codes: Array1<i32> = group.open_dataset("codes")?.read_array_cast()?;
+    let codes: Array1<i32> = group.open_dataset("codes")?.read_array_slice_cast(&[select])?;
     let codes = codes.mapv(|x| if x < 0 { None } else { Some(x as usize) });
     let categories = group.open_dataset("categories")?.read_dyn_array().unwrap();
 
     macro_rules! fun {
+        (F16, $value:expr) => {
+            codes.iter().map(|x| x.map(|i| $value[i].to_f32())).collect()
+        };
         ($variant:ident, $value:expr) => {
             codes.iter().map(|x| x.map(|i| $value[i].clone())).collect()
         };
@@ -456,10 +900,12 @@ where
             | ScalarType::U8
             | ScalarType::U16
             | ScalarType::U32
-            | ScalarType::U64 => "nullable-integer",
-            ScalarType::Bool => "nullable-boolean",
-            ScalarType::String => "nullable-string",
-            ScalarType::F32 | ScalarType::F64 => bail!("float types are not supported"),
+            | ScalarType::U64 => EncodingType::NullableInteger,
+            ScalarType::Bool => EncodingType::NullableBoolean,
+            ScalarType::String => EncodingType::NullableStringArray,
+            ScalarType::F16 | ScalarType::F32 | ScalarType::F64 => {
+                bail!(AnnDataError::UnsupportedDtype(format!("nullable {}", T::DTYPE)))
+            }
         };
         MetaData::new(encoding, "0.1.0", None).save(&mut group)?;
         Array1::from(values).write(&group, "values")?;
@@ -470,17 +916,33 @@ where
     }
 }
 
-fn read_nullable<B>(container: &DataContainer<B>) -> Result<Series>
+/// Read the rows `select` of a nullable array.
+fn read_nullable<B>(container: &DataContainer<B>, select: &SelectInfoElem) -> Result<Series>
 where
     B: Backend,
 {
-    let mask: Array1<bool> = container.as_group()?.open_dataset("mask")?.read_array()?;
+    let mask = container.as_group()?.open_dataset("mask")?;
     let dataset = container.as_group()?.open_dataset("values")?;
+    ensure!(
+        mask.shape()?[0] == dataset.shape()?[0],
+        "the mask and the values of a nullable array have different lengths"
+    );
+    let select = [select];
+    let mask: Array1<bool> = match mask.dtype()? {
+        ScalarType::Bool => mask.read_array_slice(&select)?,
+        // Some writers store the mask as integers.
+        ScalarType::U8 => mask.read_array_slice::<u8, _, Ix1>(&select)?.mapv(|x| x != 0),
+        ScalarType::I8 => mask.read_array_slice::<i8, _, Ix1>(&select)?.mapv(|x| x != 0),
+        ty => bail!("the mask of a nullable array must be boolean, found {}", ty),
+    };
 
     macro_rules! fun {
+        (f16) => {
+            bail!(AnnDataError::UnsupportedDtype("half-precision nullable arrays".into()))
+        };
         ($variant:ident) => {
             mask.iter()
-                .zip(dataset.read_array::<$variant, Ix1>()?.into_iter())
+                .zip(dataset.read_array_slice::<$variant, _, Ix1>(&select)?.into_iter())
                 .map(|(m, v)| if *m { None } else { Some(v) })
                 .collect()
         };
@@ -488,3 +950,47 @@ where
 
     Ok(crate::macros::dyn_match!(dataset.dtype()?, ScalarType, fun))
 }
+
+#[cfg(test)]
+mod series_tests {
+    use super::*;
+    use polars::prelude::NamedFrom;
+
+    #[test]
+    fn test_vstack_series() {
+        let a = Series::new("x".into(), &[1i8, 2]);
+        let b = Series::new("x".into(), &[300u16]);
+        let stacked = Stackable::vstack([a, b].into_iter()).unwrap();
+        assert_eq!(stacked.dtype(), &DataType::Int32);
+        assert_eq!(stacked.len(), 3);
+
+        let _sc = polars::datatypes::string_cache::StringCacheHolder::hold();
+        let cat = DataType::Categorical(None, Default::default());
+        let a = Series::new("c".into(), &["a", "b"]).cast(&cat).unwrap();
+        let b = Series::new("c".into(), &["c", "a"]).cast(&cat).unwrap();
+        let stacked = Stackable::vstack([a, b].into_iter()).unwrap();
+        assert_eq!(stacked.dtype(), &cat);
+        let values = stacked.cast(&DataType::String).unwrap();
+        let values: Vec<_> = values.str().unwrap().into_iter().flatten().collect();
+        assert_eq!(values, vec!["a", "b", "c", "a"]);
+
+        let text = Series::new("c".into(), &["free text"]);
+        let b = Series::new("c".into(), &["c"]).cast(&cat).unwrap();
+        let stacked = Stackable::vstack([b, text].into_iter()).unwrap();
+        assert_eq!(stacked.dtype(), &DataType::String);
+        let values: Vec<_> = stacked.str().unwrap().into_iter().flatten().collect();
+        assert_eq!(values, vec!["c", "free text"]);
+
+        let a = Series::new("x".into(), &[u64::MAX]);
+        let b = Series::new("x".into(), &[-1i8]);
+        let stacked = Stackable::vstack([a, b].into_iter()).unwrap();
+        assert_eq!(stacked.dtype(), &DataType::Float64);
+        let values: Vec<_> = stacked.f64().unwrap().into_no_null_iter().collect();
+        assert_eq!(values, vec![u64::MAX as f64, -1.0]);
+
+        let a = Series::new("bad".into(), &[1i32]);
+        let b = Series::new("bad".into(), &["a"]);
+        let err = Stackable::vstack([a, b].into_iter()).unwrap_err();
+        assert!(err.to_string().contains("'bad'"));
+    }
+}
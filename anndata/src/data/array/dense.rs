@@ -6,14 +6,14 @@ use crate::{
     backend::*,
     data::{
         data_traits::*,
-        slice::{SelectInfoElem, SelectInfoElemBounds, Shape},
+        slice::{SelectInfoElem, SelectInfoElemBounds, Shape, SliceBounds},
     },
 };
 
 use anyhow::{anyhow, Result};
 use ndarray::{Array, Array1, ArrayD, ArrayView, Axis, Dimension, RemoveAxis, SliceInfoElem};
 use polars::{
-    prelude::CategoricalChunkedBuilder,
+    prelude::{create_enum_dtype, CategoricalChunkedBuilder, NamedFrom},
     series::{IntoSeries, Series},
 };
 use std::collections::HashMap;
@@ -22,8 +22,8 @@ use std::ops::Index;
 impl<'a, T: BackendData, D> Element for ArrayView<'a, T, D> {
     fn metadata(&self) -> MetaData {
         let encoding_type = match T::DTYPE {
-            ScalarType::String => "string-array",
-            _ => "array",
+            ScalarType::String => EncodingType::StringArray,
+            _ => EncodingType::Array,
         };
         MetaData::new(encoding_type, "0.2.0", None)
     }
@@ -39,7 +39,24 @@ impl<'a, T: BackendData, D: Dimension> Writable for ArrayView<'a, T, D> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
-        let dataset = location.new_array_dataset(name, self.into(), Default::default())?;
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let mut config = config.clone();
+        // A chunk shape of the wrong rank cannot be used for this array.
+        if config.block_size.as_ref().map_or(false, |s| s.ndim() != self.ndim()) {
+            config.block_size = None;
+        }
+        if !matches!(T::DTYPE, ScalarType::F32 | ScalarType::F64) {
+            config.quantize = None;
+        }
+        let dataset = location.new_array_dataset(name, self.into(), config)?;
         let mut container = DataContainer::<B>::Dataset(dataset);
         self.metadata().save(&mut container)?;
         Ok(container)
@@ -55,8 +72,8 @@ impl<'a, T, D: Dimension> HasShape for ArrayView<'a, T, D> {
 impl<T: BackendData, D> Element for Array<T, D> {
     fn metadata(&self) -> MetaData {
         let encoding_type = match T::DTYPE {
-            ScalarType::String => "string-array",
-            _ => "array",
+            ScalarType::String => EncodingType::StringArray,
+            _ => EncodingType::Array,
         };
         MetaData::new(encoding_type, "0.2.0", None)
     }
@@ -74,6 +91,15 @@ impl<T: BackendData, D: Dimension> Writable for Array<T, D> {
     ) -> Result<DataContainer<B>> {
         self.view().write(location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        self.view().write_with_config(location, name, config)
+    }
 }
 
 impl<T, D: Dimension> HasShape for Array<T, D> {
@@ -97,11 +123,12 @@ impl<T: Clone, D: Dimension> Selectable for Array<T, D> {
         let slices = info
             .as_ref()
             .into_iter()
-            .map(|x| match x.as_ref() {
-                SelectInfoElem::Slice(slice) => Some(SliceInfoElem::from(slice.clone())),
+            .zip(self.shape())
+            .map(|(x, n)| match x.as_ref() {
+                SelectInfoElem::Slice(slice) => Some(SliceBounds::new(slice, *n).into()),
                 _ => None,
             })
-            .collect::<Option<Vec<_>>>();
+            .collect::<Option<Vec<SliceInfoElem>>>();
         if let Some(slices) = slices {
             arr.slice(slices.as_slice()).into_owned()
         } else {
@@ -144,7 +171,7 @@ impl<T: BackendData, D: Dimension> Readable for Array<T, D> {
 
 impl<T: BackendData, D: Dimension> ReadableArray for Array<T, D> {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container.as_dataset()?.shape().into())
+        Ok(container.as_dataset()?.shape()?.into())
     }
 
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
@@ -164,25 +191,48 @@ impl<'a, T: BackendData, D: Dimension> WritableArray for ArrayView<'a, T, D> {}
 /// These arrays encode the values as small width integers (codes), which map to
 /// the original label set (categories). Each entry in the codes array is the
 /// zero-based index of the encoded value in the categories array.
+/// For ordered categoricals, the order of the categories array defines the
+/// order of the values.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CategoricalArray {
     pub codes: ArrayD<Option<u32>>,
     pub categories: Array1<String>,
+    pub ordered: bool,
 }
 
+/// Ordered categoricals are converted to polars `Enum` series, whose categories
+/// keep the order of the categories array. All categories are kept, including
+/// those that none of the codes refer to.
 impl Into<Series> for CategoricalArray {
     fn into(self) -> Series {
+        if self.ordered {
+            let categories = Series::new("".into(), self.categories.to_vec());
+            let categories = categories.str().unwrap().rechunk();
+            let dtype = create_enum_dtype(categories.downcast_iter().next().unwrap().clone());
+            let values: Series = self
+                .codes
+                .iter()
+                .map(|i| i.map(|i| self.categories[i as usize].as_str()))
+                .collect();
+            return values.cast(&dtype).unwrap();
+        }
+        // The categories are added first to register all of them, and then
+        // sliced off.
+        let n = self.categories.len();
         CategoricalChunkedBuilder::new(
             "".into(),
-            self.codes.len(),
+            n + self.codes.len(),
             polars::datatypes::CategoricalOrdering::Lexical,
         )
         .drain_iter_and_finish(
-            self.codes
-                .into_iter()
-                .map(|i| Some(self.categories[i? as usize].as_str())),
+            self.categories.iter().map(|x| Some(x.as_str())).chain(
+                self.codes
+                    .iter()
+                    .map(|i| Some(self.categories[(*i)? as usize].as_str())),
+            ),
         )
         .into_series()
+        .slice(n as i64, self.codes.len())
     }
 }
 
@@ -214,6 +264,7 @@ impl<'a> FromIterator<Option<&'a str>> for CategoricalArray {
         CategoricalArray {
             codes: codes.into_dyn(),
             categories: categories.into_iter().map(|x| x.0).collect(),
+            ordered: false,
         }
     }
 }
@@ -221,8 +272,8 @@ impl<'a> FromIterator<Option<&'a str>> for CategoricalArray {
 impl Element for CategoricalArray {
     fn metadata(&self) -> MetaData {
         let mut metadata = HashMap::new();
-        metadata.insert("ordered".to_string(), false.into());
-        MetaData::new("categorical", "0.2.0", Some(metadata))
+        metadata.insert("ordered".to_string(), self.ordered.into());
+        MetaData::new(EncodingType::Categorical, "0.2.0", Some(metadata))
     }
 
     fn data_type(&self) -> DataType {
@@ -269,6 +320,7 @@ impl Selectable for CategoricalArray {
         CategoricalArray {
             codes: Selectable::select(&self.codes, info),
             categories: self.categories.clone(),
+            ordered: self.ordered,
         }
     }
 }
@@ -281,14 +333,15 @@ impl Readable for CategoricalArray {
         let codes: ArrayD<i32> = group.open_dataset("codes")?.read_array_cast()?;
         let codes = codes.mapv(|x| if x < 0 { None } else { Some(x as u32) });
         let categories = group.open_dataset("categories")?.read_array().unwrap();
-        Ok(CategoricalArray { codes, categories })
+        let ordered = group.get_attr("ordered").unwrap_or(false);
+        Ok(CategoricalArray { codes, categories, ordered })
     }
 }
 
 impl ReadableArray for CategoricalArray {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
         let group = container.as_group()?;
-        let codes = group.open_dataset("codes")?.shape();
+        let codes = group.open_dataset("codes")?.shape()?;
         Ok(codes.into())
     }
 
@@ -298,9 +351,10 @@ impl ReadableArray for CategoricalArray {
         S: AsRef<SelectInfoElem>,
     {
         let group = container.as_group()?;
-        let codes: ArrayD<i32> = group.open_dataset("codes")?.read_array_slice(info)?;
+        let codes: ArrayD<i32> = group.open_dataset("codes")?.read_array_slice_cast(info)?;
         let codes = codes.mapv(|x| if x < 0 { None } else { Some(x as u32) });
         let categories = group.open_dataset("categories")?.read_array()?;
-        Ok(CategoricalArray { codes, categories })
+        let ordered = group.get_attr("ordered").unwrap_or(false);
+        Ok(CategoricalArray { codes, categories, ordered })
     }
 }
@@ -2,13 +2,16 @@ use crate::{
     backend::*,
     data::{
         data_traits::*,
+        f16,
         slice::{SelectInfoElem, Shape},
     },
 };
 
 use anyhow::{bail, ensure, Result};
 use ndarray::{arr0, Array, ArrayD, ArrayView, CowArray, Dimension, IxDyn};
+use num::{Bounded, NumCast, ToPrimitive, Zero};
 use paste::paste;
+use polars::prelude::AnyValue;
 use polars::series::Series;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,6 +24,7 @@ pub enum DynScalar {
     U16(u16),
     U32(u32),
     U64(u64),
+    F16(f16),
     F32(f32),
     F64(f64),
     Bool(bool),
@@ -54,9 +58,9 @@ macro_rules! impl_from_dynscalar {
 
                 fn metadata(&self) -> MetaData {
                     let encoding_type = if $from::DTYPE == ScalarType::String {
-                        "string"
+                        EncodingType::String
                     } else {
-                        "numeric-scalar"
+                        EncodingType::NumericScalar
                     };
                     MetaData::new(encoding_type, "0.2.0", None)
                 }
@@ -75,10 +79,121 @@ macro_rules! impl_from_dynscalar {
 }
 
 impl_from_dynscalar!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
-    bool, Bool, String, String
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f32, F32,
+    f64, F64, bool, Bool, String, String
 );
 
+impl DynScalar {
+    /// Convert a numeric scalar to `f64`. Fails for booleans and strings.
+    pub fn as_f64(&self) -> Result<f64> {
+        let val = match self {
+            DynScalar::I8(x) => *x as f64,
+            DynScalar::I16(x) => *x as f64,
+            DynScalar::I32(x) => *x as f64,
+            DynScalar::I64(x) => *x as f64,
+            DynScalar::U8(x) => *x as f64,
+            DynScalar::U16(x) => *x as f64,
+            DynScalar::U32(x) => *x as f64,
+            DynScalar::U64(x) => *x as f64,
+            DynScalar::F16(x) => x.to_f64(),
+            DynScalar::F32(x) => *x as f64,
+            DynScalar::F64(x) => *x,
+            DynScalar::Bool(_) | DynScalar::String(_) => {
+                bail!("cannot convert {} to a number", self.data_type())
+            }
+        };
+        Ok(val)
+    }
+
+    /// Convert a numeric scalar to `i64`. Fails for booleans, strings, values
+    /// out of range, and floats with a fractional part.
+    pub fn as_i64(&self) -> Result<i64> {
+        let val = match self {
+            DynScalar::I8(x) => *x as i64,
+            DynScalar::I16(x) => *x as i64,
+            DynScalar::I32(x) => *x as i64,
+            DynScalar::I64(x) => *x,
+            DynScalar::U8(x) => *x as i64,
+            DynScalar::U16(x) => *x as i64,
+            DynScalar::U32(x) => *x as i64,
+            DynScalar::U64(x) => i64::try_from(*x)?,
+            DynScalar::F16(_) | DynScalar::F32(_) | DynScalar::F64(_) => {
+                let x = self.as_f64()?;
+                ensure!(
+                    x.fract() == 0.0 && x >= i64::MIN as f64 && x < i64::MAX as f64,
+                    "cannot convert {} to i64 without loss",
+                    x
+                );
+                x as i64
+            }
+            DynScalar::Bool(_) | DynScalar::String(_) => {
+                bail!("cannot convert {} to a number", self.data_type())
+            }
+        };
+        Ok(val)
+    }
+}
+
+impl TryFrom<DynScalar> for f64 {
+    type Error = anyhow::Error;
+
+    fn try_from(val: DynScalar) -> Result<Self> {
+        val.as_f64()
+    }
+}
+
+impl TryFrom<DynScalar> for i64 {
+    type Error = anyhow::Error;
+
+    fn try_from(val: DynScalar) -> Result<Self> {
+        val.as_i64()
+    }
+}
+
+impl From<DynScalar> for AnyValue<'static> {
+    fn from(val: DynScalar) -> Self {
+        match val {
+            DynScalar::I8(x) => AnyValue::Int8(x),
+            DynScalar::I16(x) => AnyValue::Int16(x),
+            DynScalar::I32(x) => AnyValue::Int32(x),
+            DynScalar::I64(x) => AnyValue::Int64(x),
+            DynScalar::U8(x) => AnyValue::UInt8(x),
+            DynScalar::U16(x) => AnyValue::UInt16(x),
+            DynScalar::U32(x) => AnyValue::UInt32(x),
+            DynScalar::U64(x) => AnyValue::UInt64(x),
+            DynScalar::F16(x) => AnyValue::Float32(x.to_f32()),
+            DynScalar::F32(x) => AnyValue::Float32(x),
+            DynScalar::F64(x) => AnyValue::Float64(x),
+            DynScalar::Bool(x) => AnyValue::Boolean(x),
+            DynScalar::String(x) => AnyValue::StringOwned(x.into()),
+        }
+    }
+}
+
+impl TryFrom<AnyValue<'_>> for DynScalar {
+    type Error = anyhow::Error;
+
+    fn try_from(val: AnyValue<'_>) -> Result<Self> {
+        let val = match val {
+            AnyValue::Int8(x) => DynScalar::I8(x),
+            AnyValue::Int16(x) => DynScalar::I16(x),
+            AnyValue::Int32(x) => DynScalar::I32(x),
+            AnyValue::Int64(x) => DynScalar::I64(x),
+            AnyValue::UInt8(x) => DynScalar::U8(x),
+            AnyValue::UInt16(x) => DynScalar::U16(x),
+            AnyValue::UInt32(x) => DynScalar::U32(x),
+            AnyValue::UInt64(x) => DynScalar::U64(x),
+            AnyValue::Float32(x) => DynScalar::F32(x),
+            AnyValue::Float64(x) => DynScalar::F64(x),
+            AnyValue::Boolean(x) => DynScalar::Bool(x),
+            AnyValue::String(x) => DynScalar::String(x.to_string()),
+            AnyValue::StringOwned(x) => DynScalar::String(x.to_string()),
+            x => bail!("cannot convert {} to a scalar", x.dtype()),
+        };
+        Ok(val)
+    }
+}
+
 impl Element for DynScalar {
     fn data_type(&self) -> DataType {
         crate::macros::dyn_map_fun!(self, DynScalar, data_type)
@@ -124,6 +239,7 @@ pub enum DynArray {
     U16(ArrayD<u16>),
     U32(ArrayD<u32>),
     U64(ArrayD<u64>),
+    F16(ArrayD<f16>),
     F32(ArrayD<f32>),
     F64(ArrayD<f64>),
     Bool(ArrayD<bool>),
@@ -160,12 +276,83 @@ impl DynArray {
         crate::macros::dyn_map_fun!(self, DynArray, len)
     }
 
+    /// Convert the values to the numeric type `dtype`. Values that do not fit
+    /// in the new type are an error, unless `saturating` is set, in which case
+    /// they are clamped to its range and NaN becomes zero. Booleans are read as
+    /// 0 and 1.
+    pub fn astype(self, dtype: ScalarType, saturating: bool) -> Result<Self> {
+        ensure!(
+            !matches!(dtype, ScalarType::Bool | ScalarType::String),
+            "cannot convert to {}, the target type must be numeric",
+            dtype
+        );
+        if self.data_type() == DataType::Array(dtype) {
+            return Ok(self);
+        }
+        macro_rules! fun {
+            (Bool, $x:expr) => {
+                cast_array($x.mapv(u8::from), dtype, saturating)
+            };
+            (String, $x:expr) => {
+                bail!("cannot convert strings to {}", dtype)
+            };
+            ($variant:ident, $x:expr) => {
+                cast_array($x, dtype, saturating)
+            };
+        }
+        crate::macros::dyn_map!(self, DynArray, fun)
+    }
+
     impl_dynarray_into_array!(
-        I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F32, f32, F64,
-        f64, Bool, bool, String, String
+        I8, i8, I16, i16, I32, i32, I64, i64, U8, u8, U16, u16, U32, u32, U64, u64, F16, f16, F32,
+        f32, F64, f64, Bool, bool, String, String
     );
 }
 
+fn cast_array<S>(arr: ArrayD<S>, dtype: ScalarType, saturating: bool) -> Result<DynArray>
+where
+    S: ToPrimitive + Copy + std::fmt::Display,
+{
+    macro_rules! fun {
+        (bool) => {
+            unreachable!()
+        };
+        (String) => {
+            unreachable!()
+        };
+        ($ty:ident) => {{
+            let values = arr
+                .iter()
+                .map(|x| cast_value::<_, $ty>(*x, saturating))
+                .collect::<Result<Vec<_>>>()?;
+            ArrayD::from_shape_vec(arr.raw_dim(), values)?.into()
+        }};
+    }
+    Ok(crate::macros::dyn_match!(dtype, ScalarType, fun))
+}
+
+fn cast_value<S, T>(x: S, saturating: bool) -> Result<T>
+where
+    S: ToPrimitive + Copy + std::fmt::Display,
+    T: NumCast + Bounded + Zero + BackendData,
+{
+    // Casting between floats never fails, but finite values may become infinite.
+    let finite = x.to_f64().map_or(false, f64::is_finite);
+    match <T as NumCast>::from(x) {
+        Some(v) if !(finite && v.to_f64().map_or(false, f64::is_infinite)) => Ok(v),
+        _ if saturating => Ok(match x.to_f64() {
+            Some(v) if v.is_nan() => T::zero(),
+            Some(v) if v < 0.0 => T::min_value(),
+            _ => T::max_value(),
+        }),
+        _ => bail!(
+            "cannot convert {} to {} without overflow, use a saturating conversion to clamp it",
+            x,
+            T::DTYPE
+        ),
+    }
+}
+
 macro_rules! impl_dynarray_traits{
     ($($scalar_ty:ty, $ident:ident),*) => {
         $(
@@ -189,10 +376,29 @@ macro_rules! impl_dynarray_traits{
 }
 
 impl_dynarray_traits!(
-    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f32, F32, f64, F64,
+    i8, I8, i16, I16, i32, I32, i64, I64, u8, U8, u16, U16, u32, U32, u64, U64, f16, F16, f64, F64,
     bool, Bool, String, String
 );
 
+impl<D: Dimension> From<Array<f32, D>> for DynArray {
+    fn from(data: Array<f32, D>) -> Self {
+        DynArray::F32(data.into_dyn())
+    }
+}
+
+/// Half-precision arrays are widened on the fly, so data stored as `f16` can
+/// be read by callers that ask for `f32`. The conversion is exact.
+impl<D: Dimension> TryFrom<DynArray> for Array<f32, D> {
+    type Error = anyhow::Error;
+    fn try_from(arr: DynArray) -> Result<Self, Self::Error> {
+        match arr {
+            DynArray::F32(x) => Ok(x.into_dimensionality::<D>()?),
+            DynArray::F16(x) => Ok(x.mapv(|x| x.to_f32()).into_dimensionality::<D>()?),
+            v => bail!("Cannot convert {} to f32", v.data_type()),
+        }
+    }
+}
+
 impl Into<Series> for DynArray {
     fn into(self) -> Series {
         match self {
@@ -204,6 +410,8 @@ impl Into<Series> for DynArray {
             DynArray::U16(x) => x.iter().collect(),
             DynArray::U32(x) => x.iter().collect(),
             DynArray::U64(x) => x.iter().collect(),
+            // polars has no half-precision type
+            DynArray::F16(x) => x.iter().map(|x| x.to_f32()).collect(),
             DynArray::F32(x) => x.iter().collect(),
             DynArray::F64(x) => x.iter().collect(),
             DynArray::Bool(x) => x.iter().collect(),
@@ -228,7 +436,16 @@ impl Writable for DynArray {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
-        crate::macros::dyn_map_fun!(self, Self, write, location, name)
+        crate::macros::dyn_map_fun!(self, DynArray, write, location, name)
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynArray, write_with_config, location, name, config)
     }
 }
 
@@ -299,6 +516,9 @@ impl Stackable for DynArray {
             DynArray::I64(_) => {
                 ArrayD::<i64>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
             }
+            DynArray::F16(_) => {
+                ArrayD::<f16>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
+            }
             DynArray::F32(_) => {
                 ArrayD::<f32>::vstack(iter.map(|x| x.try_into().unwrap())).map(|x| x.into())
             }
@@ -318,7 +538,7 @@ impl Stackable for DynArray {
 impl WritableArray for DynArray {}
 impl ReadableArray for DynArray {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container.as_dataset()?.shape().into())
+        Ok(container.as_dataset()?.shape()?.into())
     }
 
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
@@ -340,6 +560,7 @@ pub enum DynCowArray<'a> {
     U16(CowArray<'a, u16, IxDyn>),
     U32(CowArray<'a, u32, IxDyn>),
     U64(CowArray<'a, u64, IxDyn>),
+    F16(CowArray<'a, f16, IxDyn>),
     F32(CowArray<'a, f32, IxDyn>),
     F64(CowArray<'a, f64, IxDyn>),
     Bool(CowArray<'a, bool, IxDyn>),
@@ -422,6 +643,7 @@ impl_dyn_cowarray_convert!(u8, U8);
 impl_dyn_cowarray_convert!(u16, U16);
 impl_dyn_cowarray_convert!(u32, U32);
 impl_dyn_cowarray_convert!(u64, U64);
+impl_dyn_cowarray_convert!(f16, F16);
 impl_dyn_cowarray_convert!(f32, F32);
 impl_dyn_cowarray_convert!(f64, F64);
 impl_dyn_cowarray_convert!(bool, Bool);
@@ -586,10 +808,24 @@ impl<D: Dimension> ArrayConvert<Array<usize, D>> for DynArray {
     }
 }
 
+/// Half-precision arrays can only be narrowed from other floats. Values are
+/// rounded to the nearest `f16` and overflow to infinity above 65504.
+impl<D: Dimension> ArrayConvert<Array<f16, D>> for DynArray {
+    fn try_convert(self) -> Result<Array<f16, D>> {
+        match self {
+            DynArray::F16(data) => Ok(data.into_dimensionality()?),
+            DynArray::F32(data) => Ok(data.mapv(f16::from_f32).into_dimensionality()?),
+            DynArray::F64(data) => Ok(data.mapv(f16::from_f64).into_dimensionality()?),
+            _ => bail!("Cannot convert to f16 Array"),
+        }
+    }
+}
+
 impl<D: Dimension> ArrayConvert<Array<f32, D>> for DynArray {
     fn try_convert(self) -> Result<Array<f32, D>> {
         match self {
             DynArray::F32(data) => Ok(data.into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_f32()).into_dimensionality()?),
             DynArray::I8(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::I16(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::I32(data) => Ok(data.mapv(|x| x as f32 ).into_dimensionality()?),
@@ -617,6 +853,7 @@ impl<D: Dimension> ArrayConvert<Array<f64, D>> for DynArray {
             DynArray::U16(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::U32(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::U64(data) => Ok(data.mapv(|x| x as f64 ).into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_f64()).into_dimensionality()?),
             DynArray::F32(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             DynArray::Bool(data) => Ok(data.mapv(|x| x.into()).into_dimensionality()?),
             _ => bail!("Cannot convert to f64 Array"),
@@ -645,9 +882,29 @@ impl<D: Dimension> ArrayConvert<Array<String, D>> for DynArray {
             DynArray::U16(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::U32(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::U64(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
+            DynArray::F16(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::F32(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::F64(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
             DynArray::Bool(data) => Ok(data.mapv(|x| x.to_string()).into_dimensionality()?),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod scalar_tests {
+    use super::*;
+
+    #[test]
+    fn test_scalar_conversion() {
+        assert_eq!(DynScalar::from(3u8).as_f64().unwrap(), 3.0);
+        assert_eq!(f64::try_from(DynScalar::F32(0.5)).unwrap(), 0.5);
+        assert_eq!(i64::try_from(DynScalar::F64(-2.0)).unwrap(), -2);
+        assert!(i64::try_from(DynScalar::F64(2.5)).is_err());
+        assert!(i64::try_from(DynScalar::U64(u64::MAX)).is_err());
+        assert!(DynScalar::from(true).as_f64().is_err());
+        assert!(DynScalar::from("a".to_string()).as_i64().is_err());
+
+        let val = DynScalar::from("a".to_string());
+        assert_eq!(DynScalar::try_from(AnyValue::from(val.clone())).unwrap(), val);
+        assert_eq!(AnyValue::from(DynScalar::I32(7)), AnyValue::Int32(7));
+    }
+}
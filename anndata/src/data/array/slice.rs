@@ -4,6 +4,7 @@ use itertools::Itertools;
 use serde_json::Value;
 use std::ops::{RangeFull, Range, Index, IndexMut, RangeFrom, RangeTo};
 use smallvec::{SmallVec, smallvec};
+use std::borrow::Cow;
 
 /// A structure that represents a shape, internally represented as a small vector.
 /// 
@@ -157,6 +158,57 @@ impl SelectInfo {
 pub enum SelectInfoElem {
     Index(Vec<usize>),
     Slice(Slice),
+    Mask(Mask),
+}
+
+/// A boolean mask over an axis. The mask must have the same length as the axis,
+/// and selects the positions whose entries are `true`.
+///
+/// # Example
+/// ```
+/// use anndata::data::SelectInfoElem;
+///
+/// let select = SelectInfoElem::from_mask(&[true, false, true]);
+/// assert!(select.bound_check(3).is_ok());
+/// assert!(select.bound_check(4).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    len: usize,
+    indices: Vec<usize>,
+}
+
+impl Mask {
+    /// The length of the mask.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The positions of the set entries, in increasing order.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+}
+
+impl From<&[bool]> for Mask {
+    fn from(mask: &[bool]) -> Self {
+        Self {
+            len: mask.len(),
+            indices: mask.iter().enumerate().filter(|(_, x)| **x).map(|(i, _)| i).collect(),
+        }
+    }
+}
+
+impl From<&[bool]> for SelectInfoElem {
+    fn from(mask: &[bool]) -> Self {
+        Self::Mask(mask.into())
+    }
+}
+
+impl From<Vec<bool>> for SelectInfoElem {
+    fn from(mask: Vec<bool>) -> Self {
+        Self::Mask(mask.as_slice().into())
+    }
 }
 
 impl FromIterator<usize> for SelectInfoElem {
@@ -291,11 +343,63 @@ impl SelectInfoElem {
                     Ok(())
                 })
             }
+            SelectInfoElem::Mask(mask) => if mask.len() != bound {
+                bail!("boolean mask length {} does not match the axis length {}", mask.len(), bound)
+            } else {
+                Ok(())
+            },
+        }
+    }
+
+    /// Select the positions of the `true` entries of a boolean mask.
+    pub fn from_mask(mask: &[bool]) -> Self {
+        mask.into()
+    }
+
+    /// Select the last `n` positions of an axis, or the whole axis if it is
+    /// shorter, like `[-n:]` in Python. Slices with negative bounds, e.g.
+    /// `SelectInfoElem::from(-10isize..-2)`, count from the end of the axis in
+    /// general; they are resolved against its length when the selection is
+    /// applied.
+    ///
+    /// # Example
+    /// ```
+    /// use anndata::data::{SelectInfoElem, SelectInfoElemBounds};
+    ///
+    /// let select = SelectInfoElem::slice_from_end(3);
+    /// assert_eq!(SelectInfoElemBounds::new(&select, 10).to_vec(), vec![7, 8, 9]);
+    /// assert_eq!(SelectInfoElemBounds::new(&select, 2).to_vec(), vec![0, 1]);
+    /// ```
+    pub fn slice_from_end(n: usize) -> Self {
+        if n == 0 {
+            Self::empty()
+        } else {
+            let start = -isize::try_from(n).unwrap_or(isize::MAX);
+            Slice::new(start, None, 1).into()
+        }
+    }
+
+    /// Resolve the bounds of a slice against the length of the axis: negative
+    /// bounds count from the end and bounds beyond the axis are clamped, as in
+    /// Python. A slice whose end precedes its start selects nothing. Full
+    /// slices and other selections are returned as is.
+    pub fn resolve(&self, bound: usize) -> Cow<'_, Self> {
+        match self {
+            SelectInfoElem::Slice(slice) if !self.is_full() => {
+                let bounds = SliceBounds::new(slice, bound);
+                Cow::Owned(SelectInfoElem::Slice(Slice {
+                    start: bounds.start as isize,
+                    end: Some(bounds.end as isize),
+                    step: bounds.step,
+                }))
+            }
+            _ => Cow::Borrowed(self),
         }
     }
 
+    /// Returns true for integer indices and boolean masks.
     pub fn is_index(&self) -> bool {
-        matches!(self, SelectInfoElem::Index(_))
+        matches!(self, SelectInfoElem::Index(_) | SelectInfoElem::Mask(_))
     }
 
     pub fn is_slice(&self) -> bool {
@@ -391,6 +495,22 @@ impl<'a> SelectInfoBounds<'a> {
         }
     }
 
+    /// Like [`SelectInfoBounds::new`], but returns an error if a boolean mask
+    /// does not have the length of its axis.
+    pub fn try_new<S, E>(select: &'a S, shape: &Shape) -> Result<Self>
+    where
+        S: AsRef<[E]>,
+        E: AsRef<SelectInfoElem> + 'a,
+    {
+        let res = select.as_ref().iter().zip(shape.as_ref()).map(|(sel, dim)|
+            SelectInfoElemBounds::try_new(sel.as_ref(), *dim)
+        ).collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            input_shape: shape.clone(),
+            select: res,
+        })
+    }
+
     /// Returns the input shape.
     pub fn in_shape(&self) -> Shape {
         self.input_shape.clone()
@@ -483,11 +603,25 @@ pub enum SelectInfoElemBounds<'a> {
 
 impl<'a> SelectInfoElemBounds<'a> {
     /// Creates a new bound-aware selection element from a selection element and an axis bound.
+    /// Boolean masks are expected to have the length of the axis, use
+    /// [`SelectInfoElemBounds::try_new`] for selections that have not been checked.
     pub fn new<S: AsRef<SelectInfoElem>>(select: &'a S, bound: usize) -> Self {
         match select.as_ref() {
             SelectInfoElem::Index(idx) => Self::Index(idx.as_slice()),
             SelectInfoElem::Slice(slice) => Self::Slice(SliceBounds::new(slice, bound)),
+            SelectInfoElem::Mask(mask) => Self::Index(mask.indices()),
+        }
+    }
+
+    /// Like [`SelectInfoElemBounds::new`], but returns an error if a boolean mask
+    /// does not have the length of the axis.
+    pub fn try_new<S: AsRef<SelectInfoElem>>(select: &'a S, bound: usize) -> Result<Self> {
+        if let SelectInfoElem::Mask(mask) = select.as_ref() {
+            if mask.len() != bound {
+                bail!("boolean mask length {} does not match the axis length {}", mask.len(), bound)
+            }
         }
+        Ok(Self::new(select, bound))
     }
 
     /// Returns the length of the selection element.
@@ -562,24 +696,27 @@ impl Into<SliceInfoElem> for SliceBounds {
 
 impl SliceBounds {
     /// Constructs a new `SliceBounds` from a `Slice` and an axis bound.
+    /// Negative bounds count from the end of the axis, and bounds outside the
+    /// axis are clamped to it. The end is never smaller than the start.
     pub(crate) fn new(slice: &Slice, bound: usize) -> Self {
         fn convert(x: isize, d: usize) -> usize {
             if x < 0 {
-                d.checked_add_signed(x).unwrap()
+                d.saturating_sub(x.unsigned_abs())
             } else {
-                x as usize
+                (x as usize).min(d)
             }
         }
 
+        let start = convert(slice.start, bound);
         Self {
-            start: convert(slice.start, bound),
-            end: slice.end.map_or(bound, |x| convert(x, bound)),
+            start,
+            end: slice.end.map_or(bound, |x| convert(x, bound)).max(start),
             step: slice.step,
         }
     }
 
     pub(crate) fn len(&self) -> usize {
-        (self.end - self.start).checked_div(self.step.unsigned_abs()).unwrap()
+        (self.end - self.start).div_ceil(self.step.unsigned_abs())
     }
 
     pub(crate) fn index(&self, i: usize) -> usize {
@@ -700,4 +837,49 @@ mod tests {
             ),
         );
     }
+
+    #[test]
+    fn test_negative_slice() {
+        let to_vec = |select: SelectInfoElem, n| SelectInfoElemBounds::new(&select, n).to_vec();
+        assert_eq!(to_vec(SelectInfoElem::from(-3isize..), 5), vec![2, 3, 4]);
+        assert_eq!(to_vec(SelectInfoElem::from(-3isize..-1), 5), vec![2, 3]);
+        assert_eq!(to_vec(SelectInfoElem::from(-10isize..), 5), vec![0, 1, 2, 3, 4]);
+        assert_eq!(to_vec(SelectInfoElem::from(1..-1isize), 3), vec![1]);
+        assert_eq!(to_vec(SelectInfoElem::slice_from_end(10), 4), vec![0, 1, 2, 3]);
+        assert!(to_vec(SelectInfoElem::slice_from_end(0), 4).is_empty());
+
+        // Degenerate ranges select nothing.
+        assert!(to_vec(SelectInfoElem::from(3..1), 5).is_empty());
+        assert!(to_vec(SelectInfoElem::from(-1isize..-3), 5).is_empty());
+        assert!(to_vec(SelectInfoElem::from(7..9), 5).is_empty());
+        assert_eq!(SelectInfoElemBounds::new(&SelectInfoElem::from(3..1), 5).len(), 0);
+
+        let select = SelectInfoElem::Slice(Slice::new(0, None, 2));
+        assert_eq!(SelectInfoElemBounds::new(&select, 5).len(), 3);
+        assert_eq!(
+            SelectInfoElem::from(-2isize..).resolve(5).as_ref(),
+            &SelectInfoElem::from(3..5),
+        );
+        assert!(SelectInfoElem::full().resolve(5).is_full());
+
+        let arr = ndarray::Array1::from_iter(0..5);
+        let selected = crate::data::Selectable::select(&arr, &[SelectInfoElem::from(-9isize..2)]);
+        assert_eq!(selected, ndarray::arr1(&[0, 1]));
+    }
+
+    #[test]
+    fn test_mask() {
+        let select = SelectInfoElem::from_mask(&[false, true, true, false, true]);
+        let bounds = SelectInfoElemBounds::new(&select, 5);
+        assert_eq!(bounds.to_vec(), vec![1, 2, 4]);
+        assert!(SelectInfoElemBounds::try_new(&select, 5).is_ok());
+        assert!(SelectInfoElemBounds::try_new(&select, 6).is_err());
+        assert!(SelectInfoBounds::try_new(&[&select], &Shape::from(vec![4])).is_err());
+        assert!(select.bound_check(5).is_ok());
+        assert!(select.bound_check(4).is_err());
+
+        let arr = ndarray::Array2::from_shape_fn((5, 2), |(i, j)| i * 2 + j);
+        let selected = crate::data::Selectable::select(&arr, &[select, SelectInfoElem::full()]);
+        assert_eq!(selected, ndarray::arr2(&[[2, 3], [4, 5], [8, 9]]));
+    }
 }
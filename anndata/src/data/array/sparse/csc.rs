@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use crate::backend::*;
 use crate::data::{
-    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice},
+    array::utils::{
+        cs_major_index, cs_major_minor_index, cs_major_slice, sparse_shape, sparse_write_config,
+    },
     array::DynScalar,
     data_traits::*,
     slice::{SelectInfoElem, Shape},
@@ -197,6 +199,48 @@ impl<T: BackendData + Clone> Selectable for CscMatrix<T> {
     }
 }
 
+impl<T: Clone> Stackable for CscMatrix<T> {
+    /// Stack matrices along the rows. Each column of the result is the concatenation
+    /// of the corresponding columns, so no conversion to CSR is needed.
+    fn vstack<I: Iterator<Item = Self>>(iter: I) -> Result<Self> {
+        let mats: Vec<_> = iter.collect();
+        if mats.is_empty() {
+            bail!("Cannot stack empty iterator");
+        }
+        let num_cols = mats[0].ncols();
+        if mats.iter().any(|x| x.ncols() != num_cols) {
+            bail!("Cannot stack CSC matrices with different numbers of columns");
+        }
+        let num_rows: usize = mats.iter().map(|x| x.nrows()).sum();
+        let nnz: usize = mats.iter().map(|x| x.nnz()).sum();
+
+        let mut col_offsets = Vec::with_capacity(num_cols + 1);
+        let mut row_indices = Vec::with_capacity(nnz);
+        let mut data = Vec::with_capacity(nnz);
+        col_offsets.push(0);
+        for j in 0..num_cols {
+            let mut row_offset = 0;
+            for mat in mats.iter() {
+                let col = mat.col(j);
+                row_indices.extend(col.row_indices().iter().map(|i| i + row_offset));
+                data.extend_from_slice(col.values());
+                row_offset += mat.nrows();
+            }
+            col_offsets.push(row_indices.len());
+        }
+
+        let pattern = unsafe {
+            SparsityPattern::from_offset_and_indices_unchecked(
+                num_cols,
+                num_rows,
+                col_offsets,
+                row_indices,
+            )
+        };
+        Ok(CscMatrix::try_from_pattern_and_values(pattern, data).unwrap())
+    }
+}
+
 impl<T: BackendData> Element for CscMatrix<T> {
     fn data_type(&self) -> DataType {
         DataType::CscMatrix(T::DTYPE)
@@ -205,7 +249,7 @@ impl<T: BackendData> Element for CscMatrix<T> {
     fn metadata(&self) -> MetaData {
         let mut metadata = HashMap::new();
         metadata.insert("shape".to_string(), self.shape().into());
-        MetaData::new("csc_matrix", "0.1.0", Some(metadata))
+        MetaData::new(EncodingType::CscMatrix, "0.1.0", Some(metadata))
     }
 }
 
@@ -215,11 +259,21 @@ impl<T: BackendData> Writable for CscMatrix<T> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let config = sparse_write_config(config);
         let mut group = location.new_group(name)?;
         let shape = self.shape();
 
         self.metadata().save(&mut group)?;
-        group.new_array_dataset("data", self.values().into(), Default::default())?;
+        group.new_array_dataset("data", self.values().into(), config.clone())?;
 
         let num_rows = shape[0];
         // Use i32 or i64 as indices type in order to be compatible with scipy
@@ -230,7 +284,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                 .map(|x| (*x).try_into().ok())
                 .collect();
             if let Some(indptr_i32) = try_convert_indptr {
-                group.new_array_dataset("indptr", indptr_i32.into(), Default::default())?;
+                group.new_array_dataset("indptr", indptr_i32.into(), config.clone())?;
                 group.new_array_dataset(
                     "indices",
                     self.row_indices()
@@ -238,7 +292,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                         .map(|x| (*x) as i32)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             } else {
                 group.new_array_dataset(
@@ -248,7 +302,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                         .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
                 group.new_array_dataset(
                     "indices",
@@ -257,7 +311,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                         .map(|x| (*x) as i64)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             }
         } else if TryInto::<i64>::try_into(num_rows.saturating_sub(1)).is_ok() {
@@ -268,7 +322,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                     .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
             group.new_array_dataset(
                 "indices",
@@ -277,7 +331,7 @@ impl<T: BackendData> Writable for CscMatrix<T> {
                     .map(|x| (*x) as i64)
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
         } else {
             panic!(
@@ -295,7 +349,7 @@ impl<T: BackendData> Readable for CscMatrix<T> {
         let data_type = container.encoding_type()?;
         if let DataType::CscMatrix(_) = data_type {
             let group = container.as_group()?;
-            let shape: Vec<u64> = group.get_attr("shape")?;
+            let shape = sparse_shape::<B>(group)?;
             let data = group
                 .open_dataset("data")?
                 .read_array::<_, Ix1>()?
@@ -312,8 +366,8 @@ impl<T: BackendData> Readable for CscMatrix<T> {
                 .into_raw_vec_and_offset()
                 .0;
             CscMatrix::try_from_csc_data(
-                shape[0] as usize,
-                shape[1] as usize,
+                shape[0],
+                shape[1],
                 indptr,
                 indices,
                 data,
@@ -330,11 +384,7 @@ impl<T: BackendData> Readable for CscMatrix<T> {
 
 impl<T: BackendData> ReadableArray for CscMatrix<T> {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     // TODO: efficient implementation for slice
@@ -501,5 +551,12 @@ mod csc_matrix_index_tests {
             csc_matrix.select(s![&ridx, &cidx].as_ref()),
             csc_select(&csc_matrix, ridx.iter().cloned(), cidx.iter().cloned()),
         );
+
+        // Stacking row slices gives back the original matrix
+        let chunks = [
+            csc_matrix.select(s![0..77, ..].as_ref()),
+            csc_matrix.select(s![77..200, ..].as_ref()),
+        ];
+        assert_eq!(CscMatrix::vstack(chunks.into_iter()).unwrap(), csc_matrix);
     }
 }
@@ -2,7 +2,9 @@ use std::collections::HashMap;
 
 use crate::backend::*;
 use crate::data::{
-    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice},
+    array::utils::{
+        cs_major_index, cs_major_minor_index, cs_major_slice, sparse_shape, sparse_write_config,
+    },
     data_traits::*,
     slice::{SelectInfoElem, Shape},
     SelectInfoBounds, SelectInfoElemBounds,
@@ -23,7 +25,7 @@ impl<T: BackendData> Element for CsrMatrix<T> {
     fn metadata(&self) -> MetaData {
         let mut metadata = HashMap::new();
         metadata.insert("shape".to_string(), self.shape().into());
-        MetaData::new("csr_matrix", "0.1.0", Some(metadata))
+        MetaData::new(EncodingType::CsrMatrix, "0.1.0", Some(metadata))
     }
 }
 
@@ -225,11 +227,21 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let config = sparse_write_config(config);
         let mut group = location.new_group(name)?;
         let shape = self.shape();
 
         self.metadata().save(&mut group)?;
-        group.new_array_dataset("data", self.values().into(), Default::default())?;
+        group.new_array_dataset("data", self.values().into(), config.clone())?;
 
         let num_cols = shape[1];
         // Use i32 or i64 as indices type in order to be compatible with scipy
@@ -240,7 +252,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                 .map(|x| (*x).try_into().ok())
                 .collect();
             if let Some(indptr_i32) = try_convert_indptr {
-                group.new_array_dataset("indptr", indptr_i32.into(), Default::default())?;
+                group.new_array_dataset("indptr", indptr_i32.into(), config.clone())?;
                 group.new_array_dataset(
                     "indices",
                     self.col_indices()
@@ -248,7 +260,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                         .map(|x| (*x) as i32)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             } else {
                 group.new_array_dataset(
@@ -258,7 +270,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                         .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
                 group.new_array_dataset(
                     "indices",
@@ -267,7 +279,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                         .map(|x| (*x) as i64)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             }
         } else if TryInto::<i64>::try_into(num_cols.saturating_sub(1)).is_ok() {
@@ -278,7 +290,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                     .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
             group.new_array_dataset(
                 "indices",
@@ -287,7 +299,7 @@ impl<T: BackendData> Writable for CsrMatrix<T> {
                     .map(|x| (*x) as i64)
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
         } else {
             panic!(
@@ -305,7 +317,7 @@ impl<T: BackendData> Readable for CsrMatrix<T> {
         let data_type = container.encoding_type()?;
         if let DataType::CsrMatrix(_) = data_type {
             let group = container.as_group()?;
-            let shape: Vec<u64> = group.get_attr("shape")?;
+            let shape = sparse_shape::<B>(group)?;
             let data = group
                 .open_dataset("data")?
                 .read_array::<_, Ix1>()?
@@ -321,7 +333,7 @@ impl<T: BackendData> Readable for CsrMatrix<T> {
                 .read_array_cast::<_, Ix1>()?
                 .into_raw_vec_and_offset()
                 .0;
-            CsrMatrix::try_from_csr_data(shape[0] as usize, shape[1] as usize, indptr, indices, data)
+            CsrMatrix::try_from_csr_data(shape[0], shape[1], indptr, indices, data)
                 .map_err(|e| anyhow!("cannot read csr matrix: {}", e))
         } else {
             bail!(
@@ -334,11 +346,7 @@ impl<T: BackendData> Readable for CsrMatrix<T> {
 
 impl<T: BackendData> ReadableArray for CsrMatrix<T> {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     // TODO: efficient implementation for slice
@@ -1,6 +1,8 @@
 use crate::backend::*;
 use crate::data::ArrayConvert;
+use crate::error::AnnDataError;
 use crate::data::{
+    array::utils::sparse_shape,
     data_traits::*,
     slice::{SelectInfoElem, Shape},
 };
@@ -74,6 +76,15 @@ impl Writable for DynCsrMatrix {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCsrMatrix, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCsrMatrix, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCsrMatrix {
@@ -81,6 +92,9 @@ impl Readable for DynCsrMatrix {
         match container {
             DataContainer::Group(group) => {
                 macro_rules! fun {
+                    (f16) => {
+                        bail!(AnnDataError::f16_sparse())
+                    };
                     ($variant:ident) => {
                         CsrMatrix::<$variant>::read(container).map(Into::into)
                     };
@@ -159,11 +173,7 @@ impl Stackable for DynCsrMatrix {
 impl WritableArray for DynCsrMatrix {}
 impl ReadableArray for DynCsrMatrix {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
@@ -173,6 +183,9 @@ impl ReadableArray for DynCsrMatrix {
     {
         if let DataType::CsrMatrix(ty) = container.encoding_type()? {
             macro_rules! fun {
+                (f16) => {
+                    bail!(AnnDataError::f16_sparse())
+                };
                 ($variant:ident) => {
                     CsrMatrix::<$variant>::read_select(container, info)?.into()
                 };
@@ -248,6 +261,15 @@ impl Writable for DynCscMatrix {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCscMatrix, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCscMatrix, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCscMatrix {
@@ -255,6 +277,9 @@ impl Readable for DynCscMatrix {
         match container {
             DataContainer::Group(group) => {
                 macro_rules! fun {
+                    (f16) => {
+                        bail!(AnnDataError::f16_sparse())
+                    };
                     ($variant:ident) => {
                         CscMatrix::<$variant>::read(container).map(Into::into)
                     };
@@ -286,14 +311,54 @@ impl Selectable for DynCscMatrix {
     }
 }
 
+impl Stackable for DynCscMatrix {
+    fn vstack<I: Iterator<Item = Self>>(iter: I) -> Result<Self> {
+        let mut iter = iter.peekable();
+        match iter.peek().unwrap() {
+            DynCscMatrix::U8(_) => Ok(DynCscMatrix::U8(CscMatrix::<u8>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::U16(_) => Ok(DynCscMatrix::U16(CscMatrix::<u16>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::U32(_) => Ok(DynCscMatrix::U32(CscMatrix::<u32>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::U64(_) => Ok(DynCscMatrix::U64(CscMatrix::<u64>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::I8(_) => Ok(DynCscMatrix::I8(CscMatrix::<i8>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::I16(_) => Ok(DynCscMatrix::I16(CscMatrix::<i16>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::I32(_) => Ok(DynCscMatrix::I32(CscMatrix::<i32>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::I64(_) => Ok(DynCscMatrix::I64(CscMatrix::<i64>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::F32(_) => Ok(DynCscMatrix::F32(CscMatrix::<f32>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::F64(_) => Ok(DynCscMatrix::F64(CscMatrix::<f64>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::Bool(_) => Ok(DynCscMatrix::Bool(CscMatrix::<bool>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+            DynCscMatrix::String(_) => Ok(DynCscMatrix::String(CscMatrix::<String>::vstack(
+                iter.map(|x| x.try_into().unwrap()),
+            )?)),
+        }
+    }
+}
+
 impl WritableArray for DynCscMatrix {}
 impl ReadableArray for DynCscMatrix {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
@@ -303,6 +368,9 @@ impl ReadableArray for DynCscMatrix {
     {
         if let DataType::CscMatrix(ty) = container.encoding_type()? {
             macro_rules! fun {
+                (f16) => {
+                    bail!(AnnDataError::f16_sparse())
+                };
                 ($variant:ident) => {
                     CscMatrix::<$variant>::read_select(container, info).map(Into::into)
                 };
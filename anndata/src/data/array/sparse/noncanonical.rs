@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 
 use crate::backend::*;
+use crate::error::AnnDataError;
 use crate::data::{
-    array::utils::{cs_major_index, cs_major_minor_index, cs_major_slice},
+    array::utils::{
+        cs_major_index, cs_major_minor_index, cs_major_slice, sparse_shape, sparse_write_config,
+    },
     data_traits::*,
     slice::{SelectInfoElem, Shape},
     SelectInfoBounds, SelectInfoElemBounds,
@@ -146,6 +149,15 @@ impl Writable for DynCsrNonCanonical {
     ) -> Result<DataContainer<B>> {
         crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, write, location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        crate::macros::dyn_map_fun!(self, DynCsrNonCanonical, write_with_config, location, name, config)
+    }
 }
 
 impl Readable for DynCsrNonCanonical {
@@ -153,6 +165,9 @@ impl Readable for DynCsrNonCanonical {
         match container {
             DataContainer::Group(group) => {
                 macro_rules! fun {
+                    (f16) => {
+                        bail!(AnnDataError::f16_sparse())
+                    };
                     ($variant:ident) => {
                         CsrNonCanonical::<$variant>::read(container).map(Into::into)
                     };
@@ -233,11 +248,7 @@ impl Stackable for DynCsrNonCanonical {
 impl WritableArray for DynCsrNonCanonical {}
 impl ReadableArray for DynCsrNonCanonical {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     fn read_select<B, S>(container: &DataContainer<B>, info: &[S]) -> Result<Self>
@@ -247,6 +258,9 @@ impl ReadableArray for DynCsrNonCanonical {
     {
         if let DataType::CsrMatrix(ty) = container.encoding_type()? {
             macro_rules! fun {
+                (f16) => {
+                    bail!(AnnDataError::f16_sparse())
+                };
                 ($variant:ident) => {
                     CsrNonCanonical::<$variant>::read_select(container, info).map(Into::into)
                 };
@@ -637,7 +651,7 @@ impl<T: BackendData> Element for CsrNonCanonical<T> {
     fn metadata(&self) -> MetaData {
         let mut metadata = HashMap::new();
         metadata.insert("shape".to_string(), self.shape().into());
-        MetaData::new("csr_matrix", "0.1.0", Some(metadata))
+        MetaData::new(EncodingType::CsrMatrix, "0.1.0", Some(metadata))
     }
 }
 
@@ -647,12 +661,22 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
         location: &G,
         name: &str,
     ) -> Result<DataContainer<B>> {
+        self.write_with_config(location, name, &WriteConfig::default())
+    }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        let config = sparse_write_config(config);
         let mut group = location.new_group(name)?;
         let shape = self.shape();
 
         self.metadata().save(&mut group)?;
 
-        group.new_array_dataset("data", self.values().into(), Default::default())?;
+        group.new_array_dataset("data", self.values().into(), config.clone())?;
 
         let num_cols = shape[1];
         // Use i32 or i64 as indices type in order to be compatible with scipy
@@ -663,7 +687,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                 .map(|x| (*x).try_into().ok())
                 .collect();
             if let Some(indptr_i32) = try_convert_indptr {
-                group.new_array_dataset("indptr", indptr_i32.into(), Default::default())?;
+                group.new_array_dataset("indptr", indptr_i32.into(), config.clone())?;
                 group.new_array_dataset(
                     "indices",
                     self.col_indices()
@@ -671,7 +695,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                         .map(|x| (*x) as i32)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             } else {
                 group.new_array_dataset(
@@ -681,7 +705,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                         .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
                 group.new_array_dataset(
                     "indices",
@@ -690,7 +714,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                         .map(|x| (*x) as i64)
                         .collect::<Vec<_>>()
                         .into(),
-                    Default::default(),
+                    config.clone(),
                 )?;
             }
         } else if TryInto::<i64>::try_into(num_cols.saturating_sub(1)).is_ok() {
@@ -701,7 +725,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                     .map(|x| TryInto::<i64>::try_into(*x).unwrap())
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
             group.new_array_dataset(
                 "indices",
@@ -710,7 +734,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
                     .map(|x| (*x) as i64)
                     .collect::<Vec<_>>()
                     .into(),
-                Default::default(),
+                config.clone(),
             )?;
         } else {
             panic!(
@@ -726,7 +750,7 @@ impl<T: BackendData> Writable for CsrNonCanonical<T> {
 impl<T: BackendData> Readable for CsrNonCanonical<T> {
     fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
         let group = container.as_group()?;
-        let shape: Vec<u64> = group.get_attr("shape")?;
+        let shape = sparse_shape::<B>(group)?;
         let data = group
             .open_dataset("data")?
             .read_array::<_, Ix1>()?
@@ -743,8 +767,8 @@ impl<T: BackendData> Readable for CsrNonCanonical<T> {
             .into_raw_vec_and_offset()
             .0;
         Ok(Self::from_csr_data(
-            shape[0] as usize,
-            shape[1] as usize,
+            shape[0],
+            shape[1],
             indptr,
             indices,
             data,
@@ -754,11 +778,7 @@ impl<T: BackendData> Readable for CsrNonCanonical<T> {
 
 impl<T: BackendData> ReadableArray for CsrNonCanonical<T> {
     fn get_shape<B: Backend>(container: &DataContainer<B>) -> Result<Shape> {
-        Ok(container
-            .as_group()?
-            .get_attr::<Vec<usize>>("shape")?
-            .into_iter()
-            .collect())
+        Ok(sparse_shape::<B>(container.as_group()?)?.into())
     }
 
     // TODO: efficient implementation for slice
@@ -1,4 +1,4 @@
-use crate::backend::{Backend, BackendData, DatasetOp, GroupOp, WriteConfig};
+use crate::backend::{AttributeOp, Backend, BackendData, DatasetOp, GroupOp, WriteConfig};
 use crate::data::{SelectInfoElem, Shape};
 use crate::ArrayData;
 
@@ -25,13 +25,33 @@ impl<B: Backend, T: BackendData> ExtendableDataset<B, T> {
     where
         G: GroupOp<B>,
     {
-        let block_size = vec![1000; capacity.ndim()].into();
+        Self::with_config(group, name, capacity, &WriteConfig::default())
+    }
+
+    /// Create the dataset with the settings in `config`. A `block_size` whose
+    /// dimension differs from the dataset's contributes its first entry to
+    /// every axis, and the block size defaults to 1000 along each axis.
+    pub fn with_config<G>(
+        group: &G,
+        name: &str,
+        capacity: Shape,
+        config: &WriteConfig,
+    ) -> Result<Self>
+    where
+        G: GroupOp<B>,
+    {
+        let ndim = capacity.ndim();
+        let block_size = match &config.block_size {
+            Some(s) if s.ndim() == ndim => s.clone(),
+            Some(s) if s.ndim() > 0 => vec![s[0]; ndim].into(),
+            _ => vec![1000; ndim].into(),
+        };
         let dataset = group.new_empty_dataset::<T>(
             name,
             &capacity,
             WriteConfig {
                 block_size: Some(block_size),
-                ..Default::default()
+                ..config.clone()
             },
         )?;
         Ok(Self {
@@ -110,6 +130,28 @@ impl<B: Backend, T: BackendData> ExtendableDataset<B, T> {
     }
 }
 
+/// Dataset configuration for the one-dimensional `data`, `indices` and `indptr`
+/// arrays of a sparse matrix. Only the first entry of the chunk shape is used and
+/// quantization is not applied.
+pub(crate) fn sparse_write_config(config: &WriteConfig) -> WriteConfig {
+    WriteConfig {
+        compression: config.compression,
+        block_size: config
+            .block_size
+            .as_ref()
+            .and_then(|s| s.as_ref().first().map(|x| (*x).into())),
+        quantize: None,
+    }
+}
+
+/// The shape of the sparse matrix stored in `group`. Files written by anndata
+/// before 0.7 have an `h5sparse_shape` attribute instead of `shape`.
+pub(crate) fn sparse_shape<B: Backend>(group: &B::Group) -> Result<Vec<usize>> {
+    group
+        .get_attr("shape")
+        .or_else(|e| group.get_attr("h5sparse_shape").map_err(|_| e))
+}
+
 /// select rows of csr_matrix, or columns of csc_matrix
 /// - major_indices: row_indices/col_indices of csr/csc matrix
 /// - offset: indptr
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
-use crate::backend::{Backend, DataContainer, GroupOp, AttributeOp, DataType};
+use crate::backend::{
+    AttributeOp, Backend, DataContainer, DataType, EncodingType, GroupOp, WriteConfig,
+};
 use crate::data::{
     array::slice::{SelectInfoElem, Shape},
     array::DynScalar,
@@ -10,20 +12,20 @@ use anyhow::Result;
 use serde_json::Value;
 
 pub(crate) const MAPPING_ENCODING: MetaData = MetaData {
-    encoding_type: "dict",
+    encoding_type: EncodingType::Dict,
     version: "0.1.0",
     metadata: None,
 };
 
 pub struct MetaData {
-    encoding_type: &'static str,
+    encoding_type: EncodingType,
     version: &'static str,
     metadata: Option<HashMap<String, Value>>,
 }
 
 impl MetaData {
     pub(crate) fn new(
-        encoding_type: &'static str,
+        encoding_type: EncodingType,
         version: &'static str,
         metadata: Option<HashMap<String, Value>>
     ) -> Self {
@@ -35,7 +37,7 @@ impl MetaData {
     }
 
     pub(crate) fn save<B: Backend, A: AttributeOp<B>>(self, loc: &mut A) -> Result<()> {
-        loc.new_attr("encoding-type", self.encoding_type)?;
+        loc.new_attr("encoding-type", self.encoding_type.as_str())?;
         loc.new_attr("encoding-version", self.version)?;
         if let Some(metadata) = self.metadata {
             for (key, value) in metadata.into_iter() {
@@ -80,6 +82,18 @@ pub trait Writable: Element {
         name: &str,
     ) -> Result<DataContainer<B>>;
 
+    /// Write the data, creating datasets with the given chunk size, compression
+    /// and quantization settings. Types that do not store arrays ignore the
+    /// configuration and fall back to [`Writable::write`].
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        _config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        self.write(location, name)
+    }
+
     /// Overwrite the data in the container. The default implementation deletes the 
     /// container and creates a new one. The data is then written to the new container.
     /// Specialized implementations may choose to overwrite the data in place.
@@ -104,6 +118,15 @@ where
     ) -> Result<DataContainer<B>> {
             (*self).write(location, name)
     }
+
+    fn write_with_config<B: Backend, G: GroupOp<B>>(
+        &self,
+        location: &G,
+        name: &str,
+        config: &WriteConfig,
+    ) -> Result<DataContainer<B>> {
+        (*self).write_with_config(location, name, config)
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -3,6 +3,7 @@ use crate::data::array::slice::{SelectInfoElem, SliceBounds};
 use ndarray::Slice;
 use std::ops::Deref;
 use std::{collections::HashMap, ops::Range};
+use std::sync::{Arc, OnceLock};
 use indexmap::IndexMap;
 use smallvec::SmallVec;
 use itertools::Itertools;
@@ -55,31 +56,74 @@ impl Index {
         }
     }
 
+    /// Returns the position of `key`. Interval indices expect keys of the form
+    /// `chrom:start-end` and range indices expect the number itself. Keys that
+    /// cannot be parsed are not found.
     pub fn get_index(&self, key: &str) -> Option<usize> {
         match self {
             Index::Intervals(map) => {
-                let query: SmallVec<[&str; 3]> = key.split(&['-', ':']).collect();
-                map.get_index(query[0], (query[1].parse().unwrap(), query[2].parse().unwrap()))
+                let (chrom, start, end) = parse_region(key)?;
+                map.get_index(chrom, (start, end))
             }
             Index::List(list) => list.get_index(key),
             Index::Range(range) => {
-                let i: usize = key.parse().unwrap();
-                if i >= range.start && i < range.end {
+                let i: usize = key.parse().ok()?;
+                // Reject other spellings of the number, such as "+5" or "05".
+                if range.contains(&i) && i.to_string() == key {
                     Some(i - range.start)
                 } else {
                     None
                 }
-            },
+            }
+        }
+    }
+
+    /// Returns all positions of `key`. Unlike [`Index::get_index`], this finds
+    /// every occurrence when the index contains duplicates.
+    pub fn get_indices(&self, key: &str) -> Vec<usize> {
+        match self {
+            Index::List(list) => list.get_indices(key),
+            _ => self.get_index(key).into_iter().collect(),
         }
     }
 
+    /// Returns the positions of the entries overlapping the half-open region
+    /// `start..end` on `chrom`, in increasing order. Interval indices are
+    /// searched in constant time per chromosome. The names of a list index are
+    /// parsed once, on the first query, into regions sorted by chromosome and
+    /// start; names that are not of the form `chrom:start-end` are skipped.
+    pub fn query_overlap(&self, chrom: &str, start: usize, end: usize) -> Vec<usize> {
+        match self {
+            Index::Intervals(intervals) => intervals.query_overlap(chrom, start, end),
+            Index::List(list) => list.query_overlap(chrom, start, end),
+            Index::Range(_) => Vec::new(),
+        }
+    }
+
+    /// Select entries by position. Contiguous selections, i.e. slices with
+    /// step 1 and increasing runs of indices, keep range and interval indices
+    /// compact. Other selections produce a list.
     pub fn select(&self, select: &SelectInfoElem) -> Self {
-        match SelectInfoElemBounds::new(select, self.len()) {
-            SelectInfoElemBounds::Slice(slice) => self.slice(slice.start, slice.end),
-            SelectInfoElemBounds::Index(index) => {
+        let select = SelectInfoElemBounds::new(select, self.len());
+        if let Some(range) = contiguous_range(&select) {
+            return self.slice(range.start, range.end);
+        }
+        match self {
+            Index::List(list) => select.iter().map(|i| list.items[i].clone()).collect(),
+            _ => {
                 let vec = self.clone().into_vec();
-                index.into_iter().map(|i| vec[*i].clone()).collect()
-            },
+                select.iter().map(|i| vec[i].clone()).collect()
+            }
+        }
+    }
+
+    /// Append the entries of `other`. Adjacent ranges are merged into a single
+    /// range, other combinations become a list.
+    pub fn concat(self, other: Index) -> Self {
+        match (self, other) {
+            (a, b) if a.is_empty() => b,
+            (Index::Range(a), Index::Range(b)) if a.end == b.start => Index::Range(a.start..b.end),
+            (a, b) => a.into_iter().chain(b).collect(),
         }
     }
 
@@ -93,6 +137,8 @@ impl Index {
 
     fn slice(&self, start: usize, end: usize) -> Self {
         match self {
+            Index::Range(r) => Index::Range(r.start + start..r.start + end.max(start)),
+            _ if start >= end => Index::empty(),
             Index::Intervals(intervals) => {
                 let (o1, i1) = intervals.accum_length.ix(&start);
                 let (o2, i2) = intervals.accum_length.ix(&(end-1));
@@ -109,7 +155,6 @@ impl Index {
                 }
             },
             Index::List(list) => list.items[start..end].iter().cloned().collect(),
-            Index::Range(r) => Index::Range(r.start + start..r.start + end),
         }
     }
 
@@ -146,7 +191,7 @@ impl FromIterator<String> for Index {
             .enumerate()
             .map(|(i, x)| (x.clone(), (x, i)))
             .unzip();
-        Self::List(List { items, index_map })
+        Self::List(List { items, index_map, regions: Default::default() })
     }
 }
 
@@ -199,6 +244,42 @@ impl NamedIntervals {
     pub fn len(&self) -> usize {
         self.accum_length.len()
     }
+
+    /// Returns the positions of the bins overlapping `start..end` on `name`.
+    pub fn query_overlap(&self, name: &str, start: usize, end: usize) -> Vec<usize> {
+        self.intervals.get_full(name).map_or(Vec::new(), |(i, _, v)| {
+            let offset = self.accum_length.0[i];
+            v.query_overlap(start, end)
+                .map(|x| x + offset)
+                .collect()
+        })
+    }
+}
+
+/// Returns the positions covered by a slice with step 1 or by indices that
+/// form an increasing run without gaps.
+fn contiguous_range(select: &SelectInfoElemBounds) -> Option<Range<usize>> {
+    match select {
+        SelectInfoElemBounds::Slice(slice) if slice.step == 1 => {
+            Some(slice.start..slice.end.max(slice.start))
+        }
+        SelectInfoElemBounds::Slice(_) => None,
+        SelectInfoElemBounds::Index(index) => {
+            let start = *index.first()?;
+            index
+                .iter()
+                .enumerate()
+                .all(|(i, x)| *x == start + i)
+                .then_some(start..start + index.len())
+        }
+    }
+}
+
+/// Parse a region of the form `chrom:start-end`.
+fn parse_region(region: &str) -> Option<(&str, usize, usize)> {
+    let (chrom, range) = region.rsplit_once(':')?;
+    let (start, end) = range.split_once('-')?;
+    Some((chrom, start.parse().ok()?, end.parse().ok()?))
 }
 
 impl<S: Into<String>> FromIterator<(S, Interval)> for NamedIntervals {
@@ -241,6 +322,21 @@ impl Interval {
         num::integer::div_ceil(self.end - self.start, self.step)
     }
 
+    /// Returns the range of bins overlapping `start..end`.
+    pub fn query_overlap(&self, start: usize, end: usize) -> Range<usize> {
+        if start >= end || start >= self.end || end <= self.start {
+            return 0..0;
+        }
+        // Bin `k` spans `self.start + k * step` to `self.start + k * step + size`.
+        let lo = if self.start + self.size > start {
+            0
+        } else {
+            (start - self.start - self.size) / self.step + 1
+        };
+        let hi = num::integer::div_ceil(end - self.start, self.step).min(self.len());
+        lo.min(hi)..hi
+    }
+
     fn slice(&self, start: usize, end: usize) -> Self {
         let start = self.start + self.step * start;
         let end = self.start + self.step * end;
@@ -272,6 +368,8 @@ impl Iterator for Interval {
 pub struct List<K> {
     pub items: Vec<K>,
     index_map: HashMap<K, usize>,
+    /// Built on the first overlap query and shared by the clones of the list.
+    regions: Arc<OnceLock<RegionIndex>>,
 }
 
 impl std::cmp::PartialEq for List<String> {
@@ -282,7 +380,7 @@ impl std::cmp::PartialEq for List<String> {
 
 impl<K: Hash + Eq> List<K> {
     pub fn empty() -> Self {
-        Self { items: vec![], index_map: HashMap::new() } 
+        Self { items: vec![], index_map: HashMap::new(), regions: Default::default() }
     }
 
     pub fn get_index<Q: ?Sized>(&self, item: &Q) -> Option<usize>
@@ -292,28 +390,106 @@ impl<K: Hash + Eq> List<K> {
     {
         self.index_map.get(item).cloned()
     }
+
+    /// Returns all positions of `item`. This is a hash lookup if the list has
+    /// no duplicates and a linear scan otherwise.
+    pub fn get_indices<Q: ?Sized>(&self, item: &Q) -> Vec<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        if self.items.len() == self.index_map.len() {
+            self.get_index(item).into_iter().collect()
+        } else {
+            self.items
+                .iter()
+                .positions(|x| <K as Borrow<Q>>::borrow(x) == item)
+                .collect()
+        }
+    }
 }
 
-/// This struct is used to perform index lookup for nested Vectors (vectors of vectors).
+impl List<String> {
+    fn query_overlap(&self, chrom: &str, start: usize, end: usize) -> Vec<usize> {
+        self.regions
+            .get_or_init(|| RegionIndex::new(self.items.iter().map(|x| x.as_str())))
+            .query(chrom, start, end)
+    }
+}
+
+/// The names of a list index that are genomic regions, grouped by chromosome.
+#[derive(Debug, Default)]
+struct RegionIndex(HashMap<String, ChromRegions>);
+
+#[derive(Debug, Default)]
+struct ChromRegions {
+    /// `(start, end, position)` of each region, sorted by start.
+    regions: Vec<(usize, usize, usize)>,
+    max_len: usize,
+}
+
+impl RegionIndex {
+    fn new<'a>(names: impl Iterator<Item = &'a str>) -> Self {
+        let mut index: HashMap<String, ChromRegions> = HashMap::new();
+        names.enumerate().for_each(|(i, name)| {
+            if let Some((chrom, start, end)) = parse_region(name) {
+                let x = index.entry(chrom.to_string()).or_default();
+                x.regions.push((start, end, i));
+                x.max_len = x.max_len.max(end.saturating_sub(start));
+            }
+        });
+        index.values_mut().for_each(|x| x.regions.sort_unstable());
+        Self(index)
+    }
+
+    fn query(&self, chrom: &str, start: usize, end: usize) -> Vec<usize> {
+        let Some(x) = self.0.get(chrom) else {
+            return Vec::new();
+        };
+        // Regions starting `max_len` or more before `start` end before it.
+        let lo = x.regions.partition_point(|r| r.0 + x.max_len <= start);
+        let hi = x.regions.partition_point(|r| r.0 < end).max(lo);
+        let mut result: Vec<_> = x.regions[lo..hi]
+            .iter()
+            .filter(|r| r.1 > start)
+            .map(|r| r.2)
+            .collect();
+        result.sort_unstable();
+        result
+    }
+}
+
+/// Index lookup for the concatenation of several segments, e.g. the rows of
+/// the AnnData objects in an `AnnDataSet`. It maps positions in the
+/// concatenation to segments and positions within them, and is collected from
+/// the lengths of the segments.
+///
+/// ```
+/// use anndata::data::{index::{reverse_mapping, VecVecIndex}, SelectInfoElem};
+///
+/// // Three segments with 3, 2 and 2 elements.
+/// let index: VecVecIndex = [3, 2, 2].into_iter().collect();
+/// assert_eq!(index.len(), 7);
+/// assert_eq!(index.ix(&4), (1, 1));
+///
+/// let (parts, order) = index.split_select(&SelectInfoElem::from(vec![5, 0, 4]));
+/// assert_eq!(parts[&0], SelectInfoElem::from(vec![0]));
+/// assert_eq!(parts[&1], SelectInfoElem::from(vec![1]));
+/// assert_eq!(parts[&2], SelectInfoElem::from(vec![0]));
+///
+/// // Reading the parts in segment order gives the elements 0, 4 and 5, which
+/// // were selected at positions 1, 2 and 0. Selecting the concatenated parts
+/// // with the reverse mapping restores the requested order.
+/// let order = order.unwrap();
+/// assert_eq!(order, vec![1, 2, 0]);
+/// assert_eq!(reverse_mapping(&order), vec![2, 0, 1]);
+/// ```
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct VecVecIndex(SmallVec<[usize; 96]>);
+pub struct VecVecIndex(SmallVec<[usize; 96]>);
 
 impl VecVecIndex {
     /// Find the outer and inner index for a given index corresponding to the
-    /// flattened view.
-    ///
-    /// # Example
-    ///
-    /// let vec_of_vec = vec![vec![0, 1, 2], vec![3, 4], vec![5, 6]];
-    /// let flatten_view = vec![0, 1, 2, 3, 4, 5, 6];
-    /// let index = VecVecIndex::new(vec_of_vec);
-    /// assert_eq!(index.ix(0), (0, 0));
-    /// assert_eq!(index.ix(1), (0, 1));
-    /// assert_eq!(index.ix(2), (0, 2));
-    /// assert_eq!(index.ix(3), (1, 0));
-    /// assert_eq!(index.ix(4), (1, 1));
-    /// assert_eq!(index.ix(5), (2, 0));
-    /// assert_eq!(index.ix(6), (2, 1));
+    /// flattened view, i.e., the segment and the position within it.
     pub fn ix(&self, i: &usize) -> (usize, usize) {
         let j = self.outer_ix(i);
         (j, i - self.0[j])
@@ -332,6 +508,8 @@ impl VecVecIndex {
         }
     }
 
+    /// Split a slice of the flattened view into slices of the segments it
+    /// overlaps, keyed by segment.
     pub fn split_slice(&self, slice: &Slice) -> HashMap<usize, SelectInfoElem> {
         let bounded = SliceBounds::new(slice, self.len());
         let (outer_start, inner_start) = self.ix(&bounded.start);
@@ -395,14 +573,20 @@ impl VecVecIndex {
                 ((outer, new_indices.into()), order)
             }).unzip();
         let order: Vec<_> = orders.into_iter().flatten().collect();
-        if order.as_slice().windows(2).all(|w| w[1] - w[0] == 1) {
+        if order.as_slice().windows(2).all(|w| w[1] == w[0] + 1) {
             (new_indices, None)
         } else {
             (new_indices, Some(order))
         }
     }
 
-    /// Sort and split the indices.
+    /// Split a selection of the flattened view into selections of the
+    /// segments, keyed by segment. Segments without selected elements are
+    /// absent. Indices are grouped by segment, so concatenating the selected
+    /// parts in segment order may change the order of the elements. In that
+    /// case the second value gives, for each element of the concatenation, its
+    /// position in `select`; see [`reverse_mapping`]. It is `None` if the
+    /// order is preserved.
     pub fn split_select(
         &self,
         select: &SelectInfoElem,
@@ -410,6 +594,7 @@ impl VecVecIndex {
         match select {
             SelectInfoElem::Slice(slice) => (self.split_slice(slice), None),
             SelectInfoElem::Index(index) => self.split_indices(index.as_slice()),
+            SelectInfoElem::Mask(mask) => self.split_indices(mask.indices()),
         }
     }
 
@@ -417,6 +602,25 @@ impl VecVecIndex {
     pub fn len(&self) -> usize {
         *self.0.last().unwrap_or(&0)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of elements in each segment.
+    pub fn lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.windows(2).map(|x| x[1] - x[0])
+    }
+}
+
+/// Invert the order returned by [`VecVecIndex::split_select`]: the result
+/// selects the elements of the concatenated parts in the requested order.
+pub fn reverse_mapping(mapping: &[usize]) -> Vec<usize> {
+    let mut res = vec![0; mapping.len()];
+    for (i, x) in mapping.iter().enumerate() {
+        res[*x] = i;
+    }
+    res
 }
 
 impl FromIterator<usize> for VecVecIndex {
@@ -491,6 +695,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_select_contiguous() {
+        let range: Index = 100.into();
+        assert_eq!(range.select(&(10..20).into()), Index::Range(10..20));
+        assert_eq!(range.select(&vec![3, 4, 5].into()), Index::Range(3..6));
+        assert_eq!(range.select(&(5..5).into()), Index::Range(5..5));
+        assert!(matches!(range.select(&vec![5, 3].into()), Index::List(_)));
+        let stepped = range.select(&SelectInfoElem::from(Slice::new(0, Some(6), 2)));
+        assert_eq!(stepped.into_vec(), vec!["0", "2", "4"]);
+
+        let intervals: Index = [
+            ("chr1", Interval { start: 0, end: 500, size: 100, step: 100 }),
+            ("chr2", Interval { start: 0, end: 300, size: 100, step: 100 }),
+        ].into_iter().collect();
+        let head = intervals.select(&vec![3, 4, 5, 6].into());
+        assert!(matches!(head, Index::Intervals(_)));
+        assert_eq!(
+            head.into_vec(),
+            vec!["chr1:300-400", "chr1:400-500", "chr2:0-100", "chr2:100-200"],
+        );
+        assert!(intervals.select(&(2..2).into()).is_empty());
+        assert!(matches!(intervals.select(&vec![0, 2].into()), Index::List(_)));
+    }
+
+    #[test]
+    fn test_get_index() {
+        let range = Index::Range(10..20);
+        assert_eq!(range.get_index("10"), Some(0));
+        assert_eq!(range.get_index("19"), Some(9));
+        assert_eq!(range.get_index("20"), None);
+        assert_eq!(range.get_index("5"), None);
+        assert_eq!(range.get_index("012"), None);
+        assert_eq!(range.get_index("cell_1"), None);
+
+        let intervals: Index = [
+            ("chr1", Interval { start: 0, end: 250, size: 100, step: 100 }),
+            ("HLA-A", Interval { start: 0, end: 200, size: 100, step: 100 }),
+        ].into_iter().collect();
+        assert_eq!(intervals.get_index("chr1:100-200"), Some(1));
+        assert_eq!(intervals.get_index("chr1:200-250"), Some(2));
+        assert_eq!(intervals.get_index("HLA-A:100-200"), Some(4));
+        assert_eq!(intervals.get_index("chr1:50-150"), None);
+        assert_eq!(intervals.get_index("chr2:0-100"), None);
+        assert_eq!(intervals.get_index("chr1"), None);
+        assert_eq!(intervals.get_index("chr1:a-b"), None);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let index: Index = ["a", "b", "a", "c"].into_iter().map(|x| x.to_string()).collect();
+        assert!(index.contain_duplicates());
+        assert_eq!(index.get_indices("a"), vec![0, 2]);
+        assert_eq!(index.get_indices("c"), vec![3]);
+        assert!(index.get_indices("d").is_empty());
+    }
+
+    #[test]
+    fn test_query_overlap() {
+        let index: Index = [
+            ("chr1", Interval { start: 0, end: 1000, size: 100, step: 100 }),
+            ("chr2", Interval { start: 0, end: 250, size: 100, step: 50 }),
+        ].into_iter().collect();
+        assert_eq!(index.query_overlap("chr1", 150, 320), vec![1, 2, 3]);
+        assert_eq!(index.query_overlap("chr1", 100, 200), vec![1]);
+        assert_eq!(index.query_overlap("chr2", 120, 130), vec![11, 12]);
+        assert_eq!(index.query_overlap("chr2", 240, 500), vec![13, 14]);
+        assert!(index.query_overlap("chr3", 0, 100).is_empty());
+        assert!(index.query_overlap("chr1", 1000, 2000).is_empty());
+
+        let list: Index = index.clone().into_vec().into();
+        assert_eq!(
+            list.query_overlap("chr2", 120, 130),
+            index.query_overlap("chr2", 120, 130),
+        );
+
+        // Regions of different lengths, in no particular order.
+        let list: Index = ["chr1:500-600", "x", "chr1:0-1000", "chr2:0-10", "chr1:90-110"]
+            .into_iter()
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(list.query_overlap("chr1", 100, 120), vec![2, 4]);
+        assert_eq!(list.query_overlap("chr1", 590, 2000), vec![0, 2]);
+        assert_eq!(list.clone().query_overlap("chr1", 1000, 2000), Vec::<usize>::new());
+        assert!(list.query_overlap("x", 0, 10).is_empty());
+    }
+
     fn select_strat(n: usize) -> BoxedStrategy<SelectInfoElem> {
         if n == 0 {
             Just(Vec::new().into()).boxed()
@@ -3,13 +3,68 @@ use crate::data::{Data, Readable, Writable};
 
 use std::collections::HashMap;
 use std::ops::Deref;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 
 use super::{Element, MetaData};
 
-#[derive(Debug, Clone, PartialEq)]
+/// A dictionary of named elements, stored as a group with the "dict"
+/// encoding. Values can themselves be mappings, as in Python's `uns`.
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Mapping(HashMap<String, Data>);
 
+impl Mapping {
+    /// Get the value at a `/`-separated path, descending into nested mappings.
+    pub fn get_path(&self, path: &str) -> Option<&Data> {
+        let mut keys = path.split('/');
+        let mut data = self.0.get(keys.next()?)?;
+        for key in keys {
+            match data {
+                Data::Mapping(mapping) => data = mapping.0.get(key)?,
+                _ => return None,
+            }
+        }
+        Some(data)
+    }
+
+    /// Insert a value at a `/`-separated path. Missing intermediate mappings
+    /// are created.
+    pub fn insert_path(&mut self, path: &str, data: Data) -> Result<()> {
+        match path.split_once('/') {
+            None => {
+                self.0.insert(path.to_string(), data);
+            }
+            Some((key, rest)) => {
+                let child = self
+                    .0
+                    .entry(key.to_string())
+                    .or_insert_with(|| Data::Mapping(Mapping::default()));
+                match child {
+                    Data::Mapping(mapping) => mapping.insert_path(rest, data)?,
+                    _ => bail!("'{}' is not a mapping", key),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the value at a `/`-separated path and return it.
+    pub fn remove_path(&mut self, path: &str) -> Option<Data> {
+        match path.split_once('/') {
+            None => self.0.remove(path),
+            Some((key, rest)) => match self.0.get_mut(key)? {
+                Data::Mapping(mapping) => mapping.remove_path(rest),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<Data>> FromIterator<(K, V)> for Mapping {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
 impl Into<HashMap<String, Data>> for Mapping {
     fn into(self) -> HashMap<String, Data> {
         self.0
@@ -43,7 +98,8 @@ impl Element for Mapping {
 impl Readable for Mapping {
     fn read<B: Backend>(container: &DataContainer<B>) -> Result<Self> {
         let data: Result<_> = iter_containers::<B>(container.as_group()?).map(|(k, v)| {
-            Ok((k.to_owned(), Data::read(&v)?))
+            let data = Data::read(&v).with_context(|| format!("cannot read '{}'", k))?;
+            Ok((k.to_owned(), data))
         }).collect();
         Ok(Mapping(data?))
     }
@@ -0,0 +1,67 @@
+//! Errors for the common failure modes of the public API.
+//!
+//! Functions return [`anyhow::Result`]. An [`AnnDataError`] is wrapped in the
+//! `anyhow::Error`, possibly under added context, and can be recovered with
+//! [`AnnDataError::find`] to tell the failure modes apart.
+
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnDataError {
+    /// Keys that do not exist in a collection, e.g. in `obsm` or `obs_names`.
+    KeyNotFound { keys: Vec<String>, collection: String },
+    /// The size of an axis does not match the size of the object.
+    DimMismatch { expected: usize, found: usize },
+    /// A data type that the operation cannot handle.
+    UnsupportedDtype(String),
+    /// An entry of `uns` that is required, e.g. the location table of an
+    /// `AnnDataSet`, is missing.
+    MissingUns(String),
+    /// A write to a file that was opened with [`OpenMode::ReadOnly`].
+    ///
+    /// [`OpenMode::ReadOnly`]: crate::OpenMode::ReadOnly
+    ReadOnly(String),
+    /// AnnData files of an `AnnDataSet` that could not be opened, given by
+    /// their keys.
+    MissingChildren(Vec<String>),
+}
+
+impl AnnDataError {
+    pub fn key_not_found<K: Into<String>, C: Into<String>>(key: K, collection: C) -> Self {
+        Self::KeyNotFound {
+            keys: vec![key.into()],
+            collection: collection.into(),
+        }
+    }
+
+    /// Sparse matrices cannot hold half-precision floats.
+    pub(crate) fn f16_sparse() -> Self {
+        Self::UnsupportedDtype("half-precision sparse matrices".into())
+    }
+
+    /// Returns the first `AnnDataError` in the chain of causes of `err`.
+    pub fn find(err: &anyhow::Error) -> Option<&Self> {
+        err.chain().find_map(|e| e.downcast_ref::<Self>())
+    }
+}
+
+impl Display for AnnDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::KeyNotFound { keys, collection } => {
+                write!(f, "not found in {}: {}", collection, keys.join(", "))
+            }
+            Self::DimMismatch { expected, found } => {
+                write!(f, "dimension cannot be changed from {} to {}", expected, found)
+            }
+            Self::UnsupportedDtype(dtype) => write!(f, "unsupported data type: {}", dtype),
+            Self::MissingUns(key) => write!(f, "'{}' is missing from uns", key),
+            Self::ReadOnly(file) => write!(f, "'{}' is opened read-only", file),
+            Self::MissingChildren(keys) => {
+                write!(f, "AnnData files are missing: '{}'", keys.join("', '"))
+            }
+        }
+    }
+}
+
+impl std::error::Error for AnnDataError {}
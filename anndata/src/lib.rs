@@ -6,10 +6,15 @@ pub mod data;
 pub mod container;
 pub mod reader;
 mod macros;
+pub mod error;
 
 pub use traits::{AnnDataOp, AxisArraysOp, ElemCollectionOp, ArrayElemOp};
-pub use crate::anndata::{AnnData, AnnDataSet, StackedAnnData};
+pub use crate::anndata::{
+    AnnData, AnnDataBuilder, AnnDataSet, AnnDataView, ArrayInfo, Inventory, JoinOptions, OpenMode,
+    Raw, StackedAnnData, SymMode, TransposedAnnData, ValidationReport, WriteOptions,
+};
 pub use backend::Backend;
+pub use error::AnnDataError;
 pub use data::{HasShape, Data, Readable, Writable, ArrayData, WritableArray, ReadableArray, Selectable};
 pub use container::{
     AxisArrays, DataFrameElem, Elem, ElemCollection, ArrayElem, 
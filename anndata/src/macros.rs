@@ -9,6 +9,7 @@ macro_rules! dyn_match {
             $enum::U16 => $inner_macro!(u16),
             $enum::U32 => $inner_macro!(u32),
             $enum::U64 => $inner_macro!(u64),
+            $enum::F16 => $inner_macro!(f16),
             $enum::F32 => $inner_macro!(f32),
             $enum::F64 => $inner_macro!(f64),
             $enum::Bool => $inner_macro!(bool),
@@ -17,42 +18,45 @@ macro_rules! dyn_match {
     };
 }
 
+/// Only the dense types (`DynScalar`, `DynArray` and `DynCowArray`) have an
+/// `F16` variant, sparse matrices are never stored in half precision.
 macro_rules! dyn_map {
-    ($scalar:expr, $enum:ident, $inner_macro:ident) => {
+    (@arms $scalar:expr, $enum:ident, $inner_macro:ident, [$($variant:ident),*]) => {
         match $scalar {
-            $enum::I8(_val) => $inner_macro!(I8, _val),
-            $enum::I16(_val) => $inner_macro!(I16, _val),
-            $enum::I32(_val) => $inner_macro!(I32, _val),
-            $enum::I64(_val) => $inner_macro!(I64, _val),
-            $enum::U8(_val) => $inner_macro!(U8, _val),
-            $enum::U16(_val) => $inner_macro!(U16, _val),
-            $enum::U32(_val) => $inner_macro!(U32, _val),
-            $enum::U64(_val) => $inner_macro!(U64, _val),
-            $enum::F32(_val) => $inner_macro!(F32, _val),
-            $enum::F64(_val) => $inner_macro!(F64, _val),
-            $enum::Bool(_val) => $inner_macro!(Bool, _val),
-            $enum::String(_val) => $inner_macro!(String, _val),
+            $($enum::$variant(_val) => $inner_macro!($variant, _val),)*
         }
     };
+    ($scalar:expr, DynScalar, $inner_macro:ident) => {
+        $crate::macros::dyn_map!(@arms $scalar, DynScalar, $inner_macro, [
+            I8, I16, I32, I64, U8, U16, U32, U64, F16, F32, F64, Bool, String
+        ])
+    };
+    ($scalar:expr, DynArray, $inner_macro:ident) => {
+        $crate::macros::dyn_map!(@arms $scalar, DynArray, $inner_macro, [
+            I8, I16, I32, I64, U8, U16, U32, U64, F16, F32, F64, Bool, String
+        ])
+    };
+    ($scalar:expr, DynCowArray, $inner_macro:ident) => {
+        $crate::macros::dyn_map!(@arms $scalar, DynCowArray, $inner_macro, [
+            I8, I16, I32, I64, U8, U16, U32, U64, F16, F32, F64, Bool, String
+        ])
+    };
+    ($scalar:expr, $enum:ident, $inner_macro:ident) => {
+        $crate::macros::dyn_map!(@arms $scalar, $enum, $inner_macro, [
+            I8, I16, I32, I64, U8, U16, U32, U64, F32, F64, Bool, String
+        ])
+    };
 }
 
 macro_rules! dyn_map_fun {
-    ($scalar:expr, $enum:ident, $fun:ident $(, $arg:expr)*) => {
-        match $scalar {
-            $enum::I8(_val) => _val.$fun($($arg),*),
-            $enum::I16(_val) => _val.$fun($($arg),*),
-            $enum::I32(_val) => _val.$fun($($arg),*),
-            $enum::I64(_val) => _val.$fun($($arg),*),
-            $enum::U8(_val) => _val.$fun($($arg),*),
-            $enum::U16(_val) => _val.$fun($($arg),*),
-            $enum::U32(_val) => _val.$fun($($arg),*),
-            $enum::U64(_val) => _val.$fun($($arg),*),
-            $enum::F32(_val) => _val.$fun($($arg),*),
-            $enum::F64(_val) => _val.$fun($($arg),*),
-            $enum::Bool(_val) => _val.$fun($($arg),*),
-            $enum::String(_val) => _val.$fun($($arg),*),
+    ($scalar:expr, $enum:ident, $fun:ident $(, $arg:expr)*) => {{
+        macro_rules! __call {
+            ($variant:ident, $val:expr) => {
+                $val.$fun($($arg),*)
+            };
         }
-    };
+        $crate::macros::dyn_map!($scalar, $enum, __call)
+    }};
 }
 
-pub(crate) use {dyn_match, dyn_map, dyn_map_fun};
\ No newline at end of file
+pub(crate) use {dyn_match, dyn_map, dyn_map_fun};
@@ -1,15 +1,19 @@
 use crate::data::utils::to_csr_data;
-use crate::{data::array::DataFrameIndex, AnnDataOp, ArrayData};
+use crate::backend::{Backend, DatasetOp, GroupOp};
+use crate::data::{CsrNonCanonical, DynArray, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
+use crate::{data::array::DataFrameIndex, error::AnnDataError, AnnDataOp, ArrayData};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use flate2::read::MultiGzDecoder;
 use itertools::Itertools;
 use nalgebra_sparse::{coo::CooMatrix, csr::CsrMatrix};
+use ndarray::{Array1, Ix1, Ix2};
+use polars::prelude::{Column, DataFrame};
 use std::path::Path;
 use std::{error::Error, fmt, io};
 use std::{
     fs::File,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Seek, SeekFrom, Write},
 };
 
 pub struct MMReader {
@@ -82,6 +86,228 @@ impl MMReader {
     }
 }
 
+/// Read the feature-barcode matrix produced by CellRanger (version 3 or later).
+///
+/// The `/matrix` group stores a CSC matrix of shape `(features, barcodes)`,
+/// which is read as a CSR matrix of shape `(barcodes, features)`. The feature
+/// names and types are stored as columns of `var`, and feature ids are used as
+/// the variable names.
+pub fn read_10x_h5<B: Backend, O: AnnDataOp, P: AsRef<Path>>(path: P, output: &O) -> Result<()> {
+    let file = B::open(path.as_ref())
+        .with_context(|| format!("cannot open file: {}", path.as_ref().display()))?;
+    let matrix = file
+        .open_group("matrix")
+        .context("cannot find the 'matrix' group, is this a CellRanger v3+ file?")?;
+
+    let shape: Array1<usize> = matrix.open_dataset("shape")?.read_array_cast()?;
+    let (n_vars, n_obs) = (shape[0], shape[1]);
+    let indptr: Array1<usize> = matrix.open_dataset("indptr")?.read_array_cast()?;
+    let indices: Array1<usize> = matrix.open_dataset("indices")?.read_array_cast()?;
+    let (indptr, indices) = (indptr.to_vec(), indices.to_vec());
+    macro_rules! csr {
+        (F16, $data:expr) => {
+            bail!(AnnDataError::f16_sparse())
+        };
+        ($variant:ident, $data:expr) => {
+            ArrayData::from(nalgebra_sparse::CsrMatrix::try_from_csr_data(
+                n_obs,
+                n_vars,
+                indptr,
+                indices,
+                $data.into_raw_vec_and_offset().0,
+            )
+            .map_err(|e| anyhow!("cannot read the 10x matrix: {}", e))?)
+        };
+    }
+    let x = crate::macros::dyn_map!(matrix.open_dataset("data")?.read_dyn_array()?, DynArray, csr);
+    output.set_x(x)?;
+
+    let features = matrix.open_group("features")?;
+    let read_str = |name: &str| -> Result<Vec<String>> {
+        Ok(features
+            .open_dataset(name)?
+            .read_array::<String, Ix1>()?
+            .to_vec())
+    };
+    let var = DataFrame::new(vec![
+        Column::new("name".into(), read_str("name")?),
+        Column::new("feature_type".into(), read_str("feature_type")?),
+    ])?;
+    output.set_var(var)?;
+    let mut var_names: DataFrameIndex = read_str("id")?.into_iter().collect();
+    var_names.index_name = "id".to_string();
+    output.set_var_names(var_names)?;
+
+    let barcodes: Vec<String> = matrix
+        .open_dataset("barcodes")?
+        .read_array::<String, Ix1>()?
+        .to_vec();
+    output.set_obs_names(barcodes.into_iter().collect())?;
+    Ok(())
+}
+
+/// Write a matrix to a MatrixMarket coordinate file chunk by chunk.
+///
+/// The number of non-zero entries is only known at the end, so a fixed-width
+/// placeholder is written in the size line and filled in by [`MMWriter::finish`].
+pub struct MMWriter<W: Write + Seek> {
+    writer: W,
+    nrows: usize,
+    ncols: usize,
+    nnz: usize,
+    nnz_position: Option<u64>,
+}
+
+/// Width reserved for the number of entries in the size line.
+const NNZ_WIDTH: usize = 20;
+
+impl<W: Write + Seek> MMWriter<W> {
+    pub fn new(writer: W, nrows: usize, ncols: usize) -> Self {
+        Self {
+            writer,
+            nrows,
+            ncols,
+            nnz: 0,
+            nnz_position: None,
+        }
+    }
+
+    /// Write a chunk of rows starting at `row_offset`. Dense chunks are
+    /// converted to coordinates on the fly, skipping zero entries.
+    pub fn write_chunk(&mut self, data: ArrayData, row_offset: usize) -> Result<()> {
+        // Half-precision values are widened to f32, which MatrixMarket can represent.
+        macro_rules! numeric {
+            ($data:expr, $enum:ident, $fun:ident $(, $half:ident)?) => {
+                match $data {
+                    $enum::I8(x) => $fun!(x),
+                    $enum::I16(x) => $fun!(x),
+                    $enum::I32(x) => $fun!(x),
+                    $enum::I64(x) => $fun!(x),
+                    $enum::U8(x) => $fun!(x),
+                    $enum::U16(x) => $fun!(x),
+                    $enum::U32(x) => $fun!(x),
+                    $enum::U64(x) => $fun!(x),
+                    $($enum::$half(x) => $fun!(x.mapv(|v| v.to_f32())),)?
+                    $enum::F32(x) => $fun!(x),
+                    $enum::F64(x) => $fun!(x),
+                    $enum::Bool(x) => $fun!(x),
+                    $enum::String(_) => bail!("cannot write string matrices in MatrixMarket format"),
+                }
+            };
+        }
+        macro_rules! dense {
+            ($x:expr) => {{
+                let x = $x.into_dimensionality::<Ix2>()?;
+                self.write_entries(
+                    x.indexed_iter()
+                        .filter(|(_, v)| !v.is_zero())
+                        .map(|((i, j), v)| (i, j, *v)),
+                    row_offset,
+                )
+            }};
+        }
+        macro_rules! sparse {
+            ($x:expr) => {
+                self.write_entries($x.triplet_iter().map(|(i, j, v)| (i, j, *v)), row_offset)
+            };
+        }
+        macro_rules! noncanonical {
+            ($x:expr) => {
+                self.write_entries(noncanonical_triplets(&$x), row_offset)
+            };
+        }
+        match data {
+            ArrayData::Array(x) => numeric!(x, DynArray, dense, F16),
+            ArrayData::CsrMatrix(x) => numeric!(x, DynCsrMatrix, sparse),
+            ArrayData::CscMatrix(x) => numeric!(x, DynCscMatrix, sparse),
+            ArrayData::CsrNonCanonical(x) => numeric!(x, DynCsrNonCanonical, noncanonical),
+            ArrayData::DataFrame(_) => bail!("cannot write dataframes in MatrixMarket format"),
+        }
+    }
+
+    /// Fill in the number of entries and flush the writer.
+    pub fn finish(mut self) -> Result<W> {
+        if self.nnz_position.is_none() {
+            self.write_header("real")?;
+        }
+        self.writer.seek(SeekFrom::Start(self.nnz_position.unwrap()))?;
+        write!(self.writer, "{}", self.nnz)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_header(&mut self, field: &str) -> Result<()> {
+        writeln!(self.writer, "%%MatrixMarket matrix coordinate {} general", field)?;
+        write!(self.writer, "{} {} ", self.nrows, self.ncols)?;
+        self.nnz_position = Some(self.writer.stream_position()?);
+        writeln!(self.writer, "{:width$}", "", width = NNZ_WIDTH)?;
+        Ok(())
+    }
+
+    fn write_entries<T, I>(&mut self, entries: I, row_offset: usize) -> Result<()>
+    where
+        T: MMScalar,
+        I: Iterator<Item = (usize, usize, T)>,
+    {
+        if self.nnz_position.is_none() {
+            self.write_header(T::FIELD)?;
+        }
+        for (i, j, v) in entries {
+            write!(self.writer, "{} {} ", row_offset + i + 1, j + 1)?;
+            v.write_value(&mut self.writer)?;
+            writeln!(self.writer)?;
+            self.nnz += 1;
+        }
+        Ok(())
+    }
+}
+
+fn noncanonical_triplets<T: Copy>(
+    x: &CsrNonCanonical<T>,
+) -> impl Iterator<Item = (usize, usize, T)> + '_ {
+    let (indptr, indices, values) = x.csr_data();
+    indptr.windows(2).enumerate().flat_map(move |(i, w)| {
+        (w[0]..w[1]).map(move |k| (i, indices[k], values[k]))
+    })
+}
+
+/// Scalar types that can be stored in a MatrixMarket file.
+trait MMScalar: Copy {
+    const FIELD: &'static str;
+    fn is_zero(&self) -> bool;
+    fn write_value<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+}
+
+macro_rules! impl_mm_scalar {
+    ($field:expr, $($ty:ty),*) => {
+        $(
+            impl MMScalar for $ty {
+                const FIELD: &'static str = $field;
+                fn is_zero(&self) -> bool {
+                    *self == (0 as $ty)
+                }
+                fn write_value<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                    write!(writer, "{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_mm_scalar!("integer", i8, i16, i32, i64, u8, u16, u32, u64);
+impl_mm_scalar!("real", f32, f64);
+
+impl MMScalar for bool {
+    const FIELD: &'static str = "integer";
+    fn is_zero(&self) -> bool {
+        !*self
+    }
+    fn write_value<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{}", *self as u8)
+    }
+}
+
 fn open_file<P: AsRef<Path>>(file: P) -> Result<Box<dyn BufRead>> {
     fn is_gzipped<P: AsRef<Path>>(file: P) -> Result<bool> {
         Ok(MultiGzDecoder::new(File::open(file)?).header().is_some())
@@ -1,17 +1,39 @@
 use crate::{
     anndata::{new_layers, new_mapping, new_obsm, new_obsp, new_varm, new_varp},
-    backend::DataType,
-    container::{ChunkedArrayElem, InnerDataFrameElem, StackedChunkedArrayElem},
-    data::*,
-    AnnData, AnnDataSet, ArrayElem, AxisArrays, Backend, ElemCollection, StackedArrayElem,
-    StackedAxisArrays,
+    backend::{DataContainer, DataType, EncodingType, ScalarType, WriteConfig},
+    container::{
+        base::renamed_columns, collection::add_with_rollback, ChunkedArrayElem, ChunkedWithObsm, InnerDataFrameElem,
+        StackedChunkedArrayElem,
+    },
+    data::{array::dataframe::{check_series_dtypes, schema_of}, *},
+    AnnData, AnnDataError, AnnDataSet, ArrayElem, ArrayInfo, AxisArrays, Backend, ElemCollection,
+    Inventory, JoinOptions, OpenMode, StackedArrayElem, StackedAxisArrays, SymMode, WriteOptions,
 };
 
 use anyhow::{bail, ensure, Context, Result};
-use polars::prelude::DataFrame;
+use indexmap::IndexMap;
+use ndarray::{ArrayD, Ix2};
+use nalgebra_sparse::{CscMatrix, CsrMatrix};
+use polars::prelude::{DataFrame, Expr, IdxCa, IdxSize, IntoColumn, IntoLazy, NamedFrom, Series};
+use rayon::iter::{IndexedParallelIterator, ParallelIterator};
 use smallvec::SmallVec;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::mpsc::{sync_channel, Receiver};
 
 /// Trait defining operations on an AnnData container.
+///
+/// # Concurrency
+///
+/// Every element, e.g. `X`, `obs` or a single `obsm` array, is guarded by its
+/// own read-write lock, so the methods can be called from several threads
+/// through a shared reference. Reading an array with [`ArrayElemOp::get`] or
+/// [`ArrayElemOp::slice`] only takes a shared lock, and readers of the same or
+/// of distinct elements do not wait on each other. The exception is an element
+/// with caching enabled whose data is not cached yet: the first read fills the
+/// cache and needs exclusive access. Writes, such as adding, removing or
+/// subsetting arrays, always take the exclusive lock and wait for the readers
+/// to finish. Note that the HDF5 library serializes its own calls, so reads from
+/// HDF5 files are safe but do not perform I/O in parallel.
 pub trait AnnDataOp {
     type X: ArrayElemOp;
     type AxisArraysRef<'a>: AxisArraysOp
@@ -31,12 +53,67 @@ pub trait AnnDataOp {
         I: Iterator<Item = D>,
         D: ArrayChunk + Into<ArrayData>;
 
+    /// Sets the 'X' element from a parallel iterator of chunks.
+    ///
+    /// Chunks are produced on the rayon thread pool while the calling thread
+    /// writes them to the store in order, as the storage backends do not support
+    /// concurrent writes. This helps when computing a chunk is expensive compared
+    /// to writing it. The cost is memory: up to `2 * rayon::current_num_threads()`
+    /// finished chunks are queued, and chunks that finish ahead of their turn are
+    /// held until all preceding chunks have been written. Use
+    /// [`AnnDataOp::set_x_from_iter`] when memory is tight.
+    /// Note: The original data will be lost if an error occurs during the writing.
+    fn set_x_from_iter_parallel<I, D>(&self, iter: I) -> Result<()>
+    where
+        I: IndexedParallelIterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData> + Send,
+    {
+        let (sender, receiver) = sync_channel(2 * rayon::current_num_threads());
+        std::thread::scope(|s| {
+            s.spawn(move || {
+                // Sending fails only when the writer has stopped, in which case
+                // its error is returned below.
+                let _ = iter.enumerate().try_for_each_with(sender, |tx, x| tx.send(x));
+            });
+            self.set_x_from_iter(InOrder::new(receiver))
+        })
+    }
+
+    /// Sets the 'X' element from an iterator, calling `progress` with the number
+    /// of chunks written so far and the total number of chunks after each chunk.
+    /// Note: The original data will be lost if an error occurs during the writing.
+    fn set_x_from_iter_with_progress<I, D, F>(&self, iter: I, progress: F) -> Result<()>
+    where
+        I: ExactSizeIterator<Item = D>,
+        D: ArrayChunk + Into<ArrayData>,
+        F: FnMut(usize, usize),
+    {
+        self.set_x_from_iter(WithProgress::new(iter, progress))
+    }
+
     /// Sets the 'X' element.
     fn set_x<D: Into<ArrayData>>(&self, data: D) -> Result<()>;
 
+    /// Sets the 'X' element, creating its datasets with the given chunk size and
+    /// compression. Implementations without on-disk storage ignore the options.
+    fn set_x_with_opts<D: Into<ArrayData>>(&self, data: D, _options: &WriteOptions) -> Result<()> {
+        self.set_x(data)
+    }
+
     /// Deletes the 'X' element.
     fn del_x(&self) -> Result<()>;
 
+    /// Binarizes the 'X' element in place: entries greater than `threshold`
+    /// become 1.0 and all others 0.0. Sparse matrices keep only the nonzero
+    /// entries. The threshold is recorded in `uns["binarize"]`.
+    fn binarize_x(&self, threshold: f64) -> Result<()> {
+        if let Some(x) = self.x().get::<ArrayData>()? {
+            self.set_x(x.binarize(threshold)?)?;
+            record_binarize(self, threshold)?;
+        }
+        Ok(())
+    }
+
     /// Returns the number of observations (rows).
     fn n_obs(&self) -> usize;
     /// Returns the number of variables (columns).
@@ -51,16 +128,94 @@ pub trait AnnDataOp {
     /// Returns the names of variables.
     fn var_names(&self) -> DataFrameIndex;
 
-    /// Changes the names of observations.
+    /// Changes the names of observations. Duplicated names are allowed, as in
+    /// Python anndata, which only warns about them. Use
+    /// [`AnnDataOp::set_obs_names_checked`] to reject duplicates.
     fn set_obs_names(&self, index: DataFrameIndex) -> Result<()>;
-    /// Changes the names of variables.
+    /// Changes the names of variables. Duplicated names are allowed; see
+    /// [`AnnDataOp::set_obs_names`].
     fn set_var_names(&self, index: DataFrameIndex) -> Result<()>;
 
-    /// Returns the indices of specified observations.
+    /// Changes the names of observations, failing if any name is duplicated.
+    fn set_obs_names_checked(&self, index: DataFrameIndex) -> Result<()> {
+        ensure!(!index.contain_duplicates(), "obs_names contain duplicated names");
+        self.set_obs_names(index)
+    }
+    /// Changes the names of variables, failing if any name is duplicated.
+    fn set_var_names_checked(&self, index: DataFrameIndex) -> Result<()> {
+        ensure!(!index.contain_duplicates(), "var_names contain duplicated names");
+        self.set_var_names(index)
+    }
+
+    /// Changes the names of observations using a string or categorical series.
+    /// The series must not contain nulls or duplicated names.
+    fn set_obs_names_from_series(&self, names: &Series) -> Result<()> {
+        self.set_obs_names(names.try_into()?)
+    }
+    /// Changes the names of variables using a string or categorical series.
+    /// The series must not contain nulls or duplicated names.
+    fn set_var_names_from_series(&self, names: &Series) -> Result<()> {
+        self.set_var_names(names.try_into()?)
+    }
+
+    /// Renames the index of `obs`, keeping the names of observations. The
+    /// index name is stored in the file and used by Python anndata as the name
+    /// of `obs.index`. To set both at once, pass an index built with
+    /// [`DataFrameIndex::with_name`] to [`AnnDataOp::set_obs_names`].
+    fn set_obs_index_name(&self, name: &str) -> Result<()> {
+        let index = self.obs_names();
+        ensure!(index.len() == self.n_obs(), "obs_names have not been set");
+        self.set_obs_names(index.with_name(name))
+    }
+    /// Renames the index of `var`. See [`AnnDataOp::set_obs_index_name`].
+    fn set_var_index_name(&self, name: &str) -> Result<()> {
+        let index = self.var_names();
+        ensure!(index.len() == self.n_vars(), "var_names have not been set");
+        self.set_var_names(index.with_name(name))
+    }
+
+    /// Returns the indices of specified observations. If the observation
+    /// names contain duplicates, all positions of a duplicated name are
+    /// returned, in increasing order.
     fn obs_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>>;
-    /// Returns the indices of specified variables.
+    /// Returns the indices of specified variables. See [`AnnDataOp::obs_ix`].
     fn var_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>>;
 
+    /// Reads the rows and columns of 'X' with the given observation and
+    /// variable names, in the requested order. `None` selects all rows or
+    /// columns. Fails with the list of names that are not found.
+    fn read_x_slice_by_names<D>(
+        &self,
+        obs_names: Option<&[&str]>,
+        var_names: Option<&[&str]>,
+    ) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        let obs = obs_names.map_or(Ok(SelectInfoElem::full()), |x| {
+            names_to_selection(&self.obs_names(), x, "obs_names")
+        })?;
+        let var = var_names.map_or(Ok(SelectInfoElem::full()), |x| {
+            names_to_selection(&self.var_names(), x, "var_names")
+        })?;
+        self.x().slice([obs, var])
+    }
+
+    /// Reads 'X' as the type it is stored with, for callers that do not know
+    /// the type in advance. Returns `None` if 'X' is empty.
+    fn read_x_dyn(&self) -> Result<Option<ArrayData>> {
+        self.x().get()
+    }
+
+    /// Returns the element type of 'X' without reading its data. Fails if 'X'
+    /// is empty or has no element type, e.g. when it is a dataframe.
+    fn x_dtype(&self) -> Result<ScalarType> {
+        let dtype = self.x().dtype().context("X is empty")?;
+        let ty = dtype.scalar_type().ok_or(AnnDataError::UnsupportedDtype(dtype.to_string()))?;
+        Ok(ty)
+    }
+
     /// Reads the observation annotations.
     fn read_obs(&self) -> Result<DataFrame>;
     /// Reads the variable annotations.
@@ -72,6 +227,183 @@ pub trait AnnDataOp {
     /// Changes the variable annotations.
     fn set_var(&self, var: DataFrame) -> Result<()>;
 
+    /// Reads a single observation annotation column, without loading the
+    /// other columns when the backend allows it. Returns `None` if the column
+    /// does not exist.
+    fn fetch_obs_column(&self, name: &str) -> Result<Option<Series>> {
+        let obs = self.read_obs()?;
+        Ok(obs.column(name).ok().map(|x| x.as_materialized_series().clone()))
+    }
+    /// Reads a single variable annotation column. See [`AnnDataOp::fetch_obs_column`].
+    fn fetch_var_column(&self, name: &str) -> Result<Option<Series>> {
+        let var = self.read_var()?;
+        Ok(var.column(name).ok().map(|x| x.as_materialized_series().clone()))
+    }
+
+    /// Reads the observation annotation columns `names`, in this order, with
+    /// [`AnnDataOp::fetch_obs_column`]. Fails with [`AnnDataError::KeyNotFound`]
+    /// listing all names that are not columns.
+    fn read_obs_columns(&self, names: &[&str]) -> Result<DataFrame> {
+        read_columns(names, "obs", |name| self.fetch_obs_column(name))
+    }
+    /// Reads the variable annotation columns `names`. See
+    /// [`AnnDataOp::read_obs_columns`].
+    fn read_var_columns(&self, names: &[&str]) -> Result<DataFrame> {
+        read_columns(names, "var", |name| self.fetch_var_column(name))
+    }
+
+    /// Evaluates a boolean polars expression against the observation
+    /// annotations and returns the positions of the rows where it is true,
+    /// in increasing order. Rows where it is null are dropped. The positions
+    /// can be used as a selection to subset the object or to read slices, e.g.
+    ///
+    /// ```ignore
+    /// let cells = adata.filter_obs_expr(col("n_genes").gt(lit(200)))?;
+    /// let x: CsrMatrix<f32> = adata.x().slice_axis(0, SelectInfoElem::from(cells))?.unwrap();
+    /// ```
+    fn filter_obs_expr(&self, expr: Expr) -> Result<Vec<usize>> {
+        filter_rows(self.read_obs()?, expr).context("cannot filter obs")
+    }
+    /// Evaluates a boolean polars expression against the variable
+    /// annotations. See [`AnnDataOp::filter_obs_expr`].
+    fn filter_var_expr(&self, expr: Expr) -> Result<Vec<usize>> {
+        filter_rows(self.read_var()?, expr).context("cannot filter var")
+    }
+
+    /// Returns the name and polars dtype of each observation annotation
+    /// column. Backed objects only read the encoding and dtype attributes of
+    /// the columns, not their data.
+    fn obs_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        Ok(schema_of(&self.read_obs()?))
+    }
+    /// Returns the name and polars dtype of each variable annotation column.
+    /// See [`AnnDataOp::obs_schema`].
+    fn var_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        Ok(schema_of(&self.read_var()?))
+    }
+
+    /// Returns the user-defined attributes of an observation annotation column.
+    fn obs_column_attrs(&self, _column: &str) -> Result<IndexMap<String, String>> {
+        bail!("column attributes are not supported by this object")
+    }
+    /// Returns the user-defined attributes of a variable annotation column.
+    fn var_column_attrs(&self, _column: &str) -> Result<IndexMap<String, String>> {
+        bail!("column attributes are not supported by this object")
+    }
+
+    /// Attaches a string attribute, e.g., a description or unit, to an
+    /// observation annotation column.
+    fn set_obs_column_attr(&self, _column: &str, _key: &str, _value: &str) -> Result<()> {
+        bail!("column attributes are not supported by this object")
+    }
+    /// Attaches a string attribute, e.g., a description or unit, to a
+    /// variable annotation column.
+    fn set_var_column_attr(&self, _column: &str, _key: &str, _value: &str) -> Result<()> {
+        bail!("column attributes are not supported by this object")
+    }
+
+    /// Adds `series` as the observation annotation column `name`, replacing
+    /// the column if it exists. Fails if the length of `series` differs from
+    /// the number of observations. Backed objects only write this column,
+    /// which is much cheaper than reading and rewriting the annotations.
+    fn add_obs_column(&self, name: &str, series: Series) -> Result<()> {
+        let mut obs = self.read_obs()?;
+        ensure!(
+            obs.width() == 0 || series.len() == obs.height(),
+            AnnDataError::DimMismatch {
+                expected: obs.height(),
+                found: series.len(),
+            }
+        );
+        obs.replace_or_add(name.into(), series)?;
+        self.set_obs(obs)
+    }
+    /// Adds `series` as the variable annotation column `name`. See
+    /// [`AnnDataOp::add_obs_column`].
+    fn add_var_column(&self, name: &str, series: Series) -> Result<()> {
+        let mut var = self.read_var()?;
+        ensure!(
+            var.width() == 0 || series.len() == var.height(),
+            AnnDataError::DimMismatch {
+                expected: var.height(),
+                found: series.len(),
+            }
+        );
+        var.replace_or_add(name.into(), series)?;
+        self.set_var(var)
+    }
+
+    /// Merges the columns of `df` into the observation annotations, matching
+    /// the values of its column `on` against the observation names. Rows are
+    /// added in the order of the observations, and observations without a
+    /// match get nulls. Float columns store nulls as NaN, so these read back as
+    /// NaN. Fails if a key occurs more than once in `df`, if a column already
+    /// exists or if a column has a data type that cannot be stored; columns
+    /// are only written once all of them are checked. See
+    /// [`AnnDataOp::join_obs_with`] for options.
+    fn join_obs(&self, df: DataFrame, on: &str) -> Result<()> {
+        self.join_obs_with(df, on, &JoinOptions::default())
+    }
+    /// Merges the columns of `df` into the observation annotations, matching
+    /// against the observation names or the column `options.left_on`.
+    fn join_obs_with(&self, df: DataFrame, on: &str, options: &JoinOptions) -> Result<()> {
+        let keys = match &options.left_on {
+            Some(name) => self
+                .fetch_obs_column(name)?
+                .ok_or_else(|| AnnDataError::key_not_found(name, "obs"))?,
+            None => Series::new("".into(), self.obs_names().into_vec()),
+        };
+        let existing = self.obs_schema()?.into_iter().map(|(name, _)| name).collect();
+        let columns = align_by_key(&keys, df, on, options, existing, "obs")?;
+        columns
+            .take_columns()
+            .into_iter()
+            .try_for_each(|x| {
+                let name = x.name().to_string();
+                self.add_obs_column(&name, x.take_materialized_series())
+            })
+    }
+    /// Merges the columns of `df` into the variable annotations. See
+    /// [`AnnDataOp::join_obs`].
+    fn join_var(&self, df: DataFrame, on: &str) -> Result<()> {
+        self.join_var_with(df, on, &JoinOptions::default())
+    }
+    /// Merges the columns of `df` into the variable annotations. See
+    /// [`AnnDataOp::join_obs_with`].
+    fn join_var_with(&self, df: DataFrame, on: &str, options: &JoinOptions) -> Result<()> {
+        let keys = match &options.left_on {
+            Some(name) => self
+                .fetch_var_column(name)?
+                .ok_or_else(|| AnnDataError::key_not_found(name, "var"))?,
+            None => Series::new("".into(), self.var_names().into_vec()),
+        };
+        let existing = self.var_schema()?.into_iter().map(|(name, _)| name).collect();
+        let columns = align_by_key(&keys, df, on, options, existing, "var")?;
+        columns
+            .take_columns()
+            .into_iter()
+            .try_for_each(|x| {
+                let name = x.name().to_string();
+                self.add_var_column(&name, x.take_materialized_series())
+            })
+    }
+
+    /// Renames observation annotation columns, mapping old names to new names.
+    /// Fails if a column does not exist or if a new name is already taken.
+    fn rename_obs(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        let mut obs = self.read_obs()?;
+        let names = renamed_columns(obs.get_column_names(), mapping)?;
+        obs.set_column_names(names.iter().map(|x| x.as_str()))?;
+        self.set_obs(obs)
+    }
+    /// Renames variable annotation columns. See [`AnnDataOp::rename_obs`].
+    fn rename_var(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        let mut var = self.read_var()?;
+        let names = renamed_columns(var.get_column_names(), mapping)?;
+        var.set_column_names(names.iter().map(|x| x.as_str()))?;
+        self.set_var(var)
+    }
+
     /// Deletes the observation annotations.
     fn del_obs(&self) -> Result<()>;
 
@@ -108,17 +440,47 @@ pub trait AnnDataOp {
         I: IntoIterator<Item = (String, D)>,
         D: Into<ArrayData>,
     {
+        let data: Vec<(String, ArrayData)> =
+            data.into_iter().map(|(k, v)| (k, v.into())).collect();
+        check_shapes(&data, &[self.n_obs()], "obsm")?;
         self.del_obsm()?;
         let obsm = self.obsm();
         data.into_iter().try_for_each(|(k, v)| obsm.add(&k, v))
     }
 
+    /// Adds several observation matrices at once, e.g. all embeddings of an
+    /// analysis. The shapes of all arrays are checked against `n_obs` before
+    /// anything is written, and `obsm` is locked only once. Existing arrays
+    /// with the same keys are replaced, the others are kept. If writing fails,
+    /// the arrays written so far are rolled back, so `obsm` is never left
+    /// half-populated.
+    fn set_obsm_batch(&self, data: HashMap<String, ArrayData>) -> Result<()> {
+        let mut data: Vec<(String, ArrayData)> = data.into_iter().collect();
+        data.sort_by(|a, b| a.0.cmp(&b.0));
+        check_shapes(&data, &[self.n_obs()], "obsm")?;
+        self.obsm().add_batch(data)
+    }
+
+    /// Adds an observation matrix, creating its datasets with the given chunk
+    /// size and compression.
+    fn add_obsm_with_opts<D: Into<ArrayData>>(
+        &self,
+        key: &str,
+        data: D,
+        options: &WriteOptions,
+    ) -> Result<()> {
+        self.obsm().add_with_config(key, data, &options.into())
+    }
+
     /// Sets the observation pairwise data.
     fn set_obsp<I, D>(&self, data: I) -> Result<()>
     where
         I: IntoIterator<Item = (String, D)>,
         D: Into<ArrayData>,
     {
+        let data: Vec<(String, ArrayData)> =
+            data.into_iter().map(|(k, v)| (k, v.into())).collect();
+        check_shapes(&data, &[self.n_obs(), self.n_obs()], "obsp")?;
         self.del_obsp()?;
         let obsp = self.obsp();
         data.into_iter().try_for_each(|(k, v)| obsp.add(&k, v))
@@ -129,6 +491,9 @@ pub trait AnnDataOp {
         I: IntoIterator<Item = (String, D)>,
         D: Into<ArrayData>,
     {
+        let data: Vec<(String, ArrayData)> =
+            data.into_iter().map(|(k, v)| (k, v.into())).collect();
+        check_shapes(&data, &[self.n_vars()], "varm")?;
         self.del_varm()?;
         let varm = self.varm();
         data.into_iter().try_for_each(|(k, v)| varm.add(&k, v))
@@ -139,6 +504,9 @@ pub trait AnnDataOp {
         I: IntoIterator<Item = (String, D)>,
         D: Into<ArrayData>,
     {
+        let data: Vec<(String, ArrayData)> =
+            data.into_iter().map(|(k, v)| (k, v.into())).collect();
+        check_shapes(&data, &[self.n_vars(), self.n_vars()], "varp")?;
         self.del_varp()?;
         let varp = self.varp();
         data.into_iter().try_for_each(|(k, v)| varp.add(&k, v))
@@ -149,11 +517,170 @@ pub trait AnnDataOp {
         I: IntoIterator<Item = (String, D)>,
         D: Into<ArrayData>,
     {
+        let data: Vec<(String, ArrayData)> =
+            data.into_iter().map(|(k, v)| (k, v.into())).collect();
+        check_shapes(&data, &[self.n_obs(), self.n_vars()], "layers")?;
         self.del_layers()?;
         let layers = self.layers();
         data.into_iter().try_for_each(|(k, v)| layers.add(&k, v))
     }
 
+    /// Reads the rows `select` of an observation matrix, without loading the
+    /// other rows when the backend allows it. Returns `None` if the key does
+    /// not exist.
+    fn fetch_obsm_slice<D, S>(&self, key: &str, select: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<SelectInfoElem>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.obsm()
+            .get(key)
+            .map_or(Ok(None), |x| x.slice_axis(0, select))
+            .with_context(|| format!("key: {}", key))
+    }
+    /// Reads the rows `select` of a variable matrix.
+    /// See [`AnnDataOp::fetch_obsm_slice`].
+    fn fetch_varm_slice<D, S>(&self, key: &str, select: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<SelectInfoElem>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.varm()
+            .get(key)
+            .map_or(Ok(None), |x| x.slice_axis(0, select))
+            .with_context(|| format!("key: {}", key))
+    }
+    /// Reads the observation pairwise data between the observations `select`,
+    /// i.e., `select` is applied to both axes. Returns `None` if the key does
+    /// not exist.
+    fn fetch_obsp_slice<D, S>(&self, key: &str, select: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<SelectInfoElem>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        let select = select.as_ref();
+        self.obsp()
+            .get(key)
+            .map_or(Ok(None), |x| x.slice([select.clone(), select.clone()]))
+            .with_context(|| format!("key: {}", key))
+    }
+    /// Reads the variable pairwise data between the variables `select`.
+    /// See [`AnnDataOp::fetch_obsp_slice`].
+    fn fetch_varp_slice<D, S>(&self, key: &str, select: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<SelectInfoElem>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        let select = select.as_ref();
+        self.varp()
+            .get(key)
+            .map_or(Ok(None), |x| x.slice([select.clone(), select.clone()]))
+            .with_context(|| format!("key: {}", key))
+    }
+
+    /// Adds a layer, replacing any existing layer with the same key.
+    /// The layer must have the same shape as `X`.
+    fn add_layer<D: Into<ArrayData>>(&self, key: &str, data: D) -> Result<()> {
+        self.layers().add(key, data)
+    }
+    /// Reads a layer. Returns `None` if the layer does not exist.
+    fn fetch_layer<D>(&self, key: &str) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.layers().get_item(key)
+    }
+    /// Returns the keys of the layers.
+    fn layers_keys(&self) -> Vec<String> {
+        self.layers().keys()
+    }
+    /// Deletes a single layer. Deleting a missing layer is a no-op.
+    fn del_layer(&self, key: &str) -> Result<()> {
+        self.layers().remove(key)
+    }
+
+    /// Iterates over chunks of `chunk_size` rows of 'X', together with the same
+    /// rows of the `obsm` arrays named by `keys`, in the order of `keys`.
+    /// Rows are aligned across the component boundaries of stacked datasets.
+    fn chunked_with_obsm<D>(
+        &self,
+        chunk_size: usize,
+        keys: &[&str],
+    ) -> Result<
+        ChunkedWithObsm<
+            <Self::X as ArrayElemOp>::ArrayIter<D>,
+            <Self::AxisArraysRef<'_> as AxisArraysOp>::ArrayElem,
+        >,
+    >
+    where
+        D: TryFrom<ArrayData>,
+        <D as TryFrom<ArrayData>>::Error: std::fmt::Debug,
+    {
+        ensure!(!self.x().is_none(), "X is empty");
+        let obsm = self.obsm();
+        let elems = keys
+            .iter()
+            .map(|key| {
+                obsm.get(key)
+                    .ok_or_else(|| AnnDataError::key_not_found(*key, "obsm").into())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ChunkedWithObsm::new(self.x().iter(chunk_size), elems))
+    }
+
+    /// Returns the keys, shapes and data types of all array collections
+    /// (obsm, obsp, varm, varp and layers). Only metadata is read.
+    fn array_inventory(&self) -> Result<Inventory> {
+        Ok(Inventory {
+            obsm: array_info(self.obsm())?,
+            obsp: array_info(self.obsp())?,
+            varm: array_info(self.varm())?,
+            varp: array_info(self.varp())?,
+            layers: array_info(self.layers())?,
+            stacked_obsm: Vec::new(),
+        })
+    }
+
+    /// Returns the shape of the observation matrix `key` without reading its
+    /// data, e.g. to check the number of components of "X_pca". Returns `None`
+    /// if the key does not exist.
+    fn obsm_shape(&self, key: &str) -> Option<Shape> {
+        self.obsm().get(key).and_then(|x| x.shape())
+    }
+    /// Returns the shape of the variable matrix `key`. See
+    /// [`AnnDataOp::obsm_shape`].
+    fn varm_shape(&self, key: &str) -> Option<Shape> {
+        self.varm().get(key).and_then(|x| x.shape())
+    }
+
+    /// Reads the observation pairwise array `key`, e.g. "distances" or
+    /// "connectivities", as a sparse graph with `f64` weights. Dense arrays
+    /// are converted to their nonzero entries. If `symmetrize` is given, the
+    /// entries `(i, j)` and `(j, i)` are combined on the sparse structure
+    /// without densifying the graph.
+    fn fetch_graph(&self, key: &str, symmetrize: Option<SymMode>) -> Result<CsrMatrix<f64>> {
+        let data: ArrayData =
+            self.obsp().get_item(key)?.ok_or_else(|| AnnDataError::key_not_found(key, "obsp"))?;
+        let graph = graph_to_csr(data).with_context(|| format!("cannot read obsp '{}'", key))?;
+        match symmetrize {
+            Some(mode) => Ok(symmetrize_graph(&graph, mode)),
+            None => Ok(graph),
+        }
+    }
+
+    /// Returns the key, encoding and shape of each entry of `uns`, without
+    /// reading the data. Entries of nested mappings follow their mapping under
+    /// `/`-separated keys. Scalars have an empty shape, and the shape of a
+    /// mapping is its number of entries.
+    fn uns_entries(&self) -> Result<Vec<(String, EncodingType, Shape)>> {
+        bail!("uns introspection is not supported by this object")
+    }
+
     /// Deletes the unstructured data.
     fn del_uns(&self) -> Result<()>;
     /// Deletes the observation matrix.
@@ -166,6 +693,24 @@ pub trait AnnDataOp {
     fn del_varp(&self) -> Result<()>;
     /// Deletes the layers.
     fn del_layers(&self) -> Result<()>;
+
+    /// Removes the array `key` from the observation matrix. Unlike
+    /// [`AnnDataOp::del_obsm`], the other arrays are kept.
+    fn remove_obsm(&self, key: &str) -> Result<()> {
+        self.obsm().remove(key)
+    }
+    /// Removes the array `key` from the observation pairwise data.
+    fn remove_obsp(&self, key: &str) -> Result<()> {
+        self.obsp().remove(key)
+    }
+    /// Removes the array `key` from the variable matrix.
+    fn remove_varm(&self, key: &str) -> Result<()> {
+        self.varm().remove(key)
+    }
+    /// Removes the array `key` from the variable pairwise data.
+    fn remove_varp(&self, key: &str) -> Result<()> {
+        self.varp().remove(key)
+    }
 }
 
 impl<T: AnnDataOp> AnnDataOp for &T {
@@ -189,10 +734,18 @@ impl<T: AnnDataOp> AnnDataOp for &T {
         (*self).set_x(data)
     }
 
+    fn set_x_with_opts<D: Into<ArrayData>>(&self, data: D, options: &WriteOptions) -> Result<()> {
+        (*self).set_x_with_opts(data, options)
+    }
+
     fn del_x(&self) -> Result<()> {
         (*self).del_x()
     }
 
+    fn binarize_x(&self, threshold: f64) -> Result<()> {
+        (*self).binarize_x(threshold)
+    }
+
     fn n_obs(&self) -> usize {
         (*self).n_obs()
     }
@@ -225,6 +778,14 @@ impl<T: AnnDataOp> AnnDataOp for &T {
         (*self).set_var_names(index)
     }
 
+    fn set_obs_names_checked(&self, index: DataFrameIndex) -> Result<()> {
+        (*self).set_obs_names_checked(index)
+    }
+
+    fn set_var_names_checked(&self, index: DataFrameIndex) -> Result<()> {
+        (*self).set_var_names_checked(index)
+    }
+
     fn obs_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>> {
         (*self).obs_ix(names)
     }
@@ -249,6 +810,54 @@ impl<T: AnnDataOp> AnnDataOp for &T {
         (*self).set_var(var)
     }
 
+    fn fetch_obs_column(&self, name: &str) -> Result<Option<Series>> {
+        (*self).fetch_obs_column(name)
+    }
+
+    fn fetch_var_column(&self, name: &str) -> Result<Option<Series>> {
+        (*self).fetch_var_column(name)
+    }
+
+    fn obs_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        (*self).obs_schema()
+    }
+
+    fn var_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        (*self).var_schema()
+    }
+
+    fn obs_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        (*self).obs_column_attrs(column)
+    }
+
+    fn var_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        (*self).var_column_attrs(column)
+    }
+
+    fn set_obs_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        (*self).set_obs_column_attr(column, key, value)
+    }
+
+    fn set_var_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        (*self).set_var_column_attr(column, key, value)
+    }
+
+    fn add_obs_column(&self, name: &str, series: Series) -> Result<()> {
+        (*self).add_obs_column(name, series)
+    }
+
+    fn add_var_column(&self, name: &str, series: Series) -> Result<()> {
+        (*self).add_var_column(name, series)
+    }
+
+    fn rename_obs(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        (*self).rename_obs(mapping)
+    }
+
+    fn rename_var(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        (*self).rename_var(mapping)
+    }
+
     fn del_obs(&self) -> Result<()> {
         (*self).del_obs()
     }
@@ -321,6 +930,22 @@ impl<T: AnnDataOp> AnnDataOp for &T {
         (*self).set_varp(data)
     }
 
+    fn array_inventory(&self) -> Result<Inventory> {
+        (*self).array_inventory()
+    }
+
+    fn obsm_shape(&self, key: &str) -> Option<Shape> {
+        (*self).obsm_shape(key)
+    }
+
+    fn varm_shape(&self, key: &str) -> Option<Shape> {
+        (*self).varm_shape(key)
+    }
+
+    fn uns_entries(&self) -> Result<Vec<(String, EncodingType, Shape)>> {
+        (*self).uns_entries()
+    }
+
     fn del_uns(&self) -> Result<()> {
         (*self).del_uns()
     }
@@ -345,6 +970,22 @@ impl<T: AnnDataOp> AnnDataOp for &T {
         (*self).del_layers()
     }
 
+    fn remove_obsm(&self, key: &str) -> Result<()> {
+        (*self).remove_obsm(key)
+    }
+
+    fn remove_obsp(&self, key: &str) -> Result<()> {
+        (*self).remove_obsp(key)
+    }
+
+    fn remove_varm(&self, key: &str) -> Result<()> {
+        (*self).remove_varm(key)
+    }
+
+    fn remove_varp(&self, key: &str) -> Result<()> {
+        (*self).remove_varp(key)
+    }
+
     fn set_layers<I, D>(&self, data: I) -> Result<()>
     where
         I: IntoIterator<Item = (String, D)>,
@@ -365,6 +1006,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
 
     fn set_x_from_iter<I: Iterator<Item = D>, D: ArrayChunk>(&self, iter: I) -> Result<()> {
+        self.ensure_writable()?;
         let mut obs_lock = self.n_obs.lock();
         let mut vars_lock = self.n_vars.lock();
         self.del_x()?;
@@ -384,6 +1026,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
 
     fn set_x<D: Into<ArrayData>>(&self, data: D) -> Result<()> {
+        self.ensure_writable()?;
         let data = data.into();
         let shape = data.shape();
         ensure!(
@@ -402,10 +1045,55 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         Ok(())
     }
 
+    /// Unlike `set_x`, an existing 'X' is always replaced so that the new chunk
+    /// size and compression take effect.
+    fn set_x_with_opts<D: Into<ArrayData>>(&self, data: D, options: &WriteOptions) -> Result<()> {
+        self.ensure_writable()?;
+        let data = data.into();
+        let shape = data.shape();
+        ensure!(
+            shape.ndim() >= 2,
+            "X must be a N dimensional array, where N >= 2"
+        );
+        self.n_obs.try_set(shape[0])?;
+        self.n_vars.try_set(shape[1])?;
+
+        self.x.clear()?;
+        let container = data.write_with_config(&self.file, "X", &options.into())?;
+        let new_elem = ArrayElem::try_from(container)?;
+        self.x.swap(&new_elem);
+        Ok(())
+    }
+
     fn del_x(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.x.clear()
     }
 
+    /// X is processed chunk by chunk, so the full matrix is never loaded.
+    fn binarize_x(&self, threshold: f64) -> Result<()> {
+        self.ensure_writable()?;
+        if self.x.is_none() {
+            return Ok(());
+        }
+
+        // Write the result to a temporary location first, as X is still being read.
+        let mut error = None;
+        let chunks = self.x.chunked::<ArrayData>(BINARIZE_CHUNK_SIZE).map_while(|(x, _, _)| {
+            x.binarize(threshold).map_err(|e| error = Some(e)).ok()
+        });
+        let result = ArrayData::write_by_chunk(chunks, &self.file, "__X_binarized");
+        if let Some(e) = error {
+            if let Ok(container) = result {
+                DataContainer::delete(container)?;
+            }
+            return Err(e);
+        }
+        drop(result?);
+        self.replace_x_with("__X_binarized")?;
+        record_binarize(self, threshold)
+    }
+
     fn n_obs(&self) -> usize {
         self.n_obs.get()
     }
@@ -461,6 +1149,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
 
     fn set_obs_names(&self, index: DataFrameIndex) -> Result<()> {
+        self.ensure_writable()?;
         self.n_obs.try_set(index.len())?;
         if self.obs.is_none() {
             let df = InnerDataFrameElem::new(&self.file, "obs", Some(index), &DataFrame::empty())?;
@@ -472,6 +1161,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
 
     fn set_var_names(&self, index: DataFrameIndex) -> Result<()> {
+        self.ensure_writable()?;
         self.n_vars.try_set(index.len())?;
         if self.var.is_none() {
             let df = InnerDataFrameElem::new(&self.file, "var", Some(index), &DataFrame::empty())?;
@@ -484,28 +1174,24 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
 
     fn obs_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>> {
         let inner = self.obs.inner();
-        names
-            .into_iter()
-            .map(|i| {
-                inner
-                    .index
-                    .get_index(i)
-                    .context(format!("'{}' does not exist in obs_names", i))
-            })
-            .collect()
+        let mut indices = Vec::new();
+        for i in names {
+            let ix = inner.index.get_indices(i);
+            ensure!(!ix.is_empty(), AnnDataError::key_not_found(i, "obs_names"));
+            indices.extend(ix);
+        }
+        Ok(indices)
     }
 
     fn var_ix<'a, I: IntoIterator<Item = &'a str>>(&self, names: I) -> Result<Vec<usize>> {
         let inner = self.var.inner();
-        names
-            .into_iter()
-            .map(|i| {
-                inner
-                    .index
-                    .get_index(i)
-                    .context(format!("'{}' does not exist in obs_names", i))
-            })
-            .collect()
+        let mut indices = Vec::new();
+        for i in names {
+            let ix = inner.index.get_indices(i);
+            ensure!(!ix.is_empty(), AnnDataError::key_not_found(i, "var_names"));
+            indices.extend(ix);
+        }
+        Ok(indices)
     }
 
     fn read_obs(&self) -> Result<DataFrame> {
@@ -522,6 +1208,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
     // TODO: empty dataframe should be allowed
     fn set_obs(&self, obs: DataFrame) -> Result<()> {
+        self.ensure_writable()?;
         let nrows = obs.height();
         if nrows != 0 {
             self.n_obs.try_set(nrows)?;
@@ -536,6 +1223,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
     }
 
     fn set_var(&self, var: DataFrame) -> Result<()> {
+        self.ensure_writable()?;
         let nrows = var.height();
         if nrows != 0 {
             self.n_vars.try_set(nrows)?;
@@ -549,16 +1237,108 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         Ok(())
     }
 
+    fn fetch_obs_column(&self, name: &str) -> Result<Option<Series>> {
+        self.obs
+            .lock()
+            .as_ref()
+            .map_or(Ok(None), |x| x.read_column(name))
+    }
+
+    fn fetch_var_column(&self, name: &str) -> Result<Option<Series>> {
+        self.var
+            .lock()
+            .as_ref()
+            .map_or(Ok(None), |x| x.read_column(name))
+    }
+
+    fn obs_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        self.obs.lock().as_ref().map_or(Ok(Vec::new()), |x| x.schema())
+    }
+
+    fn var_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        self.var.lock().as_ref().map_or(Ok(Vec::new()), |x| x.schema())
+    }
+
+    fn obs_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        self.obs
+            .lock()
+            .as_ref()
+            .context("obs is empty")?
+            .get_column_attrs(column)
+    }
+
+    fn var_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        self.var
+            .lock()
+            .as_ref()
+            .context("var is empty")?
+            .get_column_attrs(column)
+    }
+
+    fn set_obs_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        self.ensure_writable()?;
+        self.obs
+            .lock()
+            .as_mut()
+            .context("obs is empty")?
+            .set_column_attr(column, key, value)
+    }
+
+    fn set_var_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        self.ensure_writable()?;
+        self.var
+            .lock()
+            .as_mut()
+            .context("var is empty")?
+            .set_column_attr(column, key, value)
+    }
+
+    fn add_obs_column(&self, name: &str, series: Series) -> Result<()> {
+        self.ensure_writable()?;
+        if self.obs.is_none() {
+            return self.set_obs(DataFrame::new(vec![series.with_name(name.into()).into_column()])?);
+        }
+        self.obs.inner().add_column(name, series)
+    }
+
+    fn add_var_column(&self, name: &str, series: Series) -> Result<()> {
+        self.ensure_writable()?;
+        if self.var.is_none() {
+            return self.set_var(DataFrame::new(vec![series.with_name(name.into()).into_column()])?);
+        }
+        self.var.inner().add_column(name, series)
+    }
+
+    fn rename_obs(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        self.ensure_writable()?;
+        self.obs
+            .lock()
+            .as_mut()
+            .context("obs is empty")?
+            .rename_columns(mapping)
+    }
+
+    fn rename_var(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        self.ensure_writable()?;
+        self.var
+            .lock()
+            .as_mut()
+            .context("var is empty")?
+            .rename_columns(mapping)
+    }
+
     fn del_obs(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.get_obs().clear()
     }
 
     fn del_var(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.get_var().clear()
     }
 
     fn uns(&self) -> Self::ElemCollectionRef<'_> {
-        if self.uns.is_none() {
+        if self.uns.is_none() && self.mode == OpenMode::ReadWrite {
             let elems = new_mapping(&self.file, "uns").and_then(ElemCollection::new);
             if let Ok(uns) = elems {
                 self.uns.swap(&uns);
@@ -567,7 +1347,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.uns
     }
     fn obsm(&self) -> Self::AxisArraysRef<'_> {
-        if self.obsm.is_none() {
+        if self.obsm.is_none() && self.mode == OpenMode::ReadWrite {
             let arrays = new_mapping(&self.file, "obsm").and_then(|g|
                 new_obsm(g, &self.n_obs)
             );
@@ -578,7 +1358,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.obsm
     }
     fn obsp(&self) -> Self::AxisArraysRef<'_> {
-        if self.obsp.is_none() {
+        if self.obsp.is_none() && self.mode == OpenMode::ReadWrite {
             let arrays = new_mapping(&self.file, "obsp").and_then(|g|
                 new_obsp(g, &self.n_obs)
             );
@@ -589,7 +1369,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.obsp
     }
     fn varm(&self) -> Self::AxisArraysRef<'_> {
-        if self.varm.is_none() {
+        if self.varm.is_none() && self.mode == OpenMode::ReadWrite {
             let arrays = new_mapping(&self.file, "varm").and_then(|g|
                 new_varm(g, &self.n_vars)
             );
@@ -600,7 +1380,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.varm
     }
     fn varp(&self) -> Self::AxisArraysRef<'_> {
-        if self.varp.is_none() {
+        if self.varp.is_none() && self.mode == OpenMode::ReadWrite {
             let arrays = new_mapping(&self.file, "varp").and_then(|g|
                 new_varp(g, &self.n_vars)
             );
@@ -611,7 +1391,7 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.varp
     }
     fn layers(&self) -> Self::AxisArraysRef<'_> {
-        if self.layers.is_none() {
+        if self.layers.is_none() && self.mode == OpenMode::ReadWrite {
             let arrays = new_mapping(&self.file, "layers").and_then(|g|
                 new_layers(g, &self.n_obs, &self.n_vars)
             );
@@ -622,22 +1402,37 @@ impl<B: Backend> AnnDataOp for AnnData<B> {
         &self.layers
     }
 
+    fn obsm_shape(&self, key: &str) -> Option<Shape> {
+        self.obsm.lock().as_ref()?.shape_of(key)
+    }
+    fn varm_shape(&self, key: &str) -> Option<Shape> {
+        self.varm.lock().as_ref()?.shape_of(key)
+    }
+    fn uns_entries(&self) -> Result<Vec<(String, EncodingType, Shape)>> {
+        self.uns.entries()
+    }
     fn del_uns(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.uns.clear()
     }
     fn del_obsm(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.obsm.clear()
     }
     fn del_obsp(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.obsp.clear()
     }
     fn del_varm(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.varm.clear()
     }
     fn del_varp(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.varp.clear()
     }
     fn del_layers(&self) -> Result<()> {
+        self.ensure_writable()?;
         self.layers.clear()
     }
 }
@@ -647,7 +1442,14 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
     type AxisArraysRef<'a> = &'a AxisArrays<B>;
     type ElemCollectionRef<'a> = &'a ElemCollection<B>;
 
+    /// While some of the AnnData files are missing, see
+    /// [`AnnDataSet::missing_children`], reading `X` fails with
+    /// [`AnnDataError::MissingChildren`].
     fn x(&self) -> Self::X {
+        if !self.missing.is_empty() {
+            let keys = self.missing.iter().map(|(k, _)| k.clone()).collect();
+            return StackedArrayElem::missing(keys);
+        }
         self.anndatas.inner().x.clone()
     }
 
@@ -664,10 +1466,16 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
     }
 
     fn n_obs(&self) -> usize {
-        self.anndatas.inner().n_obs
+        match self.anndatas.lock().as_ref().filter(|_| self.missing.is_empty()) {
+            Some(x) => x.n_obs,
+            None => self.annotation.n_obs(),
+        }
     }
     fn n_vars(&self) -> usize {
-        self.anndatas.inner().n_vars
+        match self.anndatas.lock().as_ref().filter(|_| self.missing.is_empty()) {
+            Some(x) => x.n_vars,
+            None => self.annotation.n_vars(),
+        }
     }
     fn set_n_obs(&self, n: usize) -> Result<()> {
         self.annotation.set_n_obs(n)
@@ -707,6 +1515,50 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
     fn set_var(&self, var: DataFrame) -> Result<()> {
         self.annotation.set_var(var)
     }
+    /// With [`AnnDataSet::set_lazy_obs`], columns that are not in the obs of
+    /// the AnnDataSet are read from the AnnData objects and stacked, see
+    /// [`crate::StackedDataFrame::read_column`]. This fails with
+    /// [`AnnDataError::MissingChildren`] if some of the objects are missing.
+    fn fetch_obs_column(&self, name: &str) -> Result<Option<Series>> {
+        match self.annotation.fetch_obs_column(name)? {
+            Some(series) => Ok(Some(series)),
+            None if !self.lazy_obs => Ok(None),
+            None => self.stacked()?.get_obs().read_column(name),
+        }
+    }
+    fn fetch_var_column(&self, name: &str) -> Result<Option<Series>> {
+        self.annotation.fetch_var_column(name)
+    }
+    fn obs_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        self.annotation.obs_schema()
+    }
+    fn var_schema(&self) -> Result<Vec<(String, polars::prelude::DataType)>> {
+        self.annotation.var_schema()
+    }
+    fn obs_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        self.annotation.obs_column_attrs(column)
+    }
+    fn var_column_attrs(&self, column: &str) -> Result<IndexMap<String, String>> {
+        self.annotation.var_column_attrs(column)
+    }
+    fn set_obs_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        self.annotation.set_obs_column_attr(column, key, value)
+    }
+    fn set_var_column_attr(&self, column: &str, key: &str, value: &str) -> Result<()> {
+        self.annotation.set_var_column_attr(column, key, value)
+    }
+    fn add_obs_column(&self, name: &str, series: Series) -> Result<()> {
+        self.annotation.add_obs_column(name, series)
+    }
+    fn add_var_column(&self, name: &str, series: Series) -> Result<()> {
+        self.annotation.add_var_column(name, series)
+    }
+    fn rename_obs(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        self.annotation.rename_obs(mapping)
+    }
+    fn rename_var(&self, mapping: &HashMap<String, String>) -> Result<()> {
+        self.annotation.rename_var(mapping)
+    }
     fn del_obs(&self) -> Result<()> {
         self.annotation.del_obs()
     }
@@ -773,6 +1625,26 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
         self.annotation.set_varp(data)
     }
 
+    fn array_inventory(&self) -> Result<Inventory> {
+        let mut inventory = self.annotation.array_inventory()?;
+        if let Some(stacked) = self.anndatas.lock().as_ref().filter(|_| self.missing.is_empty()) {
+            inventory.stacked_obsm = array_info(&stacked.obsm)?;
+        }
+        Ok(inventory)
+    }
+
+    fn obsm_shape(&self, key: &str) -> Option<Shape> {
+        self.annotation.obsm_shape(key)
+    }
+
+    fn varm_shape(&self, key: &str) -> Option<Shape> {
+        self.annotation.varm_shape(key)
+    }
+
+    fn uns_entries(&self) -> Result<Vec<(String, EncodingType, Shape)>> {
+        self.annotation.uns_entries()
+    }
+
     fn del_uns(&self) -> Result<()> {
         self.annotation.del_uns()
     }
@@ -793,6 +1665,335 @@ impl<B: Backend> AnnDataOp for AnnDataSet<B> {
     }
 }
 
+const BINARIZE_CHUNK_SIZE: usize = 1000;
+
+fn record_binarize<A: AnnDataOp + ?Sized>(adata: &A, threshold: f64) -> Result<()> {
+    let params: HashMap<String, Data> = [("threshold".to_string(), threshold.into())].into();
+    adata.uns().add("binarize", Mapping::from(params))
+}
+
+/// Restores the order of items received from a parallel producer.
+struct InOrder<T> {
+    receiver: Receiver<(usize, T)>,
+    pending: BTreeMap<usize, T>,
+    next: usize,
+}
+
+impl<T> InOrder<T> {
+    fn new(receiver: Receiver<(usize, T)>) -> Self {
+        Self {
+            receiver,
+            pending: BTreeMap::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<T> Iterator for InOrder<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(x) = self.pending.remove(&self.next) {
+                self.next += 1;
+                return Some(x);
+            }
+            let (i, x) = self.receiver.recv().ok()?;
+            self.pending.insert(i, x);
+        }
+    }
+}
+
+/// Reports the progress of an iterator of chunks. A chunk counts as done when
+/// the next one is requested, i.e., once the consumer has finished with it.
+struct WithProgress<I, F> {
+    iter: I,
+    callback: F,
+    total: usize,
+    done: usize,
+    started: bool,
+}
+
+impl<I: ExactSizeIterator, F> WithProgress<I, F> {
+    fn new(iter: I, callback: F) -> Self {
+        let total = iter.len();
+        Self {
+            iter,
+            callback,
+            total,
+            done: 0,
+            started: false,
+        }
+    }
+}
+
+impl<I: Iterator, F: FnMut(usize, usize)> Iterator for WithProgress<I, F> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.started && self.done < self.total {
+            self.done += 1;
+            (self.callback)(self.done, self.total);
+        }
+        self.started = true;
+        self.iter.next()
+    }
+}
+
+/// Check the shapes of arrays against the sizes of their leading axes before
+/// anything is written. A size of 0 is unknown and taken from the first array.
+fn check_shapes(data: &[(String, ArrayData)], dims: &[usize], what: &str) -> Result<()> {
+    let mut dims: SmallVec<[usize; 2]> = dims.iter().copied().collect();
+    for (key, arr) in data {
+        let shape = arr.shape();
+        ensure!(
+            shape.ndim() >= dims.len(),
+            "{} '{}' has shape {}, but at least {} dimensions are required",
+            what,
+            key,
+            shape,
+            dims.len()
+        );
+        for (i, n) in dims.iter_mut().enumerate() {
+            if *n == 0 {
+                *n = shape[i];
+            }
+            ensure!(
+                shape[i] == *n,
+                "{} '{}' has shape {}, but the size of axis {} must be {}",
+                what,
+                key,
+                shape,
+                i,
+                n
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Resolve names to positions, reporting all names that are not found.
+fn names_to_selection(index: &DataFrameIndex, names: &[&str], what: &str) -> Result<SelectInfoElem> {
+    let (found, missing): (Vec<_>, Vec<_>) = names
+        .iter()
+        .map(|x| index.get_index(x).ok_or(*x))
+        .partition(|x| x.is_ok());
+    ensure!(
+        missing.is_empty(),
+        AnnDataError::KeyNotFound {
+            keys: missing.into_iter().map(|x| x.unwrap_err().to_string()).collect(),
+            collection: what.to_string(),
+        }
+    );
+    Ok(found.into_iter().map(|x| x.unwrap()).collect::<Vec<_>>().into())
+}
+
+/// Build a dataframe from the columns `names`, reporting all names for which
+/// `fetch` returns `None`.
+fn read_columns<F>(names: &[&str], what: &str, mut fetch: F) -> Result<DataFrame>
+where
+    F: FnMut(&str) -> Result<Option<Series>>,
+{
+    let mut columns = Vec::with_capacity(names.len());
+    let mut missing = Vec::new();
+    for name in names {
+        match fetch(name)? {
+            Some(series) => columns.push(series.into_column()),
+            None => missing.push(name.to_string()),
+        }
+    }
+    ensure!(
+        missing.is_empty(),
+        AnnDataError::KeyNotFound {
+            keys: missing,
+            collection: what.to_string(),
+        }
+    );
+    Ok(DataFrame::new(columns)?)
+}
+
+/// Returns the columns of `df` other than `on`, with the rows reordered so
+/// that the values of `on` match `keys`. Keys without a match get null rows.
+/// `existing` are the columns of the annotations in `what`, which must not
+/// overlap with the new columns. The data types of the new columns are
+/// checked as well, so that writing them does not fail halfway.
+fn align_by_key(
+    keys: &Series,
+    df: DataFrame,
+    on: &str,
+    options: &JoinOptions,
+    existing: HashSet<String>,
+    what: &str,
+) -> Result<DataFrame> {
+    let to_str = |x: &Series| x.cast(&polars::prelude::DataType::String);
+    let right = to_str(df.column(on)?.as_materialized_series())?;
+    let mut rows: HashMap<&str, IdxSize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (i, key) in right.str()?.into_iter().enumerate() {
+        if let Some(key) = key {
+            if rows.contains_key(key) {
+                duplicates.push(key);
+            } else {
+                rows.insert(key, i as IdxSize);
+            }
+        }
+    }
+    if !options.allow_duplicates && !duplicates.is_empty() {
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        bail!("duplicated keys in column '{}': {}", on, duplicates.join(", "));
+    }
+
+    let df = df.drop(on)?;
+    let mut overlap: Vec<_> = df
+        .get_column_names()
+        .into_iter()
+        .filter(|x| existing.contains(x.as_str()))
+        .map(|x| x.as_str())
+        .collect();
+    if !overlap.is_empty() {
+        overlap.sort_unstable();
+        bail!("columns already exist in {}: {}", what, overlap.join(", "));
+    }
+    check_series_dtypes(&df)?;
+
+    let left = to_str(keys)?;
+    let idx: IdxCa = left
+        .str()?
+        .into_iter()
+        .map(|x| x.and_then(|k| rows.get(k).copied()))
+        .collect();
+    Ok(df.take(&idx)?)
+}
+
+/// Evaluate a boolean expression against the rows of `df`, returning the
+/// positions of the rows where it is true.
+fn filter_rows(df: DataFrame, expr: Expr) -> Result<Vec<usize>> {
+    let height = df.height();
+    let mask = df.lazy().select([expr]).collect()?;
+    let mask = &mask.get_columns()[0];
+    ensure!(
+        mask.dtype() == &polars::prelude::DataType::Boolean,
+        "the expression must evaluate to booleans, but evaluates to {}",
+        mask.dtype()
+    );
+    ensure!(
+        mask.len() == height,
+        "the expression must evaluate to {} values, one for each row, but evaluates to {}",
+        height,
+        mask.len()
+    );
+    Ok(mask
+        .bool()?
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, x)| x.unwrap_or(false).then_some(i))
+        .collect())
+}
+
+/// Convert a pairwise array to a CSR matrix with `f64` values.
+fn graph_to_csr(data: ArrayData) -> Result<CsrMatrix<f64>> {
+    let csr = match data {
+        ArrayData::CsrMatrix(csr) => csr.try_convert()?,
+        ArrayData::CscMatrix(csc) => {
+            let csc: CscMatrix<f64> = csc.try_convert()?;
+            CsrMatrix::from(&csc)
+        }
+        ArrayData::CsrNonCanonical(csr) => match csr.canonicalize() {
+            Ok(csr) => csr.try_convert()?,
+            Err(_) => bail!("the graph has duplicated entries"),
+        },
+        ArrayData::Array(arr) => {
+            let arr: ArrayD<f64> = arr.try_convert()?;
+            let arr = arr.into_dimensionality::<Ix2>()?;
+            let mut offsets = vec![0];
+            let mut indices = Vec::new();
+            let mut values = Vec::new();
+            for row in arr.rows() {
+                for (j, x) in row.iter().enumerate().filter(|(_, x)| **x != 0.0) {
+                    indices.push(j);
+                    values.push(*x);
+                }
+                offsets.push(indices.len());
+            }
+            CsrMatrix::try_from_csr_data(arr.nrows(), arr.ncols(), offsets, indices, values)
+                .map_err(|e| anyhow::anyhow!("{}", e))?
+        }
+        ArrayData::DataFrame(_) => bail!("a dataframe is not a graph"),
+    };
+    ensure!(
+        csr.nrows() == csr.ncols(),
+        "a graph must be square, but has shape {} x {}",
+        csr.nrows(),
+        csr.ncols()
+    );
+    Ok(csr)
+}
+
+/// Combine the entries `(i, j)` and `(j, i)` of a square matrix by merging
+/// each row with the corresponding row of the transpose.
+fn symmetrize_graph(graph: &CsrMatrix<f64>, mode: SymMode) -> CsrMatrix<f64> {
+    let transposed = graph.transpose();
+    let mut offsets = Vec::with_capacity(graph.nrows() + 1);
+    let mut indices = Vec::new();
+    let mut values = Vec::new();
+    offsets.push(0);
+    for (row, row_t) in graph.row_iter().zip(transposed.row_iter()) {
+        let (cols, vals) = (row.col_indices(), row.values());
+        let (cols_t, vals_t) = (row_t.col_indices(), row_t.values());
+        let (mut p, mut q) = (0, 0);
+        while p < cols.len() || q < cols_t.len() {
+            let (j, x, y) = match (cols.get(p), cols_t.get(q)) {
+                (Some(&j), Some(&k)) if j == k => (j, Some(vals[p]), Some(vals_t[q])),
+                (Some(&j), Some(&k)) if j < k => (j, Some(vals[p]), None),
+                (Some(&j), None) => (j, Some(vals[p]), None),
+                (_, Some(&k)) => (k, None, Some(vals_t[q])),
+                (None, None) => unreachable!(),
+            };
+            p += x.is_some() as usize;
+            q += y.is_some() as usize;
+            let value = match (mode, x, y) {
+                (SymMode::Max, Some(x), Some(y)) => Some(x.max(y)),
+                (SymMode::Max, x, y) => x.or(y),
+                (SymMode::Min, Some(x), Some(y)) => Some(x.min(y)),
+                (SymMode::Min, _, _) => None,
+                (SymMode::Mean, x, y) => Some((x.unwrap_or(0.0) + y.unwrap_or(0.0)) / 2.0),
+            };
+            if let Some(value) = value {
+                indices.push(j);
+                values.push(value);
+            }
+        }
+        offsets.push(indices.len());
+    }
+    let n = graph.nrows();
+    CsrMatrix::try_from_csr_data(n, n, offsets, indices, values)
+        .expect("merged rows have sorted, unique column indices")
+}
+
+fn array_info<A: AxisArraysOp>(arrays: A) -> Result<Vec<ArrayInfo>> {
+    arrays
+        .keys()
+        .into_iter()
+        .map(|key| {
+            let elem = arrays
+                .get(&key)
+                .with_context(|| format!("key '{}' does not exist", key))?;
+            let shape = elem.shape().with_context(|| format!("'{}' is empty", key))?;
+            let dtype = elem.dtype().with_context(|| format!("'{}' is empty", key))?;
+            let encoding = match dtype {
+                DataType::Array(ScalarType::String) => EncodingType::StringArray,
+                DataType::Array(_) => EncodingType::Array,
+                DataType::CsrMatrix(_) => EncodingType::CsrMatrix,
+                DataType::CscMatrix(_) => EncodingType::CscMatrix,
+                DataType::Categorical => EncodingType::Categorical,
+                ty => bail!("'{}' is not an array: {}", key, ty),
+            };
+            Ok(ArrayInfo { key, shape, encoding, dtype })
+        })
+        .collect()
+}
+
 /// Trait for operations on element collections.
 pub trait ElemCollectionOp {
     /// Returns the keys of the collection.
@@ -861,12 +2062,38 @@ pub trait AxisArraysOp {
     /// Adds array data by key.
     fn add<D: Into<ArrayData>>(&self, key: &str, data: D) -> Result<()>;
 
+    /// Adds array data by key, creating its datasets with the given configuration.
+    /// Implementations without on-disk storage ignore the configuration.
+    fn add_with_config<D: Into<ArrayData>>(
+        &self,
+        key: &str,
+        data: D,
+        _config: &WriteConfig,
+    ) -> Result<()> {
+        self.add(key, data)
+    }
+
     /// Adds array data from an iterator by key.
     fn add_iter<I, D>(&self, key: &str, data: I) -> Result<()>
     where
         I: Iterator<Item = D>,
         D: ArrayChunk + Into<ArrayData>;
 
+    /// Adds several arrays at once. If one of them cannot be added, the arrays
+    /// added so far are removed and the arrays they replaced are restored.
+    fn add_batch(&self, data: Vec<(String, ArrayData)>) -> Result<()> {
+        add_with_rollback(
+            &mut (),
+            data,
+            |_, key| self.get_item::<ArrayData>(key),
+            |_, key, arr| self.add(key, arr),
+            |_, key, old| match old {
+                Some(old) => self.add(key, old),
+                None => self.remove(key),
+            },
+        )
+    }
+
     /// Removes data by key.
     fn remove(&self, key: &str) -> Result<()>;
 }
@@ -875,21 +2102,28 @@ impl<B: Backend> AxisArraysOp for &AxisArrays<B> {
     type ArrayElem = ArrayElem<B>;
 
     fn keys(&self) -> Vec<String> {
-        if self.is_empty() {
-            Vec::new()
-        } else {
-            self.inner().keys().cloned().collect()
-        }
+        self.read()
+            .as_ref()
+            .map_or(Vec::new(), |x| x.keys().cloned().collect())
     }
 
     fn get(&self, key: &str) -> Option<Self::ArrayElem> {
-        self.lock().as_ref().and_then(|x| x.get(key).cloned())
+        self.read().as_ref().and_then(|x| x.get(key).cloned())
     }
 
     fn add<D: Into<ArrayData>>(&self, key: &str, data: D) -> Result<()> {
         self.inner().add_data(key, data)
     }
 
+    fn add_with_config<D: Into<ArrayData>>(
+        &self,
+        key: &str,
+        data: D,
+        config: &WriteConfig,
+    ) -> Result<()> {
+        self.inner().add_data_with_config(key, data, config)
+    }
+
     fn add_iter<I, D>(&self, key: &str, data: I) -> Result<()>
     where
         I: Iterator<Item = D>,
@@ -898,6 +2132,10 @@ impl<B: Backend> AxisArraysOp for &AxisArrays<B> {
         self.inner().add_data_from_iter(key, data)
     }
 
+    fn add_batch(&self, data: Vec<(String, ArrayData)>) -> Result<()> {
+        self.inner().add_batch(data)
+    }
+
     fn remove(&self, key: &str) -> Result<()> {
         self.inner().remove_data(key)
     }
@@ -995,22 +2233,30 @@ impl<B: Backend> ArrayElemOp for ArrayElem<B> {
         <D as TryFrom<ArrayData>>::Error: std::fmt::Debug;
 
     fn is_none(&self) -> bool {
-        self.lock().is_none()
+        self.read().is_none()
     }
 
     fn dtype(&self) -> Option<DataType> {
-        self.lock().as_ref().map(|x| x.dtype())
+        self.read().as_ref().map(|x| x.dtype())
     }
 
     fn shape(&self) -> Option<Shape> {
-        self.lock().as_ref().map(|x| x.shape().clone())
+        self.read().as_ref().map(|x| x.shape().clone())
     }
 
+    /// Only takes shared access, unless the data is about to be cached.
     fn get<D>(&self) -> Result<Option<D>>
     where
         D: TryFrom<ArrayData>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        match self.read().as_ref() {
+            None => return Ok(None),
+            Some(elem) if !elem.needs_caching() => {
+                return elem.read_data()?.try_into().map_err(Into::into).map(Some)
+            }
+            Some(_) => {}
+        }
         let mut lock = self.lock();
         if let Some(elem) = lock.as_mut() {
             elem.data()?.try_into().map_err(Into::into).map(Some)
@@ -1019,12 +2265,24 @@ impl<B: Backend> ArrayElemOp for ArrayElem<B> {
         }
     }
 
+    /// Only takes shared access, unless the data is about to be cached.
     fn slice<D, S>(&self, slice: S) -> Result<Option<D>>
     where
         D: TryFrom<ArrayData>,
         S: AsRef<[SelectInfoElem]>,
         <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
     {
+        match self.read().as_ref() {
+            None => return Ok(None),
+            Some(elem) if !elem.needs_caching() => {
+                return elem
+                    .read_select(slice.as_ref())?
+                    .try_into()
+                    .map_err(Into::into)
+                    .map(Some)
+            }
+            Some(_) => {}
+        }
         let mut lock = self.lock();
         if let Some(elem) = lock.as_mut() {
             elem.select(slice.as_ref())?
@@ -1084,6 +2342,15 @@ impl<B: Backend> ArrayElemOp for StackedArrayElem<B> {
         self.select(slice.as_ref())
     }
 
+    fn slice_axis<D, S>(&self, axis: usize, slice: S) -> Result<Option<D>>
+    where
+        D: TryFrom<ArrayData>,
+        S: AsRef<SelectInfoElem>,
+        <D as TryFrom<ArrayData>>::Error: Into<anyhow::Error>,
+    {
+        self.select_axis(axis, slice)
+    }
+
     fn iter<D>(&self, chunk_size: usize) -> Self::ArrayIter<D>
     where
         D: TryFrom<ArrayData>,
@@ -9,9 +9,9 @@ pub use memory::PyAnnData;
 
 use anndata;
 use anndata::concat::JoinType;
-use anndata::Backend;
+use anndata::{Backend, OpenMode};
 use anndata_hdf5::H5;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use pyo3::prelude::*;
 use std::{
     collections::HashMap,
@@ -35,6 +35,15 @@ pub(crate) fn get_backend<P: AsRef<Path>>(filename: P, backend: Option<&str>) ->
     }
 }
 
+/// Parse the Python file mode: "r" is read-only and "r+" is read/write.
+pub(crate) fn get_open_mode(mode: &str) -> Result<OpenMode> {
+    match mode {
+        "r" => Ok(OpenMode::ReadOnly),
+        "r+" => Ok(OpenMode::ReadWrite),
+        _ => bail!("Unknown mode: {}", mode),
+    }
+}
+
 /// Read `.h5ad`-formatted hdf5 file.
 ///
 /// Parameters
@@ -288,23 +297,14 @@ pub fn read_dataset(
         Some(LocationUpdate::Dir(dir)) => Some(Err(dir)),
         None => None,
     };
+    let mode = get_open_mode(mode)?;
     let backend = get_backend(&filename, backend);
     match backend {
         H5::NAME => {
-            let file = match mode {
-                "r" => H5::open(filename)?,
-                "r+" => H5::open_rw(filename)?,
-                _ => panic!("Unkown mode"),
-            };
-            Ok(anndata::AnnDataSet::<H5>::open(file, adata_files_update)?.into())
+            Ok(anndata::AnnDataSet::<H5>::open_with_mode(filename, mode, adata_files_update)?.into())
         }
         Zarr::NAME => {
-            let file = match mode {
-                "r" => Zarr::open(filename)?,
-                "r+" => Zarr::open_rw(filename)?,
-                _ => panic!("Unkown mode"),
-            };
-            Ok(anndata::AnnDataSet::<Zarr>::open(file, adata_files_update)?.into())
+            Ok(anndata::AnnDataSet::<Zarr>::open_with_mode(filename, mode, adata_files_update)?.into())
         }
         _ => todo!(),
     }
@@ -3,6 +3,7 @@ use crate::container::{
     PyArrayElem, PyAxisArrays, PyChunkedArray, PyDataFrameElem, PyElemCollection,
 };
 use crate::data::{isinstance_of_pandas, to_select_elem, PyArrayData, PyData};
+use crate::error::to_py_err;
 
 use anndata::container::Slot;
 use anndata::data::{DataFrameIndex, SelectInfoElem, SelectInfoElemBounds};
@@ -18,7 +19,7 @@ use std::collections::HashMap;
 use std::ops::Deref;
 use std::path::PathBuf;
 
-use super::get_backend;
+use super::{get_backend, get_open_mode};
 
 /** An annotated data matrix.
 
@@ -93,22 +94,10 @@ impl AnnData {
 
     pub fn new_from(filename: PathBuf, mode: &str, backend: &str) -> Result<Self> {
         match backend {
-            H5::NAME => {
-                let file = match mode {
-                    "r" => H5::open(filename)?,
-                    "r+" => H5::open_rw(filename)?,
-                    _ => bail!("Unknown mode: {}", mode),
-                };
-                anndata::AnnData::<H5>::open(file).map(|adata| adata.into())
-            }
-            Zarr::NAME => {
-                let file = match mode {
-                    "r" => Zarr::open(filename)?,
-                    "r+" => Zarr::open_rw(filename)?,
-                    _ => bail!("Unknown mode: {}", mode),
-                };
-                anndata::AnnData::<Zarr>::open(file).map(|adata| adata.into())
-            }
+            H5::NAME => anndata::AnnData::<H5>::open_with_mode(filename, get_open_mode(mode)?)
+                .map(|adata| adata.into()),
+            Zarr::NAME => anndata::AnnData::<Zarr>::open_with_mode(filename, get_open_mode(mode)?)
+                .map(|adata| adata.into()),
             x => bail!("Unknown backend: {}", x),
         }
     }
@@ -267,13 +256,13 @@ impl AnnData {
         self.0.obs_names().into_vec()
     }
     #[setter(obs_names)]
-    pub fn set_obs_names(&self, names: Bound<'_, PyAny>) -> Result<()> {
-        self.0.set_obs_names(names)
+    pub fn set_obs_names(&self, names: Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.set_obs_names(names).map_err(to_py_err)
     }
 
     #[pyo3(text_signature = "($self, names)")]
-    fn obs_ix(&self, names: Bound<'_, PyAny>) -> Result<Vec<usize>> {
-        self.0.obs_ix(names)
+    fn obs_ix(&self, names: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+        self.0.obs_ix(names).map_err(to_py_err)
     }
 
     /// Names of variables.
@@ -286,13 +275,13 @@ impl AnnData {
         self.0.var_names().into_vec()
     }
     #[setter(var_names)]
-    pub fn set_var_names(&self, names: Bound<'_, PyAny>) -> Result<()> {
-        self.0.set_var_names(names)
+    pub fn set_var_names(&self, names: Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.set_var_names(names).map_err(to_py_err)
     }
 
     #[pyo3(text_signature = "($self, names)")]
-    fn var_ix(&self, names: Bound<'_, PyAny>) -> Result<Vec<usize>> {
-        self.0.var_ix(names)
+    fn var_ix(&self, names: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+        self.0.var_ix(names).map_err(to_py_err)
     }
 
     /// Data matrix of shape n_obs × n_vars.
@@ -305,8 +294,8 @@ impl AnnData {
         self.0.get_x()
     }
     #[setter(X)]
-    pub fn set_x(&self, data: Option<PyArrayData>) -> Result<()> {
-        self.0.set_x(data)
+    pub fn set_x(&self, data: Option<PyArrayData>) -> PyResult<()> {
+        self.0.set_x(data).map_err(to_py_err)
     }
 
     /// Observation annotations.
@@ -319,8 +308,8 @@ impl AnnData {
         self.0.get_obs()
     }
     #[setter(obs)]
-    fn set_obs(&self, obs: Option<Bound<'_, PyAny>>) -> Result<()> {
-        self.0.set_obs(obs)
+    fn set_obs(&self, obs: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+        self.0.set_obs(obs).map_err(to_py_err)
     }
 
     /// Variable annotations.
@@ -333,8 +322,8 @@ impl AnnData {
         self.0.get_var()
     }
     #[setter(var)]
-    fn set_var(&self, var: Option<Bound<'_, PyAny>>) -> Result<()> {
-        self.0.set_var(var)
+    fn set_var(&self, var: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+        self.0.set_var(var).map_err(to_py_err)
     }
 
     /// Unstructured annotation (ordered dictionary).
@@ -347,8 +336,8 @@ impl AnnData {
         self.0.get_uns()
     }
     #[setter(uns)]
-    pub fn set_uns(&self, uns: Option<HashMap<String, PyData>>) -> Result<()> {
-        self.0.set_uns(uns)
+    pub fn set_uns(&self, uns: Option<HashMap<String, PyData>>) -> PyResult<()> {
+        self.0.set_uns(uns).map_err(to_py_err)
     }
 
     #[getter(obsm)]
@@ -356,8 +345,8 @@ impl AnnData {
         self.0.get_obsm()
     }
     #[setter(obsm)]
-    pub fn set_obsm(&self, obsm: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_obsm(obsm)
+    pub fn set_obsm(&self, obsm: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_obsm(obsm).map_err(to_py_err)
     }
 
     #[getter(obsp)]
@@ -365,8 +354,8 @@ impl AnnData {
         self.0.get_obsp()
     }
     #[setter(obsp)]
-    pub fn set_obsp(&self, obsp: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_obsp(obsp)
+    pub fn set_obsp(&self, obsp: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_obsp(obsp).map_err(to_py_err)
     }
 
     #[getter(varm)]
@@ -374,8 +363,8 @@ impl AnnData {
         self.0.get_varm()
     }
     #[setter(varm)]
-    pub fn set_varm(&self, varm: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_varm(varm)
+    pub fn set_varm(&self, varm: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_varm(varm).map_err(to_py_err)
     }
 
     #[getter(varp)]
@@ -383,8 +372,8 @@ impl AnnData {
         self.0.get_varp()
     }
     #[setter(varp)]
-    pub fn set_varp(&self, varp: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_varp(varp)
+    pub fn set_varp(&self, varp: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_varp(varp).map_err(to_py_err)
     }
 
     #[getter(layers)]
@@ -392,8 +381,8 @@ impl AnnData {
         self.0.get_layers()
     }
     #[setter(layers)]
-    pub fn set_layers(&self, layers: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_layers(layers)
+    pub fn set_layers(&self, layers: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_layers(layers).map_err(to_py_err)
     }
 
     /// Subsetting the AnnData object.
@@ -655,7 +644,9 @@ impl<B: Backend> AnnDataTrait for InnerAnnData<B> {
             .try_iter()?
             .map(|x| Ok(x?.extract::<String>()?))
             .collect();
-        self.adata.inner().set_obs_names(obs_names?)
+        // Keep the name of the current index.
+        let index_name = self.adata.inner().obs_names().index_name;
+        self.adata.inner().set_obs_names(obs_names?.with_name(index_name))
     }
 
     fn var_names(&self) -> DataFrameIndex {
@@ -674,7 +665,9 @@ impl<B: Backend> AnnDataTrait for InnerAnnData<B> {
             .try_iter()?
             .map(|x| Ok(x?.extract::<String>()?))
             .collect();
-        self.adata.inner().set_var_names(var_names?)
+        // Keep the name of the current index.
+        let index_name = self.adata.inner().var_names().index_name;
+        self.adata.inner().set_var_names(var_names?.with_name(index_name))
     }
 
     fn get_x(&self) -> Option<PyArrayElem> {
@@ -893,8 +886,8 @@ impl<B: Backend> AnnDataTrait for InnerAnnData<B> {
             }
         } else {
             let adata = PyAnnData::new(py)?;
-            let obs_slice = SelectInfoElemBounds::new(&slice[0], inner.n_obs());
-            let var_slice = SelectInfoElemBounds::new(&slice[1], inner.n_vars());
+            let obs_slice = SelectInfoElemBounds::try_new(&slice[0], inner.n_obs())?;
+            let var_slice = SelectInfoElemBounds::try_new(&slice[1], inner.n_vars())?;
             let n_obs = obs_slice.len();
             let n_vars = var_slice.len();
             adata.set_n_obs(n_obs)?;
@@ -1037,12 +1030,8 @@ impl<B: Backend> AnnDataTrait for InnerAnnData<B> {
 
     fn open(&self, mode: &str) -> Result<()> {
         if self.is_closed() {
-            let file = match mode {
-                "r" => B::open(self.filename())?,
-                "r+" => B::open_rw(self.filename())?,
-                _ => bail!("Unknown mode: {}", mode),
-            };
-            self.adata.insert(anndata::AnnData::<B>::open(file)?);
+            let adata = anndata::AnnData::<B>::open_with_mode(self.filename(), get_open_mode(mode)?)?;
+            self.adata.insert(adata);
         }
         Ok(())
     }
@@ -2,16 +2,18 @@ use crate::container::{
     PyArrayElem, PyAxisArrays, PyChunkedArray, PyDataFrameElem, PyElemCollection,
 };
 use crate::data::{isinstance_of_pandas, to_select_elem, PyArrayData, PyData};
+use crate::error::to_py_err;
 use crate::{AnnData, PyAnnData};
 
 use anndata::container::Slot;
 use anndata::data::{ArrayData, DataFrameIndex, SelectInfoElem, SelectInfoElemBounds};
 use anndata::{self, ArrayElemOp, Data, Selectable};
-use anndata::{AnnDataOp, Backend};
+use anndata::concat::JoinType;
+use anndata::{AnnDataError, AnnDataOp, Backend, OpenMode};
 use anndata::{AxisArraysOp, ElemCollectionOp};
 use anndata_hdf5::H5;
 use anndata_zarr::Zarr;
-use anyhow::{bail, Result};
+use anyhow::{bail, ensure, Result};
 use downcast_rs::{impl_downcast, Downcast};
 use pyo3::prelude::*;
 use pyo3_polars::PyDataFrame;
@@ -34,6 +36,9 @@ use super::get_backend;
         File name of the output file containing the AnnDataSet object.
     add_key: str
         The column name in obs to store the keys
+    index_unique: str | None
+        If given, the key of each AnnData object is appended to its obs names,
+        separated by `index_unique`, to make the obs names unique.
     backend: Literal['hdf5', 'zarr']
         The backend to use for the AnnDataSet object.
 
@@ -142,11 +147,12 @@ pub enum AnnDataFile<'py> {
 #[pymethods]
 impl AnnDataSet {
     #[new]
-    #[pyo3(signature = (adatas, *, filename, add_key="sample", backend=None))]
+    #[pyo3(signature = (adatas, *, filename, add_key="sample", index_unique=None, backend=None))]
     pub fn new(
         adatas: Vec<(String, AnnDataFile)>,
         filename: PathBuf,
         add_key: &str,
+        index_unique: Option<&str>,
         backend: Option<&str>,
     ) -> Result<Self> {
         let backend = get_backend(&filename, backend);
@@ -156,24 +162,38 @@ impl AnnDataSet {
                     let adata = match data_file {
                         AnnDataFile::Data(data) => data.borrow().take_inner::<H5>().unwrap(),
                         AnnDataFile::Path(path) => {
-                            anndata::AnnData::open(H5::open(path).unwrap()).unwrap()
+                            anndata::AnnData::open_with_mode(path, OpenMode::ReadOnly).unwrap()
                         }
                     };
                     (key, adata)
                 });
-                Ok(anndata::AnnDataSet::new(anndatas, filename, add_key)?.into())
+                Ok(anndata::AnnDataSet::new_with_join(
+                    anndatas,
+                    filename,
+                    add_key,
+                    JoinType::Inner,
+                    index_unique,
+                )?
+                .into())
             }
             Zarr::NAME => {
                 let anndatas = adatas.into_iter().map(|(key, data_file)| {
                     let adata = match data_file {
                         AnnDataFile::Data(data) => data.borrow().take_inner::<Zarr>().unwrap(),
                         AnnDataFile::Path(path) => {
-                            anndata::AnnData::open(Zarr::open(path).unwrap()).unwrap()
+                            anndata::AnnData::open_with_mode(path, OpenMode::ReadOnly).unwrap()
                         }
                     };
                     (key, adata)
                 });
-                Ok(anndata::AnnDataSet::new(anndatas, filename, add_key)?.into())
+                Ok(anndata::AnnDataSet::new_with_join(
+                    anndatas,
+                    filename,
+                    add_key,
+                    JoinType::Inner,
+                    index_unique,
+                )?
+                .into())
             }
             _ => todo!(),
         }
@@ -219,13 +239,13 @@ impl AnnDataSet {
         self.0.obs_names().into_vec()
     }
     #[setter(obs_names)]
-    pub fn set_obs_names(&self, names: Bound<'_, PyAny>) -> Result<()> {
-        self.0.set_obs_names(names)
+    pub fn set_obs_names(&self, names: Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.set_obs_names(names).map_err(to_py_err)
     }
 
     #[pyo3(text_signature = "($self, names)")]
-    fn obs_ix(&self, names: &Bound<'_, PyAny>) -> Result<Vec<usize>> {
-        self.0.obs_ix(names)
+    fn obs_ix(&self, names: &Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+        self.0.obs_ix(names).map_err(to_py_err)
     }
 
     /// Names of variables.
@@ -238,13 +258,13 @@ impl AnnDataSet {
         self.0.var_names().into_vec()
     }
     #[setter(var_names)]
-    pub fn set_var_names(&self, names: Bound<'_, PyAny>) -> Result<()> {
-        self.0.set_var_names(names)
+    pub fn set_var_names(&self, names: Bound<'_, PyAny>) -> PyResult<()> {
+        self.0.set_var_names(names).map_err(to_py_err)
     }
 
     #[pyo3(text_signature = "($self, names)")]
-    fn var_ix(&self, names: Bound<'_, PyAny>) -> Result<Vec<usize>> {
-        self.0.var_ix(names)
+    fn var_ix(&self, names: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+        self.0.var_ix(names).map_err(to_py_err)
     }
 
     /// Data matrix of shape n_obs × n_vars.
@@ -267,8 +287,8 @@ impl AnnDataSet {
         self.0.get_obs()
     }
     #[setter(obs)]
-    fn set_obs(&self, obs: Option<Bound<'_, PyAny>>) -> Result<()> {
-        self.0.set_obs(obs)
+    fn set_obs(&self, obs: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+        self.0.set_obs(obs).map_err(to_py_err)
     }
 
     /// Variable annotations.
@@ -281,8 +301,8 @@ impl AnnDataSet {
         self.0.get_var()
     }
     #[setter(var)]
-    fn set_var(&self, var: Option<Bound<'_, PyAny>>) -> Result<()> {
-        self.0.set_var(var)
+    fn set_var(&self, var: Option<Bound<'_, PyAny>>) -> PyResult<()> {
+        self.0.set_var(var).map_err(to_py_err)
     }
 
     /// Unstructured annotation (ordered dictionary).
@@ -295,8 +315,8 @@ impl AnnDataSet {
         self.0.get_uns()
     }
     #[setter(uns)]
-    pub fn set_uns(&self, uns: Option<HashMap<String, PyData>>) -> Result<()> {
-        self.0.set_uns(uns)
+    pub fn set_uns(&self, uns: Option<HashMap<String, PyData>>) -> PyResult<()> {
+        self.0.set_uns(uns).map_err(to_py_err)
     }
 
     #[getter(obsm)]
@@ -304,8 +324,8 @@ impl AnnDataSet {
         self.0.get_obsm()
     }
     #[setter(obsm)]
-    pub fn set_obsm(&self, obsm: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_obsm(obsm)
+    pub fn set_obsm(&self, obsm: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_obsm(obsm).map_err(to_py_err)
     }
 
     #[getter(obsp)]
@@ -313,8 +333,8 @@ impl AnnDataSet {
         self.0.get_obsp()
     }
     #[setter(obsp)]
-    pub fn set_obsp(&self, obsp: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_obsp(obsp)
+    pub fn set_obsp(&self, obsp: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_obsp(obsp).map_err(to_py_err)
     }
 
     #[getter(varm)]
@@ -322,8 +342,8 @@ impl AnnDataSet {
         self.0.get_varm()
     }
     #[setter(varm)]
-    pub fn set_varm(&self, varm: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_varm(varm)
+    pub fn set_varm(&self, varm: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_varm(varm).map_err(to_py_err)
     }
 
     #[getter(varp)]
@@ -331,8 +351,8 @@ impl AnnDataSet {
         self.0.get_varp()
     }
     #[setter(varp)]
-    pub fn set_varp(&self, varp: Option<HashMap<String, PyArrayData>>) -> Result<()> {
-        self.0.set_varp(varp)
+    pub fn set_varp(&self, varp: Option<HashMap<String, PyArrayData>>) -> PyResult<()> {
+        self.0.set_varp(varp).map_err(to_py_err)
     }
 
     /// Subsetting the AnnDataSet object.
@@ -394,8 +414,8 @@ impl AnnDataSet {
     /// -------
     /// StackedAnnData
     #[getter(adatas)]
-    pub fn adatas(&self) -> StackedAnnData {
-        self.0.get_adatas()
+    pub fn adatas(&self) -> PyResult<StackedAnnData> {
+        self.0.get_adatas().map_err(to_py_err)
     }
 
     /// Convert AnnDataSet to AnnData object.
@@ -430,7 +450,7 @@ impl AnnDataSet {
         text_signature = "($self, chunk_size=500, /)",
         name = "chunked_X",
     )]
-    pub fn chunked_x(&self, chunk_size: usize) -> PyChunkedArray {
+    pub fn chunked_x(&self, chunk_size: usize) -> Result<PyChunkedArray> {
         self.0.chunked_x(chunk_size)
     }
 
@@ -507,7 +527,7 @@ trait AnnDataSetTrait: Send + Sync + Downcast {
     fn set_varm(&self, varm: Option<HashMap<String, PyArrayData>>) -> Result<()>;
     fn set_varp(&self, varp: Option<HashMap<String, PyArrayData>>) -> Result<()>;
 
-    fn get_adatas(&self) -> StackedAnnData;
+    fn get_adatas(&self) -> Result<StackedAnnData>;
 
     fn subset(
         &self,
@@ -525,7 +545,7 @@ trait AnnDataSetTrait: Send + Sync + Downcast {
         backend: Option<&str>,
     ) -> Result<Bound<'py, PyAny>>;
 
-    fn chunked_x(&self, chunk_size: usize) -> PyChunkedArray;
+    fn chunked_x(&self, chunk_size: usize) -> Result<PyChunkedArray>;
 
     fn backend(&self) -> &str;
     fn is_closed(&self) -> bool;
@@ -551,7 +571,9 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
             .try_iter()?
             .map(|x| Ok(x?.extract::<String>()?))
             .collect();
-        self.inner().set_obs_names(obs_names?)
+        // Keep the name of the current index.
+        let index_name = self.inner().obs_names().index_name;
+        self.inner().set_obs_names(obs_names?.with_name(index_name))
     }
 
     fn obs_ix(&self, index: &Bound<'_, PyAny>) -> Result<Vec<usize>> {
@@ -569,7 +591,9 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
             .try_iter()?
             .map(|x| Ok(x?.extract::<String>()?))
             .collect();
-        self.inner().set_var_names(var_names?)
+        // Keep the name of the current index.
+        let index_name = self.inner().var_names().index_name;
+        self.inner().set_var_names(var_names?.with_name(index_name))
     }
 
     fn var_ix(&self, index: Bound<'_, PyAny>) -> Result<Vec<usize>> {
@@ -725,8 +749,17 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
         Ok(())
     }
 
-    fn get_adatas(&self) -> StackedAnnData {
-        self.inner().adatas().clone().into()
+    fn get_adatas(&self) -> Result<StackedAnnData> {
+        let inner = self.inner();
+        let adatas = inner.adatas();
+        // The slot is empty when none of the AnnData files could be opened.
+        ensure!(
+            !adatas.is_none(),
+            AnnDataError::MissingChildren(
+                inner.missing_children().iter().map(|(k, _)| k.clone()).collect()
+            )
+        );
+        Ok(adatas.clone().into())
     }
 
     fn subset(
@@ -738,17 +771,19 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
         match backend {
             H5::NAME => {
                 let order = self.inner().write_select::<H5, _, _>(slice, &out)?;
-                let file = H5::open_rw(out.join("_dataset.h5ads"))?;
+                let file = out.join("_dataset.h5ads");
                 Ok((
-                    anndata::AnnDataSet::<H5>::open::<PathBuf>(file, None)?.into(),
+                    anndata::AnnDataSet::<H5>::open_with_mode::<PathBuf>(file, OpenMode::ReadWrite, None)?
+                        .into(),
                     order,
                 ))
             }
             Zarr::NAME => {
                 let order = self.inner().write_select::<Zarr, _, _>(slice, &out)?;
-                let file = Zarr::open_rw(out.join("_dataset.zarrs"))?;
+                let file = out.join("_dataset.zarrs");
                 Ok((
-                    anndata::AnnDataSet::<Zarr>::open::<PathBuf>(file, None)?.into(),
+                    anndata::AnnDataSet::<Zarr>::open_with_mode::<PathBuf>(file, OpenMode::ReadWrite, None)?
+                        .into(),
                     order,
                 ))
             }
@@ -780,8 +815,8 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
             }
         } else {
             let adata = PyAnnData::new(py)?;
-            let obs_slice = SelectInfoElemBounds::new(&slice[0], inner.n_obs());
-            let var_slice = SelectInfoElemBounds::new(&slice[1], inner.n_vars());
+            let obs_slice = SelectInfoElemBounds::try_new(&slice[0], inner.n_obs())?;
+            let var_slice = SelectInfoElemBounds::try_new(&slice[1], inner.n_vars())?;
             let n_obs = obs_slice.len();
             let n_vars = var_slice.len();
             adata.set_n_obs(n_obs)?;
@@ -864,8 +899,8 @@ impl<B: Backend> AnnDataSetTrait for Slot<anndata::AnnDataSet<B>> {
         }
     }
 
-    fn chunked_x(&self, chunk_size: usize) -> PyChunkedArray {
-        self.inner().x().chunked(chunk_size).into()
+    fn chunked_x(&self, chunk_size: usize) -> Result<PyChunkedArray> {
+        Ok(self.inner().x().try_chunked(chunk_size)?.into())
     }
 
     fn backend(&self) -> &str {
@@ -1,4 +1,4 @@
-use crate::data::{isinstance_of_pyanndata, isinstance_of_polars, PyArrayData, PyData};
+use crate::data::{isinstance_of_pyanndata, to_pandas, PyArrayData, PyData};
 
 use std::ops::Deref;
 use polars::prelude::DataFrame;
@@ -239,9 +239,7 @@ impl<'py> AnnDataOp for PyAnnData<'py> {
     fn set_obs(&self, obs: DataFrame) -> Result<()> {
         let py = self.py();
         let index = self.getattr("obs")?.getattr("index")?;
-        let df = PyDataFrame(obs).into_pyobject(py)?
-            .call_method0("to_pandas")?
-            .call_method1("set_index", (index,))?;
+        let df = to_pandas(obs, py)?.call_method1("set_index", (index,))?;
         self.setattr("obs", df)?;
         Ok(())
     }
@@ -249,9 +247,7 @@ impl<'py> AnnDataOp for PyAnnData<'py> {
     fn set_var(&self, var: DataFrame) -> Result<()> {
         let py = self.py();
         let index = self.getattr("var")?.getattr("index")?;
-        let df = PyDataFrame(var).into_pyobject(py)?
-            .call_method0("to_pandas")?
-            .call_method1("set_index", (index,))?;
+        let df = to_pandas(var, py)?.call_method1("set_index", (index,))?;
         self.setattr("var", df)?;
         Ok(())
     }
@@ -419,13 +415,11 @@ impl ElemCollectionOp for ElemCollection<'_> {
         ) -> Result<()>
     {
         let py = self.0.py();
-        let d = PyData::from(data.into()).into_pyobject(py)?.into_any();
-        let new_d = if isinstance_of_polars(&d)? {
-            d.call_method0("to_pandas")?
-        } else {
-            d
+        let d = match data.into() {
+            Data::ArrayData(ArrayData::DataFrame(df)) => to_pandas(df, py)?,
+            data => PyData::from(data).into_pyobject(py)?.into_any(),
         };
-        self.0.call_method1("__setitem__", (key, new_d))?;
+        self.0.call_method1("__setitem__", (key, d))?;
         Ok(())
     }
 
@@ -469,13 +463,11 @@ impl<'py> AxisArraysOp for AxisArrays<'py> {
             self.adata.set_n_obs(shape[0])?;
             self.adata.set_n_vars(shape[1])?;
         }
-        let d = PyArrayData::from(data).into_pyobject(py)?.into_any();
-        let new_d = if isinstance_of_polars(&d)? {
-            d.call_method0("to_pandas")?
-        } else {
-            d
+        let d = match data {
+            ArrayData::DataFrame(df) => to_pandas(df, py)?,
+            data => PyArrayData::from(data).into_pyobject(py)?.into_any(),
         };
-        self.arrays.call_method1("__setitem__", (key, new_d))?;
+        self.arrays.call_method1("__setitem__", (key, d))?;
         Ok(())
     }
 
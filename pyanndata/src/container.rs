@@ -1,6 +1,7 @@
 mod traits;
 
 use crate::data::{PyData, PyArrayData};
+use crate::error::to_py_err;
 
 use pyo3::prelude::*;
 use pyo3_polars::PySeries;
@@ -123,7 +124,7 @@ impl PyArrayElem {
     /// -------
     /// An iterator, of which the elements are matrices.
     #[pyo3(text_signature = "($self, chunk_size)")]
-    pub fn chunked(&self, chunk_size: usize) -> PyChunkedArray {
+    pub fn chunked(&self, chunk_size: usize) -> Result<PyChunkedArray> {
         self.0.chunked(chunk_size)
     }
 
@@ -149,13 +150,13 @@ impl<T: DataFrameElemTrait + 'static> From<T> for PyDataFrameElem {
 
 #[pymethods]
 impl PyDataFrameElem {
-    fn __getitem__<'py>(&self, subscript: &Bound<'py, PyAny>) -> Result<Bound<'py, PyAny>> {
-        self.0.get(subscript)
+    fn __getitem__<'py>(&self, subscript: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+        self.0.get(subscript).map_err(to_py_err)
     }
 
-    fn __setitem__(&self, key: &str, data: &Bound<'_, PyAny>) -> Result<()> {
+    fn __setitem__(&self, key: &str, data: &Bound<'_, PyAny>) -> PyResult<()> {
         let data: PySeries = data.py().import("polars")?.call_method1("Series", (data, ))?.extract()?;
-        self.0.set(key, data.into())
+        self.0.set(key, data.into()).map_err(to_py_err)
     }
 
     fn __contains__(&self, key: &str) -> bool {
@@ -215,8 +216,8 @@ impl PyAxisArrays {
         self.0.contains(key)
     }
 
-    fn __getitem__(&self, key: &str) -> Result<PyArrayData> {
-        self.0.get(key)
+    fn __getitem__(&self, key: &str) -> PyResult<PyArrayData> {
+        self.0.get(key).map_err(to_py_err)
     }
 
     /// Provide a lazy access to the elements.
@@ -250,12 +251,12 @@ impl PyAxisArrays {
     /// -------
     /// Optional[PyArrayElem]
     #[pyo3(text_signature = "($self, key)")]
-    fn el(&self, key: &str) -> Result<PyArrayElem> {
-        self.0.el(key)
+    fn el(&self, key: &str) -> PyResult<PyArrayElem> {
+        self.0.el(key).map_err(to_py_err)
     }
 
-    fn __setitem__(&self, key: &str, data: PyArrayData) -> Result<()> {
-        self.0.set(key, data)
+    fn __setitem__(&self, key: &str, data: PyArrayData) -> PyResult<()> {
+        self.0.set(key, data).map_err(to_py_err)
     }
 
     fn __repr__(&self) -> String {
@@ -288,12 +289,12 @@ impl PyElemCollection {
         self.0.contains(key)
     }
 
-    fn __getitem__(&self, key: &str) -> Result<PyData> {
-        self.0.get(key)
+    fn __getitem__(&self, key: &str) -> PyResult<PyData> {
+        self.0.get(key).map_err(to_py_err)
     }
 
-    fn __setitem__(&self, key: &str, data: PyData) -> Result<()> {
-        self.0.set(key, data)
+    fn __setitem__(&self, key: &str, data: PyData) -> PyResult<()> {
+        self.0.set(key, data).map_err(to_py_err)
     }
 
     fn __repr__(&self) -> String {
@@ -306,6 +307,9 @@ impl PyElemCollection {
 }
 
 
+/// An iterator over chunks of rows. Each item is a tuple of the chunk and the
+/// range of rows it covers. Chunks are read only when requested and are
+/// converted to numpy arrays or scipy sparse matrices one at a time.
 #[pyclass]
 #[repr(transparent)]
 pub struct PyChunkedArray(Box<dyn ChunkedArrayTrait>);
@@ -316,12 +320,20 @@ impl PyChunkedArray {
         self.0.len()
     }
 
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
     fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
         slf
     }
 
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<(PyArrayData, usize, usize)> {
-        slf.0.next().map(|(data, start, end)| (data.into(), start, end))
+    fn __next__(mut slf: PyRefMut<Self>, py: Python<'_>) -> Option<(PyArrayData, usize, usize)> {
+        // Reading from the file does not touch Python objects, so other
+        // Python threads can run in the meantime.
+        let chunks = &mut slf.0;
+        py.allow_threads(|| chunks.next())
+            .map(|(data, start, end)| (data.into(), start, end))
     }
 }
 
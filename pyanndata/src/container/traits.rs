@@ -7,11 +7,11 @@ use crate::data::{
 use anndata::backend::DataType;
 use anndata::data::SelectInfoElem;
 use anndata::{
-    ArrayData, ArrayElem, AxisArrays, Backend,
+    AnnDataError, ArrayData, ArrayElem, AxisArrays, Backend,
     DataFrameElem, Elem, ElemCollection, StackedArrayElem, StackedDataFrame, StackedAxisArrays,
 };
 use anndata::container::{ChunkedArrayElem, StackedChunkedArrayElem};
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, Result};
 use polars::series::Series;
 use pyo3::prelude::*;
 use pyo3_polars::{PySeries, PyDataFrame};
@@ -70,7 +70,7 @@ pub trait ArrayElemTrait: Send + Sync {
         replace: bool,
         seed: u64,
     ) -> Result<ArrayData>;
-    fn chunked(&self, chunk_size: usize) -> PyChunkedArray;
+    fn chunked(&self, chunk_size: usize) -> Result<PyChunkedArray>;
 }
 
 impl<B: Backend + 'static> ArrayElemTrait for ArrayElem<B> {
@@ -115,8 +115,8 @@ impl<B: Backend + 'static> ArrayElemTrait for ArrayElem<B> {
         self.inner().select_axis::<_>(0, &SelectInfoElem::from(idx))
     }
 
-    fn chunked(&self, chunk_size: usize) -> PyChunkedArray {
-        self.chunked(chunk_size).into()
+    fn chunked(&self, chunk_size: usize) -> Result<PyChunkedArray> {
+        Ok(self.chunked(chunk_size).into())
     }
 }
 
@@ -130,6 +130,7 @@ impl<B: Backend + 'static> ArrayElemTrait for StackedArrayElem<B> {
     }
 
     fn get(&self, subscript: &Bound<'_, PyAny>) -> Result<PyArrayData> {
+        self.ensure_complete()?;
         let slice = to_select_info(subscript, self.deref().shape().as_ref().unwrap())?;
         self.select::<ArrayData, _>(slice.as_ref())
             .map(|x| x.unwrap().into())
@@ -149,6 +150,7 @@ impl<B: Backend + 'static> ArrayElemTrait for StackedArrayElem<B> {
         replace: bool,
         seed: u64,
     ) -> Result<ArrayData> {
+        self.ensure_complete()?;
         let length = self.shape()[0];
         let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
         let idx: Vec<usize> = if replace {
@@ -162,8 +164,8 @@ impl<B: Backend + 'static> ArrayElemTrait for StackedArrayElem<B> {
             .map(|x| x.unwrap())
     }
 
-    fn chunked(&self, chunk_size: usize) -> PyChunkedArray {
-        self.chunked(chunk_size).into()
+    fn chunked(&self, chunk_size: usize) -> Result<PyChunkedArray> {
+        Ok(self.try_chunked(chunk_size)?.into())
     }
 }
 
@@ -256,7 +258,7 @@ impl<B: Backend + 'static> AxisArrayTrait for AxisArrays<B> {
         Ok(self
             .inner()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "axis arrays"))?
             .inner()
             .data()?
             .into())
@@ -266,7 +268,7 @@ impl<B: Backend + 'static> AxisArrayTrait for AxisArrays<B> {
         Ok(self
             .inner()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "axis arrays"))?
             .clone()
             .into())
     }
@@ -293,7 +295,7 @@ impl<B: Backend + 'static> AxisArrayTrait for StackedAxisArrays<B> {
         Ok(self
             .deref()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "axis arrays"))?
             .data::<ArrayData>()?.unwrap()
             .into())
     }
@@ -302,7 +304,7 @@ impl<B: Backend + 'static> AxisArrayTrait for StackedAxisArrays<B> {
         Ok(self
             .deref()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "axis arrays"))?
             .clone()
             .into())
     }
@@ -340,7 +342,7 @@ impl<B: Backend + 'static> ElemCollectionTrait for ElemCollection<B> {
         Ok(self
             .inner()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "uns"))?
             .inner()
             .data()?
             .into())
@@ -350,7 +352,7 @@ impl<B: Backend + 'static> ElemCollectionTrait for ElemCollection<B> {
         Ok(self
             .inner()
             .get(key)
-            .context(format!("No such key: {}", key))?
+            .ok_or_else(|| AnnDataError::key_not_found(key, "uns"))?
             .clone()
             .into())
     }
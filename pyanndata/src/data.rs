@@ -7,7 +7,8 @@ use pyo3_polars::PyDataFrame;
 pub use slice::{to_select_info, to_select_elem};
 
 use std::{collections::HashMap, ops::Deref};
-use pyo3::{prelude::*, types::PyDict};
+use polars::prelude::{DataFrame, DataType};
+use pyo3::{prelude::*, types::{IntoPyDict, PyDict}};
 use anndata::data::{Data, ArrayData, DynScalar, Mapping};
 
 pub struct PyArrayData(ArrayData);
@@ -102,6 +103,46 @@ impl<'py> FromPyObject<'py> for PyData {
     }
 }
 
+/// Convert a DataFrame to a pandas DataFrame.
+///
+/// Categorical columns become `pandas.Categorical` with the categories of the
+/// column in their original order, including categories that do not occur.
+/// Ordered categoricals (polars `Enum`) stay ordered.
+pub(crate) fn to_pandas<'py>(df: DataFrame, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+    let categoricals: Vec<_> = df
+        .get_columns()
+        .iter()
+        .filter_map(|column| {
+            let ordered = match column.dtype() {
+                DataType::Categorical(..) => false,
+                DataType::Enum(..) => true,
+                _ => return None,
+            };
+            let ca = column.as_materialized_series().categorical().ok()?;
+            let categories: Vec<&str> = ca.get_rev_map().get_categories().values_iter().collect();
+            let index: HashMap<&str, i64> =
+                categories.iter().enumerate().map(|(i, x)| (*x, i as i64)).collect();
+            // Codes are looked up by value as the physical codes of polars may
+            // refer to the global string cache.
+            let codes: Vec<i64> = ca.iter_str().map(|x| x.map_or(-1, |x| index[x])).collect();
+            let categories: Vec<String> = categories.into_iter().map(String::from).collect();
+            Some((column.name().to_string(), codes, categories, ordered))
+        })
+        .collect();
+
+    let pandas_df = PyDataFrame(df).into_pyobject(py)?.call_method0("to_pandas")?;
+    if !categoricals.is_empty() {
+        let categorical = py.import("pandas")?.getattr("Categorical")?;
+        for (name, codes, categories, ordered) in categoricals {
+            let kwargs = [("ordered", ordered)].into_py_dict(py)?;
+            let values =
+                categorical.call_method("from_codes", (codes, categories), Some(&kwargs))?;
+            pandas_df.set_item(name, values)?;
+        }
+    }
+    Ok(pandas_df)
+}
+
 fn to_scalar(ob: &Bound<'_, PyAny>) -> PyResult<DynScalar> {
     if ob.is_instance_of::<pyo3::types::PyBool>() {
         ob.extract::<bool>().map(Into::into)
@@ -150,6 +191,7 @@ fn scalar_to_py<'py>(s: DynScalar, py: Python<'py>) -> PyResult<Bound<'py, PyAny
         DynScalar::U16(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::U32(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::U64(s) => Ok(s.into_pyobject(py)?.into_any()),
+        DynScalar::F16(s) => Ok(s.to_f32().into_pyobject(py)?.into_any()),
         DynScalar::F32(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::F64(s) => Ok(s.into_pyobject(py)?.into_any()),
         DynScalar::Bool(s) => Ok(s.into_pyobject(py)?.to_owned().into_any()),
@@ -1,10 +1,14 @@
 use crate::data::{isinstance_of_csc, isinstance_of_csr};
 
-use anndata::data::{CsrNonCanonical, DynArray, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
+use anndata::data::{f16, CsrNonCanonical, DynArray, DynCscMatrix, DynCsrMatrix, DynCsrNonCanonical};
 use nalgebra_sparse::{CscMatrix, CsrMatrix};
 use ndarray::ArrayD;
 use numpy::{IntoPyArray, PyArrayMethods, PyReadonlyArrayDyn};
-use pyo3::{exceptions::PyTypeError, prelude::*};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyString,
+};
 
 macro_rules! proc_py_numeric {
     ($dtype:expr, $data:expr, $ty_anno:tt) => {
@@ -77,46 +81,68 @@ pub(super) fn to_array(ob: &Bound<'_, PyAny>) -> PyResult<DynArray> {
     } else {
         let ty = ob.getattr("dtype")?.getattr("name")?;
         let ty = ty.extract::<&str>()?;
-        proc_py_numeric!(
-            ty,
-            ob.extract::<PyReadonlyArrayDyn<_>>()?.to_owned_array(),
-            ArrayD
-        )
+        if ty == "float16" {
+            // Only dense arrays can hold half-precision values.
+            let x: ArrayD<f16> = ob.extract::<PyReadonlyArrayDyn<_>>()?.to_owned_array();
+            x.into()
+        } else {
+            proc_py_numeric!(
+                ty,
+                ob.extract::<PyReadonlyArrayDyn<_>>()?.to_owned_array(),
+                ArrayD
+            )
+        }
     };
     Ok(arr)
 }
 
-pub(super) fn to_csr(ob: &Bound<'_, PyAny>) -> PyResult<DynCsrMatrix> {
-    fn extract_csr_indicies(indicies: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
-        let res = match indicies
-            .getattr("dtype")?
-            .getattr("name")?
-            .extract::<&str>()?
-        {
-            "int32" => indicies
-                .extract::<PyReadonlyArrayDyn<i32>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            "int64" => indicies
-                .extract::<PyReadonlyArrayDyn<i64>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            other => panic!("CSR indicies type '{}' is not supported", other),
-        };
-        Ok(res)
+/// Read the `indices` or `indptr` array of a scipy sparse matrix. scipy uses
+/// int32 or int64 depending on the size of the matrix; other integer types
+/// are cast to int64 first.
+fn extract_indices(indices: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
+    fn to_usize<T: Copy + TryInto<usize> + std::fmt::Display>(x: &T) -> PyResult<usize> {
+        (*x)
+            .try_into()
+            .map_err(|_| PyValueError::new_err(format!("invalid sparse matrix index: {}", x)))
+    }
+
+    let dtype = indices.getattr("dtype")?;
+    match dtype.getattr("name")?.extract::<&str>()? {
+        "int32" => indices
+            .extract::<PyReadonlyArrayDyn<i32>>()?
+            .as_array()
+            .iter()
+            .map(to_usize)
+            .collect(),
+        "int64" => indices
+            .extract::<PyReadonlyArrayDyn<i64>>()?
+            .as_array()
+            .iter()
+            .map(to_usize)
+            .collect(),
+        other => {
+            let kind = dtype.getattr("kind")?;
+            let kind = kind.extract::<&str>()?;
+            if kind == "i" || kind == "u" {
+                extract_indices(indices.call_method1("astype", ("int64",))?)
+            } else {
+                Err(PyTypeError::new_err(format!(
+                    "sparse matrix indices must be integers, found '{}'",
+                    other
+                )))
+            }
+        }
     }
+}
 
+pub(super) fn to_csr(ob: &Bound<'_, PyAny>) -> PyResult<DynCsrMatrix> {
     if !isinstance_of_csr(ob)? {
         return Err(PyTypeError::new_err("not a csr matrix"));
     }
 
     let shape: Vec<usize> = ob.getattr("shape")?.extract()?;
-    let indices = extract_csr_indicies(ob.getattr("indices")?)?;
-    let indptr = extract_csr_indicies(ob.getattr("indptr")?)?;
+    let indices = extract_indices(ob.getattr("indices")?)?;
+    let indptr = extract_indices(ob.getattr("indptr")?)?;
     let ty = ob.getattr("data")?.getattr("dtype")?.getattr("name")?;
     let ty = ty.extract::<&str>()?;
 
@@ -139,36 +165,13 @@ pub(super) fn to_csr(ob: &Bound<'_, PyAny>) -> PyResult<DynCsrMatrix> {
 }
 
 pub(super) fn to_csr_noncanonical(ob: &Bound<'_, PyAny>) -> PyResult<DynCsrNonCanonical> {
-    fn extract_csr_indicies(indicies: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
-        let res = match indicies
-            .getattr("dtype")?
-            .getattr("name")?
-            .extract::<&str>()?
-        {
-            "int32" => indicies
-                .extract::<PyReadonlyArrayDyn<i32>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            "int64" => indicies
-                .extract::<PyReadonlyArrayDyn<i64>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            other => panic!("CSR indicies type '{}' is not supported", other),
-        };
-        Ok(res)
-    }
-
     if !isinstance_of_csr(ob)? {
         return Err(PyTypeError::new_err("not a csr matrix"));
     }
 
     let shape: Vec<usize> = ob.getattr("shape")?.extract()?;
-    let indices = extract_csr_indicies(ob.getattr("indices")?)?;
-    let indptr = extract_csr_indicies(ob.getattr("indptr")?)?;
+    let indices = extract_indices(ob.getattr("indices")?)?;
+    let indptr = extract_indices(ob.getattr("indptr")?)?;
     let ty = ob.getattr("data")?.getattr("dtype")?.getattr("name")?;
     let ty = ty.extract::<&str>()?;
 
@@ -190,36 +193,13 @@ pub(super) fn to_csr_noncanonical(ob: &Bound<'_, PyAny>) -> PyResult<DynCsrNonCa
 }
 
 pub(super) fn to_csc(ob: &Bound<'_, PyAny>) -> PyResult<DynCscMatrix> {
-    fn extract_csc_indicies(indicies: Bound<'_, PyAny>) -> PyResult<Vec<usize>> {
-        let res = match indicies
-            .getattr("dtype")?
-            .getattr("name")?
-            .extract::<&str>()?
-        {
-            "int32" => indicies
-                .extract::<PyReadonlyArrayDyn<i32>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            "int64" => indicies
-                .extract::<PyReadonlyArrayDyn<i64>>()?
-                .as_array()
-                .iter()
-                .map(|x| (*x).try_into().unwrap())
-                .collect(),
-            other => panic!("CSC indicies type '{}' is not supported", other),
-        };
-        Ok(res)
-    }
-
     if !isinstance_of_csc(ob)? {
         return Err(PyTypeError::new_err("not a csc matrix"));
     }
 
     let shape: Vec<usize> = ob.getattr("shape")?.extract()?;
-    let indices = extract_csc_indicies(ob.getattr("indices")?)?;
-    let indptr = extract_csc_indicies(ob.getattr("indptr")?)?;
+    let indices = extract_indices(ob.getattr("indices")?)?;
+    let indptr = extract_indices(ob.getattr("indptr")?)?;
     let ty = ob.getattr("data")?.getattr("dtype")?.getattr("name")?;
     let ty = ty.extract::<&str>()?;
 
@@ -241,6 +221,12 @@ pub(super) fn to_csc(ob: &Bound<'_, PyAny>) -> PyResult<DynCscMatrix> {
     Ok(csc)
 }
 
+/// Convert a dense array to a numpy array.
+///
+/// Numeric arrays are not copied: numpy takes ownership of the buffer of the
+/// `ndarray` and uses its strides, so this holds for non-contiguous arrays as
+/// well. Strings have no fixed-size numpy representation and are converted to
+/// an array of Python objects.
 pub(super) fn arr_to_py<'py>(arr: DynArray, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
     let res = match arr {
         DynArray::I8(arr) => arr.into_pyarray(py).into_any(),
@@ -251,10 +237,14 @@ pub(super) fn arr_to_py<'py>(arr: DynArray, py: Python<'py>) -> PyResult<Bound<'
         DynArray::U16(arr) => arr.into_pyarray(py).into_any(),
         DynArray::U32(arr) => arr.into_pyarray(py).into_any(),
         DynArray::U64(arr) => arr.into_pyarray(py).into_any(),
+        DynArray::F16(arr) => arr.into_pyarray(py).into_any(),
         DynArray::F32(arr) => arr.into_pyarray(py).into_any(),
         DynArray::F64(arr) => arr.into_pyarray(py).into_any(),
         DynArray::Bool(arr) => arr.into_pyarray(py).into_any(),
-        DynArray::String(_) => todo!(),
+        DynArray::String(arr) => {
+            let arr: ArrayD<PyObject> = arr.map(|x| PyString::new(py, x).into_any().unbind());
+            arr.into_pyarray(py).into_any()
+        }
     };
     Ok(res)
 }
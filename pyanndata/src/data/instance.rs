@@ -1,23 +1,26 @@
 use pyo3::{prelude::*, types::PyType, PyResult};
 
+/// Whether `obj` is a `scipy.sparse.csr_matrix` or `scipy.sparse.csr_array`.
 pub fn isinstance_of_csr<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
-    obj.is_instance(
-        obj.py()
-            .import("scipy.sparse")?
-            .getattr("csr_matrix")?
-            .downcast::<PyType>()
-            .unwrap(),
-    )
+    isinstance_of_sparse(obj, &["csr_matrix", "csr_array"])
 }
 
+/// Whether `obj` is a `scipy.sparse.csc_matrix` or `scipy.sparse.csc_array`.
 pub fn isinstance_of_csc<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
-    obj.is_instance(
-        obj.py()
-            .import("scipy.sparse")?
-            .getattr("csc_matrix")?
-            .downcast::<PyType>()
-            .unwrap(),
-    )
+    isinstance_of_sparse(obj, &["csc_matrix", "csc_array"])
+}
+
+/// Sparse arrays were added in scipy 1.8, so missing types are skipped.
+fn isinstance_of_sparse<'py>(obj: &Bound<'py, PyAny>, types: &[&str]) -> PyResult<bool> {
+    let scipy = obj.py().import("scipy.sparse")?;
+    for ty in types {
+        if let Ok(ty) = scipy.getattr(*ty) {
+            if obj.is_instance(ty.downcast::<PyType>()?)? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
 }
 
 pub fn isinstance_of_arr<'py>(obj: &Bound<'py, PyAny>) -> PyResult<bool> {
@@ -18,16 +18,27 @@ pub fn to_select_info(ob: &Bound<'_, PyAny>, shape: &Shape) -> PyResult<SelectIn
 
 pub fn to_select_elem(ob: &Bound<'_, PyAny>, length: usize) -> PyResult<SelectInfoElem> {
     let select = if let Ok(slice) = ob.downcast::<pyo3::types::PySlice>() {
+        // `indices` resolves negative and out-of-range bounds as Python does.
         let s = slice.indices(length.try_into()?)?;
-        ndarray::Slice { 
-            start: s.start,
-            end: Some(s.stop),
-            step: s.step,
-        }.into()
+        if s.step > 0 {
+            ndarray::Slice::new(s.start, Some(s.stop.max(s.start)), s.step).into()
+        } else {
+            // Python walks backwards from `start` to `stop` (exclusive), while
+            // ndarray takes the range first and then walks it from the back.
+            let start = s.stop + 1;
+            ndarray::Slice::new(start, Some((s.start + 1).max(start)), s.step).into()
+        }
     } else if is_none_slice(ob)? {
         SelectInfoElem::full()
     } else if ob.is_instance_of::<pyo3::types::PyInt>() {
-        ob.extract::<usize>()?.into()
+        let i = ob.extract::<isize>()?;
+        let resolved = if i < 0 { length.checked_sub(i.unsigned_abs()) } else { Some(i as usize) };
+        match resolved.filter(|x| *x < length) {
+            Some(i) => i.into(),
+            None => return Err(pyo3::exceptions::PyIndexError::new_err(format!(
+                "index {} is out of bounds for axis with size {}", i, length
+            ))),
+        }
     } else if isinstance_of_arr(ob)? && ob.getattr("dtype")?.getattr("name")?.extract::<&str>()? == "bool" {
         let arr = ob
             .extract::<numpy::PyReadonlyArray1<bool>>()?;
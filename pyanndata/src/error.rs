@@ -0,0 +1,24 @@
+use anndata::AnnDataError;
+use pyo3::exceptions::{
+    PyFileNotFoundError, PyKeyError, PyPermissionError, PyTypeError, PyValueError,
+};
+use pyo3::PyErr;
+
+/// Convert an error to the Python exception matching its [`AnnDataError`]:
+/// `KeyError` for missing keys, `ValueError` for dimension mismatches,
+/// `TypeError` for unsupported data types, `PermissionError` for writes to
+/// read-only files and `FileNotFoundError` for missing files of an
+/// `AnnDataSet`. Other errors are converted as usual and become `RuntimeError`.
+pub(crate) fn to_py_err(err: anyhow::Error) -> PyErr {
+    let msg = format!("{:#}", err);
+    match AnnDataError::find(&err) {
+        Some(AnnDataError::KeyNotFound { .. } | AnnDataError::MissingUns(_)) => {
+            PyKeyError::new_err(msg)
+        }
+        Some(AnnDataError::DimMismatch { .. }) => PyValueError::new_err(msg),
+        Some(AnnDataError::UnsupportedDtype(_)) => PyTypeError::new_err(msg),
+        Some(AnnDataError::ReadOnly(_)) => PyPermissionError::new_err(msg),
+        Some(AnnDataError::MissingChildren(_)) => PyFileNotFoundError::new_err(msg),
+        None => err.into(),
+    }
+}
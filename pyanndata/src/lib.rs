@@ -1,6 +1,7 @@
 pub mod anndata;
 pub mod data;
 pub mod container;
+mod error;
 
 pub use crate::anndata::{AnnData, AnnDataSet, PyAnnData, read, read_mtx, read_dataset, concat};
 pub use crate::container::{
@@ -238,3 +238,116 @@ def test_noncanonical_csr(tmp_path, backend):
         adata.write(file)
         adata = read(file, backed=None, backend='hdf5')
         assert_csr_equal(csr, adata.X)
+
+@pytest.mark.parametrize("backend", ["hdf5", "zarr"])
+def test_error_types(tmp_path, backend):
+    adata = AnnData(filename=h5ad(tmp_path), X=np.zeros((3, 2)), backend=backend)
+
+    with pytest.raises(KeyError):
+        adata.obsm["missing"]
+    with pytest.raises(KeyError):
+        adata.uns["missing"]
+    with pytest.raises(ValueError):
+        adata.obsm["X_pca"] = np.zeros((4, 2))
+    with pytest.raises(ValueError):
+        adata.layers["counts"] = np.zeros((3, 5))
+
+    # Sparse matrices cannot hold half-precision floats.
+    if backend == "hdf5":
+        file = h5ad(tmp_path)
+        ad.AnnData(X=csr_matrix(np.ones((2, 2), dtype=np.float16))).write(file)
+        with pytest.raises(TypeError):
+            read(file).X[:]
+
+@pytest.mark.parametrize("backend", ["hdf5", "zarr"])
+def test_numpy_export(tmp_path, backend):
+    adata = AnnData(filename=h5ad(tmp_path), backend=backend)
+
+    # Strings are returned as object arrays.
+    adata.uns["names"] = np.array([["a", "bb"], ["ccc", ""]])
+    names = adata.uns["names"]
+    assert names.dtype == object
+    assert names.shape == (2, 2)
+    assert names.tolist() == [["a", "bb"], ["ccc", ""]]
+
+    x = np.asfortranarray(np.arange(12, dtype=np.float32).reshape(3, 4))
+    adata.uns["x"] = x
+    x_ = adata.uns["x"]
+    assert x_.dtype == np.float32
+    np.testing.assert_array_equal(x_, x)
+
+@pytest.mark.parametrize("backend", ["hdf5", "zarr"])
+def test_sparse_input(tmp_path, backend):
+    from scipy.sparse import csr_array, csc_array
+
+    x = np.array([[0, 1, 0], [2, 0, 3]], dtype=np.float64)
+    adata = AnnData(filename=h5ad(tmp_path), backend=backend)
+
+    adata.X = csr_array(x)
+    np.testing.assert_array_equal(x, adata.X[:].todense())
+    adata.X = csc_array(x)
+    np.testing.assert_array_equal(x, adata.X[:].todense())
+
+    # Index arrays of any integer width are accepted. The constructor would
+    # convert them to int32, so they are replaced afterwards.
+    for dtype in [np.int8, np.int16, np.uint32, np.uint64]:
+        csr = csr_matrix(x)
+        csr.indices = csr.indices.astype(dtype)
+        csr.indptr = csr.indptr.astype(dtype)
+        adata.X = csr
+        np.testing.assert_array_equal(x, adata.X[:].todense())
+
+    csr = csr_matrix(x)
+    csr.indices = csr.indices.astype(np.float64)
+    with pytest.raises(TypeError):
+        adata.X = csr
+
+def test_categorical_to_pandas(tmp_path):
+    file = h5ad(tmp_path)
+    obs = pd.DataFrame(
+        {
+            "cell_type": pd.Categorical(["b", "a", "b"], categories=["c", "b", "a"]),
+            "stage": pd.Categorical(["late", "early", None], categories=["early", "late"], ordered=True),
+        },
+        index=["x", "y", "z"],
+    )
+    ad.AnnData(X=np.zeros((3, 2)), obs=obs).write(file)
+
+    adata = read(file).to_memory()
+    cell_type = adata.obs["cell_type"]
+    assert isinstance(cell_type.dtype, pd.CategoricalDtype)
+    assert list(cell_type.cat.categories) == ["c", "b", "a"]
+    assert not cell_type.cat.ordered
+    assert list(cell_type) == ["b", "a", "b"]
+
+    stage = adata.obs["stage"]
+    assert list(stage.cat.categories) == ["early", "late"]
+    assert stage.cat.ordered
+    assert list(stage.cat.codes) == [1, 0, -1]
+
+
+@pytest.mark.parametrize("backend", ["hdf5", "zarr"])
+def test_chunk_threads(tmp_path, backend):
+    import threading
+
+    X = np.arange(1000 * 20, dtype=np.int64).reshape(1000, 20)
+    adata = AnnData(X=X, filename=h5ad(tmp_path), backend=backend)
+    assert len(adata.X.chunked(100)) == 10
+    assert len(adata.X.chunked(300)) == 4
+
+    # Chunks are read without holding the GIL, so several threads can iterate
+    # at the same time.
+    results = {}
+    def read(i):
+        chunks = list(adata.X.chunked(100 + i))
+        assert chunks[0][1] == 0 and chunks[-1][2] == 1000
+        results[i] = np.concatenate([m for m, _, _ in chunks])
+
+    threads = [threading.Thread(target=read, args=(i,)) for i in range(4)]
+    for t in threads:
+        t.start()
+    for t in threads:
+        t.join()
+    assert sorted(results) == [0, 1, 2, 3]
+    for x in results.values():
+        np.testing.assert_array_equal(X, x)
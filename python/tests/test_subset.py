@@ -210,4 +210,4 @@ def test_anndataset_subset(x1, x2, x3, idx1, idx2, idx3, tmp_path, backend):
 
     # Check if open is OK
     os.chdir(tmp_path)
-    dataset_subset.subset([], out = "a_copy", backend=backend)
\ No newline at end of file
+    dataset_subset.subset([], out = "a_copy", backend=backend)